@@ -3,11 +3,13 @@
 extern crate meval_symbolic;
 extern crate test;
 
-use meval_symbolic::{max_array, min_array, Context, ContextProvider, Expr, FuncEvalError};
+use meval_symbolic::{max_array, min_array, Context, ContextProvider, Dag, Expr, FuncEvalError};
 use std::f64::consts;
 use test::Bencher;
 
 const EXPR: &str = "abs(sin(x + 1) * (x^2 + x + 1))";
+/// Differentiated to get an expression repeating large subexpressions.
+const REPEATED: &str = "exp(sin(x)^2 + cos(x)^2 * x) * sqrt(sin(x)^2 + cos(x)^2 * x)";
 
 #[bench]
 fn parsing(b: &mut Bencher) {
@@ -34,6 +36,25 @@ fn evaluation_hashcontext(b: &mut Bencher) {
   });
 }
 
+#[bench]
+fn evaluation_repeated_expr(b: &mut Bencher) {
+  let expr = REPEATED.parse::<Expr>().unwrap().derivative("x").unwrap();
+  let func = expr.bind_with_context(Context::new(), "x").unwrap();
+  b.iter(|| {
+    func(1.);
+  });
+}
+
+#[bench]
+fn evaluation_repeated_dag(b: &mut Bencher) {
+  let expr = REPEATED.parse::<Expr>().unwrap().derivative("x").unwrap();
+  let dag = Dag::new(&expr, Context::new());
+  let func = dag.bind_with_context(Context::new(), "x").unwrap();
+  b.iter(|| {
+    func(1.);
+  });
+}
+
 #[bench]
 fn default_context(b: &mut Bencher) {
   let expr: Expr = "1 + 2 * 3".parse().unwrap();
@@ -0,0 +1,231 @@
+//! Intervals of real numbers and their unions, the solution sets of inequalities.
+use std::cmp::Ordering;
+use std::f64;
+use std::fmt::{self, Display, Formatter};
+
+/// An interval of real numbers, with each end open or closed, e.g. `(-1, 1]`.
+///
+/// The ends may be infinite, in which case they are open.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+  pub lo: f64,
+  pub hi: f64,
+  pub lo_closed: bool,
+  pub hi_closed: bool,
+}
+
+impl Interval {
+  /// The interval `(lo, hi)`.
+  pub fn open(lo: f64, hi: f64) -> Self {
+    Interval {
+      lo,
+      hi,
+      lo_closed: false,
+      hi_closed: false,
+    }
+  }
+
+  /// The interval `[lo, hi]`, with infinite ends left open.
+  pub fn closed(lo: f64, hi: f64) -> Self {
+    Interval {
+      lo,
+      hi,
+      lo_closed: lo.is_finite(),
+      hi_closed: hi.is_finite(),
+    }
+  }
+
+  /// The interval `[x, x]` containing only `x`.
+  pub fn point(x: f64) -> Self {
+    Interval::closed(x, x)
+  }
+
+  /// The interval `(-inf, inf)` of all real numbers.
+  pub fn real_line() -> Self {
+    Interval::open(f64::NEG_INFINITY, f64::INFINITY)
+  }
+
+  pub fn is_point(&self) -> bool {
+    self.lo == self.hi && self.lo_closed && self.hi_closed
+  }
+
+  pub fn is_empty(&self) -> bool {
+    !(self.lo < self.hi || self.is_point())
+  }
+
+  pub fn contains(&self, x: f64) -> bool {
+    let above = if self.lo_closed {
+      x >= self.lo
+    } else {
+      x > self.lo
+    };
+    let below = if self.hi_closed {
+      x <= self.hi
+    } else {
+      x < self.hi
+    };
+    above && below
+  }
+}
+
+impl Display for Interval {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    if self.is_point() {
+      return write!(f, "{{{}}}", self.lo);
+    }
+    let open = if self.lo_closed { '[' } else { '(' };
+    let close = if self.hi_closed { ']' } else { ')' };
+    write!(f, "{}{}, {}{}", open, self.lo, self.hi, close)
+  }
+}
+
+/// A union of disjoint intervals, in increasing order, e.g. `(-inf, -1) ∪ [2, 3]`.
+///
+/// Overlapping and adjacent intervals are merged, so that every set is written in one way.
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::{Interval, IntervalSet};
+/// let set: IntervalSet = vec![
+///   Interval::open(2., 3.),
+///   Interval::open(-1., 1.),
+///   Interval::point(1.),
+///   Interval::closed(0.5, 1.5),
+/// ]
+/// .into_iter()
+/// .collect();
+/// assert_eq!(set.to_string(), "(-1, 1.5] ∪ (2, 3)");
+/// assert!(set.contains(1.) && !set.contains(2.));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IntervalSet(Vec<Interval>);
+
+impl IntervalSet {
+  /// The empty set.
+  pub fn empty() -> Self {
+    IntervalSet::default()
+  }
+
+  /// The disjoint intervals of the set, in increasing order.
+  pub fn intervals(&self) -> &[Interval] {
+    &self.0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn contains(&self, x: f64) -> bool {
+    self.0.iter().any(|interval| interval.contains(x))
+  }
+
+  /// The union of two sets.
+  pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+    self.0.iter().chain(&other.0).cloned().collect()
+  }
+
+  /// Adds an interval to the set, merging it with the ones it overlaps or touches.
+  pub fn insert(&mut self, interval: Interval) {
+    if interval.is_empty() {
+      return;
+    }
+    let start = |i: &Interval| (i.lo, !i.lo_closed);
+    let pos = self
+      .0
+      .iter()
+      .position(|i| start(i).partial_cmp(&start(&interval)) == Some(Ordering::Greater))
+      .unwrap_or(self.0.len());
+    self.0.insert(pos, interval);
+
+    let mut merged: Vec<Interval> = Vec::with_capacity(self.0.len());
+    for next in self.0.drain(..) {
+      match merged.last_mut() {
+        Some(last)
+          if last.hi > next.lo || (last.hi == next.lo && (last.hi_closed || next.lo_closed)) =>
+        {
+          if next.hi > last.hi || (next.hi == last.hi && next.hi_closed) {
+            last.hi = next.hi;
+            last.hi_closed = next.hi_closed;
+          }
+        }
+        _ => merged.push(next),
+      }
+    }
+    self.0 = merged;
+  }
+}
+
+impl From<Interval> for IntervalSet {
+  fn from(interval: Interval) -> Self {
+    Some(interval).into_iter().collect()
+  }
+}
+
+impl ::std::iter::FromIterator<Interval> for IntervalSet {
+  fn from_iter<I: IntoIterator<Item = Interval>>(iter: I) -> Self {
+    let mut set = IntervalSet::empty();
+    for interval in iter {
+      set.insert(interval);
+    }
+    set
+  }
+}
+
+impl Display for IntervalSet {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    if self.0.is_empty() {
+      return write!(f, "∅");
+    }
+    for (i, interval) in self.0.iter().enumerate() {
+      if i > 0 {
+        write!(f, " ∪ ")?;
+      }
+      write!(f, "{}", interval)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn intervals() {
+    assert_eq!(Interval::open(-1., 1.).to_string(), "(-1, 1)");
+    assert_eq!(Interval::closed(0., f64::INFINITY).to_string(), "[0, inf)");
+    assert_eq!(Interval::point(2.5).to_string(), "{2.5}");
+    assert!(Interval::open(1., 1.).is_empty());
+    assert!(!Interval::point(1.).is_empty());
+    assert!(Interval::closed(0., 1.).contains(1.));
+    assert!(!Interval::open(0., 1.).contains(1.));
+    assert!(Interval::real_line().contains(-1e300));
+  }
+
+  #[test]
+  fn unions() {
+    let set: IntervalSet = vec![
+      Interval::open(1., 2.),
+      Interval::open(f64::NEG_INFINITY, 1.),
+      Interval::point(1.),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(set.to_string(), "(-inf, 2)");
+
+    let gap: IntervalSet = vec![Interval::open(0., 1.), Interval::open(1., 2.)]
+      .into_iter()
+      .collect();
+    assert_eq!(gap.to_string(), "(0, 1) ∪ (1, 2)");
+    assert!(!gap.contains(1.));
+
+    let set = gap.union(&Interval::closed(1.5, 3.).into());
+    assert_eq!(set.to_string(), "(0, 1) ∪ (1, 3]");
+    assert_eq!(IntervalSet::empty().to_string(), "∅");
+    assert_eq!(
+      IntervalSet::from(Interval::open(1., 1.)),
+      IntervalSet::empty()
+    );
+  }
+}
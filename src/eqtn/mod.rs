@@ -1,17 +1,37 @@
-use super::{builtin, ContextProvider, Expr, SubstituteError};
+use self::relation::PreparedRelation;
+use super::{builtin, Comparison, ContextProvider, Expr, SubstituteError};
 use crate::Evaluatable_Trait;
+use std::fmt::{self, Display, Formatter};
 use Error;
 
+mod interval;
+mod numeric;
 mod parser;
+mod relation;
 mod symbolic;
+mod system;
+
+pub use self::interval::{Interval, IntervalSet};
+pub use self::numeric::{Derivative, Root, RootError, RootMethod, RootOptions};
+pub use self::relation::{Relation, Sign};
+pub(crate) use self::system::gauss;
+pub use self::system::{EqtnSystem, NewtonOptions, SystemError, SystemRoot};
 
 /// A relation between two expressions, e.g. `x = 5`
-#[derive(Clone, PartialEq)]
+///
+/// Equations are hashed and ordered by their left and then right hand sides, see
+/// [`Expr`](struct.Expr.html).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Eqtn(pub(crate) Expr, pub(crate) Expr);
 
 /// We can implement all of the eval methods in terms of the left and right
 /// hand sides of an equation
 impl Eqtn {
+  /// The equation `lhs = rhs`.
+  pub fn new(lhs: Expr, rhs: Expr) -> Self {
+    Eqtn(lhs, rhs)
+  }
+
   /// Evaluates the equation with variables given by the argument.
   pub fn eval_with_context<C: ContextProvider + Clone>(&self, ctx: C) -> Result<bool, Error> {
     let lhs = self.0.eval_with_context(ctx.clone())?;
@@ -29,6 +49,16 @@ impl Eqtn {
     self.0.check_context(ctx.clone())?;
     self.1.check_context(ctx)
   }
+
+  fn prepared(self) -> PreparedRelation {
+    PreparedRelation::new(self.0, Comparison::Eq, self.1)
+  }
 }
 
 Evaluatable_Trait!(Eqtn bool);
+
+impl Display for Eqtn {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{} = {}", self.0, self.1)
+  }
+}
@@ -0,0 +1,489 @@
+//! Numerical root finding for equations in one variable.
+use super::{ContextProvider, Eqtn, Expr};
+use std::fmt::{self, Display, Formatter};
+use Error;
+
+/// A method of finding a root numerically, with its starting interval or points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RootMethod {
+  /// Halving an interval `[a, b]` on which the sign changes.
+  Bisection(f64, f64),
+  /// Brent's method on an interval `[a, b]` on which the sign changes, combining bisection
+  /// with the secant method and inverse quadratic interpolation.
+  Brent(f64, f64),
+  /// Newton's method from a starting point.
+  Newton(f64),
+  /// The secant method from two starting points.
+  Secant(f64, f64),
+}
+
+/// How Newton's method finds derivatives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Derivative {
+  /// Differentiating the equation symbolically once, see
+  /// [`Expr::derivative`](struct.Expr.html#method.derivative).
+  #[default]
+  Symbolic,
+  /// Evaluating the derivative along with the value with dual numbers, which also works for
+  /// functions without known derivatives by differentiating them numerically.
+  Dual,
+}
+
+/// The method and stopping criteria of [`Eqtn::solve_numeric`].
+///
+/// [`Eqtn::solve_numeric`]: struct.Eqtn.html#method.solve_numeric
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RootOptions {
+  pub method: RootMethod,
+  /// The root is accepted once it is known to within `tolerance * max(1, |x|)`, or the
+  /// equation holds exactly.
+  pub tolerance: f64,
+  pub max_iterations: usize,
+  pub derivative: Derivative,
+}
+
+impl RootOptions {
+  /// Options for `method`, with a tolerance of `1e-12`, at most 100 iterations and symbolic
+  /// derivatives.
+  pub fn new(method: RootMethod) -> Self {
+    RootOptions {
+      method,
+      tolerance: 1e-12,
+      max_iterations: 100,
+      derivative: Derivative::default(),
+    }
+  }
+
+  pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+    self.tolerance = tolerance;
+    self
+  }
+
+  pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+    self.max_iterations = max_iterations;
+    self
+  }
+
+  pub fn with_derivative(mut self, derivative: Derivative) -> Self {
+    self.derivative = derivative;
+    self
+  }
+}
+
+/// The result of a numerical root search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Root {
+  /// The root, or the last estimate if the search didn't converge.
+  pub value: f64,
+  /// Whether the tolerance was reached, rather than the maximum number of iterations or a
+  /// point where the method breaks down, e.g. a zero derivative.
+  pub converged: bool,
+  pub iterations: usize,
+  /// `|lhs - rhs|` at the root.
+  pub residual: f64,
+}
+
+/// An error finding a root numerically.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RootError {
+  /// The sides of the equation aren't different on the two ends of the interval.
+  NoSignChange(f64, f64),
+  /// An error evaluating or differentiating the equation.
+  Eval(Error),
+}
+
+impl Display for RootError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      RootError::NoSignChange(a, b) => {
+        write!(f, "No sign change on the interval [{}, {}].", a, b)
+      }
+      RootError::Eval(e) => e.fmt(f),
+    }
+  }
+}
+
+impl From<Error> for RootError {
+  fn from(err: Error) -> RootError {
+    RootError::Eval(err)
+  }
+}
+
+fn within(step: f64, x: f64, tolerance: f64) -> bool {
+  step.abs() <= tolerance * x.abs().max(1.)
+}
+
+/// `lhs - rhs` as a function of one variable.
+struct Residual<'a, C> {
+  expr: Expr,
+  var: &'a str,
+  ctx: C,
+}
+
+impl<'a, C: ContextProvider> Residual<'a, C> {
+  fn at(&self, x: f64) -> Result<f64, Error> {
+    self.expr.eval_with_context(((self.var, x), &self.ctx))
+  }
+
+  fn root(&self, value: f64, converged: bool, iterations: usize) -> Result<Root, Error> {
+    Ok(Root {
+      value,
+      converged,
+      iterations,
+      residual: self.at(value)?.abs(),
+    })
+  }
+
+  fn bracket(&self, a: f64, b: f64) -> Result<(f64, f64), RootError> {
+    let (fa, fb) = (self.at(a)?, self.at(b)?);
+    if fa * fb > 0. || fa.is_nan() || fb.is_nan() {
+      return Err(RootError::NoSignChange(a, b));
+    }
+    Ok((fa, fb))
+  }
+
+  fn bisection(&self, mut a: f64, mut b: f64, opts: &RootOptions) -> Result<Root, RootError> {
+    let (mut fa, fb) = self.bracket(a, b)?;
+    if fa == 0. || fb == 0. {
+      return Ok(self.root(if fa == 0. { a } else { b }, true, 0)?);
+    }
+    for i in 1..=opts.max_iterations {
+      let m = (a + b) / 2.;
+      let fm = self.at(m)?;
+      if fm == 0. || within((b - a) / 2., m, opts.tolerance) {
+        return Ok(self.root(m, true, i)?);
+      }
+      if fm.signum() == fa.signum() {
+        a = m;
+        fa = fm;
+      } else {
+        b = m;
+      }
+    }
+    Ok(self.root((a + b) / 2., false, opts.max_iterations)?)
+  }
+
+  /// Brent's method as in Numerical Recipes, keeping the root between `b` and `c`.
+  fn brent(&self, mut a: f64, mut b: f64, opts: &RootOptions) -> Result<Root, RootError> {
+    let (mut fa, mut fb) = self.bracket(a, b)?;
+    let (mut c, mut fc) = (b, fb);
+    let (mut d, mut e) = (b - a, b - a);
+    for i in 1..=opts.max_iterations {
+      if fb * fc > 0. {
+        c = a;
+        fc = fa;
+        d = b - a;
+        e = d;
+      }
+      if fc.abs() < fb.abs() {
+        a = b;
+        b = c;
+        c = a;
+        fa = fb;
+        fb = fc;
+        fc = fa;
+      }
+      let tol = 2. * f64::EPSILON * b.abs() + 0.5 * opts.tolerance * b.abs().max(1.);
+      let m = 0.5 * (c - b);
+      if m.abs() <= tol || fb == 0. {
+        return Ok(self.root(b, true, i)?);
+      }
+      if e.abs() >= tol && fa.abs() > fb.abs() {
+        // try inverse quadratic interpolation, or the secant step if a == c
+        let s = fb / fa;
+        let (mut p, mut q) = if a == c {
+          (2. * m * s, 1. - s)
+        } else {
+          let (q, r) = (fa / fc, fb / fc);
+          (
+            s * (2. * m * q * (q - r) - (b - a) * (r - 1.)),
+            (q - 1.) * (r - 1.) * (s - 1.),
+          )
+        };
+        if p > 0. {
+          q = -q;
+        }
+        p = p.abs();
+        if 2. * p < (3. * m * q - (tol * q).abs()).min((e * q).abs()) {
+          e = d;
+          d = p / q;
+        } else {
+          d = m;
+          e = d;
+        }
+      } else {
+        d = m;
+        e = d;
+      }
+      a = b;
+      fa = fb;
+      b += if d.abs() > tol { d } else { tol.copysign(m) };
+      fb = self.at(b)?;
+    }
+    Ok(self.root(b, false, opts.max_iterations)?)
+  }
+
+  fn newton(&self, mut x: f64, opts: &RootOptions) -> Result<Root, RootError> {
+    let derivative = match opts.derivative {
+      Derivative::Symbolic => Some(self.expr.derivative_with_context(self.var, &self.ctx)?),
+      Derivative::Dual => None,
+    };
+    for i in 1..=opts.max_iterations {
+      let (fx, dfx) = match derivative {
+        Some(ref derivative) => (
+          self.at(x)?,
+          derivative.eval_with_context(((self.var, x), &self.ctx))?,
+        ),
+        None => self.expr.eval_dual(self.var, x, &self.ctx)?,
+      };
+      if fx == 0. {
+        return Ok(self.root(x, true, i)?);
+      }
+      let step = fx / dfx;
+      if !step.is_finite() {
+        return Ok(self.root(x, false, i)?);
+      }
+      x -= step;
+      if within(step, x, opts.tolerance) {
+        return Ok(self.root(x, true, i)?);
+      }
+    }
+    Ok(self.root(x, false, opts.max_iterations)?)
+  }
+
+  fn secant(&self, mut x0: f64, mut x1: f64, opts: &RootOptions) -> Result<Root, RootError> {
+    let (mut f0, mut f1) = (self.at(x0)?, self.at(x1)?);
+    for i in 1..=opts.max_iterations {
+      if f1 == 0. {
+        return Ok(self.root(x1, true, i)?);
+      }
+      let step = f1 * (x1 - x0) / (f1 - f0);
+      if !step.is_finite() {
+        return Ok(self.root(x1, false, i)?);
+      }
+      x0 = x1;
+      f0 = f1;
+      x1 -= step;
+      f1 = self.at(x1)?;
+      if within(step, x1, opts.tolerance) {
+        return Ok(self.root(x1, true, i)?);
+      }
+    }
+    Ok(self.root(x1, false, opts.max_iterations)?)
+  }
+}
+
+impl Eqtn {
+  /// Finds a root of `lhs - rhs` as a function of `var` numerically, with the other variables
+  /// and the functions given by `ctx`.
+  ///
+  /// The bracketing methods always converge, to a root or to a discontinuity where the sign
+  /// changes, while Newton's method and the secant method converge faster from a good
+  /// starting point but may not converge at all. Use [`sign_changes`](#method.sign_changes)
+  /// to find intervals for the bracketing methods.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NoSignChange)` if a bracketing method is given an interval on which the
+  /// sign of `lhs - rhs` doesn't change, and `Err(Eval)` if the equation can't be evaluated,
+  /// or can't be differentiated for Newton's method.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Context, Eqtn, RootMethod, RootOptions};
+  /// let eqtn: Eqtn = "cos(x) = a * x".parse().unwrap();
+  /// let mut ctx = Context::new();
+  /// ctx.var("a", 1.);
+  ///
+  /// let brent = eqtn.solve_numeric("x", &ctx, &RootOptions::new(RootMethod::Brent(0., 1.)));
+  /// let newton = eqtn.solve_numeric("x", &ctx, &RootOptions::new(RootMethod::Newton(1.)));
+  /// let (brent, newton) = (brent.unwrap(), newton.unwrap());
+  /// assert!(brent.converged && newton.converged);
+  /// assert!((brent.value - 0.7390851332151607).abs() < 1e-12);
+  /// assert!((newton.value - 0.7390851332151607).abs() < 1e-12);
+  /// assert!(newton.residual < 1e-12);
+  /// ```
+  pub fn solve_numeric<C: ContextProvider>(
+    &self,
+    var: &str,
+    ctx: C,
+    options: &RootOptions,
+  ) -> Result<Root, RootError> {
+    let f = Residual {
+      expr: &self.0 - self.1.clone(),
+      var,
+      ctx,
+    };
+    match options.method {
+      RootMethod::Bisection(a, b) => f.bisection(a, b, options),
+      RootMethod::Brent(a, b) => f.brent(a, b, options),
+      RootMethod::Newton(x) => f.newton(x, options),
+      RootMethod::Secant(x0, x1) => f.secant(x0, x1, options),
+    }
+  }
+
+  /// Scans `[a, b]` in `steps` equal steps for the intervals on which the sign of
+  /// `lhs - rhs` changes, in increasing order.
+  ///
+  /// Each interval contains a root, or a discontinuity such as a pole of `tan`, and roots hit
+  /// exactly are given as intervals `(x, x)`. Roots where the sign doesn't change, e.g. of
+  /// `x^2 = 0`, are only found if they are hit exactly, and roots closer together than the
+  /// step may be missed. Points where the equation evaluates to NaN are skipped.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err` if the equation can't be evaluated, e.g. because of an unknown variable.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Context, Eqtn, RootMethod, RootOptions};
+  /// let eqtn: Eqtn = "sin(x) = 0.5".parse().unwrap();
+  /// let ctx = Context::new();
+  /// let brackets = eqtn.sign_changes("x", &ctx, 0., 10., 100).unwrap();
+  /// assert_eq!(brackets.len(), 4);
+  ///
+  /// let roots: Vec<f64> = brackets
+  ///   .iter()
+  ///   .map(|&(a, b)| {
+  ///     let options = RootOptions::new(RootMethod::Brent(a, b));
+  ///     eqtn.solve_numeric("x", &ctx, &options).unwrap().value
+  ///   })
+  ///   .collect();
+  /// assert!((roots[0] - std::f64::consts::PI / 6.).abs() < 1e-12);
+  /// ```
+  pub fn sign_changes<C: ContextProvider>(
+    &self,
+    var: &str,
+    ctx: C,
+    a: f64,
+    b: f64,
+    steps: usize,
+  ) -> Result<Vec<(f64, f64)>, Error> {
+    let f = Residual {
+      expr: &self.0 - self.1.clone(),
+      var,
+      ctx,
+    };
+    let mut brackets = vec![];
+    let mut prev: Option<(f64, f64)> = None;
+    for i in 0..=steps {
+      let x = a + (b - a) * i as f64 / steps as f64;
+      let fx = f.at(x)?;
+      if fx.is_nan() {
+        continue;
+      }
+      match prev {
+        _ if fx == 0. => brackets.push((x, x)),
+        Some((x0, f0)) if f0 * fx < 0. => brackets.push((x0, x)),
+        _ => {}
+      }
+      prev = Some((x, fx));
+    }
+    Ok(brackets)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use expr::builtin;
+  use Context;
+
+  fn solve(eqtn: Eqtn, method: RootMethod) -> Root {
+    eqtn
+      .solve_numeric("x", builtin(), &RootOptions::new(method))
+      .unwrap()
+  }
+
+  #[test]
+  fn methods() {
+    let sqrt2 = 2f64.sqrt();
+    for &method in &[
+      RootMethod::Bisection(0., 2.),
+      RootMethod::Brent(0., 2.),
+      RootMethod::Newton(1.),
+      RootMethod::Secant(1., 2.),
+    ] {
+      let root = solve(eqtn!(x ^ 2 = 2), method);
+      assert!(root.converged, "{:?}: {:?}", method, root);
+      assert!(
+        (root.value - sqrt2).abs() < 1e-12,
+        "{:?}: {:?}",
+        method,
+        root
+      );
+      assert!(root.residual < 1e-11, "{:?}: {:?}", method, root);
+    }
+
+    // Brent's method takes far fewer steps than bisection
+    let bisection = solve(eqtn!(exp(x) = 3), RootMethod::Bisection(0., 2.));
+    let brent = solve(eqtn!(exp(x) = 3), RootMethod::Brent(0., 2.));
+    assert!(brent.iterations * 3 < bisection.iterations);
+    assert!((brent.value - 3f64.ln()).abs() < 1e-12);
+  }
+
+  #[test]
+  fn newton_derivatives() {
+    let mut ctx = Context::new();
+    ctx.func("cube", |x| x * x * x);
+    let eqtn = eqtn!(cube(x) = 27);
+    let options = RootOptions::new(RootMethod::Newton(1.));
+    assert_eq!(
+      eqtn.solve_numeric("x", &ctx, &options),
+      Err(RootError::Eval(Error::NoDerivative("cube".into())))
+    );
+    let root = eqtn
+      .solve_numeric("x", &ctx, &options.with_derivative(Derivative::Dual))
+      .unwrap();
+    assert!(root.converged);
+    assert!((root.value - 3.).abs() < 1e-9);
+
+    // a zero derivative stops the search
+    let root = solve(eqtn!(x ^ 2 + 1 = 0), RootMethod::Newton(0.));
+    assert!(!root.converged);
+    assert_eq!((root.value, root.iterations), (0., 1));
+  }
+
+  #[test]
+  fn failures() {
+    assert_eq!(
+      eqtn!(x ^ 2 = 2).solve_numeric("x", builtin(), &RootOptions::new(RootMethod::Brent(2., 3.))),
+      Err(RootError::NoSignChange(2., 3.))
+    );
+    assert_eq!(
+      eqtn!(x = y).solve_numeric("x", builtin(), &RootOptions::new(RootMethod::Newton(0.))),
+      Err(RootError::Eval(Error::UnknownVariable("y".into())))
+    );
+
+    // Newton's method cycles on x^3 - 2 * x + 2 from 0
+    let options = RootOptions::new(RootMethod::Newton(0.)).with_max_iterations(20);
+    let root = eqtn!(x ^ 3 - 2 * x + 2 = 0)
+      .solve_numeric("x", builtin(), &options)
+      .unwrap();
+    assert!(!root.converged);
+    assert_eq!(root.iterations, 20);
+    assert_eq!(root.residual, 2.);
+  }
+
+  #[test]
+  fn sign_changes() {
+    let ctx = builtin();
+    let eqtn = eqtn!((x - 1) * (x - 2) * (x + 3) = 0);
+    assert_eq!(
+      eqtn.sign_changes("x", &ctx, -4., 4., 8).unwrap(),
+      [(-3., -3.), (1., 1.), (2., 2.)]
+    );
+    assert_eq!(
+      eqtn.sign_changes("x", &ctx, -3.5, 3.5, 7).unwrap(),
+      [(-3.5, -2.5), (0.5, 1.5), (1.5, 2.5)]
+    );
+    // the square root isn't defined left of 1
+    let eqtn = eqtn!(sqrt(x - 1) = 0.5);
+    assert_eq!(
+      eqtn.sign_changes("x", &ctx, 0., 2., 4).unwrap(),
+      [(1., 1.5)]
+    );
+  }
+}
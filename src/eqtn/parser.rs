@@ -1,5 +1,7 @@
 use super::{Eqtn, Error};
 use crate::expr::ParseError;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::str::FromStr;
 
 impl FromStr for Eqtn {
@@ -10,3 +12,35 @@ impl FromStr for Eqtn {
     Ok(Eqtn(eqtn.0.parse()?, eqtn.1.parse()?))
   }
 }
+
+/// Serializes as a string, e.g. `"x^2 = 2 * y"`.
+#[cfg(feature = "serde")]
+impl Serialize for Eqtn {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Eqtn {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(de::Error::custom)
+  }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+  use super::*;
+  use serde_json;
+
+  #[test]
+  fn test_json_round_trip() {
+    let eqtn: Eqtn = "x^2 = 2 * (y + 1)".parse().unwrap();
+    let json = serde_json::to_string(&eqtn).unwrap();
+    assert_eq!(json, r#""x^2 = 2 * (y + 1)""#);
+    assert_eq!(serde_json::from_str::<Eqtn>(&json).unwrap(), eqtn);
+    assert!(serde_json::from_str::<Eqtn>(r#""x + 1""#).is_err());
+  }
+}
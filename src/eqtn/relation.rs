@@ -0,0 +1,399 @@
+//! Relations `lhs op rhs` with any comparison, and the solution sets of inequalities in one
+//! variable.
+use super::{Eqtn, Interval, IntervalSet};
+use crate::Evaluatable_Trait;
+use expr::{ParseError, PreparedExpr};
+use polynomial::{Polynomial, PolynomialError};
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::f64;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use {builtin, Comparison, ContextProvider, Error, Expr};
+
+/// A comparison between two expressions, e.g. `x^2 - 1 < 0` or `x != y`.
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::Relation;
+/// let rel: Relation = "x^2 - 1 < 0".parse().unwrap();
+/// assert_eq!(rel.solve("x").unwrap().to_string(), "(-1, 1)");
+/// assert_eq!(rel.bind("x").unwrap()(0.5), true);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Relation(pub(crate) Expr, pub(crate) Comparison, pub(crate) Expr);
+
+/// A relation with the calls binding a variable in its sides found once, to evaluate it many
+/// times.
+pub(crate) struct PreparedRelation(PreparedExpr, Comparison, PreparedExpr);
+
+impl PreparedRelation {
+  pub(crate) fn new(lhs: Expr, op: Comparison, rhs: Expr) -> Self {
+    PreparedRelation(lhs.prepared(), op, rhs.prepared())
+  }
+
+  pub(crate) fn eval_with_context<C: ContextProvider + Clone>(
+    &self,
+    ctx: C,
+  ) -> Result<bool, Error> {
+    let lhs = self.0.eval_with_context(ctx.clone())?;
+    let rhs = self.2.eval_with_context(ctx)?;
+    Ok(self.1.holds(lhs, rhs))
+  }
+}
+
+/// The sign of an expression on a piece of a [`Relation::sign_chart`].
+///
+/// [`Relation::sign_chart`]: struct.Relation.html#method.sign_chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sign {
+  Negative,
+  Zero,
+  Positive,
+  /// The expression is undefined, at a root of its denominator.
+  Undefined,
+}
+
+impl Sign {
+  fn of(x: f64) -> Sign {
+    match x.partial_cmp(&0.) {
+      Some(Ordering::Less) => Sign::Negative,
+      Some(Ordering::Equal) => Sign::Zero,
+      Some(Ordering::Greater) => Sign::Positive,
+      None => Sign::Undefined,
+    }
+  }
+}
+
+/// Whether two roots are the same up to rounding errors, relative to their size.
+fn same_root(x: f64, y: f64) -> bool {
+  (x - y).abs() <= 1e-9 * x.abs().max(y.abs())
+}
+
+/// The real roots of a polynomial in `var`, each once and in increasing order.
+fn real_roots(poly: &Polynomial, var: &str) -> Result<Vec<f64>, PolynomialError> {
+  if poly.as_constant().is_some() {
+    return Ok(vec![]);
+  }
+  let mut roots: Vec<f64> = poly
+    .roots(var)?
+    .iter()
+    .filter_map(|x| x.eval().ok())
+    .filter(|x| x.is_finite())
+    .collect();
+  roots.dedup_by(|x, y| same_root(*x, *y));
+  Ok(roots)
+}
+
+impl Relation {
+  /// The relation `lhs op rhs`.
+  pub fn new(lhs: Expr, op: Comparison, rhs: Expr) -> Self {
+    Relation(lhs, op, rhs)
+  }
+
+  pub fn lhs(&self) -> &Expr {
+    &self.0
+  }
+
+  pub fn op(&self) -> Comparison {
+    self.1
+  }
+
+  pub fn rhs(&self) -> &Expr {
+    &self.2
+  }
+
+  /// Evaluates the relation with variables given by the argument.
+  pub fn eval_with_context<C: ContextProvider + Clone>(&self, ctx: C) -> Result<bool, Error> {
+    let lhs = self.0.eval_with_context(ctx.clone())?;
+    let rhs = self.2.eval_with_context(ctx)?;
+    Ok(self.1.holds(lhs, rhs))
+  }
+
+  /// Checks that the value of every variable in the relation is specified by
+  /// the context `ctx`.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err` if a missing variable is detected.
+  fn check_context<C: ContextProvider + Clone>(&self, ctx: C) -> Result<(), Error> {
+    self.0.check_context(ctx.clone())?;
+    self.2.check_context(ctx)
+  }
+
+  fn prepared(self) -> PreparedRelation {
+    PreparedRelation::new(self.0, self.1, self.2)
+  }
+
+  /// The same relation with the sides swapped, e.g. `y > x` for `x < y`.
+  pub fn swapped(&self) -> Relation {
+    Relation(self.2.clone(), self.1.reversed(), self.0.clone())
+  }
+
+  /// Multiplies both sides by `c`, reversing the comparison if `c` is negative, so that the
+  /// relation holds for the same values, e.g. `-2 * x > -2 * 3` for `x < 3` and `c = -2`.
+  ///
+  /// Returns `None` if `c` is zero or NaN, which doesn't keep the solutions.
+  pub fn scaled(&self, c: f64) -> Option<Relation> {
+    let op = match c.partial_cmp(&0.)? {
+      Ordering::Less => self.1.reversed(),
+      Ordering::Equal => return None,
+      Ordering::Greater => self.1,
+    };
+    Some(Relation(c * &self.0, op, c * &self.2))
+  }
+
+  /// The sign chart of `lhs - rhs` as a fraction of polynomials in `var`: the roots of the
+  /// numerator and the denominator, and the open intervals between them, in increasing order,
+  /// each with the sign of the expression there.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotPolynomial)` if `lhs - rhs` isn't a fraction of polynomials,
+  /// `Err(NotUnivariate)` if it has variables other than `var`, and `Err(NoClosedForm)` if the
+  /// numerator or the denominator has a factor of degree higher than 4.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Relation, Sign};
+  /// let rel: Relation = "(x - 1) / x >= 0".parse().unwrap();
+  /// let chart: Vec<_> = rel
+  ///   .sign_chart("x")
+  ///   .unwrap()
+  ///   .into_iter()
+  ///   .map(|(interval, sign)| format!("{} {:?}", interval, sign))
+  ///   .collect();
+  /// assert_eq!(
+  ///   chart,
+  ///   [
+  ///     "(-inf, 0) Positive",
+  ///     "{0} Undefined",
+  ///     "(0, 1) Negative",
+  ///     "{1} Zero",
+  ///     "(1, inf) Positive",
+  ///   ]
+  /// );
+  /// ```
+  pub fn sign_chart(&self, var: &str) -> Result<Vec<(Interval, Sign)>, PolynomialError> {
+    let (num, den) = Polynomial::fraction_from_expr(&(&self.0 - &self.2))?;
+    if num.vars().iter().chain(&den.vars()).any(|v| v != var) {
+      return Err(PolynomialError::NotUnivariate);
+    }
+
+    // the roots of the denominator take precedence over those of the numerator
+    let mut points: Vec<(f64, Sign)> = real_roots(&den, var)?
+      .into_iter()
+      .map(|x| (x, Sign::Undefined))
+      .chain(real_roots(&num, var)?.into_iter().map(|x| (x, Sign::Zero)))
+      .collect();
+    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    points.dedup_by(|next, prev| {
+      if !same_root(prev.0, next.0) {
+        return false;
+      }
+      if next.1 == Sign::Undefined {
+        prev.1 = Sign::Undefined;
+      }
+      true
+    });
+
+    let sign_at = |x: f64| Sign::of(num.eval_at(var, x) / den.eval_at(var, x));
+    let mut chart = vec![];
+    let mut lo = f64::NEG_INFINITY;
+    for &(x, sign) in &points {
+      let sample = if lo.is_finite() {
+        (lo + x) / 2.
+      } else {
+        x - 1.
+      };
+      chart.push((Interval::open(lo, x), sign_at(sample)));
+      chart.push((Interval::point(x), sign));
+      lo = x;
+    }
+    let sample = if lo.is_finite() { lo + 1. } else { 0. };
+    chart.push((Interval::open(lo, f64::INFINITY), sign_at(sample)));
+    Ok(chart)
+  }
+
+  /// The set of values of `var` for which the relation holds, if both sides are fractions of
+  /// polynomials in `var`, from the [`sign_chart`](#method.sign_chart) of `lhs - rhs`.
+  ///
+  /// The values where either side is undefined are never solutions.
+  ///
+  /// # Failure
+  ///
+  /// As for [`sign_chart`](#method.sign_chart).
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Relation;
+  /// let solve = |rel: &str| rel.parse::<Relation>().unwrap().solve("x").unwrap().to_string();
+  /// assert_eq!(solve("x^2 - 1 < 0"), "(-1, 1)");
+  /// assert_eq!(solve("x^2 >= 4"), "(-inf, -2] ∪ [2, inf)");
+  /// assert_eq!(solve("1 / (x - 1) > 0"), "(1, inf)");
+  /// assert_eq!(solve("x^2 + 1 <= 0"), "∅");
+  /// ```
+  pub fn solve(&self, var: &str) -> Result<IntervalSet, PolynomialError> {
+    let op = self.1;
+    let holds = |sign: Sign| match sign {
+      Sign::Negative => op.holds(-1., 0.),
+      Sign::Zero => op.holds(0., 0.),
+      Sign::Positive => op.holds(1., 0.),
+      Sign::Undefined => false,
+    };
+    Ok(
+      self
+        .sign_chart(var)?
+        .into_iter()
+        .filter(|&(_, sign)| holds(sign))
+        .map(|(interval, _)| interval)
+        .collect(),
+    )
+  }
+}
+
+Evaluatable_Trait!(Relation bool);
+
+impl From<Eqtn> for Relation {
+  fn from(eqtn: Eqtn) -> Self {
+    Relation(eqtn.0, Comparison::Eq, eqtn.1)
+  }
+}
+
+impl Display for Relation {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{} {} {}", self.0, self.1, self.2)
+  }
+}
+
+/// Parses the sides around the first comparison symbol, e.g. `x^2 <= 2 * y`.
+impl FromStr for Relation {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (i, symbol, op) =
+      Comparison::find(s).ok_or(Error::ParseError(ParseError::MissingArgument))?;
+    Ok(Relation(
+      s[..i].parse()?,
+      op,
+      s[i + symbol.len()..].parse()?,
+    ))
+  }
+}
+
+/// Serializes as a string, e.g. `"x^2 <= 2 * y"`.
+#[cfg(feature = "serde")]
+impl Serialize for Relation {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Relation {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rel(s: &str) -> Relation {
+    s.parse().unwrap()
+  }
+
+  fn solve(s: &str) -> String {
+    rel(s).solve("x").unwrap().to_string()
+  }
+
+  #[test]
+  fn parse_and_display() {
+    assert_eq!(rel("x<=2*y").to_string(), "x <= 2 * y");
+    assert_eq!(rel("x != 1").op(), Comparison::Ne);
+    assert_eq!(rel("3! > x").rhs(), &expr!(x));
+    assert_eq!(
+      Relation::from(Eqtn::new(expr!(x), expr!(1))).to_string(),
+      "x = 1"
+    );
+    // `!` right after an operand is a factorial
+    assert_eq!(rel("3!=6").to_string(), "3! = 6");
+    assert_eq!(rel("3!=6").eval(), Ok(true));
+    assert_eq!(rel("3 != 6").op(), Comparison::Ne);
+    assert_eq!(rel("(x)!>=2").to_string(), "x! >= 2");
+    assert!("x + 1".parse::<Relation>().is_err());
+    assert!("x < < 1".parse::<Relation>().is_err());
+  }
+
+  #[test]
+  fn eval() {
+    assert_eq!(rel("2 < 3").eval(), Ok(true));
+    assert_eq!(rel("x >= 2").eval_with_context(("x", 2.)), Ok(true));
+    assert_eq!(rel("x != 2").eval_with_context(("x", 2.)), Ok(false));
+    assert!(rel("x < y").eval_with_context(("x", 2.)).is_err());
+  }
+
+  #[test]
+  fn scaling() {
+    let scaled = rel("x < 3").scaled(-2.).unwrap();
+    assert_eq!(scaled.op(), Comparison::Gt);
+    assert_eq!(scaled.solve("x"), rel("x < 3").solve("x"));
+    assert_eq!(rel("x <= 3").scaled(2.).unwrap().op(), Comparison::Le);
+    assert_eq!(rel("x < 3").scaled(0.), None);
+    assert_eq!(rel("x < 3").swapped().to_string(), "3 > x");
+  }
+
+  #[test]
+  fn polynomial_inequalities() {
+    assert_eq!(solve("x^2 - 1 < 0"), "(-1, 1)");
+    assert_eq!(solve("x^2 - 1 <= 0"), "[-1, 1]");
+    assert_eq!(solve("2 * x + 1 > x - 3"), "(-4, inf)");
+    assert_eq!(solve("-2 * x >= 6"), "(-inf, -3]");
+    assert_eq!(solve("(x - 1)^2 > 0"), "(-inf, 1) ∪ (1, inf)");
+    assert_eq!(solve("(x - 1)^2 <= 0"), "{1}");
+    assert_eq!(solve("x^3 - x >= 0"), "[-1, 0] ∪ [1, inf)");
+    assert_eq!(solve("x^2 != 4"), "(-inf, -2) ∪ (-2, 2) ∪ (2, inf)");
+    assert_eq!(solve("x^2 + 1 > 0"), "(-inf, inf)");
+    assert_eq!(solve("x - x < 1"), "(-inf, inf)");
+    assert_eq!(solve("x - x > 1"), "∅");
+    // badly scaled roots
+    assert_eq!(solve("x^2 - 10000000000 < 0"), "(-100000, 100000)");
+    assert_eq!(
+      solve("x^2 - 0.0000000001^2 <= 0"),
+      "[-0.0000000001, 0.0000000001]"
+    );
+    assert_eq!(
+      solve("(x - 0.00000000001) * (x - 0.00000000003) > 0"),
+      "(-inf, 0.00000000001) ∪ (0.00000000003, inf)"
+    );
+  }
+
+  #[test]
+  fn rational_inequalities() {
+    assert_eq!(solve("(x + 2) / (x - 1) <= 0"), "[-2, 1)");
+    assert_eq!(solve("1 / x < 1"), "(-inf, 0) ∪ (1, inf)");
+    // the common factor x - 1 still makes x = 1 undefined
+    assert_eq!(solve("(x^2 - 1) / (x - 1) >= 0"), "[-1, 1) ∪ (1, inf)");
+    assert_eq!(solve("x^-2 > 0"), "(-inf, 0) ∪ (0, inf)");
+    assert_eq!(solve("(x - 1) / (x - 1) = 1"), "(-inf, 1) ∪ (1, inf)");
+  }
+
+  #[test]
+  fn errors() {
+    assert_eq!(rel("x < y").solve("x"), Err(PolynomialError::NotUnivariate));
+    assert_eq!(
+      rel("sin(x) < 0").solve("x"),
+      Err(PolynomialError::NotPolynomial(expr!(sin(x))))
+    );
+    assert_eq!(
+      rel("1 / (x - x) < 0").solve("x"),
+      Err(PolynomialError::DivisionByZero)
+    );
+  }
+}
@@ -0,0 +1,718 @@
+//! Systems of equations, solved exactly when linear and by Newton's method otherwise.
+use super::{Derivative, Eqtn};
+use polynomial::Polynomial;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use {ContextProvider, Error, Expr};
+
+/// Several equations that hold together, e.g. `x + y = 3; x - y = 1`.
+///
+/// Systems are parsed from equations separated by `;` or new lines, ignoring blank lines.
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::{Expr, EqtnSystem};
+/// let system: EqtnSystem = "x + y = 3; x - y = 1".parse().unwrap();
+/// assert_eq!(system.solve_linear(&["x", "y"]).unwrap(), [Expr::num(2.), Expr::num(1.)]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct EqtnSystem(Vec<Eqtn>);
+
+/// The largest number of unknowns of a linear system with symbolic coefficients, whose
+/// determinants are expanded in `n!` terms.
+const MAX_SYMBOLIC_UNKNOWNS: usize = 6;
+
+/// Why a system of equations couldn't be solved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SystemError {
+  /// The equation with this index isn't linear in the unknowns.
+  NotLinear(usize),
+  /// There are infinitely many solutions, as only `rank` of the equations are independent,
+  /// fewer than the unknowns.
+  Underdetermined { rank: usize, unknowns: usize },
+  /// There are no solutions, as the equation with this index contradicts the others.
+  Inconsistent(usize),
+  /// The determinant of a linear system with symbolic coefficients vanishes.
+  Singular,
+  /// A linear system with symbolic coefficients has more unknowns than can be solved exactly,
+  /// at most `MAX_SYMBOLIC_UNKNOWNS`.
+  TooManyUnknowns(usize),
+  /// The method needs as many equations as unknowns.
+  NotSquare { equations: usize, unknowns: usize },
+  /// Newton's method didn't converge, with the largest residual at the last iterate.
+  NoConvergence { iterations: usize, residual: f64 },
+  /// An error evaluating or differentiating the equations.
+  Eval(Error),
+}
+
+impl Display for SystemError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      SystemError::NotLinear(i) => {
+        write!(f, "Equation {} is not linear in the unknowns.", i + 1)
+      }
+      SystemError::Underdetermined { rank, unknowns } => write!(
+        f,
+        "Infinitely many solutions: {} independent equations for {} unknowns.",
+        rank, unknowns
+      ),
+      SystemError::Inconsistent(i) => {
+        write!(
+          f,
+          "No solutions: equation {} contradicts the others.",
+          i + 1
+        )
+      }
+      SystemError::Singular => write!(f, "The determinant of the system vanishes."),
+      SystemError::TooManyUnknowns(unknowns) => write!(
+        f,
+        "Expected at most {} unknowns for symbolic coefficients, got {}.",
+        MAX_SYMBOLIC_UNKNOWNS, unknowns
+      ),
+      SystemError::NotSquare {
+        equations,
+        unknowns,
+      } => write!(
+        f,
+        "Expected as many equations as unknowns, got {} equations for {} unknowns.",
+        equations, unknowns
+      ),
+      SystemError::NoConvergence {
+        iterations,
+        residual,
+      } => write!(
+        f,
+        "Newton's method didn't converge in {} iterations, with residual {}.",
+        iterations, residual
+      ),
+      SystemError::Eval(e) => e.fmt(f),
+    }
+  }
+}
+
+impl From<Error> for SystemError {
+  fn from(err: Error) -> SystemError {
+    SystemError::Eval(err)
+  }
+}
+
+/// The stopping criteria and derivatives of Newton's method for systems.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NewtonOptions {
+  /// The iteration stops once every unknown changes by at most `tolerance * max(1, |x|)`, or
+  /// all the equations hold exactly.
+  pub tolerance: f64,
+  pub max_iterations: usize,
+  /// How the Jacobian is found.
+  pub derivative: Derivative,
+}
+
+impl Default for NewtonOptions {
+  fn default() -> Self {
+    NewtonOptions {
+      tolerance: 1e-12,
+      max_iterations: 100,
+      derivative: Derivative::default(),
+    }
+  }
+}
+
+/// The result of Newton's method for a system of equations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemRoot {
+  /// The values of the unknowns, in the order given.
+  pub values: Vec<f64>,
+  /// Whether the tolerance was reached, rather than the maximum number of iterations or a
+  /// singular Jacobian.
+  pub converged: bool,
+  pub iterations: usize,
+  /// The largest `|lhs - rhs|` at the root.
+  pub residual: f64,
+}
+
+/// Solves `a * x = b` by Gaussian elimination with partial pivoting.
+pub(crate) fn gauss(
+  mut a: Vec<Vec<f64>>,
+  mut b: Vec<f64>,
+  unknowns: usize,
+) -> Result<Vec<f64>, SystemError> {
+  let m = a.len();
+  let scale = a
+    .iter()
+    .flatten()
+    .chain(&b)
+    .fold(0f64, |max, x| max.max(x.abs()));
+  let negligible = |x: f64| x.abs() <= 1e-10 * scale;
+  // the original index of each row
+  let mut rows: Vec<usize> = (0..m).collect();
+  let mut pivots = vec![];
+
+  for col in 0..unknowns {
+    let r = pivots.len();
+    if r == m {
+      break;
+    }
+    // the largest entry, the first of equal ones
+    let p = (r..m).fold(r, |p, i| {
+      if a[i][col].abs() > a[p][col].abs() {
+        i
+      } else {
+        p
+      }
+    });
+    if negligible(a[p][col]) {
+      continue;
+    }
+    a.swap(r, p);
+    b.swap(r, p);
+    rows.swap(r, p);
+    let (top, bottom) = a.split_at_mut(r + 1);
+    let pivot = &top[r][col..unknowns];
+    for (i, row) in (r + 1..).zip(bottom) {
+      let factor = row[col] / pivot[0];
+      for (x, p) in row[col..unknowns].iter_mut().zip(pivot) {
+        *x -= factor * p;
+      }
+      b[i] -= factor * b[r];
+    }
+    pivots.push(col);
+  }
+
+  let rank = pivots.len();
+  if let Some(i) = (rank..m).find(|&i| !negligible(b[i])) {
+    return Err(SystemError::Inconsistent(rows[i]));
+  }
+  if rank < unknowns {
+    return Err(SystemError::Underdetermined { rank, unknowns });
+  }
+  let mut x = vec![0.; unknowns];
+  for k in (0..rank).rev() {
+    let sum: f64 = (k + 1..unknowns).map(|j| a[k][j] * x[j]).sum();
+    x[k] = (b[k] - sum) / a[k][k];
+  }
+  Ok(x)
+}
+
+/// The determinant by expansion along the first row, for matrices of symbolic coefficients.
+fn determinant(a: &[Vec<Polynomial>]) -> Polynomial {
+  if a.is_empty() {
+    return Polynomial::constant(1.);
+  }
+  let mut det = Polynomial::zero();
+  for (j, entry) in a[0].iter().enumerate() {
+    if entry.is_zero() {
+      continue;
+    }
+    let minor: Vec<Vec<Polynomial>> = a[1..]
+      .iter()
+      .map(|row| {
+        row
+          .iter()
+          .enumerate()
+          .filter(|&(k, _)| k != j)
+          .map(|(_, x)| x.clone())
+          .collect()
+      })
+      .collect();
+    let term = entry * &determinant(&minor);
+    det = if j % 2 == 0 {
+      &det + &term
+    } else {
+      &det - &term
+    };
+  }
+  det
+}
+
+impl EqtnSystem {
+  pub fn new(eqtns: Vec<Eqtn>) -> Self {
+    EqtnSystem(eqtns)
+  }
+
+  pub fn eqtns(&self) -> &[Eqtn] {
+    &self.0
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  /// The expressions `lhs - rhs`, which vanish at a solution.
+  fn residuals(&self) -> Vec<Expr> {
+    self
+      .0
+      .iter()
+      .map(|Eqtn(lhs, rhs)| lhs - rhs.clone())
+      .collect()
+  }
+
+  /// Writes the system as `a * x = b` for the `unknowns` `x`, returning the coefficient
+  /// matrix `a`, with a row for each equation, and the right hand sides `b`.
+  ///
+  /// The coefficients are polynomials in the other variables.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotLinear)` with the first equation that isn't a polynomial of degree at most
+  /// 1 in the unknowns, e.g. `x * y = 1` or `sin(x) = 0`.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{EqtnSystem, Polynomial};
+  /// let system: EqtnSystem = "a * x + 2 * y = 1\nx = y - b".parse().unwrap();
+  /// let (a, b) = system.linear_coefficients(&["x", "y"]).unwrap();
+  /// let poly = |s: &str| Polynomial::from_expr(&s.parse().unwrap()).unwrap();
+  /// assert_eq!(a, [[poly("a"), poly("2")], [poly("1"), poly("-1")]]);
+  /// assert_eq!(b, [poly("1"), poly("-b")]);
+  /// ```
+  pub fn linear_coefficients(
+    &self,
+    unknowns: &[&str],
+  ) -> Result<(Vec<Vec<Polynomial>>, Vec<Polynomial>), SystemError> {
+    let mut matrix = vec![];
+    let mut rhs = vec![];
+    for (i, expr) in self.residuals().iter().enumerate() {
+      let poly = Polynomial::from_expr(expr).map_err(|_| SystemError::NotLinear(i))?;
+      let row: Vec<Polynomial> = unknowns.iter().map(|u| poly.coefficient(u, 1)).collect();
+      let linear = unknowns.iter().all(|u| poly.degree_in(u) <= 1)
+        && row
+          .iter()
+          .all(|c| unknowns.iter().all(|u| c.degree_in(u) == 0));
+      if !linear {
+        return Err(SystemError::NotLinear(i));
+      }
+      let constant = unknowns.iter().fold(poly, |poly, u| poly.coefficient(u, 0));
+      matrix.push(row);
+      rhs.push(-constant);
+    }
+    Ok((matrix, rhs))
+  }
+
+  /// Solves a linear system for the `unknowns`, giving their values in the same order.
+  ///
+  /// Systems with numbers as coefficients are solved by Gaussian elimination with partial
+  /// pivoting, and may have more equations than unknowns if they are consistent. Systems with
+  /// symbolic coefficients are solved exactly by Cramer's rule, which needs as many equations
+  /// as unknowns, at most 6 of them, and a determinant that doesn't vanish identically.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotLinear)` if the system isn't linear in the unknowns,
+  /// `Err(Inconsistent)` if it has no solutions and `Err(Underdetermined)` if it has infinitely
+  /// many, and `Err(NotSquare)`, `Err(TooManyUnknowns)` or `Err(Singular)` for symbolic
+  /// coefficients as above.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{EqtnSystem, SystemError};
+  /// let system: EqtnSystem = "x + y = a; x - y = 1".parse().unwrap();
+  /// let solution: Vec<_> = system
+  ///   .solve_linear(&["x", "y"])
+  ///   .unwrap()
+  ///   .iter()
+  ///   .map(|x| x.to_string())
+  ///   .collect();
+  /// assert_eq!(solution, ["0.5 * a + 0.5", "0.5 * a - 0.5"]);
+  ///
+  /// let system: EqtnSystem = "x + y = 1; x + y = 3".parse().unwrap();
+  /// assert_eq!(system.solve_linear(&["x", "y"]), Err(SystemError::Inconsistent(1)));
+  /// ```
+  pub fn solve_linear(&self, unknowns: &[&str]) -> Result<Vec<Expr>, SystemError> {
+    let (a, b) = self.linear_coefficients(unknowns)?;
+    let numbers = |row: &[Polynomial]| -> Option<Vec<f64>> {
+      row.iter().map(Polynomial::as_constant).collect()
+    };
+    let numeric: Option<Vec<Vec<f64>>> = a.iter().map(|row| numbers(row)).collect();
+    if let (Some(a), Some(b)) = (numeric, numbers(&b)) {
+      // integers up to rounding errors are most likely exact
+      let snap = |x: f64| {
+        if (x.round() - x).abs() < 1e-9 * (1. + x.abs()) {
+          x.round()
+        } else {
+          x
+        }
+      };
+      let x = gauss(a, b, unknowns.len())?;
+      return Ok(x.into_iter().map(|x| Expr::num(snap(x))).collect());
+    }
+
+    if a.len() != unknowns.len() {
+      return Err(SystemError::NotSquare {
+        equations: a.len(),
+        unknowns: unknowns.len(),
+      });
+    }
+    if a.len() > MAX_SYMBOLIC_UNKNOWNS {
+      return Err(SystemError::TooManyUnknowns(a.len()));
+    }
+    let mut det = determinant(&a);
+    if det.is_zero() {
+      return Err(SystemError::Singular);
+    }
+    // keep the leading term of the denominator positive
+    let sign = if det.sorted_terms()[0].1 < 0. {
+      -1.
+    } else {
+      1.
+    };
+    det = &det * &Polynomial::constant(sign);
+    // Cramer's rule, x_j = det(a_j) / det(a) with column j of a replaced by b
+    let solution = (0..unknowns.len())
+      .map(|j| {
+        let a_j: Vec<Vec<Polynomial>> = a
+          .iter()
+          .zip(&b)
+          .map(|(row, b)| {
+            let mut row = row.clone();
+            row[j] = b.clone();
+            row
+          })
+          .collect();
+        let det_j = &determinant(&a_j) * &Polynomial::constant(sign);
+        match det.as_constant() {
+          Some(det) => (&det_j * &Polynomial::constant(1. / det)).to_expr(),
+          None if det_j.is_zero() => Expr::num(0.),
+          None => det_j.to_expr() / det.to_expr(),
+        }
+      })
+      .collect();
+    Ok(solution)
+  }
+
+  /// Solves the system by Newton's method, for the unknowns starting from the given values
+  /// and with the other variables and the functions given by `ctx`.
+  ///
+  /// Each step solves the linear system given by the Jacobian of `lhs - rhs`, found as set by
+  /// `options.derivative`.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotSquare)` if there aren't as many equations as unknowns, and `Err(Eval)`
+  /// if the equations can't be evaluated or differentiated. Not converging isn't an error,
+  /// but reported in the result.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Context, EqtnSystem, NewtonOptions};
+  /// let system: EqtnSystem = "x^2 + y^2 = 4; y = exp(x)".parse().unwrap();
+  /// let root = system
+  ///   .solve_newton(&[("x", 1.), ("y", 1.)], Context::new(), &NewtonOptions::default())
+  ///   .unwrap();
+  /// assert!(root.converged && root.residual < 1e-12);
+  /// assert!((root.values[1] - root.values[0].exp()).abs() < 1e-12);
+  /// ```
+  pub fn solve_newton<C: ContextProvider>(
+    &self,
+    start: &[(&str, f64)],
+    ctx: C,
+    options: &NewtonOptions,
+  ) -> Result<SystemRoot, SystemError> {
+    let n = start.len();
+    if self.len() != n {
+      return Err(SystemError::NotSquare {
+        equations: self.len(),
+        unknowns: n,
+      });
+    }
+    let residuals = self.residuals();
+    let jacobian = match options.derivative {
+      Derivative::Symbolic => Some(
+        residuals
+          .iter()
+          .map(|expr| {
+            start
+              .iter()
+              .map(|&(u, _)| expr.derivative_with_context(u, &ctx))
+              .collect::<Result<Vec<_>, _>>()
+          })
+          .collect::<Result<Vec<_>, _>>()?,
+      ),
+      Derivative::Dual => None,
+    };
+
+    let mut vars = start.to_vec();
+    let eval = |vars: &Vec<(&str, f64)>| -> Result<Vec<f64>, Error> {
+      residuals
+        .iter()
+        .map(|expr| expr.eval_with_context((vars, &ctx)))
+        .collect()
+    };
+    let root =
+      |vars: &Vec<(&str, f64)>, converged, iterations| -> Result<SystemRoot, SystemError> {
+        Ok(SystemRoot {
+          values: vars.iter().map(|&(_, x)| x).collect(),
+          converged,
+          iterations,
+          residual: eval(vars)?.iter().fold(0., |max, f| f.abs().max(max)),
+        })
+      };
+
+    for i in 1..=options.max_iterations {
+      let f = eval(&vars)?;
+      if f.iter().all(|&f| f == 0.) {
+        return root(&vars, true, i);
+      }
+      let j: Vec<Vec<f64>> = match jacobian {
+        Some(ref jacobian) => jacobian
+          .iter()
+          .map(|row| {
+            row
+              .iter()
+              .map(|d| d.eval_with_context((&vars, &ctx)))
+              .collect()
+          })
+          .collect::<Result<_, _>>()?,
+        None => residuals
+          .iter()
+          .map(|expr| {
+            vars
+              .iter()
+              .map(|&(u, x)| Ok(expr.eval_dual(u, x, (&vars, &ctx))?.1))
+              .collect()
+          })
+          .collect::<Result<_, Error>>()?,
+      };
+      let step = match gauss(j, f.iter().map(|f| -f).collect(), n) {
+        Ok(step) if step.iter().all(|dx| dx.is_finite()) => step,
+        _ => return root(&vars, false, i),
+      };
+      let mut small = true;
+      for ((_, x), dx) in vars.iter_mut().zip(step) {
+        *x += dx;
+        small &= dx.abs() <= options.tolerance * x.abs().max(1.);
+      }
+      if small {
+        return root(&vars, true, i);
+      }
+    }
+    root(&vars, false, options.max_iterations)
+  }
+
+  /// Solves the system for the unknowns, exactly with [`solve_linear`] if it is linear in
+  /// them, and otherwise with [`solve_newton`] from the given values with the default options.
+  ///
+  /// The starting values and `ctx` are only used by Newton's method.
+  ///
+  /// # Failure
+  ///
+  /// Returns the errors of the methods used, and `Err(NoConvergence)` if Newton's method
+  /// didn't converge.
+  ///
+  /// [`solve_linear`]: #method.solve_linear
+  /// [`solve_newton`]: #method.solve_newton
+  pub fn solve<C: ContextProvider>(
+    &self,
+    start: &[(&str, f64)],
+    ctx: C,
+  ) -> Result<Vec<Expr>, SystemError> {
+    let unknowns: Vec<&str> = start.iter().map(|&(u, _)| u).collect();
+    match self.solve_linear(&unknowns) {
+      Err(SystemError::NotLinear(_)) => {
+        let root = self.solve_newton(start, ctx, &NewtonOptions::default())?;
+        if !root.converged {
+          return Err(SystemError::NoConvergence {
+            iterations: root.iterations,
+            residual: root.residual,
+          });
+        }
+        Ok(root.values.into_iter().map(Expr::num).collect())
+      }
+      res => res,
+    }
+  }
+}
+
+impl FromStr for EqtnSystem {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    s.split(['\n', ';'])
+      .filter(|eqtn| !eqtn.trim().is_empty())
+      .map(str::parse)
+      .collect::<Result<_, _>>()
+      .map(EqtnSystem)
+  }
+}
+
+impl Display for EqtnSystem {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let eqtns: Vec<_> = self.0.iter().map(|eqtn| eqtn.to_string()).collect();
+    write!(f, "{}", eqtns.join("; "))
+  }
+}
+
+/// Serializes as a string, e.g. `"x + y = 3; x - y = 1"`.
+#[cfg(feature = "serde")]
+impl Serialize for EqtnSystem {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for EqtnSystem {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use expr::builtin;
+
+  fn system(s: &str) -> EqtnSystem {
+    s.parse().unwrap()
+  }
+
+  fn solve(s: &str, unknowns: &[&str]) -> Result<Vec<String>, SystemError> {
+    let solution = system(s).solve_linear(unknowns)?;
+    Ok(solution.iter().map(|x| x.to_string()).collect())
+  }
+
+  #[test]
+  fn parse() {
+    let sys = system("x + y = 3;  x - y = 1\n\n  2 * x = z ;");
+    assert_eq!(sys.len(), 3);
+    assert_eq!(sys.eqtns()[2], eqtn!(2 * x = z));
+    assert_eq!(sys.to_string(), "x + y = 3; x - y = 1; 2 * x = z");
+    assert!("x + y = 3; x - 1".parse::<EqtnSystem>().is_err());
+  }
+
+  #[test]
+  fn linear() {
+    assert_eq!(
+      solve(
+        "2 * x + y - z = 8; -3 * x - y + 2 * z = -11; -2 * x + y + 2 * z = -3",
+        &["x", "y", "z"]
+      ),
+      Ok(vec!["2".into(), "3".into(), "-1".into()])
+    );
+    // the first column needs pivoting
+    assert_eq!(
+      solve("y = 1; x + y = 3", &["x", "y"]),
+      Ok(vec!["2".into(), "1".into()])
+    );
+    // consistent extra equations are fine
+    assert_eq!(
+      solve("x = 1; y = 2; x + y = 3", &["x", "y"]),
+      Ok(vec!["1".into(), "2".into()])
+    );
+    assert_eq!(
+      solve("a * x + b * y = c; x = y", &["x", "y"]),
+      Ok(vec!["c / (a + b)".into(), "c / (a + b)".into()])
+    );
+    assert_eq!(
+      solve("x + y = 2 * c; x - y = 0", &["x", "y"]),
+      Ok(vec!["c".into(), "c".into()])
+    );
+  }
+
+  #[test]
+  fn diagnostics() {
+    assert_eq!(
+      solve("x + y = 1; x * y = 2", &["x", "y"]),
+      Err(SystemError::NotLinear(1))
+    );
+    assert_eq!(solve("sin(x) = 1", &["x"]), Err(SystemError::NotLinear(0)));
+    assert_eq!(
+      solve("x + y = 1; 2 * x + 2 * y = 2", &["x", "y"]),
+      Err(SystemError::Underdetermined {
+        rank: 1,
+        unknowns: 2
+      })
+    );
+    assert_eq!(
+      solve("x + y + z = 1; x - y = 0", &["x", "y", "z"]),
+      Err(SystemError::Underdetermined {
+        rank: 2,
+        unknowns: 3
+      })
+    );
+    assert_eq!(
+      solve("x = 1; y = 2; x + y = 4", &["x", "y"]),
+      Err(SystemError::Inconsistent(2))
+    );
+    assert_eq!(
+      solve("a * x + a * y = 1; x + y = 2", &["x", "y"]),
+      Err(SystemError::Singular)
+    );
+    assert_eq!(
+      solve("a * x = 1", &["x", "y"]),
+      Err(SystemError::NotSquare {
+        equations: 1,
+        unknowns: 2
+      })
+    );
+    // x_k = a for 7 unknowns
+    let eqtns: Vec<_> = (0..7).map(|k| format!("x{} = a", k)).collect();
+    let unknowns: Vec<_> = (0..7).map(|k| format!("x{}", k)).collect();
+    let unknowns: Vec<_> = unknowns.iter().map(String::as_str).collect();
+    assert_eq!(
+      solve(&eqtns.join("; "), &unknowns),
+      Err(SystemError::TooManyUnknowns(7))
+    );
+    assert_eq!(
+      solve(&eqtns[..6].join("; "), &unknowns[..6]),
+      Ok(vec!["a".into(); 6])
+    );
+  }
+
+  #[test]
+  fn newton() {
+    let sys = system("x^2 + y^2 = 2; x * y = 1 - z");
+    let mut ctx = builtin();
+    ctx.var("z", 0.);
+    for &derivative in &[Derivative::Symbolic, Derivative::Dual] {
+      let options = NewtonOptions {
+        derivative,
+        ..Default::default()
+      };
+      let root = sys
+        .solve_newton(&[("x", 2.), ("y", 0.5)], &ctx, &options)
+        .unwrap();
+      assert!(root.converged, "{:?}", root);
+      assert!((root.values[0] - 1.).abs() < 1e-6 && (root.values[1] - 1.).abs() < 1e-6);
+    }
+
+    // a singular Jacobian stops the iteration
+    let root = sys
+      .solve_newton(&[("x", 0.), ("y", 0.)], &ctx, &NewtonOptions::default())
+      .unwrap();
+    assert!(!root.converged);
+    assert_eq!(root.iterations, 1);
+
+    assert!(matches!(
+      system("x^2 + 1 = 0").solve(&[("x", 0.5)], &ctx),
+      Err(SystemError::NoConvergence {
+        iterations: 100,
+        ..
+      })
+    ));
+    assert_eq!(
+      system("x + y = 3; x - y = 1").solve(&[("x", 0.), ("y", 0.)], &ctx),
+      Ok(vec![Expr::num(2.), Expr::num(1.)])
+    );
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn json_round_trip() {
+    use serde_json;
+    let sys = system("x + y = 3; x - y = 1");
+    let json = serde_json::to_string(&sys).unwrap();
+    assert_eq!(json, r#""x + y = 3; x - y = 1""#);
+    assert_eq!(serde_json::from_str::<EqtnSystem>(&json).unwrap(), sys);
+  }
+}
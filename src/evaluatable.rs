@@ -3,6 +3,9 @@
 /// `bind` methods return closures.
 ///
 /// TODO: when #91611 is stabilised, rewrite to use the trait below instead.
+///
+/// The closures evaluate what the `prepared` method of the type gives, so that the work that
+/// only depends on the term is done once when it is bound.
 #[macro_export]
 macro_rules! Evaluatable_Trait {
   ($name:ident $Res:ty) => {
@@ -43,8 +46,9 @@ macro_rules! Evaluatable_Trait {
       {
         self.check_context(((var, 0.), &ctx))?;
         let var = var.to_owned();
+        let this = self.prepared();
         Ok(move |x| {
-          self
+          this
             .eval_with_context(((&var, x), &ctx))
             .expect("Expr::bind")
         })
@@ -87,8 +91,9 @@ macro_rules! Evaluatable_Trait {
         self.check_context(([(var1, 0.), (var2, 0.)], &ctx))?;
         let var1 = var1.to_owned();
         let var2 = var2.to_owned();
+        let this = self.prepared();
         Ok(move |x, y| {
-          self
+          this
             .eval_with_context(([(&var1, x), (&var2, y)], &ctx))
             .expect("Expr::bind2")
         })
@@ -134,8 +139,9 @@ macro_rules! Evaluatable_Trait {
         let var1 = var1.to_owned();
         let var2 = var2.to_owned();
         let var3 = var3.to_owned();
+        let this = self.prepared();
         Ok(move |x, y, z| {
-          self
+          this
             .eval_with_context(([(&var1, x), (&var2, y), (&var3, z)], &ctx))
             .expect("Expr::bind3")
         })
@@ -184,8 +190,9 @@ macro_rules! Evaluatable_Trait {
         let var2 = var2.to_owned();
         let var3 = var3.to_owned();
         let var4 = var4.to_owned();
+        let this = self.prepared();
         Ok(move |x1, x2, x3, x4| {
-          self
+          this
             .eval_with_context(([(&var1, x1), (&var2, x2), (&var3, x3), (&var4, x4)], &ctx))
             .expect("Expr::bind4")
         })
@@ -240,8 +247,9 @@ macro_rules! Evaluatable_Trait {
         let var3 = var3.to_owned();
         let var4 = var4.to_owned();
         let var5 = var5.to_owned();
+        let this = self.prepared();
         Ok(move |x1, x2, x3, x4, x5| {
-          self
+          this
             .eval_with_context((
               [
                 (&var1, x1),
@@ -291,8 +299,9 @@ macro_rules! Evaluatable_Trait {
           &ctx,
         ))?;
         let vars = vars.iter().map(|v| v.to_owned()).collect::<Vec<_>>();
+        let this = self.prepared();
         Ok(move |x: &[f64]| {
-          self
+          this
             .eval_with_context((
               vars
                 .iter()
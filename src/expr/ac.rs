@@ -0,0 +1,359 @@
+//! Matching of expressions modulo associativity and commutativity (AC).
+use super::symbolic::Substitutions;
+use super::{Expr, Operation, Token};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::RangeInclusive;
+
+/// How patterns are matched against expressions, e.g. in
+/// [`Expr::replace_with`](struct.Expr.html#method.replace_with).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Matching {
+  /// Tokens have to match position by position.
+  #[default]
+  Exact,
+  /// Sums, products, `max` and `min` match modulo associativity and commutativity, e.g.
+  /// `0 + a` matches `x + 0` and `a * b` matches `x * y * z`.
+  AC,
+}
+
+/// An expression as a tree, with nested sums, products, `max` and `min` flattened into a single
+/// node with all of the operands.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Tree(Token, Vec<Tree>);
+
+/// Whether the token is an associative and commutative operation.
+fn is_ac(tkn: &Token) -> bool {
+  use self::Operation::*;
+  use self::Token::*;
+  match tkn {
+    Binary(Plus) | Binary(Times) => true,
+    Func(name, _) => name == "max" || name == "min",
+    _ => false,
+  }
+}
+
+/// Whether two tokens are the same AC operation, ignoring the number of arguments.
+fn same_ac(a: &Token, b: &Token) -> bool {
+  match (a, b) {
+    (Token::Func(a, _), Token::Func(b, _)) => a == b,
+    _ => a == b,
+  }
+}
+
+impl Tree {
+  fn from_expr(expr: &Expr) -> Tree {
+    expr.fold_expr(|children: Vec<Tree>, tkn| {
+      if !is_ac(&tkn) {
+        return Tree(tkn, children);
+      }
+      let tkn = match tkn {
+        Token::Func(name, _) => Token::Func(name, None),
+        tkn => tkn,
+      };
+      let mut flat = vec![];
+      for child in children {
+        if same_ac(&child.0, &tkn) {
+          flat.extend(child.1);
+        } else {
+          flat.push(child);
+        }
+      }
+      Tree(tkn, flat)
+    })
+  }
+
+  /// An AC node of `tkn` with the given operands, or the operand itself if there is only one.
+  fn ac_node(tkn: &Token, mut operands: Vec<Tree>) -> Tree {
+    if operands.len() == 1 {
+      operands.pop().unwrap()
+    } else {
+      Tree(tkn.clone(), operands)
+    }
+  }
+
+  fn push_rpn(&self, rpn: &mut Vec<Token>) {
+    let Tree(tkn, children) = self;
+    match tkn {
+      Token::Binary(_) if is_ac(tkn) => {
+        for (i, child) in children.iter().enumerate() {
+          child.push_rpn(rpn);
+          if i > 0 {
+            rpn.push(tkn.clone());
+          }
+        }
+      }
+      Token::Func(name, _) => {
+        children.iter().for_each(|child| child.push_rpn(rpn));
+        rpn.push(Token::Func(name.clone(), Some(children.len())));
+      }
+      _ => {
+        children.iter().for_each(|child| child.push_rpn(rpn));
+        rpn.push(tkn.clone());
+      }
+    }
+  }
+
+  fn to_expr(&self) -> Expr {
+    let mut rpn = vec![];
+    self.push_rpn(&mut rpn);
+    Expr(rpn)
+  }
+
+  /// The tree with the operands of AC nodes sorted, such that trees equal modulo AC are equal.
+  fn canonical(&self) -> Tree {
+    let mut children: Vec<_> = self.1.iter().map(Tree::canonical).collect();
+    if is_ac(&self.0) {
+      children.sort();
+    }
+    Tree(self.0.clone(), children)
+  }
+}
+
+type Bindings = HashMap<String, Tree>;
+
+/// Binds `name` to `value`, or checks that it's already bound to an equal value.
+fn bind(name: &str, value: Tree, mut bindings: Bindings) -> Option<Bindings> {
+  match bindings.get(name) {
+    Some(prev) if prev.canonical() != value.canonical() => None,
+    Some(_) => Some(bindings),
+    None => {
+      bindings.insert(name.into(), value);
+      Some(bindings)
+    }
+  }
+}
+
+/// All extensions of `bindings` with which `pat` matches `tree`.
+fn match_tree(pat: &Tree, tree: &Tree, bindings: Bindings) -> Vec<Bindings> {
+  match pat {
+    Tree(Token::Var(name), _) => bind(name, tree.clone(), bindings).into_iter().collect(),
+    Tree(tkn, pats) if is_ac(tkn) && same_ac(tkn, &tree.0) => {
+      match_ac(tkn, pats, tree.1.iter().collect(), bindings)
+    }
+    Tree(tkn, pats) if *tkn == tree.0 && pats.len() == tree.1.len() => pats
+      .iter()
+      .zip(&tree.1)
+      .fold(vec![bindings], |all, (pat, tree)| {
+        all
+          .into_iter()
+          .flat_map(|bindings| match_tree(pat, tree, bindings))
+          .collect()
+      }),
+    _ => vec![],
+  }
+}
+
+/// The largest number of subsets of the operands a variable of an AC pattern is tried against,
+/// and of the matches kept for each AC node, so that a pattern with several variables among
+/// many operands finds the matches binding variables to the smallest subsets rather than
+/// trying exponentially many ways. All subsets of up to 12 operands are tried.
+const MAX_AC_SPLITS: usize = 1 << 12;
+
+/// Calls `f` with the subsets of `0..n` of the sizes in `sizes`, smallest first, and the indices
+/// left out of each, until `f` returns `false`.
+fn subsets<F>(n: usize, sizes: RangeInclusive<usize>, mut f: F)
+where
+  F: FnMut(&[usize], &[usize]) -> bool,
+{
+  for k in sizes {
+    let mut used: Vec<usize> = (0..k).collect();
+    loop {
+      let left: Vec<_> = (0..n).filter(|i| !used.contains(i)).collect();
+      if !f(&used, &left) {
+        return;
+      }
+      // the next subset of size k in lexicographic order
+      match (0..k).rev().find(|&i| used[i] < n - k + i) {
+        Some(i) => {
+          used[i] += 1;
+          for j in i + 1..k {
+            used[j] = used[j - 1] + 1;
+          }
+        }
+        None => break,
+      }
+    }
+  }
+}
+
+/// All extensions of `bindings` with which the operands `pats` of an AC node match the operands
+/// `trees`, with every pattern matching at least one operand.
+fn match_ac(tkn: &Token, pats: &[Tree], trees: Vec<&Tree>, bindings: Bindings) -> Vec<Bindings> {
+  // match operands that aren't variables first, so variables can take whatever is left
+  let next = pats
+    .iter()
+    .position(|pat| !matches!(pat.0, Token::Var(_)))
+    .unwrap_or(0);
+  let pat = match pats.get(next) {
+    Some(pat) => pat,
+    None if trees.is_empty() => return vec![bindings],
+    None => return vec![],
+  };
+  let rest: Vec<_> = pats[..next]
+    .iter()
+    .chain(&pats[next + 1..])
+    .cloned()
+    .collect();
+  if trees.len() < pats.len() {
+    return vec![];
+  }
+
+  let mut res = vec![];
+  if let Token::Var(name) = &pat.0 {
+    let take = |used: &[usize], left: &[usize], res: &mut Vec<Bindings>| {
+      let value = Tree::ac_node(tkn, used.iter().map(|&i| trees[i].clone()).collect());
+      if let Some(bindings) = bind(name, value, bindings.clone()) {
+        let left = left.iter().map(|&i| trees[i]).collect();
+        res.extend(match_ac(tkn, &rest, left, bindings));
+      }
+    };
+    if rest.is_empty() {
+      // the last variable takes all of the operands left
+      let all: Vec<_> = (0..trees.len()).collect();
+      take(&all, &[], &mut res);
+    } else {
+      // a variable takes non-empty subsets of the operands, leaving enough for the others
+      let mut tried = 0;
+      subsets(trees.len(), 1..=trees.len() - rest.len(), |used, left| {
+        take(used, left, &mut res);
+        tried += 1;
+        tried < MAX_AC_SPLITS && res.len() < MAX_AC_SPLITS
+      });
+      res.truncate(MAX_AC_SPLITS);
+    }
+  } else {
+    for (i, tree) in trees.iter().enumerate() {
+      for bindings in match_tree(pat, tree, bindings.clone()) {
+        let mut left = trees.clone();
+        left.remove(i);
+        res.extend(match_ac(tkn, &rest, left, bindings));
+      }
+    }
+  }
+  res
+}
+
+impl Expr {
+  /// Matches `term` against the whole of self modulo associativity and commutativity of `+`,
+  /// `*`, `max` and `min`, returning every consistent way of binding the variables of `term`.
+  ///
+  /// Nested sums, products, `max` and `min` are flattened, and a variable that is an operand
+  /// of one of these can match several operands at once. The result is empty if `term`
+  /// doesn't match. With more than 12 operands, a variable is only tried against the 4096
+  /// smallest subsets of them, and at most 4096 matches are found.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Expr;
+  /// let expr: Expr = "x + 0".parse().unwrap();
+  /// let mtch: Expr = "0 + a".parse().unwrap();
+  /// assert_eq!(expr.extract_ac(&mtch), vec![[("a", "x")].into()]);
+  ///
+  /// let expr: Expr = "x * (y * z)".parse().unwrap();
+  /// let mtch: Expr = "a * b".parse().unwrap();
+  /// assert_eq!(expr.extract_ac(&mtch).len(), 6);
+  /// ```
+  pub fn extract_ac(&self, term: &Expr) -> Vec<Substitutions> {
+    let mut seen = BTreeSet::new();
+    let mut res = vec![];
+    for bindings in match_tree(
+      &Tree::from_expr(term),
+      &Tree::from_expr(self),
+      Bindings::new(),
+    ) {
+      // skip bindings equal to earlier ones modulo AC
+      let canonical: BTreeMap<_, _> = bindings
+        .iter()
+        .map(|(name, tree)| (name.clone(), tree.canonical()))
+        .collect();
+      if !seen.insert(canonical) {
+        continue;
+      }
+      let mut subs = Substitutions::new();
+      for (name, tree) in bindings {
+        subs.insert(name, tree.to_expr());
+      }
+      res.push(subs);
+    }
+    res
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(s: &str) -> Expr {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn commuted_operands() {
+    assert_eq!(
+      expr!(x + 0).extract_ac(&expr!(0 + a)),
+      vec![[("a", "x")].into()]
+    );
+    assert_eq!(expr!(x + 0).extract(&expr!(0 + a)).ok(), None);
+    assert!(expr!(x - 0).extract_ac(&expr!(0 - a)).is_empty());
+  }
+
+  #[test]
+  fn flattened_operands() {
+    let subs = expr!(((a + b) + c) + 5).extract_ac(&expr!(x + (y + 5)));
+    assert_eq!(subs.len(), 6);
+    assert!(subs.contains(&[("x", "a"), ("y", "b + c")].into()));
+    assert!(subs.contains(&[("x", "a + c"), ("y", "b")].into()));
+
+    let subs = expr!(max(1, max(x, 2), y)).extract_ac(&expr!(max(u, 2, v)));
+    assert_eq!(subs.len(), 6);
+    assert!(subs.contains(&[("u", "max(1, y)"), ("v", "x")].into()));
+  }
+
+  #[test]
+  fn consistent_variables() {
+    let subs = expr!(x * y * x).extract_ac(&expr!(a * a * b));
+    assert_eq!(subs, vec![[("a", "x"), ("b", "y")].into()]);
+
+    // bound values are compared modulo AC as well
+    let subs = expr!((x + y) * (y + x)).extract_ac(&expr!(a * a));
+    assert_eq!(subs, vec![[("a", "x + y")].into()]);
+
+    assert!(expr!(x * y * z).extract_ac(&expr!(a * a * b)).is_empty());
+  }
+
+  #[test]
+  fn many_operands() {
+    let terms: Vec<_> = (0..70).map(|i| format!("x{}", i)).collect();
+    let expr = parse(&format!("5 + {}", terms.join(" + ")));
+    let subs = expr.extract_ac(&expr!(p + q));
+    assert_eq!(subs.len(), 1 << 12);
+    assert_eq!(subs[0]["p"], expr!(5));
+    let subs = expr.extract_ac(&expr!(p + 5));
+    assert_eq!(subs.len(), 1);
+    assert_eq!(subs[0]["p"], parse(&terms.join(" + ")));
+
+    let subs = parse(&terms[..12].join(" + ")).extract_ac(&expr!(p + q));
+    assert_eq!(subs.len(), (1 << 12) - 2);
+
+    // past 12 operands, variables take the smallest subsets first
+    let expr = parse(&terms[..13].join(" + "));
+    let subs = expr.extract_ac(&expr!(p + q));
+    assert_eq!(subs.len(), 1 << 12);
+    assert_eq!(subs[0]["p"], expr!(x0));
+    assert_eq!(subs[0]["q"], parse(&terms[1..13].join(" + ")));
+    let subs = expr.extract_ac(&expr!(p + q + r));
+    assert_eq!(subs.len(), 1 << 12);
+    assert!(subs.iter().all(|s| s["p"].0.len() == 1));
+  }
+
+  #[test]
+  fn nested_patterns() {
+    let expr = parse("2 * (x + 7) * (9 + 2 * x)");
+    let subs = expr.extract_ac(&expr!(a * (b + 2 * c)));
+    assert_eq!(
+      subs,
+      vec![[("a", "2 * (x + 7)"), ("b", "9"), ("c", "x")].into()]
+    );
+  }
+}
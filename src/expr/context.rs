@@ -1,5 +1,14 @@
+use super::extra_math::{
+  clamp, mean_array, median_array, norm_array, product_array, quantile_array, softmax_lse_array,
+  stddev_array, sum_array, var_array,
+};
+use super::func_info::{
+  Arity, FuncInfo, BUILTIN_ANTIDERIVATIVES, BUILTIN_INFO, BUILTIN_VARIADIC_DERIVATIVES,
+};
 use super::FuncEvalError;
 use fnv::FnvHashMap;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::rc::Rc;
 
 type ContextHashMap<K, V> = FnvHashMap<K, V>;
@@ -67,6 +76,17 @@ pub trait ContextProvider {
   fn eval_func(&self, _: &str, _: &[f64]) -> Result<f64, FuncEvalError> {
     Err(FuncEvalError::UnknownFunction)
   }
+  /// Information about the function, such as the number of arguments it accepts, if known.
+  fn func_info(&self, _: &str) -> Option<FuncInfo> {
+    None
+  }
+  /// Whether the function always gives the same result for the same arguments.
+  ///
+  /// Calls to impure functions, such as random number generators, are never evaluated ahead
+  /// of time, e.g. by [`Expr::fold_constants`](struct.Expr.html#method.fold_constants).
+  fn is_pure(&self, name: &str) -> bool {
+    self.func_info(name).is_none_or(|info| info.pure)
+  }
 }
 
 #[doc(hidden)]
@@ -86,21 +106,33 @@ pub fn builtin<'a>() -> Context<'a> {
   Context::new()
 }
 
-impl<'a, T: ContextProvider> ContextProvider for &'a T {
+impl<T: ContextProvider + ?Sized> ContextProvider for &T {
   fn get_var(&self, name: &str) -> Option<f64> {
-    (&**self).get_var(name)
+    (**self).get_var(name)
   }
   fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, FuncEvalError> {
-    (&**self).eval_func(name, args)
+    (**self).eval_func(name, args)
+  }
+  fn func_info(&self, name: &str) -> Option<FuncInfo> {
+    (**self).func_info(name)
+  }
+  fn is_pure(&self, name: &str) -> bool {
+    (**self).is_pure(name)
   }
 }
 
-impl<'a, T: ContextProvider> ContextProvider for &'a mut T {
+impl<T: ContextProvider + ?Sized> ContextProvider for &mut T {
   fn get_var(&self, name: &str) -> Option<f64> {
-    (&**self).get_var(name)
+    (**self).get_var(name)
   }
   fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, FuncEvalError> {
-    (&**self).eval_func(name, args)
+    (**self).eval_func(name, args)
+  }
+  fn func_info(&self, name: &str) -> Option<FuncInfo> {
+    (**self).func_info(name)
+  }
+  fn is_pure(&self, name: &str) -> bool {
+    (**self).is_pure(name)
   }
 }
 
@@ -114,6 +146,12 @@ impl<T: ContextProvider, S: ContextProvider> ContextProvider for (T, S) {
       e => e,
     }
   }
+  fn func_info(&self, name: &str) -> Option<FuncInfo> {
+    self.0.func_info(name).or_else(|| self.1.func_info(name))
+  }
+  fn is_pure(&self, name: &str) -> bool {
+    self.0.is_pure(name) && self.1.is_pure(name)
+  }
 }
 
 impl<S: AsRef<str>> ContextProvider for (S, f64) {
@@ -146,16 +184,33 @@ where
   }
 }
 
+/// Evaluates the function in the first context that knows it, keeping any errors it reports.
+fn eval_first<C: ContextProvider>(
+  ctxs: &[C],
+  name: &str,
+  args: &[f64],
+) -> Result<f64, FuncEvalError> {
+  for ctx in ctxs {
+    match ctx.eval_func(name, args) {
+      Err(FuncEvalError::UnknownFunction) => continue,
+      res => return res,
+    }
+  }
+  Err(FuncEvalError::UnknownFunction)
+}
+
 impl<C: ContextProvider> ContextProvider for Vec<C> {
   fn get_var(&self, name: &str) -> Option<f64> {
     self.iter().find_map(|ctx| ctx.get_var(name))
   }
   fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, FuncEvalError> {
-    self
-      .iter()
-      .fold(Err(FuncEvalError::UnknownFunction), |res, ctx| {
-        res.or(ctx.eval_func(name, args))
-      })
+    eval_first(self, name, args)
+  }
+  fn func_info(&self, name: &str) -> Option<FuncInfo> {
+    self.iter().find_map(|ctx| ctx.func_info(name))
+  }
+  fn is_pure(&self, name: &str) -> bool {
+    self.iter().all(|ctx| ctx.is_pure(name))
   }
 }
 
@@ -164,11 +219,13 @@ impl<C: ContextProvider, const N: usize> ContextProvider for [C; N] {
     self.iter().find_map(|ctx| ctx.get_var(name))
   }
   fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, FuncEvalError> {
-    self
-      .iter()
-      .fold(Err(FuncEvalError::UnknownFunction), |res, ctx| {
-        res.or(ctx.eval_func(name, args))
-      })
+    eval_first(self, name, args)
+  }
+  fn func_info(&self, name: &str) -> Option<FuncInfo> {
+    self.iter().find_map(|ctx| ctx.func_info(name))
+  }
+  fn is_pure(&self, name: &str) -> bool {
+    self.iter().all(|ctx| ctx.is_pure(name))
   }
 }
 
@@ -190,7 +247,7 @@ impl<C: ContextProvider, const N: usize> ContextProvider for [C; N] {
 #[derive(Clone)]
 pub struct Context<'a> {
   pub(crate) vars: ContextHashMap<String, f64>,
-  pub(crate) funcs: ContextHashMap<String, GuardedFunc<'a>>,
+  pub(crate) funcs: ContextHashMap<String, (GuardedFunc<'a>, Rc<FuncInfo>)>,
 }
 
 impl<'a> Context<'a> {
@@ -225,6 +282,16 @@ impl<'a> Context<'a> {
         ctx.func2("atan2", f64::atan2);
         ctx.funcn("max", max_array, 1..);
         ctx.funcn("min", min_array, 1..);
+        ctx.funcn("sum", sum_array, 1..);
+        ctx.funcn("product", product_array, 1..);
+        ctx.funcn("mean", mean_array, 1..);
+        ctx.funcn("median", median_array, 1..);
+        ctx.funcn("var", var_array, 1..);
+        ctx.funcn("stddev", stddev_array, 1..);
+        ctx.funcn("quantile", quantile_array, 2..);
+        ctx.funcn("norm", norm_array, 1..);
+        ctx.funcn("softmax_lse", softmax_lse_array, 1..);
+        ctx.funcn("clamp", |xs: &[f64]| clamp(xs[0], xs[1], xs[2]), 3);
 
         ctx.func("cbrt", f64::cbrt);
         ctx.func("exp2", f64::exp2);
@@ -238,6 +305,23 @@ impl<'a> Context<'a> {
         ctx.func2("hypot", f64::hypot);
         ctx.func3("mul_add", f64::mul_add);
 
+        for &(name, doc, derivatives, inverse) in BUILTIN_INFO {
+          let parse = |s: &str| s.parse().expect("built-in function info did not parse");
+          let info = ctx.info_mut(name).expect("built-in function info for unknown function");
+          info.doc = Some(doc.into());
+          info.derivatives = derivatives.iter().map(|d| parse(d)).collect();
+          info.inverse = inverse.map(parse);
+        }
+        for &(name, derivatives) in BUILTIN_VARIADIC_DERIVATIVES {
+          let info = ctx.info_mut(name).expect("built-in derivatives for unknown function");
+          info.variadic_derivatives = Some(derivatives);
+        }
+        for &(name, antiderivative) in BUILTIN_ANTIDERIVATIVES {
+          let info = ctx.info_mut(name).expect("built-in antiderivative for unknown function");
+          info.antiderivative =
+            Some(antiderivative.parse().expect("built-in antiderivative did not parse"));
+        }
+
         ctx
     });
 
@@ -264,17 +348,7 @@ impl<'a> Context<'a> {
     S: Into<String>,
     F: Fn(f64) -> f64 + 'a,
   {
-    self.funcs.insert(
-      name.into(),
-      Rc::new(move |args: &[f64]| {
-        if args.len() == 1 {
-          Ok(func(args[0]))
-        } else {
-          Err(FuncEvalError::NumberArgs(1))
-        }
-      }),
-    );
-    self
+    self.funcn(name, move |xs| func(xs[0]), 1)
   }
 
   /// Adds a new function of two arguments.
@@ -283,17 +357,7 @@ impl<'a> Context<'a> {
     S: Into<String>,
     F: Fn(f64, f64) -> f64 + 'a,
   {
-    self.funcs.insert(
-      name.into(),
-      Rc::new(move |args: &[f64]| {
-        if args.len() == 2 {
-          Ok(func(args[0], args[1]))
-        } else {
-          Err(FuncEvalError::NumberArgs(2))
-        }
-      }),
-    );
-    self
+    self.funcn(name, move |xs| func(xs[0], xs[1]), 2)
   }
 
   /// Adds a new function of three arguments.
@@ -302,17 +366,7 @@ impl<'a> Context<'a> {
     S: Into<String>,
     F: Fn(f64, f64, f64) -> f64 + 'a,
   {
-    self.funcs.insert(
-      name.into(),
-      Rc::new(move |args: &[f64]| {
-        if args.len() == 3 {
-          Ok(func(args[0], args[1], args[2]))
-        } else {
-          Err(FuncEvalError::NumberArgs(3))
-        }
-      }),
-    );
-    self
+    self.funcn(name, move |xs| func(xs[0], xs[1], xs[2]), 3)
   }
 
   /// Adds a new function of a variable number of arguments.
@@ -338,9 +392,54 @@ impl<'a> Context<'a> {
     F: Fn(&[f64]) -> f64 + 'a,
     N: ArgGuard,
   {
-    self.funcs.insert(name.into(), n_args.to_arg_guard(func));
+    let info = FuncInfo::new(n_args.to_arity());
+    self
+      .funcs
+      .insert(name.into(), (n_args.to_arg_guard(func), Rc::new(info)));
     self
   }
+
+  /// Information about the function `name`, if it exists.
+  pub fn info(&self, name: &str) -> Option<&FuncInfo> {
+    self.funcs.get(name).map(|(_, info)| &**info)
+  }
+
+  /// Mutable information about the function `name`, if it exists, e.g. to describe it or to
+  /// mark it as impure.
+  ///
+  /// # Example
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Context, ContextProvider};
+  ///
+  /// let mut ctx = Context::empty();
+  /// ctx.func("double", |x| 2. * x);
+  /// let info = ctx.info_mut("double").unwrap();
+  /// info.doc = Some("Twice the argument".into());
+  /// info.derivatives = vec!["2".parse().unwrap()];
+  ///
+  /// assert_eq!(ctx.info("double").unwrap().arity.to_string(), "1");
+  /// assert!(ctx.is_pure("double"));
+  /// ```
+  pub fn info_mut(&mut self, name: &str) -> Option<&mut FuncInfo> {
+    self.funcs.get_mut(name).map(|(_, info)| Rc::make_mut(info))
+  }
+
+  /// Iterates over the variables/constants and their values.
+  pub fn vars(&self) -> impl Iterator<Item = (&str, f64)> {
+    self
+      .vars
+      .iter()
+      .map(|(name, &value)| (name.as_str(), value))
+  }
+
+  /// Iterates over the functions and information about them.
+  pub fn funcs(&self) -> impl Iterator<Item = (&str, &FuncInfo)> {
+    self
+      .funcs
+      .iter()
+      .map(|(name, (_, info))| (name.as_str(), &**info))
+  }
 }
 
 impl<'a> Default for Context<'a> {
@@ -366,62 +465,57 @@ pub(crate) type GuardedFunc<'a> = Rc<dyn Fn(&[f64]) -> Result<f64, FuncEvalError
 /// ctx.funcn("sum", |xs| xs.iter().sum(), ..);
 /// ```
 pub trait ArgGuard {
-  fn to_arg_guard<'a, F: Fn(&[f64]) -> f64 + 'a>(self, func: F) -> GuardedFunc<'a>;
+  /// The allowed number of arguments.
+  fn to_arity(&self) -> Arity;
+
+  fn to_arg_guard<'a, F: Fn(&[f64]) -> f64 + 'a>(self, func: F) -> GuardedFunc<'a>
+  where
+    Self: Sized,
+  {
+    let arity = self.to_arity();
+    Rc::new(move |args: &[f64]| arity.check(args.len()).map(|_| func(args)))
+  }
+}
+
+impl ArgGuard for Arity {
+  fn to_arity(&self) -> Arity {
+    *self
+  }
 }
 
 impl ArgGuard for usize {
-  fn to_arg_guard<'a, F: Fn(&[f64]) -> f64 + 'a>(self, func: F) -> GuardedFunc<'a> {
-    Rc::new(move |args: &[f64]| {
-      if args.len() == self {
-        Ok(func(args))
-      } else {
-        Err(FuncEvalError::NumberArgs(1))
-      }
-    })
+  fn to_arity(&self) -> Arity {
+    Arity::exact(*self)
   }
 }
 
 impl ArgGuard for std::ops::RangeFrom<usize> {
-  fn to_arg_guard<'a, F: Fn(&[f64]) -> f64 + 'a>(self, func: F) -> GuardedFunc<'a> {
-    Rc::new(move |args: &[f64]| {
-      if args.len() >= self.start {
-        Ok(func(args))
-      } else {
-        Err(FuncEvalError::TooFewArguments)
-      }
-    })
+  fn to_arity(&self) -> Arity {
+    Arity::at_least(self.start)
   }
 }
 
 impl ArgGuard for std::ops::RangeTo<usize> {
-  fn to_arg_guard<'a, F: Fn(&[f64]) -> f64 + 'a>(self, func: F) -> GuardedFunc<'a> {
-    Rc::new(move |args: &[f64]| {
-      if args.len() < self.end {
-        Ok(func(args))
-      } else {
-        Err(FuncEvalError::TooManyArguments)
-      }
-    })
+  fn to_arity(&self) -> Arity {
+    Arity {
+      min: 0,
+      max: Some(self.end.saturating_sub(1)),
+    }
   }
 }
 
 impl ArgGuard for std::ops::Range<usize> {
-  fn to_arg_guard<'a, F: Fn(&[f64]) -> f64 + 'a>(self, func: F) -> GuardedFunc<'a> {
-    Rc::new(move |args: &[f64]| {
-      if args.len() >= self.start && args.len() < self.end {
-        Ok(func(args))
-      } else if args.len() < self.start {
-        Err(FuncEvalError::TooFewArguments)
-      } else {
-        Err(FuncEvalError::TooManyArguments)
-      }
-    })
+  fn to_arity(&self) -> Arity {
+    Arity {
+      min: self.start,
+      max: Some(self.end.saturating_sub(1)),
+    }
   }
 }
 
 impl ArgGuard for std::ops::RangeFull {
-  fn to_arg_guard<'a, F: Fn(&[f64]) -> f64 + 'a>(self, func: F) -> GuardedFunc<'a> {
-    Rc::new(move |args: &[f64]| Ok(func(args)))
+  fn to_arity(&self) -> Arity {
+    Arity::at_least(0)
   }
 }
 
@@ -433,7 +527,39 @@ impl<'a> ContextProvider for Context<'a> {
     self
       .funcs
       .get(name)
-      .map_or(Err(FuncEvalError::UnknownFunction), |f| f(args))
+      .map_or(Err(FuncEvalError::UnknownFunction), |(f, _)| f(args))
+  }
+  fn func_info(&self, name: &str) -> Option<FuncInfo> {
+    self.info(name).cloned()
+  }
+  fn is_pure(&self, name: &str) -> bool {
+    self.info(name).is_none_or(|info| info.pure)
+  }
+}
+
+/// Serializes the variables, sorted by name, as a map from names to values. Functions can't be
+/// serialized and are skipped.
+#[cfg(feature = "serde")]
+impl<'a> Serialize for Context<'a> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let vars: std::collections::BTreeMap<_, _> = self.vars().collect();
+    vars.serialize(serializer)
+  }
+}
+
+/// Deserializes a map from names to values into a context with the built-in constants and
+/// functions, see [`Context::new`](#method.new). Values can be numbers or expressions, which
+/// are evaluated like [`de::as_f64`](de/fn.as_f64.html).
+#[cfg(feature = "serde")]
+impl<'de, 'a> Deserialize<'de> for Context<'a> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let vars = std::collections::BTreeMap::<String, super::Expr>::deserialize(deserializer)?;
+    let mut ctx = Context::new();
+    for (name, expr) in vars {
+      let value = expr.eval().map_err(de::Error::custom)?;
+      ctx.var(name, value);
+    }
+    Ok(ctx)
   }
 }
 
@@ -452,4 +578,91 @@ mod tests {
       ctx.func2("g", |x, y| x + y);
     }
   }
+
+  #[test]
+  fn introspect_context() {
+    let mut ctx = Context::empty();
+    ctx.var("x", 1.).funcn("f", |xs| xs[0], 1..3);
+    assert_eq!(ctx.vars().collect::<Vec<_>>(), vec![("x", 1.)]);
+
+    let funcs: Vec<_> = ctx.funcs().collect();
+    assert_eq!(funcs.len(), 1);
+    assert_eq!(funcs[0].0, "f");
+    assert_eq!(
+      funcs[0].1.arity,
+      Arity {
+        min: 1,
+        max: Some(2)
+      }
+    );
+    assert_eq!(
+      ctx.eval_func("f", &[1., 2., 3.]),
+      Err(FuncEvalError::TooManyArguments)
+    );
+  }
+
+  #[test]
+  fn builtin_info() {
+    let ctx = Context::new();
+    let sin = ctx.info("sin").unwrap();
+    assert_eq!(sin.arity, Arity::exact(1));
+    assert!(sin.pure);
+    assert_eq!(sin.derivatives, vec!["cos(x1)".parse().unwrap()]);
+    assert_eq!(sin.inverse, Some("asin(x1)".parse().unwrap()));
+    assert_eq!(ctx.info("max").unwrap().arity, Arity::at_least(1));
+    assert!(ctx.funcs().all(|(_, info)| info.doc.is_some()));
+  }
+
+  #[test]
+  fn impure_functions() {
+    let mut ctx = Context::new();
+    ctx.func("noise", |x| x);
+    ctx.info_mut("noise").unwrap().pure = false;
+    assert!(!ctx.is_pure("noise"));
+    assert!(!(&ctx, Context::new()).is_pure("noise"));
+    assert!(ctx.is_pure("sin"));
+  }
+
+  #[test]
+  fn vec_context_first_match() {
+    let mut first = Context::empty();
+    first.func2("f", |x, y| x + y);
+    let mut second = Context::empty();
+    second.func("f", |x| x).func("g", |x| -x);
+
+    let ctxs = vec![first, second];
+    assert_eq!(ctxs.eval_func("f", &[1., 2.]), Ok(3.));
+    assert_eq!(
+      ctxs.eval_func("f", &[1.]),
+      Err(FuncEvalError::NumberArgs(2))
+    );
+    assert_eq!(ctxs.eval_func("g", &[1.]), Ok(-1.));
+    assert_eq!(
+      ctxs.eval_func("h", &[1.]),
+      Err(FuncEvalError::UnknownFunction)
+    );
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn serde_vars() {
+    use serde_json;
+    use toml;
+
+    let ctx: Context = toml::from_str("g = 9.81\nhalf_pi = \"pi / 2\"\nn = 3").unwrap();
+    assert_eq!(ctx.get_var("g"), Some(9.81));
+    assert_eq!(ctx.get_var("half_pi"), Some(std::f64::consts::FRAC_PI_2));
+    assert_eq!(ctx.get_var("n"), Some(3.));
+    assert_eq!(ctx.eval_func("sin", &[0.]), Ok(0.));
+
+    let mut ctx = Context::empty();
+    ctx.var("y", 2.).var("x", 0.5).func("f", |x| x);
+    let json = serde_json::to_string(&ctx).unwrap();
+    assert_eq!(json, r#"{"x":0.5,"y":2.0}"#);
+    let back: Context = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.get_var("x"), Some(0.5));
+    assert_eq!(back.get_var("y"), Some(2.));
+
+    assert!(toml::from_str::<Context>(r#"x = "y + 1""#).is_err());
+  }
 }
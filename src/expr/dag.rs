@@ -0,0 +1,338 @@
+//! Expressions as directed acyclic graphs, sharing identical subexpressions.
+use super::quadrature::{is_binding, BindingCall};
+use super::{builtin, ContextProvider, Expr, Token};
+use crate::Evaluatable_Trait;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use Error;
+
+/// A node of a [`Dag`](struct.Dag.html), evaluated after the nodes it refers to.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+  /// A token with the nodes of its arguments.
+  Token(Token, Vec<usize>),
+  /// A call binding a variable, like `sum_over(i^2, i, 1, n)`, evaluated as a whole, with the
+  /// calls binding a variable in it.
+  Binding(Expr, Vec<BindingCall>),
+}
+
+/// An expression as a directed acyclic graph, in which identical subexpressions are stored,
+/// and evaluated, only once, e.g. `sqrt(b^2 - 4 * a * c)` in the product of the roots of a
+/// quadratic.
+///
+/// Subexpressions calling impure functions (see [`ContextProvider::is_pure`]) aren't shared,
+/// so that `rand() - rand()` still calls `rand` twice. Formatting a `Dag` writes the shared
+/// subexpressions as `let` bindings before the expression.
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::{Context, Dag, Expr};
+/// let expr: Expr = "(-b + sqrt(b^2 - 4*a*c)) * (-b - sqrt(b^2 - 4*a*c))".parse().unwrap();
+/// let dag = Dag::new(&expr, Context::new());
+/// assert_eq!(
+///   dag.to_string(),
+///   "let t0 = -b;\nlet t1 = sqrt(b^2 - 4 * a * c);\n(t0 + t1) * (t0 - t1)"
+/// );
+///
+/// let ctx = (vec![("a", 1.), ("b", 5.), ("c", 6.)], Context::new());
+/// assert_eq!(dag.eval_with_context(&ctx), Ok(24.));
+/// ```
+///
+/// [`ContextProvider::is_pure`]: trait.ContextProvider.html#method.is_pure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dag {
+  expr: Expr,
+  /// The nodes, each after those it refers to.
+  nodes: Vec<Node>,
+  root: usize,
+}
+
+impl Dag {
+  /// The graph of `expr`, sharing the subexpressions whose tokens are the same, unless they
+  /// call functions that are impure in `ctx`.
+  pub fn new<C: ContextProvider>(expr: &Expr, ctx: C) -> Dag {
+    let ptrs = expr.start_pointers();
+    // the end of the outermost call binding a variable starting at each token
+    let mut bindings = HashMap::new();
+    for (end, tkn) in expr.iter().enumerate() {
+      if is_binding(tkn) {
+        let start = ptrs[end];
+        let outer = bindings.entry(start).or_insert(end);
+        *outer = end.max(*outer);
+      }
+    }
+    let is_impure = |tkn: &Token| match tkn {
+      Token::Func(name, _) => !ctx.is_pure(name),
+      _ => false,
+    };
+
+    let mut nodes = vec![];
+    let mut impure = vec![];
+    let mut shared: HashMap<&[Token], usize> = HashMap::new();
+    let mut stack: Vec<usize> = vec![];
+    let mut i = 0;
+    while i < expr.len() {
+      let (start, end) = match bindings.get(&i) {
+        Some(&end) => (i, end),
+        None => (ptrs[i], i),
+      };
+      let slice = &expr[start..=end];
+      let node = if start == i && end > i {
+        let expr = Expr(slice.to_vec());
+        let calls = expr.binding_calls();
+        Node::Binding(expr, calls)
+      } else {
+        let n = Expr::no_children(&expr[i]);
+        Node::Token(expr[i].clone(), stack.split_off(stack.len() - n))
+      };
+      let is_impure = match &node {
+        Node::Binding(..) => slice.iter().any(is_impure),
+        Node::Token(tkn, args) => is_impure(tkn) || args.iter().any(|&j| impure[j]),
+      };
+      let id = match shared.get(slice) {
+        Some(&id) if !is_impure => id,
+        _ => {
+          nodes.push(node);
+          impure.push(is_impure);
+          if !is_impure {
+            shared.insert(slice, nodes.len() - 1);
+          }
+          nodes.len() - 1
+        }
+      };
+      stack.push(id);
+      i = end + 1;
+    }
+
+    Dag {
+      expr: expr.clone(),
+      root: stack.pop().expect("empty expression"),
+      nodes,
+    }
+  }
+
+  /// The expression the graph was made from.
+  pub fn expr(&self) -> &Expr {
+    &self.expr
+  }
+
+  /// The number of distinct subexpressions.
+  pub fn len(&self) -> usize {
+    self.nodes.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.nodes.is_empty()
+  }
+
+  /// Evaluates the expression with variables given by the argument, evaluating each shared
+  /// subexpression once.
+  pub fn eval_with_context<C: ContextProvider>(&self, ctx: C) -> Result<f64, Error> {
+    let mut values = Vec::with_capacity(self.nodes.len());
+    let mut args = Vec::with_capacity(4);
+    for node in &self.nodes {
+      let value = match node {
+        Node::Token(Token::Var(name), _) => match ctx.get_var(name) {
+          Some(x) => x,
+          None => return Err(Error::UnknownVariable(name.clone())),
+        },
+        Node::Token(Token::Number(x), _) => *x,
+        Node::Token(Token::Binary(op), ids) => Expr::binary(*op, values[ids[0]], values[ids[1]])?,
+        Node::Token(Token::Unary(op), ids) => Expr::unary(*op, values[ids[0]])?,
+        Node::Token(Token::Func(name, _), ids) => {
+          args.clear();
+          args.extend(ids.iter().map(|&i| values[i]));
+          match ctx.eval_func(name, &args) {
+            Ok(x) => x,
+            Err(e) => return Err(Error::Function(name.clone(), e)),
+          }
+        }
+        Node::Token(tkn, _) => {
+          return Err(Error::EvalError(format!("Unrecognized token: {:?}", tkn)));
+        }
+        Node::Binding(expr, calls) => expr.eval_with_calls(calls, &ctx)?,
+      };
+      values.push(value);
+    }
+    Ok(values[self.root])
+  }
+
+  fn check_context<C: ContextProvider>(&self, ctx: C) -> Result<(), Error> {
+    self.expr.check_context(ctx)
+  }
+
+  /// The graph itself, as the calls binding a variable are found when it is made.
+  fn prepared(self) -> Self {
+    self
+  }
+}
+
+Evaluatable_Trait!(Dag f64);
+
+impl Display for Dag {
+  /// Writes the subexpressions used more than once as `let t0 = ...;` lines, in the order they
+  /// are evaluated, followed by the expression in terms of them.
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let mut uses = vec![0; self.nodes.len()];
+    for node in &self.nodes {
+      if let Node::Token(_, args) = node {
+        for &i in args {
+          uses[i] += 1;
+        }
+      }
+    }
+
+    // names not already taken by variables
+    let vars: HashSet<_> = self
+      .expr
+      .iter()
+      .filter_map(|tkn| match tkn {
+        Token::Var(name) => Some(name.as_str()),
+        _ => None,
+      })
+      .collect();
+    let mut names = (0..)
+      .map(|k| format!("t{}", k))
+      .filter(|name| !vars.contains(name.as_str()));
+
+    // the tokens of each node, with the shared nodes it refers to by name
+    let mut inline: Vec<Vec<Token>> = Vec::with_capacity(self.nodes.len());
+    let mut named: Vec<Option<String>> = Vec::with_capacity(self.nodes.len());
+    for (i, node) in self.nodes.iter().enumerate() {
+      let tkns = match node {
+        Node::Token(tkn, args) => {
+          let mut tkns = vec![];
+          for &j in args {
+            match named[j] {
+              Some(ref name) => tkns.push(Token::Var(name.clone())),
+              None => tkns.extend(inline[j].iter().cloned()),
+            }
+          }
+          tkns.push(tkn.clone());
+          tkns
+        }
+        Node::Binding(expr, _) => expr.0.clone(),
+      };
+      let is_leaf = matches!(node, Node::Token(_, args) if args.is_empty());
+      let name = if uses[i] > 1 && !is_leaf {
+        let name = names.next().unwrap();
+        writeln!(f, "let {} = {};", name, Expr(tkns.clone()))?;
+        Some(name)
+      } else {
+        None
+      };
+      inline.push(tkns);
+      named.push(name);
+    }
+    match named[self.root] {
+      Some(ref name) => write!(f, "{}", name),
+      None => write!(f, "{}", Expr(inline[self.root].clone())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use expr::FuncEvalError;
+  use std::cell::Cell;
+  use Context;
+
+  /// A context counting the calls of `f`, which is pure, and `g`, which isn't.
+  #[derive(Default)]
+  struct Counting {
+    calls: Cell<usize>,
+  }
+
+  impl ContextProvider for Counting {
+    fn get_var(&self, name: &str) -> Option<f64> {
+      if name == "x" {
+        Some(3.)
+      } else {
+        None
+      }
+    }
+    fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, FuncEvalError> {
+      match name {
+        "f" | "g" => {
+          self.calls.set(self.calls.get() + 1);
+          Ok(2. * args[0])
+        }
+        _ => Err(FuncEvalError::UnknownFunction),
+      }
+    }
+    fn is_pure(&self, name: &str) -> bool {
+      name != "g"
+    }
+  }
+
+  fn dag(expr: Expr) -> Dag {
+    Dag::new(&expr, builtin())
+  }
+
+  #[test]
+  fn sharing() {
+    let expr = expr!((x + 1) * (x + 1) + sin(x + 1));
+    let d = dag(expr.clone());
+    // x, 1, x + 1, *, sin, +
+    assert_eq!(d.len(), 6);
+    assert_eq!(d.expr(), &expr);
+    assert_eq!(d.to_string(), "let t0 = x + 1;\nt0 * t0 + sin(t0)");
+    assert_eq!(
+      d.eval_with_context(("x", 2.)),
+      expr.eval_with_context(("x", 2.))
+    );
+
+    // leaves aren't named, and names don't clash with variables
+    let d = dag(expr!(t0 * sin(x) + sin(x) / t0));
+    assert_eq!(d.to_string(), "let t1 = sin(x);\nt0 * t1 + t1 / t0");
+    assert_eq!(dag(expr!(x * x)).to_string(), "x * x");
+    assert_eq!(dag(expr!(2)).to_string(), "2");
+  }
+
+  #[test]
+  fn evaluates_once() {
+    let ctx = Counting::default();
+    let expr = expr!(f(x) + f(x) * f(f(x)));
+    let d = Dag::new(&expr, &ctx);
+    assert_eq!(d.eval_with_context(&ctx), Ok(78.));
+    assert_eq!(ctx.calls.get(), 2);
+    ctx.calls.set(0);
+    assert_eq!(expr.eval_with_context(&ctx), Ok(78.));
+    assert_eq!(ctx.calls.get(), 4);
+
+    // calls of impure functions are kept apart
+    let ctx = Counting::default();
+    let d = Dag::new(&expr!(g(x) + g(x) + (g(x) + 1) * (g(x) + 1)), &ctx);
+    assert_eq!(d.eval_with_context(&ctx), Ok(61.));
+    assert_eq!(ctx.calls.get(), 4);
+    assert!(!d.to_string().contains("let"));
+  }
+
+  #[test]
+  fn derivatives_and_bindings() {
+    let expr = expr!(exp(sin(x) ^ 2) * sin(x) ^ 2).derivative("x").unwrap();
+    let d = dag(expr.clone());
+    assert!(d.len() < expr.len());
+    let f = d.bind("x").unwrap();
+    let g = expr.bind("x").unwrap();
+    for &x in &[-1., 0., 0.5, 2.] {
+      assert_eq!(f(x), g(x));
+    }
+
+    let expr = expr!(sum_over(i ^ 2, i, 1, n) + sum_over(i ^ 2, i, 1, n) * integrate(t, t, 0, n));
+    let d = dag(expr);
+    assert_eq!(
+      d.to_string(),
+      "let t0 = sum_over(i^2, i, 1, n);\nt0 + t0 * integrate(t, t, 0, n)"
+    );
+    let ctx = (("n", 2.), Context::new());
+    assert_eq!(d.eval_with_context(&ctx), Ok(15.));
+    assert_eq!(
+      d.eval_with_context(Context::new()),
+      Err(Error::UnknownVariable("n".into()))
+    );
+    assert!(d.bind("m").is_err());
+  }
+}
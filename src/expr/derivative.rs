@@ -0,0 +1,326 @@
+//! Symbolic differentiation, and derivatives evaluated alongside values with dual numbers.
+use super::operators::{Floor, Ln, Powf};
+use super::{builtin, ContextProvider, Expr, Operation, Substitutions, Token};
+use Error;
+
+fn is_num(expr: &Expr, x: f64) -> bool {
+  expr.as_num() == Some(x)
+}
+
+// Constructors skipping the terms and factors that are 0 or 1, which differentiation is full of.
+
+pub(super) fn add(a: Expr, b: Expr) -> Expr {
+  match (is_num(&a, 0.), is_num(&b, 0.)) {
+    (true, _) => b,
+    (_, true) => a,
+    _ => a + b,
+  }
+}
+
+pub(super) fn sub(a: Expr, b: Expr) -> Expr {
+  match (is_num(&a, 0.), is_num(&b, 0.)) {
+    (_, true) => a,
+    (true, _) => -b,
+    _ => a - b,
+  }
+}
+
+pub(super) fn mul(a: Expr, b: Expr) -> Expr {
+  if is_num(&a, 0.) || is_num(&b, 0.) {
+    Expr::num(0.)
+  } else if is_num(&a, 1.) {
+    b
+  } else if is_num(&b, 1.) {
+    a
+  } else {
+    a * b
+  }
+}
+
+pub(super) fn div(a: Expr, b: Expr) -> Expr {
+  if is_num(&a, 0.) || is_num(&b, 1.) {
+    a
+  } else {
+    a / b
+  }
+}
+
+/// The name of the function or operator of the token, for errors.
+fn name(tkn: &Token) -> String {
+  match tkn {
+    Token::Func(name, _) => name.clone(),
+    Token::Unary(Operation::Fact) => "!".into(),
+    tkn => format!("{:?}", tkn),
+  }
+}
+
+impl Expr {
+  /// The derivative with respect to `var`, with the built-in constants and functions.
+  ///
+  /// See [`derivative_with_context`](#method.derivative_with_context).
+  pub fn derivative(&self, var: &str) -> Result<Expr, Error> {
+    self.derivative_with_context(var, builtin())
+  }
+
+  /// The derivative with respect to `var`, taking the derivatives of functions from their
+  /// [`FuncInfo`](struct.FuncInfo.html) in `ctx`.
+  ///
+  /// Terms multiplied by zero and factors of one are dropped, but the result is not simplified
+  /// any further.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NoDerivative)` if a function whose argument depends on `var` has no known
+  /// derivatives, or with `!` for a factorial depending on `var`.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Expr;
+  /// let expr: Expr = "x^3 + sin(2 * x)".parse().unwrap();
+  /// assert_eq!(expr.derivative("x").unwrap().to_string(), "3 * x^2 + cos(2 * x) * 2");
+  /// ```
+  pub fn derivative_with_context<C: ContextProvider>(
+    &self,
+    var: &str,
+    ctx: C,
+  ) -> Result<Expr, Error> {
+    use self::Operation::*;
+    use self::Token::*;
+
+    let res = self.fold_expr(|args: Vec<Result<(Expr, Expr), Error>>, tkn| {
+      let args = args.into_iter().collect::<Result<Vec<_>, _>>()?;
+      let d = match (&tkn, &args[..]) {
+        (Number(_), _) => Expr::num(0.),
+        (Var(v), _) => Expr::num(if v == var { 1. } else { 0. }),
+        (_, [_, ..]) if args.iter().all(|(_, du)| is_num(du, 0.)) => Expr::num(0.),
+        (Unary(Plus), [(_, du)]) => du.clone(),
+        (Unary(Minus), [(_, du)]) => sub(Expr::num(0.), du.clone()),
+        (Binary(Plus), [(_, du), (_, dv)]) => add(du.clone(), dv.clone()),
+        (Binary(Minus), [(_, du), (_, dv)]) => sub(du.clone(), dv.clone()),
+        (Binary(Times), [(u, du), (v, dv)]) => {
+          add(mul(du.clone(), v.clone()), mul(u.clone(), dv.clone()))
+        }
+        (Binary(Div), [(u, du), (v, dv)]) if is_num(dv, 0.) => div(du.clone(), v.clone()),
+        (Binary(Div), [(u, du), (v, dv)]) => div(
+          sub(mul(du.clone(), v.clone()), mul(u.clone(), dv.clone())),
+          v.powf(2.),
+        ),
+        (Binary(Rem), [(u, du), (v, dv)]) => sub(du.clone(), mul((u / v).floor(), dv.clone())),
+        (Binary(Pow), [(u, du), (v, dv)]) if is_num(dv, 0.) => {
+          let power = match v.as_num() {
+            Some(2.) => u.clone(),
+            Some(n) => u.powf(n - 1.),
+            None => u.powf(v - 1.),
+          };
+          mul(mul(v.clone(), power), du.clone())
+        }
+        // d(u^v) = u^v * (v' * ln(u) + v * u' / u)
+        (Binary(Pow), [(u, du), (v, dv)]) => mul(
+          u.powf(v),
+          add(
+            mul(dv.clone(), u.ln()),
+            div(mul(v.clone(), du.clone()), u.clone()),
+          ),
+        ),
+        (Func(name, Some(n)), _) => {
+          let mut d = Expr::num(0.);
+          for (i, (_, du)) in args.iter().enumerate() {
+            if is_num(du, 0.) {
+              continue;
+            }
+            let partials = ctx
+              .func_info(name)
+              .and_then(|info| info.partials(*n))
+              .ok_or_else(|| Error::NoDerivative(name.clone()))?;
+            let mut subs = Substitutions::new();
+            for (j, (u, _)) in args.iter().enumerate() {
+              subs.insert(format!("x{}", j + 1), u.clone());
+            }
+            d = add(d, mul(subs.apply(&partials[i]), du.clone()));
+          }
+          d
+        }
+        _ => return Err(Error::NoDerivative(name(&tkn))),
+      };
+      let mut tkns: Vec<Token> = args.into_iter().flat_map(|(u, _)| u.0).collect();
+      tkns.push(tkn);
+      Ok((Expr(tkns), d))
+    });
+    Ok(res?.1)
+  }
+
+  /// The value and the derivative with respect to `var` at `var = x`, evaluated together in
+  /// forward mode with dual numbers.
+  ///
+  /// Functions without known derivatives are differentiated numerically, by central
+  /// differences.
+  pub(crate) fn eval_dual<C: ContextProvider>(
+    &self,
+    var: &str,
+    x: f64,
+    ctx: C,
+  ) -> Result<(f64, f64), Error> {
+    use self::Operation::*;
+    use self::Token::*;
+
+    let mut stack: Vec<(f64, f64)> = Vec::with_capacity(16);
+    for tkn in &self.0 {
+      let res = match tkn {
+        Var(name) if name == var => (x, 1.),
+        Var(name) => match ctx.get_var(name) {
+          Some(v) => (v, 0.),
+          None => return Err(Error::UnknownVariable(name.clone())),
+        },
+        Number(f) => (*f, 0.),
+        Binary(op) => {
+          let (v, dv) = stack.pop().unwrap();
+          let (u, du) = stack.pop().unwrap();
+          match op {
+            Plus => (u + v, du + dv),
+            Minus => (u - v, du - dv),
+            Times => (u * v, du * v + u * dv),
+            Div => (u / v, (du * v - u * dv) / (v * v)),
+            Rem => (u % v, du - (u / v).floor() * dv),
+            Pow if dv == 0. => (
+              u.powf(v),
+              if du == 0. {
+                0.
+              } else {
+                v * u.powf(v - 1.) * du
+              },
+            ),
+            Pow => (u.powf(v), u.powf(v) * (dv * u.ln() + v * du / u)),
+            _ => return Err(Error::NoDerivative(name(tkn))),
+          }
+        }
+        Unary(Plus) => stack.pop().unwrap(),
+        Unary(Minus) => {
+          let (u, du) = stack.pop().unwrap();
+          (-u, -du)
+        }
+        Func(name, Some(n)) => {
+          let args = stack.split_off(stack.len() - n);
+          let values: Vec<f64> = args.iter().map(|&(u, _)| u).collect();
+          let eval = |values: &[f64]| {
+            ctx
+              .eval_func(name, values)
+              .map_err(|e| Error::Function(name.clone(), e))
+          };
+          let value = eval(&values)?;
+          let partials = ctx.func_info(name).and_then(|info| info.partials(*n));
+          let mut d = 0.;
+          for (i, &(u, du)) in args.iter().enumerate() {
+            if du == 0. {
+              continue;
+            }
+            let partial = match partials {
+              Some(ref partials) => {
+                let xs: Vec<_> = values
+                  .iter()
+                  .enumerate()
+                  .map(|(j, &x)| (format!("x{}", j + 1), x))
+                  .collect();
+                partials[i].eval_with_context((xs, &ctx))?
+              }
+              None => {
+                let h = 1e-6 * (1. + u.abs());
+                let mut shifted = values.clone();
+                shifted[i] = u + h;
+                let above = eval(&shifted)?;
+                shifted[i] = u - h;
+                (above - eval(&shifted)?) / (2. * h)
+              }
+            };
+            d += partial * du;
+          }
+          (value, d)
+        }
+        Unary(_) => {
+          let (u, du) = stack.pop().unwrap();
+          if du != 0. {
+            return Err(Error::NoDerivative(name(tkn)));
+          }
+          (
+            Expr(vec![Number(u), tkn.clone()]).eval_with_context(&ctx)?,
+            0.,
+          )
+        }
+        _ => return Err(Error::EvalError(format!("Unrecognized token: {:?}", tkn))),
+      };
+      stack.push(res);
+    }
+    Ok(stack.pop().expect("Stack is empty, this is impossible."))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use Context;
+
+  fn derivative(expr: Expr) -> String {
+    expr.derivative("x").unwrap().to_string()
+  }
+
+  #[test]
+  fn derivatives() {
+    assert_eq!(derivative(expr!(3 * x + y)), "3");
+    assert_eq!(derivative(expr!(x ^ 2)), "2 * x");
+    assert_eq!(derivative(expr!(x ^ n)), "n * x^(n - 1)");
+    assert_eq!(derivative(expr!(x * y)), "y");
+    assert_eq!(derivative(expr!(1 / x)), "-1 / x^2");
+    assert_eq!(derivative(expr!(2 ^ x)), "2^x * ln(2)");
+    assert_eq!(derivative(expr!(-exp(x))), "-exp(x)");
+    assert_eq!(
+      derivative(expr!(sin(x) * cos(x))),
+      "cos(x) * cos(x) + sin(x) * -sin(x)"
+    );
+    assert_eq!(derivative(expr!(atan2(y, x))), "-y / (y^2 + x^2)");
+    assert_eq!(derivative(expr!(max(y, 2))), "0");
+    assert_eq!(derivative(expr!(sum(x ^ 2, y, 3 * x))), "2 * x + 3");
+    assert_eq!(derivative(expr!(sum(y, 2))), "0");
+    assert_eq!(
+      derivative(expr!(product(x, y, sin(x)))),
+      "y * sin(x) + x * y * cos(x)"
+    );
+    assert_eq!(
+      expr!(max(x, 2)).derivative("x"),
+      Err(Error::NoDerivative("max".into()))
+    );
+  }
+
+  #[test]
+  fn dual_numbers() {
+    let ctx = builtin();
+    let at = |expr: Expr, x: f64| expr.eval_dual("x", x, &ctx).unwrap();
+    assert_eq!(at(expr!(x ^ 3 - 2 * x), 2.), (4., 10.));
+    assert_eq!(at(expr!(x ^ 2), -3.), (9., -6.));
+    let (value, d) = at(expr!(sin(x) * exp(x)), 1.);
+    assert_eq!(value, 1f64.sin() * 1f64.exp());
+    assert!((d - (1f64.cos() + 1f64.sin()) * 1f64.exp()).abs() < 1e-12);
+    assert_eq!(at(expr!(sum(x ^ 2, 3 * x, 1)), 2.), (11., 7.));
+    assert_eq!(at(expr!(product(x, x + 1, 2)), 3.), (24., 14.));
+
+    // functions without derivatives are differentiated numerically
+    let mut ctx = Context::new();
+    ctx.func("cube", |x| x * x * x);
+    let (value, d) = expr!(cube(x + 1)).eval_dual("x", 1., &ctx).unwrap();
+    assert_eq!(value, 8.);
+    assert!((d - 12.).abs() < 1e-6);
+    assert_eq!(
+      expr!(cube(x)).derivative_with_context("x", &ctx),
+      Err(Error::NoDerivative("cube".into()))
+    );
+
+    // a function replacing a built-in one doesn't keep its derivatives
+    ctx.funcn("sum", |xs| xs.iter().map(|x| x * x).sum(), ..);
+    let (value, d) = expr!(sum(x, 2 * x)).eval_dual("x", 1., &ctx).unwrap();
+    assert_eq!(value, 5.);
+    assert!((d - 10.).abs() < 1e-6);
+    assert_eq!(
+      expr!(sum(x, 2 * x)).derivative_with_context("x", &ctx),
+      Err(Error::NoDerivative("sum".into()))
+    );
+  }
+}
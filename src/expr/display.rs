@@ -0,0 +1,169 @@
+//! Formatting of expressions in infix notation.
+use super::{Expr, Operation, Token};
+use std::fmt::{self, Display, Formatter};
+
+/// Precedence of atoms, i.e. numbers, variables and function calls.
+const ATOM: u32 = u32::MAX;
+
+/// The precedence of a token as it appears in infix notation, matching the shunting-yard
+/// algorithm.
+fn precedence(token: &Token) -> u32 {
+  use self::Operation::*;
+  use self::Token::*;
+
+  match *token {
+    Binary(Plus) | Binary(Minus) => 1,
+    Binary(Times) | Binary(Div) | Binary(Rem) => 2,
+    Unary(Plus) | Unary(Minus) => 3,
+    Binary(_) => 4,
+    Unary(_) => 5,
+    // negative numbers are written with a unary minus
+    Number(x) if x.is_sign_negative() && !x.is_nan() => 3,
+    _ => ATOM,
+  }
+}
+
+fn number(x: f64) -> String {
+  // infinities and NaN don't have literals, so write them as expressions giving the same value
+  if x.is_nan() {
+    "(0/0)".into()
+  } else if x.is_infinite() {
+    format!("({}1/0)", if x < 0. { "-" } else { "" })
+  } else {
+    x.to_string()
+  }
+}
+
+fn parens(s: String, prec: u32, min_prec: u32) -> String {
+  if prec < min_prec {
+    format!("({})", s)
+  } else {
+    s
+  }
+}
+
+impl Expr {
+  /// Converts the expression to infix notation, with only the necessary parentheses.
+  fn to_infix(&self) -> String {
+    use self::Operation::*;
+    use self::Token::*;
+
+    let (s, _) = self.fold_expr(|mut args: Vec<(String, u32)>, t| {
+      let prec = precedence(&t);
+      let s = match t {
+        Number(x) => number(x),
+        Var(name) => name,
+        Func(name, _) => {
+          let args: Vec<_> = args.into_iter().map(|(s, _)| s).collect();
+          format!("{}({})", name, args.join(", "))
+        }
+        Unary(op) => {
+          let (x, x_prec) = args.pop().unwrap();
+          match op {
+            Fact => format!("{}!", parens(x, x_prec, ATOM)),
+            Minus => format!("-{}", parens(x, x_prec, prec)),
+            _ => format!("+{}", parens(x, x_prec, prec)),
+          }
+        }
+        Binary(op) => {
+          let (r, r_prec) = args.pop().unwrap();
+          let (l, l_prec) = args.pop().unwrap();
+          // `^` is right associative, every other operator is left associative
+          let (l, r) = match op {
+            Pow => (parens(l, l_prec, prec + 1), parens(r, r_prec, prec)),
+            _ => (parens(l, l_prec, prec), parens(r, r_prec, prec + 1)),
+          };
+          match op {
+            Plus => format!("{} + {}", l, r),
+            Minus => format!("{} - {}", l, r),
+            Times => format!("{} * {}", l, r),
+            Div => format!("{} / {}", l, r),
+            Rem => format!("{} % {}", l, r),
+            _ => format!("{}^{}", l, r),
+          }
+        }
+        _ => panic!("expression wasn't parsed correctly!"),
+      };
+      (s, prec)
+    });
+
+    s
+  }
+}
+
+/// Formats the expression in infix notation, such that parsing the result gives back an
+/// expression with the same value.
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::Expr;
+/// let expr: Expr = "((x + 1) * -y)^2^(3) - f(1, (2))".parse().unwrap();
+/// assert_eq!(expr.to_string(), "((x + 1) * -y)^2^3 - f(1, 2)");
+/// ```
+impl Display for Expr {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    f.write_str(&self.to_infix())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn round_trip(s: &str) -> String {
+    let expr: Expr = s.parse().unwrap();
+    let printed = expr.to_string();
+    assert_eq!(printed.parse::<Expr>().unwrap(), expr, "{}", printed);
+    printed
+  }
+
+  #[test]
+  fn display_precedence() {
+    assert_eq!(round_trip("1 + 2 * 3"), "1 + 2 * 3");
+    assert_eq!(round_trip("(1 + 2) * 3"), "(1 + 2) * 3");
+    assert_eq!(round_trip("a - (b - c)"), "a - (b - c)");
+    assert_eq!(round_trip("(a - b) - c"), "a - b - c");
+    assert_eq!(round_trip("a / (b * c)"), "a / (b * c)");
+    assert_eq!(round_trip("(a^b)^c"), "(a^b)^c");
+    assert_eq!(round_trip("a^(b^c)"), "a^b^c");
+    assert_eq!(round_trip("a % b"), "a % b");
+  }
+
+  #[test]
+  fn display_unary() {
+    assert_eq!(round_trip("-2^2"), "-2^2");
+    assert_eq!(round_trip("(-2)^2"), "(-2)^2");
+    assert_eq!(round_trip("-(a + b)"), "-(a + b)");
+    assert_eq!(round_trip("a - -b"), "a - -b");
+    assert_eq!(round_trip("a^(-b)"), "a^(-b)");
+    assert_eq!(round_trip("(a + 1)! * 3!"), "(a + 1)! * 3!");
+    assert_eq!(round_trip("(-3)!"), "(-3)!");
+    assert_eq!(round_trip("+x"), "+x");
+  }
+
+  #[test]
+  fn display_functions() {
+    assert_eq!(round_trip("max(1, sin(x), y + 2)"), "max(1, sin(x), y + 2)");
+    assert_eq!(round_trip("rand() * 2"), "rand() * 2");
+  }
+
+  #[test]
+  fn display_numbers() {
+    assert_eq!(Expr(vec![Token::Number(-3.)]).to_string(), "-3");
+    let pow = Expr(vec![
+      Token::Var("x".into()),
+      Token::Number(-0.5),
+      Token::Binary(Operation::Pow),
+    ]);
+    assert_eq!(pow.to_string(), "x^(-0.5)");
+    assert_eq!(Expr(vec![Token::Number(1.5e-7)]).to_string(), "0.00000015");
+
+    for &x in &[f64::INFINITY, f64::NEG_INFINITY] {
+      let printed = Expr(vec![Token::Number(x)]).to_string();
+      assert_eq!(printed.parse::<Expr>().unwrap().eval(), Ok(x));
+    }
+    let nan = Expr(vec![Token::Number(f64::NAN)]).to_string();
+    assert!(nan.parse::<Expr>().unwrap().eval().unwrap().is_nan());
+  }
+}
@@ -18,6 +18,84 @@ pub fn factorial(num: f64) -> Result<f64, &'static str> {
   }
 }
 
+/// Sum of all the numbers.
+pub fn sum_array(xs: &[f64]) -> f64 {
+  xs.iter().sum()
+}
+
+/// Product of all the numbers.
+pub fn product_array(xs: &[f64]) -> f64 {
+  xs.iter().product()
+}
+
+/// Arithmetic mean of the numbers.
+pub fn mean_array(xs: &[f64]) -> f64 {
+  sum_array(xs) / xs.len() as f64
+}
+
+/// Population variance of the numbers, i.e. the mean squared deviation from
+/// the mean.
+pub fn var_array(xs: &[f64]) -> f64 {
+  let mean = mean_array(xs);
+  xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / xs.len() as f64
+}
+
+/// Population standard deviation of the numbers.
+pub fn stddev_array(xs: &[f64]) -> f64 {
+  var_array(xs).sqrt()
+}
+
+/// The `p`-th quantile of the numbers, linearly interpolating between the two
+/// closest ranks. Gives `NaN` if `p` lies outside `[0, 1]` or any number is
+/// `NaN`.
+pub fn quantile(p: f64, xs: &[f64]) -> f64 {
+  if !(0. ..=1.).contains(&p) || xs.iter().any(|x| x.is_nan()) {
+    return f64::NAN;
+  }
+  let mut sorted = xs.to_vec();
+  sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+  let rank = p * (sorted.len() - 1) as f64;
+  let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+  sorted[lo] + (sorted[hi] - sorted[lo]) * rank.fract()
+}
+
+/// `quantile(p, ...)` with the quantile given as the first argument.
+pub fn quantile_array(xs: &[f64]) -> f64 {
+  quantile(xs[0], &xs[1..])
+}
+
+/// Median of the numbers.
+pub fn median_array(xs: &[f64]) -> f64 {
+  quantile(0.5, xs)
+}
+
+/// Euclidean norm of the numbers, avoiding intermediate overflow.
+pub fn norm_array(xs: &[f64]) -> f64 {
+  xs.iter().fold(0., |n, &x| n.hypot(x))
+}
+
+/// The log-sum-exp `ln(exp(x1) + ... + exp(xn))`, shifted by the maximum so
+/// that it doesn't overflow for large arguments.
+pub fn softmax_lse_array(xs: &[f64]) -> f64 {
+  let max = xs.iter().fold(f64::NEG_INFINITY, |m, &x| m.max(x));
+  if max.is_infinite() {
+    return max;
+  }
+  max + xs.iter().map(|x| (x - max).exp()).sum::<f64>().ln()
+}
+
+/// Restricts `x` to the interval `[lo, hi]`.
+///
+/// Unlike `f64::clamp`, this doesn't panic on an empty interval but gives `NaN`.
+pub fn clamp(x: f64, lo: f64, hi: f64) -> f64 {
+  if lo > hi {
+    f64::NAN
+  } else {
+    x.max(lo).min(hi)
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -41,4 +119,43 @@ mod tests {
       _ => panic!("Shouldn't be able to do factorial on negative number!"),
     }
   }
+
+  #[test]
+  fn test_aggregates() {
+    let xs = [3., 1., 4., 1., 5.];
+    assert_eq!(sum_array(&xs), 14.);
+    assert_eq!(product_array(&xs), 60.);
+    assert_eq!(mean_array(&xs), 2.8);
+    assert!((var_array(&xs) - 2.56).abs() < 1e-12);
+    assert!((stddev_array(&xs) - 1.6).abs() < 1e-12);
+    assert_eq!(norm_array(&[3., 4.]), 5.);
+    assert_eq!(norm_array(&[1e300, 1e300]), 1e300 * 2f64.sqrt());
+  }
+
+  #[test]
+  fn test_quantiles() {
+    assert_eq!(median_array(&[3., 1., 4., 1., 5.]), 3.);
+    assert_eq!(median_array(&[4., 1., 3., 2.]), 2.5);
+    assert_eq!(quantile_array(&[0., 5., 1., 3.]), 1.);
+    assert_eq!(quantile_array(&[1., 5., 1., 3.]), 5.);
+    assert_eq!(quantile(0.25, &[1., 2., 3., 4., 5.]), 2.);
+    assert!(quantile(1.5, &[1., 2.]).is_nan());
+    assert!(median_array(&[1., f64::NAN]).is_nan());
+  }
+
+  #[test]
+  fn test_softmax_lse() {
+    assert!((softmax_lse_array(&[0., 0.]) - 2f64.ln()).abs() < 1e-12);
+    assert!((softmax_lse_array(&[1000., 1000.]) - (1000. + 2f64.ln())).abs() < 1e-9);
+    assert_eq!(softmax_lse_array(&[f64::NEG_INFINITY]), f64::NEG_INFINITY);
+    assert_eq!(softmax_lse_array(&[1., f64::INFINITY]), f64::INFINITY);
+  }
+
+  #[test]
+  fn test_clamp() {
+    assert_eq!(clamp(5., 0., 1.), 1.);
+    assert_eq!(clamp(-5., 0., 1.), 0.);
+    assert_eq!(clamp(0.5, 0., 1.), 0.5);
+    assert!(clamp(0.5, 1., 0.).is_nan());
+  }
 }
@@ -0,0 +1,390 @@
+//! Metadata describing the functions available in a context.
+use super::{Expr, FuncEvalError};
+use std::fmt::{self, Display, Formatter};
+
+/// The number of arguments accepted by a function, `min..=max`, without an upper bound if `max`
+/// is `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+  pub min: usize,
+  pub max: Option<usize>,
+}
+
+impl Arity {
+  /// Exactly `n` arguments.
+  pub const fn exact(n: usize) -> Self {
+    Arity {
+      min: n,
+      max: Some(n),
+    }
+  }
+
+  /// At least `n` arguments.
+  pub const fn at_least(n: usize) -> Self {
+    Arity { min: n, max: None }
+  }
+
+  /// Whether `n` arguments are accepted.
+  pub fn accepts(&self, n: usize) -> bool {
+    n >= self.min && self.max.is_none_or(|max| n <= max)
+  }
+
+  /// Checks that `n` arguments are accepted, reporting the same errors as a function called
+  /// with the wrong number of arguments.
+  pub fn check(&self, n: usize) -> Result<(), FuncEvalError> {
+    match self.max {
+      _ if self.accepts(n) => Ok(()),
+      Some(max) if max == self.min => Err(FuncEvalError::NumberArgs(max)),
+      _ if n < self.min => Err(FuncEvalError::TooFewArguments),
+      _ => Err(FuncEvalError::TooManyArguments),
+    }
+  }
+}
+
+impl Display for Arity {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self.max {
+      Some(max) if max == self.min => write!(f, "{}", max),
+      Some(max) => write!(f, "{}..={}", self.min, max),
+      None => write!(f, "{}..", self.min),
+    }
+  }
+}
+
+/// The partial derivatives of a function of any number of arguments, which can't be listed one
+/// per argument in [`FuncInfo::derivatives`](struct.FuncInfo.html#structfield.derivatives).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariadicDerivatives {
+  /// Each partial derivative is 1, as for `sum`.
+  One,
+  /// Each partial derivative is the product of the other arguments, as for `product`.
+  ProductOfOthers,
+}
+
+/// Information about a function registered in a [`Context`](struct.Context.html).
+///
+/// Expressions for the derivatives, inverse and antiderivative refer to the arguments of the
+/// function by the variables `x1`, `x2`, ..., e.g. the derivative of `sin` is given by `cos(x1)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncInfo {
+  /// The number of arguments accepted.
+  pub arity: Arity,
+  /// Whether the function always gives the same result for the same arguments.
+  pub pure: bool,
+  /// A short description of the function.
+  pub doc: Option<String>,
+  /// The partial derivatives with respect to each argument, empty if unknown.
+  pub derivatives: Vec<Expr>,
+  /// The partial derivatives for any number of arguments, used when `derivatives` doesn't have
+  /// one for each argument.
+  pub variadic_derivatives: Option<VariadicDerivatives>,
+  /// The inverse of a function of one argument, if known.
+  pub inverse: Option<Expr>,
+  /// An antiderivative of a function of one argument, if known.
+  pub antiderivative: Option<Expr>,
+}
+
+impl FuncInfo {
+  /// Information about a pure function with no further description.
+  pub fn new(arity: Arity) -> Self {
+    FuncInfo {
+      arity,
+      pure: true,
+      doc: None,
+      derivatives: vec![],
+      variadic_derivatives: None,
+      inverse: None,
+      antiderivative: None,
+    }
+  }
+
+  /// The partial derivatives with respect to each of `n` arguments, in `x1`, ..., `xn`, if
+  /// known.
+  pub fn partials(&self, n: usize) -> Option<Vec<Expr>> {
+    if self.derivatives.len() == n {
+      return Some(self.derivatives.clone());
+    }
+    let x = |j: usize| Expr::var(format!("x{}", j));
+    match self.variadic_derivatives? {
+      VariadicDerivatives::One => Some(vec![Expr::num(1.); n]),
+      VariadicDerivatives::ProductOfOthers => Some(
+        (1..=n)
+          .map(|i| {
+            (1..=n)
+              .filter(|&j| j != i)
+              .map(x)
+              .reduce(|p, x| p * x)
+              .unwrap_or_else(|| Expr::num(1.))
+          })
+          .collect(),
+      ),
+    }
+  }
+}
+
+/// Descriptions, partial derivatives and inverses of the built-in functions.
+pub(crate) const BUILTIN_INFO: &[(&str, &str, &[&str], Option<&str>)] = &[
+  ("sqrt", "Square root", &["1 / (2 * sqrt(x1))"], Some("x1^2")),
+  ("exp", "Exponential function", &["exp(x1)"], Some("ln(x1)")),
+  ("ln", "Natural logarithm", &["1 / x1"], Some("exp(x1)")),
+  (
+    "log10",
+    "Base 10 logarithm",
+    &["1 / (x1 * ln(10))"],
+    Some("10^x1"),
+  ),
+  ("abs", "Absolute value", &["signum(x1)"], None),
+  ("sin", "Sine, in radians", &["cos(x1)"], Some("asin(x1)")),
+  ("cos", "Cosine, in radians", &["-sin(x1)"], Some("acos(x1)")),
+  (
+    "tan",
+    "Tangent, in radians",
+    &["1 / cos(x1)^2"],
+    Some("atan(x1)"),
+  ),
+  (
+    "asin",
+    "Inverse sine",
+    &["1 / sqrt(1 - x1^2)"],
+    Some("sin(x1)"),
+  ),
+  (
+    "acos",
+    "Inverse cosine",
+    &["-1 / sqrt(1 - x1^2)"],
+    Some("cos(x1)"),
+  ),
+  (
+    "atan",
+    "Inverse tangent",
+    &["1 / (1 + x1^2)"],
+    Some("tan(x1)"),
+  ),
+  ("sinh", "Hyperbolic sine", &["cosh(x1)"], Some("asinh(x1)")),
+  (
+    "cosh",
+    "Hyperbolic cosine",
+    &["sinh(x1)"],
+    Some("acosh(x1)"),
+  ),
+  (
+    "tanh",
+    "Hyperbolic tangent",
+    &["1 - tanh(x1)^2"],
+    Some("atanh(x1)"),
+  ),
+  (
+    "asinh",
+    "Inverse hyperbolic sine",
+    &["1 / sqrt(x1^2 + 1)"],
+    Some("sinh(x1)"),
+  ),
+  (
+    "acosh",
+    "Inverse hyperbolic cosine",
+    &["1 / sqrt(x1^2 - 1)"],
+    Some("cosh(x1)"),
+  ),
+  (
+    "atanh",
+    "Inverse hyperbolic tangent",
+    &["1 / (1 - x1^2)"],
+    Some("tanh(x1)"),
+  ),
+  (
+    "floor",
+    "Largest integer not greater than the argument",
+    &["0"],
+    None,
+  ),
+  (
+    "ceil",
+    "Smallest integer not less than the argument",
+    &["0"],
+    None,
+  ),
+  (
+    "round",
+    "Nearest integer, rounding half away from zero",
+    &["0"],
+    None,
+  ),
+  ("signum", "Sign of the argument", &["0"], None),
+  (
+    "atan2",
+    "Four quadrant inverse tangent of `x1 / x2`",
+    &["x2 / (x1^2 + x2^2)", "-x1 / (x1^2 + x2^2)"],
+    None,
+  ),
+  ("max", "Maximum of the arguments", &[], None),
+  ("min", "Minimum of the arguments", &[], None),
+  ("sum", "Sum of the arguments", &[], None),
+  ("product", "Product of the arguments", &[], None),
+  ("mean", "Arithmetic mean of the arguments", &[], None),
+  ("median", "Median of the arguments", &[], None),
+  ("var", "Population variance of the arguments", &[], None),
+  (
+    "stddev",
+    "Population standard deviation of the arguments",
+    &[],
+    None,
+  ),
+  (
+    "quantile",
+    "The `x1`-th quantile of the remaining arguments",
+    &[],
+    None,
+  ),
+  ("norm", "Euclidean norm of the arguments", &[], None),
+  (
+    "softmax_lse",
+    "Logarithm of the sum of exponentials of the arguments",
+    &[],
+    None,
+  ),
+  (
+    "clamp",
+    "`x1` restricted to the interval `[x2, x3]`",
+    &[],
+    None,
+  ),
+  ("cbrt", "Cube root", &["1 / (3 * cbrt(x1)^2)"], Some("x1^3")),
+  (
+    "exp2",
+    "Base 2 exponential function",
+    &["exp2(x1) * ln(2)"],
+    Some("log2(x1)"),
+  ),
+  (
+    "exp_m1",
+    "`exp(x1) - 1`, accurate near zero",
+    &["exp(x1)"],
+    Some("ln_1p(x1)"),
+  ),
+  (
+    "log",
+    "Logarithm of `x1` to the base `x2`",
+    &["1 / (x1 * ln(x2))", "-ln(x1) / (x2 * ln(x2)^2)"],
+    None,
+  ),
+  (
+    "log2",
+    "Base 2 logarithm",
+    &["1 / (x1 * ln(2))"],
+    Some("exp2(x1)"),
+  ),
+  (
+    "ln_1p",
+    "`ln(1 + x1)`, accurate near zero",
+    &["1 / (1 + x1)"],
+    Some("exp_m1(x1)"),
+  ),
+  ("recip", "Reciprocal", &["-1 / x1^2"], Some("recip(x1)")),
+  ("fract", "Fractional part", &["1"], None),
+  ("trunc", "Integer part", &["0"], None),
+  (
+    "hypot",
+    "Length of the hypotenuse of a right triangle with sides `x1` and `x2`",
+    &["x1 / hypot(x1, x2)", "x2 / hypot(x1, x2)"],
+    None,
+  ),
+  (
+    "mul_add",
+    "`x1 * x2 + x3` with only one rounding error",
+    &["x2", "x1", "1"],
+    None,
+  ),
+];
+
+/// Partial derivatives of the built-in functions of any number of arguments.
+pub(crate) const BUILTIN_VARIADIC_DERIVATIVES: &[(&str, VariadicDerivatives)] = &[
+  ("sum", VariadicDerivatives::One),
+  ("product", VariadicDerivatives::ProductOfOthers),
+];
+
+/// Antiderivatives of the built-in functions of one argument.
+pub(crate) const BUILTIN_ANTIDERIVATIVES: &[(&str, &str)] = &[
+  ("sqrt", "2 * x1^1.5 / 3"),
+  ("cbrt", "3 * x1^(4 / 3) / 4"),
+  ("exp", "exp(x1)"),
+  ("exp2", "exp2(x1) / ln(2)"),
+  ("exp_m1", "exp(x1) - x1"),
+  ("ln", "x1 * ln(x1) - x1"),
+  ("log10", "(x1 * ln(x1) - x1) / ln(10)"),
+  ("log2", "(x1 * ln(x1) - x1) / ln(2)"),
+  ("ln_1p", "(1 + x1) * ln_1p(x1) - x1"),
+  ("abs", "x1 * abs(x1) / 2"),
+  ("sin", "-cos(x1)"),
+  ("cos", "sin(x1)"),
+  ("tan", "-ln(abs(cos(x1)))"),
+  ("asin", "x1 * asin(x1) + sqrt(1 - x1^2)"),
+  ("acos", "x1 * acos(x1) - sqrt(1 - x1^2)"),
+  ("atan", "x1 * atan(x1) - ln(1 + x1^2) / 2"),
+  ("sinh", "cosh(x1)"),
+  ("cosh", "sinh(x1)"),
+  ("tanh", "ln(cosh(x1))"),
+  ("asinh", "x1 * asinh(x1) - sqrt(x1^2 + 1)"),
+  ("acosh", "x1 * acosh(x1) - sqrt(x1^2 - 1)"),
+  ("atanh", "x1 * atanh(x1) + ln(1 - x1^2) / 2"),
+  ("signum", "abs(x1)"),
+  ("recip", "ln(abs(x1))"),
+];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn arity_check() {
+    assert_eq!(Arity::exact(2).check(2), Ok(()));
+    assert_eq!(Arity::exact(2).check(3), Err(FuncEvalError::NumberArgs(2)));
+    assert_eq!(Arity::at_least(1).check(5), Ok(()));
+    assert_eq!(
+      Arity::at_least(1).check(0),
+      Err(FuncEvalError::TooFewArguments)
+    );
+    let arity = Arity {
+      min: 1,
+      max: Some(2),
+    };
+    assert_eq!(arity.check(3), Err(FuncEvalError::TooManyArguments));
+    assert_eq!(arity.to_string(), "1..=2");
+  }
+
+  #[test]
+  fn variadic_partials() {
+    let mut info = FuncInfo::new(Arity::at_least(1));
+    assert_eq!(info.partials(2), None);
+    info.variadic_derivatives = Some(VariadicDerivatives::One);
+    assert_eq!(info.partials(2), Some(vec![Expr::num(1.), Expr::num(1.)]));
+    info.variadic_derivatives = Some(VariadicDerivatives::ProductOfOthers);
+    let partials: Vec<_> = info
+      .partials(3)
+      .unwrap()
+      .iter()
+      .map(|d| d.to_string())
+      .collect();
+    assert_eq!(partials, ["x2 * x3", "x1 * x3", "x1 * x2"]);
+    assert_eq!(info.partials(1), Some(vec![Expr::num(1.)]));
+    // listed derivatives take precedence for their number of arguments
+    info.derivatives = vec![Expr::var("x2"), Expr::var("x1")];
+    assert_eq!(info.partials(2), Some(info.derivatives.clone()));
+  }
+
+  #[test]
+  fn builtin_info_parses() {
+    for &(name, _, derivs, inverse) in BUILTIN_INFO {
+      for d in derivs.iter().chain(inverse.iter()) {
+        assert!(d.parse::<Expr>().is_ok(), "{}: {}", name, d);
+      }
+    }
+    for &(name, antiderivative) in BUILTIN_ANTIDERIVATIVES {
+      assert!(
+        antiderivative.parse::<Expr>().is_ok(),
+        "{}: {}",
+        name,
+        antiderivative
+      );
+    }
+  }
+}
@@ -0,0 +1,769 @@
+//! Symbolic integration of elementary functions.
+use super::derivative::{add, div, mul, sub};
+use super::operators::{ATan, Abs, Ln, Powf};
+use super::{builtin, ContextProvider, Expr, Operation, Substitutions, Token};
+use eqtn::gauss;
+use polynomial::Polynomial;
+use std::f64;
+use Error;
+
+/// The largest number of nested substitutions and integrations by parts.
+const MAX_DEPTH: usize = 8;
+
+/// The values of the variable at which integrands and antiderivatives are compared.
+const SAMPLES: [f64; 6] = [-2.3, -1.1, -0.35, 0.45, 1.3, 2.7];
+
+/// The largest denominator of the fractions written as `p * e / q`.
+const MAX_DENOMINATOR: u32 = 12;
+
+fn no_integral(expr: &Expr) -> Error {
+  Error::NoIntegral(expr.to_string())
+}
+
+fn depends_on(expr: &Expr, var: &str) -> bool {
+  expr
+    .iter()
+    .any(|tkn| matches!(tkn, Token::Var(name) if name == var))
+}
+
+fn is_integer(x: f64) -> bool {
+  (x.round() - x).abs() < 1e-9 * (1. + x.abs())
+}
+
+/// The last token of `expr` and its arguments.
+fn split(expr: &Expr) -> (&Token, Vec<Expr>) {
+  let ptrs = expr.start_pointers();
+  let last = expr.len() - 1;
+  let mut args = vec![];
+  let mut end = last;
+  for _ in 0..Expr::no_children(&expr[last]) {
+    let start = ptrs[end - 1];
+    args.push(Expr(expr[start..end].to_vec()));
+    end = start;
+  }
+  args.reverse();
+  (&expr[last], args)
+}
+
+/// A number `c` and an expression `e` with `expr = c * e`, taking the numbers out of products,
+/// quotients and negations.
+fn coefficient(expr: &Expr) -> (f64, Expr) {
+  use self::Operation::*;
+  use self::Token::*;
+
+  if let Some(c) = expr.as_num() {
+    return (c, Expr::num(1.));
+  }
+  match split(expr) {
+    (Unary(Minus), args) => {
+      let (c, e) = coefficient(&args[0]);
+      (-c, e)
+    }
+    (Binary(Times), args) => {
+      let (a, u) = coefficient(&args[0]);
+      let (b, v) = coefficient(&args[1]);
+      (a * b, mul(u, v))
+    }
+    (Binary(Div), args) => match args[1].as_num() {
+      Some(q) => {
+        let (c, e) = coefficient(&args[0]);
+        (c / q, e)
+      }
+      None => (1., expr.clone()),
+    },
+    _ => (1., expr.clone()),
+  }
+}
+
+/// `r` as `p / q` with a small denominator `q`.
+fn fraction(r: f64) -> Option<(f64, f64)> {
+  (1..=MAX_DENOMINATOR)
+    .map(f64::from)
+    .find(|q| is_integer(r * q))
+    .map(|q| ((r * q).round(), q))
+}
+
+/// `c * e / n` for numbers `c` and `n`, written as `p * e / q` if `c / n` is a fraction with a
+/// small denominator, e.g. `2 * x^3 / 3` or `-1 / (2 * x^2)`.
+fn scaled(c: f64, e: Expr, n: f64) -> Expr {
+  let r = c / n;
+  if let (Token::Binary(Operation::Div), args) = split(&e) {
+    if let Some(k) = args[0].as_num() {
+      return match fraction(r * k) {
+        Some((p, q)) => div(Expr::num(p), mul(Expr::num(q), args[1].clone())),
+        None => div(Expr::num(r * k), args[1].clone()),
+      };
+    }
+  }
+  let (p, q) = match fraction(r) {
+    Some(f) => f,
+    None => return mul(Expr::num(r), e),
+  };
+  let num = match e.as_num() {
+    Some(x) => Expr::num(p * x),
+    None if p == -1. => -e,
+    None => mul(Expr::num(p), e),
+  };
+  div(num, Expr::num(q))
+}
+
+fn neg(expr: Expr) -> Expr {
+  let (c, e) = coefficient(&expr);
+  scaled(-c, e, 1.)
+}
+
+fn plus(a: Expr, b: Expr) -> Expr {
+  match coefficient(&b) {
+    (c, e) if c < 0. => sub(a, scaled(-c, e, 1.)),
+    _ => add(a, b),
+  }
+}
+
+fn minus(a: Expr, b: Expr) -> Expr {
+  match coefficient(&b) {
+    (c, e) if c < 0. => add(a, scaled(-c, e, 1.)),
+    _ => sub(a, b),
+  }
+}
+
+/// `a * b`, with the numbers moved to the front.
+fn times(a: Expr, b: Expr) -> Expr {
+  let (c, u) = coefficient(&a);
+  let (d, v) = coefficient(&b);
+  scaled(c * d, mul(u, v), 1.)
+}
+
+/// `u^n`, as `1 / u^-n` for a negative `n`.
+fn power(u: &Expr, n: f64) -> Expr {
+  let pow = |n: f64| if n == 1. { u.clone() } else { u.powf(n) };
+  if n < 0. {
+    div(Expr::num(1.), pow(-n))
+  } else {
+    pow(n)
+  }
+}
+
+/// The factors of a product or quotient, with the power `1` or `-1` they appear with.
+fn factors(expr: &Expr, power: i32, res: &mut Vec<(Expr, i32)>) {
+  match split(expr) {
+    (Token::Binary(Operation::Times), args) => {
+      factors(&args[0], power, res);
+      factors(&args[1], power, res);
+    }
+    (Token::Binary(Operation::Div), args) => {
+      factors(&args[0], power, res);
+      factors(&args[1], -power, res);
+    }
+    _ => res.push((expr.clone(), power)),
+  }
+}
+
+fn product(factors: &[(Expr, i32)]) -> Expr {
+  let (num, den) = factors
+    .iter()
+    .fold((Expr::num(1.), Expr::num(1.)), |(num, den), (f, power)| {
+      if *power > 0 {
+        (mul(num, f.clone()), den)
+      } else {
+        (num, mul(den, f.clone()))
+      }
+    });
+  div(num, den)
+}
+
+/// Replaces every occurrence of the subexpression `term` by `rplc`.
+fn replace_all(expr: &Expr, term: &Expr, rplc: &Expr) -> Expr {
+  expr.fold_expr(|args: Vec<Expr>, tkn| {
+    let mut tkns: Vec<Token> = args.into_iter().flat_map(|e| e.0).collect();
+    tkns.push(tkn);
+    let e = Expr(tkns);
+    if e == *term {
+      rplc.clone()
+    } else {
+      e
+    }
+  })
+}
+
+/// Antiderivatives in `var`, with the functions and constants of `ctx`.
+struct Integrator<'a, C: ContextProvider> {
+  var: &'a str,
+  ctx: &'a C,
+}
+
+impl<'a, C: ContextProvider> Integrator<'a, C> {
+  fn integrate(&self, expr: &Expr, depth: usize) -> Result<Expr, Error> {
+    use self::Operation::*;
+    use self::Token::*;
+
+    if depth > MAX_DEPTH {
+      return Err(no_integral(expr));
+    }
+    if !depends_on(expr, self.var) {
+      return Ok(times(expr.clone(), Expr::var(self.var)));
+    }
+    if let Ok(poly) = Polynomial::from_expr(expr) {
+      return Ok(self.polynomial(&poly));
+    }
+
+    let res = match split(expr) {
+      (Unary(Plus), args) => Some(self.integrate(&args[0], depth)?),
+      (Unary(Minus), args) => Some(neg(self.integrate(&args[0], depth)?)),
+      (Binary(Plus), args) => Some(plus(
+        self.integrate(&args[0], depth)?,
+        self.integrate(&args[1], depth)?,
+      )),
+      (Binary(Minus), args) => Some(minus(
+        self.integrate(&args[0], depth)?,
+        self.integrate(&args[1], depth)?,
+      )),
+      (Binary(Times), _) | (Binary(Div), _) => {
+        let mut fs = vec![];
+        factors(expr, 1, &mut fs);
+        fs.retain(|(f, _)| f.as_num() != Some(1.));
+        let (consts, fs): (Vec<_>, Vec<_>) =
+          fs.into_iter().partition(|(f, _)| !depends_on(f, self.var));
+        if !consts.is_empty() {
+          let rest = self.integrate(&product(&fs), depth)?;
+          return Ok(times(product(&consts), rest));
+        }
+        self
+          .rational(expr)
+          .or_else(|| self.substitution(expr, &fs, depth))
+          .or_else(|| self.by_parts(&fs, depth))
+      }
+      (Binary(Pow), args) => self
+        .power(expr, &args[0], &args[1])
+        .or_else(|| self.rational(expr))
+        .or_else(|| self.substitution(expr, &[(expr.clone(), 1)], depth)),
+      (Func(name, Some(1)), args) => self
+        .function(name, &args[0])
+        .or_else(|| self.substitution(expr, &[(expr.clone(), 1)], depth)),
+      _ => None,
+    };
+    res.ok_or_else(|| no_integral(expr))
+  }
+
+  /// `expr / a`, by the number `a` if it evaluates to one.
+  fn over(&self, expr: Expr, a: &Expr) -> Expr {
+    match a.eval_with_context(self.ctx) {
+      Ok(a) => times(Expr::num(1. / a), expr),
+      Err(_) => div(expr, a.clone()),
+    }
+  }
+
+  /// The slope `a` of an argument `a * var + b` linear in `var`.
+  fn slope(&self, u: &Expr) -> Option<Expr> {
+    let a = u.derivative_with_context(self.var, self.ctx).ok()?;
+    if depends_on(&a, self.var) || a.as_num() == Some(0.) {
+      None
+    } else {
+      Some(a)
+    }
+  }
+
+  /// The antiderivative of a polynomial, term by term.
+  fn polynomial(&self, poly: &Polynomial) -> Expr {
+    let x = Expr::var(self.var);
+    let mut res = Expr::num(0.);
+    for (k, c) in poly.coefficients(self.var).iter().enumerate().rev() {
+      let n = (k + 1) as f64;
+      res = match c.as_constant() {
+        _ if c.is_zero() => res,
+        Some(c) => plus(res, scaled(c, power(&x, n), n)),
+        None => plus(res, div(mul(c.to_expr(), power(&x, n)), Expr::num(n))),
+      };
+    }
+    res
+  }
+
+  /// `u^v` for `u` linear in `var` and a constant `v`, and for a constant `u` and `v` linear in
+  /// `var`.
+  fn power(&self, expr: &Expr, u: &Expr, v: &Expr) -> Option<Expr> {
+    if !depends_on(v, self.var) {
+      let a = self.slope(u)?;
+      return Some(match v.eval_with_context(self.ctx) {
+        Ok(-1.) => self.over(u.abs().ln(), &a),
+        Ok(n) => self.over(scaled(1., power(u, n + 1.), n + 1.), &a),
+        Err(_) => div(u.powf(v + 1.), mul(v + 1., a)),
+      });
+    }
+    if !depends_on(u, self.var) {
+      let a = self.slope(v)?;
+      return Some(div(expr.clone(), mul(u.ln(), a)));
+    }
+    None
+  }
+
+  /// A function of an argument linear in `var`, from its antiderivative in the context.
+  fn function(&self, name: &str, u: &Expr) -> Option<Expr> {
+    let antiderivative = self.ctx.func_info(name)?.antiderivative?;
+    let a = self.slope(u)?;
+    let mut subs = Substitutions::new();
+    subs.insert("x1".into(), u.clone());
+    Some(self.over(subs.apply(&antiderivative), &a))
+  }
+
+  /// A rational function of `var`, by partial fractions over the linear and quadratic factors
+  /// of the denominator.
+  fn rational(&self, expr: &Expr) -> Option<Expr> {
+    let (num, den) = Polynomial::fraction_from_expr(expr).ok()?;
+    if num.vars().iter().chain(&den.vars()).any(|v| v != self.var) {
+      return None;
+    }
+    let var = self.var;
+    let (quotient, rem) = num.div_rem(&den, var).ok()?;
+    let mut res = self.polynomial(&quotient);
+    if rem.is_zero() {
+      return Some(res);
+    }
+
+    // split the quadratic factors with real roots
+    let factors = den.factor().ok()?;
+    let mut constant = factors.constant;
+    let mut fs: Vec<(Polynomial, u32)> = vec![];
+    for (f, k) in factors.factors {
+      let c: Vec<f64> = f
+        .coefficients(var)
+        .iter()
+        .map(|c| c.as_constant().unwrap_or(0.))
+        .collect();
+      match c.len() - 1 {
+        1 => fs.push((f, k)),
+        2 if c[1] * c[1] - 4. * c[2] * c[0] < 0. => fs.push((f, k)),
+        2 => {
+          let d = (c[1] * c[1] - 4. * c[2] * c[0]).sqrt();
+          for r in &[(-c[1] - d) / (2. * c[2]), (-c[1] + d) / (2. * c[2])] {
+            fs.push((&Polynomial::var(var) - &Polynomial::constant(*r), k));
+          }
+          constant *= c[2].powi(k as i32);
+        }
+        _ => return None,
+      }
+    }
+
+    // rem / den as the sum of x^t / f^j over the factors f^k, for j <= k and t < deg(f)
+    let mut basis = vec![];
+    for (i, (f, k)) in fs.iter().enumerate() {
+      let others = fs
+        .iter()
+        .enumerate()
+        .filter(|&(l, _)| l != i)
+        .fold(Polynomial::constant(constant), |acc, (_, (g, kg))| {
+          &acc * &g.pow(*kg)
+        });
+      for j in 1..=*k {
+        let cofactor = &others * &f.pow(k - j);
+        for t in 0..f.degree_in(var) {
+          basis.push((i, j, t, &cofactor * &Polynomial::var(var).pow(t)));
+        }
+      }
+    }
+    let n = basis.len();
+    let a = (0..n)
+      .map(|row| {
+        basis
+          .iter()
+          .map(|(.., b)| b.coefficient(var, row as u32).as_constant().unwrap_or(0.))
+          .collect()
+      })
+      .collect();
+    let b = (0..n)
+      .map(|row| rem.coefficient(var, row as u32).as_constant().unwrap_or(0.))
+      .collect();
+    let coeffs = gauss(a, b, n).ok()?;
+    let scale = coeffs.iter().fold(0f64, |max, c| max.max(c.abs()));
+
+    let x = Expr::var(var);
+    for (i, (f, _)) in fs.iter().enumerate() {
+      let c: Vec<f64> = f
+        .coefficients(var)
+        .iter()
+        .map(|c| c.as_constant().unwrap_or(0.))
+        .collect();
+      // the coefficient of x^t / f^j
+      let coeff = |j: u32, t: u32| {
+        basis
+          .iter()
+          .zip(&coeffs)
+          .find(|((l, k, s, _), _)| (*l, *k, *s) == (i, j, t))
+          .map_or(0., |(_, c)| if c.abs() > 1e-12 * scale { *c } else { 0. })
+      };
+      let f = f.to_expr();
+      if c.len() == 2 {
+        for j in 1..=fs[i].1 {
+          res = plus(
+            res,
+            if j == 1 {
+              scaled(coeff(1, 0), (&f).abs().ln(), c[1])
+            } else {
+              let n = 1. - j as f64;
+              scaled(coeff(j, 0), power(&f, n), c[1] * n)
+            },
+          );
+        }
+        continue;
+      }
+      if fs[i].1 > 1 {
+        return None;
+      }
+      // (b * x + a) / f as b / (2 * c2) * f' / f + (a - b * c1 / (2 * c2)) / f
+      let (a, b) = (coeff(1, 0), coeff(1, 1));
+      if b != 0. {
+        let log = if c[2] > 0. { f.ln() } else { f.abs().ln() };
+        res = plus(res, scaled(b, log, 2. * c[2]));
+      }
+      let a = a - b * c[1] / (2. * c[2]);
+      if a.abs() > 1e-12 * scale {
+        let s = (4. * c[2] * c[0] - c[1] * c[1]).sqrt();
+        let arg = plus(scaled(2. * c[2], x.clone(), s), Expr::num(c[1] / s));
+        res = plus(res, scaled(2. * a, arg.atan(), s));
+      }
+    }
+    Some(res)
+  }
+
+  /// By the substitution `t = g` of a subexpression `g` whose derivative is a constant multiple
+  /// of a factor, so that the integrand is `c * h(g) * g'`.
+  fn substitution(&self, expr: &Expr, fs: &[(Expr, i32)], depth: usize) -> Option<Expr> {
+    let t = (0..)
+      .map(|i| format!("t{}", i))
+      .find(|t| !depends_on(expr, t) && t != self.var)?;
+    let t_expr = Expr::var(t.clone());
+    let ptrs = expr.start_pointers();
+    let mut candidates: Vec<Expr> = vec![];
+    for (i, &start) in ptrs.iter().enumerate() {
+      let g = Expr(expr[start..=i].to_vec());
+      if depends_on(&g, self.var) && g.as_var().is_none() && !candidates.contains(&g) {
+        candidates.push(g);
+      }
+    }
+
+    for g in &candidates {
+      let dg = match g.derivative_with_context(self.var, self.ctx) {
+        Ok(dg) if depends_on(&dg, self.var) => dg,
+        _ => continue,
+      };
+      for (i, (f, power)) in fs.iter().enumerate() {
+        let f = if *power > 0 {
+          f.clone()
+        } else {
+          div(Expr::num(1.), f.clone())
+        };
+        let c = match self.constant_ratio(&f, &dg) {
+          Some(c) => c,
+          None => continue,
+        };
+        let rest: Vec<_> = fs
+          .iter()
+          .enumerate()
+          .filter(|&(l, _)| l != i)
+          .map(|(_, f)| f.clone())
+          .collect();
+        let h = replace_all(&product(&rest), g, &t_expr);
+        if depends_on(&h, self.var) {
+          continue;
+        }
+        let inner = Integrator {
+          var: &t,
+          ctx: self.ctx,
+        };
+        if let Ok(res) = inner.integrate(&h, depth + 1) {
+          let mut subs = Substitutions::new();
+          subs.insert(t.clone(), g.clone());
+          return Some(times(Expr::num(c), subs.apply(&res)));
+        }
+      }
+    }
+    None
+  }
+
+  /// `f / g` if it is the same number at every sample point where both can be evaluated.
+  fn constant_ratio(&self, f: &Expr, g: &Expr) -> Option<f64> {
+    let ratios: Vec<f64> = self
+      .values(f)
+      .iter()
+      .zip(self.values(g))
+      .map(|(f, g)| f / g)
+      .filter(|r| r.is_finite())
+      .collect();
+    let r = *ratios.first()?;
+    if ratios.len() > 1 && ratios.iter().all(|s| (s - r).abs() < 1e-9 * (1. + r.abs())) {
+      Some(r)
+    } else {
+      None
+    }
+  }
+
+  /// A polynomial times another function, by parts: integrating the other function and
+  /// differentiating the polynomial, or the other way around if the other function has a
+  /// rational derivative, e.g. `ln(x)` or `atan(x)`.
+  fn by_parts(&self, fs: &[(Expr, i32)], depth: usize) -> Option<Expr> {
+    let (polys, others): (Vec<_>, Vec<_>) = fs
+      .iter()
+      .cloned()
+      .partition(|(f, power)| *power > 0 && Polynomial::from_expr(f).is_ok());
+    if polys.is_empty() || others.is_empty() {
+      return None;
+    }
+    let (p, f) = (product(&polys), product(&others));
+    let poly = Polynomial::from_expr(&p).ok()?;
+    let df = f.derivative_with_context(self.var, self.ctx).ok()?;
+    if Polynomial::fraction_from_expr(&df).is_ok() {
+      let g = self.polynomial(&poly);
+      let rest = self.integrate(&times(g.clone(), df), depth + 1).ok()?;
+      Some(minus(times(g, f), rest))
+    } else {
+      let g = self.integrate(&f, depth + 1).ok()?;
+      let dp = poly.derivative(self.var).to_expr();
+      let rest = self.integrate(&times(dp, g.clone()), depth + 1).ok()?;
+      Some(minus(times(p, g), rest))
+    }
+  }
+
+  /// The values of `expr` at the sample points, with two choices of values for the variables
+  /// not in the context, NaN where it can't be evaluated.
+  fn values(&self, expr: &Expr) -> Vec<f64> {
+    let mut others: Vec<&str> = vec![];
+    for tkn in expr.iter() {
+      if let Token::Var(name) = tkn {
+        if name != self.var && self.ctx.get_var(name).is_none() && !others.contains(&&**name) {
+          others.push(name);
+        }
+      }
+    }
+    let mut res = vec![];
+    for &shift in &[0.6, 1.7] {
+      let bound: Vec<(String, f64)> = others
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.to_string(), shift + 0.31 * i as f64))
+        .collect();
+      for &x in &SAMPLES {
+        let value = expr.eval_with_context(((self.var, x), (&bound, self.ctx)));
+        res.push(value.unwrap_or(f64::NAN));
+      }
+    }
+    res
+  }
+
+  /// Whether the derivative of `antiderivative` is `expr`, relative to its size, at the sample
+  /// points where both can be evaluated, of which there has to be at least one.
+  fn checks(&self, expr: &Expr, antiderivative: &Expr) -> bool {
+    let d = match antiderivative.derivative_with_context(self.var, self.ctx) {
+      Ok(d) => d,
+      Err(_) => return false,
+    };
+    let values = self.values(expr);
+    let mut compared = values
+      .iter()
+      .zip(self.values(&d))
+      .filter(|(f, g)| f.is_finite() && g.is_finite())
+      .peekable();
+    compared.peek().is_some() && compared.all(|(f, g)| (f - g).abs() <= 1e-6 * f.abs())
+  }
+}
+
+impl Expr {
+  /// An antiderivative with respect to `var`, with the built-in constants and functions.
+  ///
+  /// See [`integrate_with_context`](#method.integrate_with_context).
+  pub fn integrate(&self, var: &str) -> Result<Expr, Error> {
+    self.integrate_with_context(var, builtin())
+  }
+
+  /// An antiderivative with respect to `var`, without the constant of integration, taking the
+  /// antiderivatives of functions from their [`FuncInfo`](struct.FuncInfo.html) in `ctx`.
+  ///
+  /// Integrates polynomials, functions with known antiderivatives of arguments linear in
+  /// `var`, sums and constant multiples, rational functions by partial fractions,
+  /// substitutions `t = g(x)` where the derivative of `g` is a factor up to a constant, and
+  /// polynomials times other functions by parts. The antiderivative found is checked by
+  /// differentiating it at a few points.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NoIntegral)` if none of the methods applies, e.g. to `exp(x^2)`.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Expr;
+  /// let expr: Expr = "3 * x^2 + cos(2 * x)".parse().unwrap();
+  /// assert_eq!(expr.integrate("x").unwrap().to_string(), "x^3 + sin(2 * x) / 2");
+  /// ```
+  pub fn integrate_with_context<C: ContextProvider>(
+    &self,
+    var: &str,
+    ctx: C,
+  ) -> Result<Expr, Error> {
+    let integrator = Integrator { var, ctx: &ctx };
+    let res = integrator.integrate(self, 0)?;
+    if integrator.checks(self, &res) {
+      Ok(res)
+    } else {
+      Err(no_integral(self))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use expr::func_info::BUILTIN_ANTIDERIVATIVES;
+  use Context;
+
+  /// Whether `f` and `g` agree at the points where both can be evaluated, and at some point.
+  fn same_values<C: ContextProvider>(f: &Expr, g: &Expr, ctx: C) -> bool {
+    let mut compared = 0;
+    for &x in &[-1.7, -0.4, 0.3, 0.8, 1.6, 2.5] {
+      let ctx = (("x", x), &ctx);
+      match (f.eval_with_context(ctx), g.eval_with_context(ctx)) {
+        (Ok(f), Ok(g)) if f.is_finite() && g.is_finite() => {
+          if (f - g).abs() > 1e-9 * (1. + f.abs()) {
+            return false;
+          }
+          compared += 1;
+        }
+        _ => {}
+      }
+    }
+    compared > 0
+  }
+
+  /// The antiderivative, after checking that its derivative is `expr`.
+  fn integral(expr: Expr) -> String {
+    let res = expr.integrate("x").unwrap();
+    let d = res.derivative("x").unwrap();
+    let ctx = ([("a", 1.3), ("b", -0.7), ("n", 2.5), ("y", 0.9)], builtin());
+    assert!(same_values(&d, &expr, ctx), "{} of {}", res, expr);
+    res.to_string()
+  }
+
+  #[test]
+  fn polynomials_and_builtins() {
+    assert_eq!(integral(expr!(3 * x ^ 2 - 4 * x + 1)), "x^3 - 2 * x^2 + x");
+    assert_eq!(integral(expr!(x ^ 3 / 2)), "x^4 / 8");
+    assert_eq!(integral(expr!(a * x + b)), "a * x^2 / 2 + b * x");
+    assert_eq!(integral(expr!(2 * sin(x))), "-2 * cos(x)");
+    assert_eq!(integral(expr!(exp(3 * x + 1))), "exp(3 * x + 1) / 3");
+    assert_eq!(integral(expr!(cosh(x / 2))), "2 * sinh(x / 2)");
+    assert_eq!(integral(expr!(ln(x))), "x * ln(x) - x");
+    assert_eq!(integral(expr!(sqrt(2 * x + 1))), "(2 * x + 1)^1.5 / 3");
+    assert_eq!(integral(expr!((x + 1) ^ -3)), "-1 / (2 * (x + 1)^2)");
+    assert_eq!(integral(expr!(2 ^ x)), "2^x / ln(2)");
+    assert_eq!(integral(expr!(x ^ n)), "x^(n + 1) / (n + 1)");
+    assert_eq!(integral(expr!(1 / x + y)), "ln(abs(x)) + y * x");
+  }
+
+  #[test]
+  fn rational_functions() {
+    assert_eq!(integral(expr!(1 / (x ^ 2 + 1))), "atan(x)");
+    assert_eq!(
+      integral(expr!(1 / (x ^ 2 - 1))),
+      "-(ln(abs(x + 1)) / 2) + ln(abs(x - 1)) / 2"
+    );
+    assert_eq!(
+      integral(expr!((x ^ 3 + 1) / (x ^ 2 - 3 * x + 2))),
+      "x^2 / 2 + 3 * x - 2 * ln(abs(x - 1)) + 9 * ln(abs(x - 2))"
+    );
+    assert_eq!(integral(expr!(1 / (x - 1) ^ 2)), "-1 / (x - 1)");
+    assert_eq!(integral(expr!(x / (x ^ 2 + 1))), "ln(x^2 + 1) / 2");
+    assert_eq!(
+      integral(expr!(1 / (x ^ 2 + 2 * x + 5))),
+      "atan(x / 2 + 0.5) / 2"
+    );
+    // irrational roots and an irreducible quadratic factor
+    integral(expr!(1 / (x ^ 2 - 2)));
+    integral(expr!((x + 2) / (x ^ 3 + 1)));
+  }
+
+  #[test]
+  fn substitutions() {
+    assert_eq!(integral(expr!(x * exp(x ^ 2))), "exp(x^2) / 2");
+    assert_eq!(integral(expr!(sin(x) ^ 2 * cos(x))), "sin(x)^3 / 3");
+    assert_eq!(integral(expr!(ln(x) / x)), "ln(x)^2 / 2");
+    assert_eq!(integral(expr!(cos(x) / sin(x))), "ln(abs(sin(x)))");
+    assert_eq!(integral(expr!(x * sqrt(x ^ 2 + 1))), "(x^2 + 1)^1.5 / 3");
+    assert_eq!(integral(expr!(1 / (x * ln(x)))), "ln(abs(ln(x)))");
+  }
+
+  #[test]
+  fn by_parts() {
+    assert_eq!(integral(expr!(x * exp(x))), "x * exp(x) - exp(x)");
+    assert_eq!(
+      integral(expr!(x ^ 2 * exp(x))),
+      "x^2 * exp(x) - 2 * (x * exp(x) - exp(x))"
+    );
+    assert_eq!(integral(expr!(x * sin(x))), "-(x * cos(x)) + sin(x)");
+    assert_eq!(integral(expr!(x * ln(x))), "x^2 * ln(x) / 2 - x^2 / 4");
+    assert_eq!(
+      integral(expr!(x * atan(x))),
+      "x^2 * atan(x) / 2 - (x - atan(x)) / 2"
+    );
+    integral(expr!(x ^ 2 * sin(3 * x)));
+  }
+
+  #[test]
+  fn functions_from_the_context() {
+    let mut ctx = Context::new();
+    ctx.var("k", 2.).func("double", |x| 2. * x);
+    {
+      let info = ctx.info_mut("double").unwrap();
+      info.derivatives = vec![expr!(2)];
+      info.antiderivative = Some(expr!(x1 ^ 2));
+    }
+    let expr = expr!(double(k * x));
+    let res = expr.integrate_with_context("x", &ctx).unwrap();
+    assert_eq!(res.to_string(), "(k * x)^2 / 2");
+    let d = res.derivative_with_context("x", &ctx).unwrap();
+    assert!(same_values(&d, &expr, &ctx));
+  }
+
+  #[test]
+  fn without_elementary_antiderivative() {
+    for expr in &[expr!(exp(x ^ 2)), expr!(sin(x) / x), expr!(exp(x) * sin(x))] {
+      assert_eq!(
+        expr.integrate("x"),
+        Err(Error::NoIntegral(expr.to_string()))
+      );
+    }
+    assert_eq!(
+      expr!(max(x, 1)).integrate("x"),
+      Err(Error::NoIntegral("max(x, 1)".into()))
+    ); // undefined at all the sample points
+    assert_eq!(
+      expr!(sqrt(x - 10)).integrate("x"),
+      Err(Error::NoIntegral("sqrt(x - 10)".into()))
+    );
+  }
+
+  #[test]
+  fn check_relative_to_the_integrand() {
+    let ctx = builtin();
+    let integrator = Integrator {
+      var: "x",
+      ctx: &ctx,
+    };
+    let small: Expr = "1e-9 * x".parse().unwrap();
+    assert!(!integrator.checks(&small, &expr!(0)));
+    assert!(integrator.checks(&small, &"5e-10 * x^2".parse().unwrap()));
+
+    // however small the integrand is
+    let expr: Expr = "1 / (x^2 - 1e10)".parse().unwrap();
+    let d = expr.integrate("x").unwrap().derivative("x").unwrap();
+    let ctx = (("x", 0.3), builtin());
+    let (f, g) = (expr.eval_with_context(&ctx), d.eval_with_context(&ctx));
+    assert!((f.unwrap() - g.unwrap()).abs() < 1e-9 * 1e-10, "{}", d);
+  }
+
+  #[test]
+  fn builtin_antiderivatives() {
+    let ctx = builtin();
+    for &(name, _) in BUILTIN_ANTIDERIVATIVES {
+      let f = Expr::call(name, vec![Expr::var("x")]);
+      let d = f.integrate("x").unwrap().derivative("x").unwrap();
+      assert!(same_values(&d, &f, &ctx), "{}", name);
+    }
+  }
+}
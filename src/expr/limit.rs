@@ -0,0 +1,782 @@
+//! Limits of expressions at points and at infinity, from either side.
+use super::operators::{Exp, Ln, Powf};
+use super::{builtin, ContextProvider, Expr, Operation, Substitutions, Token};
+use polynomial::Polynomial;
+use std::f64;
+use std::f64::consts::FRAC_PI_2;
+use std::fmt::{self, Display, Formatter};
+use Error;
+
+/// How many times L'Hôpital's rule and the other rewrites are applied within one limit.
+const MAX_DEPTH: usize = 8;
+
+/// The functions that jump at integers or at zero, whose value at a point says nothing about
+/// their limits there.
+const STEP_FUNCTIONS: &[&str] = &["floor", "ceil", "round", "trunc", "fract", "signum"];
+
+/// The side a limit at a finite point is taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Side {
+  /// The limit from both sides, which exists if the one-sided limits are equal.
+  #[default]
+  Both,
+  /// From below, `x -> a-`.
+  Left,
+  /// From above, `x -> a+`.
+  Right,
+}
+
+/// The limit of an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limit {
+  Finite(Expr),
+  Infinity,
+  NegInfinity,
+  /// The limit doesn't exist, e.g. of `sin(x)` at infinity, or of `1 / x` at 0 from both
+  /// sides.
+  Undefined,
+}
+
+impl Limit {
+  /// The limit as a number, infinite or NaN if it isn't finite.
+  pub fn as_f64(&self) -> f64 {
+    match self {
+      Limit::Finite(expr) => expr.as_num().unwrap_or(f64::NAN),
+      Limit::Infinity => f64::INFINITY,
+      Limit::NegInfinity => f64::NEG_INFINITY,
+      Limit::Undefined => f64::NAN,
+    }
+  }
+}
+
+impl Display for Limit {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Limit::Finite(expr) => write!(f, "{}", expr),
+      Limit::Infinity => write!(f, "inf"),
+      Limit::NegInfinity => write!(f, "-inf"),
+      Limit::Undefined => write!(f, "undefined"),
+    }
+  }
+}
+
+/// The limit of a subexpression, with infinities signed by `±1`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ext {
+  Finite(f64),
+  Inf(f64),
+  Undefined,
+}
+
+impl Ext {
+  fn neg(self) -> Ext {
+    match self {
+      Ext::Finite(x) => Ext::Finite(-x),
+      Ext::Inf(s) => Ext::Inf(-s),
+      Ext::Undefined => Ext::Undefined,
+    }
+  }
+
+  /// The limit of `exp(u)` where `u` has this limit.
+  fn exp(self) -> Ext {
+    match self {
+      Ext::Finite(x) => Ext::Finite(x.exp()),
+      Ext::Inf(s) if s > 0. => Ext::Inf(1.),
+      Ext::Inf(_) => Ext::Finite(0.),
+      Ext::Undefined => Ext::Undefined,
+    }
+  }
+}
+
+/// The limit of `name(u)` where `u` goes to infinity with sign `s`, for the built-in
+/// functions of one argument.
+fn at_infinity(name: &str, s: f64) -> Option<Ext> {
+  let ext = match name {
+    "exp" | "exp2" if s > 0. => Ext::Inf(1.),
+    "exp" | "exp2" => Ext::Finite(0.),
+    "exp_m1" if s > 0. => Ext::Inf(1.),
+    "exp_m1" => Ext::Finite(-1.),
+    "ln" | "log10" | "log2" | "ln_1p" | "sqrt" | "acosh" if s > 0. => Ext::Inf(1.),
+    "ln" | "log10" | "log2" | "ln_1p" | "sqrt" | "acosh" => Ext::Undefined,
+    "cbrt" | "sinh" | "asinh" | "floor" | "ceil" | "round" | "trunc" => Ext::Inf(s),
+    "cosh" | "abs" => Ext::Inf(1.),
+    "atan" => Ext::Finite(s * FRAC_PI_2),
+    "tanh" | "signum" => Ext::Finite(s),
+    "recip" => Ext::Finite(0.),
+    "sin" | "cos" | "tan" | "fract" => Ext::Undefined,
+    _ => return None,
+  };
+  Some(ext)
+}
+
+fn no_limit(expr: &Expr) -> Error {
+  Error::NoLimit(expr.to_string())
+}
+
+/// Rounds `x` if it is an integer up to rounding errors.
+fn snap(x: f64) -> f64 {
+  if (x.round() - x).abs() < 1e-9 * (1. + x.abs()) {
+    x.round()
+  } else {
+    x
+  }
+}
+
+/// The point close to `x` where the step function `name` jumps, if there is one.
+fn jump(name: &str, x: f64) -> Option<f64> {
+  let n = snap(x);
+  match name {
+    "round" => Some(snap(2. * x) / 2.).filter(|h| h.fract().abs() == 0.5),
+    "signum" => Some(n).filter(|&n| n == 0.),
+    "trunc" | "fract" => Some(n).filter(|&n| n.fract() == 0. && n != 0.),
+    _ => Some(n).filter(|&n| n.fract() == 0.),
+  }
+}
+
+/// The limit of expressions in `var` at `point`, from the side `dir`: `±1` for the side of a
+/// finite point, or the sign of an infinite one.
+struct LimitEval<'a, C: ContextProvider> {
+  var: &'a str,
+  point: f64,
+  dir: f64,
+  ctx: C,
+}
+
+impl<'a, C: ContextProvider> LimitEval<'a, C> {
+  /// The values of `expr` at points closer and closer to the limit point, NaN where it can't
+  /// be evaluated.
+  fn near(&self, expr: &Expr) -> Vec<f64> {
+    [1e3, 1e5, 1e7]
+      .iter()
+      .map(|&n| {
+        let x = if self.point.is_finite() {
+          self.point + self.dir * (1. + self.point.abs()) / n
+        } else {
+          self.dir * n
+        };
+        expr
+          .eval_with_context(((self.var, x), &self.ctx))
+          .unwrap_or(f64::NAN)
+      })
+      .collect()
+  }
+
+  /// Whether `expr` is defined close to the limit point, on the side it is taken from.
+  fn defined_near(&self, expr: &Expr) -> bool {
+    self.near(expr).last().is_some_and(|x| !x.is_nan())
+  }
+
+  /// The side `expr` approaches `x` from close to the limit point: `±1`, or 0 if it is equal
+  /// to `x` there.
+  fn approach(&self, expr: &Expr, x: f64) -> Option<f64> {
+    let values = self.near(expr);
+    if values.iter().all(|&y| y == x) {
+      Some(0.)
+    } else if values.iter().all(|&y| y > x) {
+      Some(1.)
+    } else if values.iter().all(|&y| y < x) {
+      Some(-1.)
+    } else {
+      None
+    }
+  }
+
+  /// The limit of the step function `name` of `u`, whose limit is `x`: the value at `x` where
+  /// the function is continuous, and at a jump the value on the side `u` approaches it from.
+  fn step(&self, name: &str, u: &Expr, x: f64) -> Ext {
+    let eval = |x: f64| {
+      self
+        .ctx
+        .eval_func(name, &[x])
+        .ok()
+        .filter(|y| y.is_finite())
+    };
+    let value = match jump(name, x) {
+      None => eval(x),
+      Some(j) => match self.approach(u, j) {
+        Some(0.) => eval(j),
+        // the jumps are at least 1/2 apart, and the functions constant between them, except
+        // `fract`, which grows like its argument
+        Some(s) if name == "fract" => eval(j + s / 4.).map(|y| y - s / 4.),
+        Some(s) => eval(j + s / 4.),
+        None => None,
+      },
+    };
+    value.map_or(Ext::Undefined, Ext::Finite)
+  }
+
+  /// The sign of `expr` close to the limit point, if it doesn't change there.
+  fn sign_near(&self, expr: &Expr) -> Option<f64> {
+    let values = self.near(expr);
+    if values.iter().all(|&x| x > 0.) {
+      Some(1.)
+    } else if values.iter().all(|&x| x < 0.) {
+      Some(-1.)
+    } else {
+      None
+    }
+  }
+
+  /// An infinite limit with the sign of `expr` close to the limit point, or undefined if the
+  /// sign changes.
+  fn infinite(&self, expr: &Expr, sign: f64) -> Ext {
+    self
+      .sign_near(expr)
+      .map_or(Ext::Undefined, |s| Ext::Inf(s * sign))
+  }
+
+  fn limit(&self, expr: &Expr, depth: usize) -> Result<Ext, Error> {
+    if depth > MAX_DEPTH {
+      return Err(no_limit(expr));
+    }
+    if let Some(ext) = self.rational(expr) {
+      return Ok(ext);
+    }
+    let continuous = !expr.iter().any(|tkn| match tkn {
+      Token::Func(name, _) => STEP_FUNCTIONS.contains(&name.as_str()),
+      Token::Binary(Operation::Rem) => true,
+      _ => false,
+    });
+    if self.point.is_finite() && continuous {
+      match expr.eval_with_context(((self.var, self.point), &self.ctx)) {
+        Ok(x) if x.is_finite() && self.defined_near(expr) => return Ok(Ext::Finite(x)),
+        Ok(x) if x.is_finite() => return Ok(Ext::Undefined),
+        _ => {}
+      }
+    }
+    self.structural(expr, depth)
+  }
+
+  /// The limit of a fraction of polynomials in `var`, from the ratio of the leading terms at
+  /// infinity, and after cancelling their common factors at a finite point.
+  fn rational(&self, expr: &Expr) -> Option<Ext> {
+    let (num, den) = Polynomial::fraction_from_expr(expr).ok()?;
+    if num.vars().iter().chain(&den.vars()).any(|v| v != self.var) {
+      return None;
+    }
+    if num.is_zero() {
+      return Some(Ext::Finite(0.));
+    }
+    if den.as_constant().is_some() && self.point.is_finite() {
+      // a polynomial, continuous everywhere
+      return None;
+    }
+
+    if self.point.is_infinite() {
+      let (n, d) = (num.degree_in(self.var), den.degree_in(self.var));
+      let lead = |p: &Polynomial| p.leading_coefficient(self.var).as_constant();
+      let ratio = lead(&num)? / lead(&den)?;
+      return Some(if n == d {
+        Ext::Finite(ratio)
+      } else if n < d {
+        Ext::Finite(0.)
+      } else {
+        Ext::Inf(ratio.signum() * self.dir.powi((n - d) as i32))
+      });
+    }
+
+    let gcd = num.gcd(&den).ok()?;
+    let (num, _) = num.div_rem(&gcd, self.var).ok()?;
+    let (den, _) = den.div_rem(&gcd, self.var).ok()?;
+    let (n, d) = (
+      num.eval_at(self.var, self.point),
+      den.eval_at(self.var, self.point),
+    );
+    Some(if d.abs() > 1e-12 * (1. + n.abs()) {
+      Ext::Finite(n / d)
+    } else {
+      let (num, den) = (num.to_expr(), den.to_expr());
+      self.infinite(&(num / den), 1.)
+    })
+  }
+
+  /// The limit from the limits of the arguments of each operation and function, resolving the
+  /// indeterminate forms by rewriting them.
+  fn structural(&self, expr: &Expr, depth: usize) -> Result<Ext, Error> {
+    use self::Ext::*;
+    use self::Operation::*;
+    use self::Token::*;
+
+    let mut stack: Vec<(Expr, Ext)> = vec![];
+    for tkn in expr.iter() {
+      let args = stack.split_off(stack.len() - Expr::no_children(tkn));
+      let mut tkns: Vec<Token> = args.iter().flat_map(|(u, _)| u.0.clone()).collect();
+      tkns.push(tkn.clone());
+      let sub = Expr(tkns);
+
+      let ext = match (tkn, &args[..]) {
+        (Number(x), _) => Finite(*x),
+        (Var(name), _) if name == self.var => {
+          if self.point.is_finite() {
+            Finite(self.point)
+          } else {
+            Inf(self.dir)
+          }
+        }
+        (Var(name), _) => match self.ctx.get_var(name) {
+          Some(x) => Finite(x),
+          None => return Err(Error::UnknownVariable(name.clone())),
+        },
+        (_, args) if args.iter().any(|(_, ext)| *ext == Undefined) => Undefined,
+        (Unary(Plus), [(_, a)]) => *a,
+        (Unary(Minus), [(_, a)]) => a.neg(),
+        (Binary(Plus), [(u, a), (v, b)]) => self.sum(u, *a, v, *b, depth)?,
+        (Binary(Minus), [(u, a), (v, b)]) => self.sum(u, *a, &-v, b.neg(), depth)?,
+        (Binary(Times), [(u, a), (v, b)]) => self.product(u, *a, v, *b, depth)?,
+        (Binary(Div), [(u, a), (v, b)]) => self.quotient(u, *a, v, *b, depth)?,
+        (Binary(Pow), [(u, a), (v, b)]) => self.power(u, *a, v, *b, depth)?,
+        (Func(name, _), _) => self.function(name, &sub, &args)?,
+        // u % v = u - v * trunc(u / v)
+        (Binary(Rem), [(u, Finite(a)), (v, Finite(b))]) if *b != 0. => match jump("trunc", a / b) {
+          None => Finite(a % b),
+          Some(_) => match self.step("trunc", &(u.clone() / v.clone()), a / b) {
+            Finite(t) => Finite(a - b * t),
+            ext => ext,
+          },
+        },
+        // the remaining operations, e.g. `!`, only for finite limits
+        (_, args) if args.iter().all(|(_, ext)| matches!(ext, Finite(_))) => {
+          match self.near(&sub).last() {
+            Some(&x) if x.is_finite() => Finite(x),
+            _ => Undefined,
+          }
+        }
+        (Unary(Fact), [(_, Inf(s))]) if *s > 0. => Inf(1.),
+        _ => return Err(no_limit(&sub)),
+      };
+      // e.g. `sqrt(x)` at 0 from the left
+      let ext = match (tkn, ext) {
+        (Func(..), Finite(_)) | (Binary(Pow), Finite(_))
+          if self.point.is_finite() && !self.defined_near(&sub) =>
+        {
+          Undefined
+        }
+        (_, ext) => ext,
+      };
+      stack.push((sub, ext));
+    }
+    Ok(stack.pop().expect("Stack is empty, this is impossible.").1)
+  }
+
+  fn sum(&self, u: &Expr, a: Ext, v: &Expr, b: Ext, depth: usize) -> Result<Ext, Error> {
+    use self::Ext::*;
+    Ok(match (a, b) {
+      (Finite(x), Finite(y)) => Finite(x + y),
+      (Inf(s), Finite(_)) | (Finite(_), Inf(s)) => Inf(s),
+      (Inf(s), Inf(t)) if s == t => Inf(s),
+      _ => {
+        // inf - inf: from the series, or as u * (1 + v / u)
+        if let Ok(ext) = self.by_series(&(u.clone() + v.clone())) {
+          return Ok(ext);
+        }
+        match self.limit(&(v.clone() / u.clone()), depth + 1)? {
+          Finite(r) if r != -1. => Inf(a_sign(a) * (1. + r).signum()),
+          _ => return Err(no_limit(&(u.clone() + v.clone()))),
+        }
+      }
+    })
+  }
+
+  fn product(&self, u: &Expr, a: Ext, v: &Expr, b: Ext, depth: usize) -> Result<Ext, Error> {
+    use self::Ext::*;
+    Ok(match (a, b) {
+      (Finite(x), Finite(y)) => Finite(x * y),
+      (Inf(s), Finite(x)) | (Finite(x), Inf(s)) if x != 0. => Inf(s * x.signum()),
+      (Inf(s), Inf(t)) => Inf(s * t),
+      _ => {
+        // 0 * inf, from the series, or as inf / (1 / 0), or else 0 / (1 / inf)
+        if let Ok(ext) = self.by_series(&(u.clone() * v.clone())) {
+          return Ok(ext);
+        }
+        let (zero, inf) = if a == Finite(0.) { (u, v) } else { (v, u) };
+        match self.lhopital(inf, &recip(zero), depth) {
+          Ok(ext) => ext,
+          Err(_) => self.lhopital(zero, &recip(inf), depth)?,
+        }
+      }
+    })
+  }
+
+  fn quotient(&self, u: &Expr, a: Ext, v: &Expr, b: Ext, depth: usize) -> Result<Ext, Error> {
+    use self::Ext::*;
+    Ok(match (a, b) {
+      (Finite(x), Finite(y)) if y != 0. => Finite(x / y),
+      (Finite(x), Finite(_)) if x != 0. => self.infinite(v, x.signum()),
+      (Finite(_), Inf(_)) => Finite(0.),
+      (Inf(s), Finite(y)) if y != 0. => Inf(s * y.signum()),
+      (Inf(s), Finite(_)) => self.infinite(v, s),
+      // 0 / 0 and inf / inf
+      _ => self.lhopital(u, v, depth)?,
+    })
+  }
+
+  /// The limit of `u / v` by L'Hôpital's rule, or else from the series of the quotient.
+  fn lhopital(&self, u: &Expr, v: &Expr, depth: usize) -> Result<Ext, Error> {
+    let quotient = u.clone() / v.clone();
+    let derivatives = u
+      .derivative_with_context(self.var, &self.ctx)
+      .and_then(|du| Ok((du, v.derivative_with_context(self.var, &self.ctx)?)));
+    if let Ok((du, dv)) = derivatives {
+      if dv.as_num() != Some(0.) {
+        match self.limit(&(du / dv), depth + 1) {
+          Ok(ext) => return Ok(ext),
+          Err(Error::NoLimit(_)) => {}
+          Err(e) => return Err(e),
+        }
+      }
+    }
+    self.by_series(&quotient).map_err(|_| no_limit(&quotient))
+  }
+
+  fn power(&self, u: &Expr, a: Ext, v: &Expr, b: Ext, depth: usize) -> Result<Ext, Error> {
+    use self::Ext::*;
+    // 0^0, 1^inf and inf^0, as exp(v * ln(u))
+    let exp_ln = || -> Result<Ext, Error> {
+      if v
+        .iter()
+        .all(|tkn| !matches!(tkn, Token::Var(name) if name == self.var))
+      {
+        return Ok(Finite(1.));
+      }
+      Ok(self.limit(&(v.clone() * u.ln()), depth + 1)?.exp())
+    };
+    let integer = |y: f64| y.fract() == 0.;
+    Ok(match (a, b) {
+      (Finite(x), Finite(y)) if x == 0. && y == 0. => exp_ln()?,
+      (Finite(x), Finite(y)) if x == 0. && y < 0. => match self.sign_near(u) {
+        Some(s) if s > 0. || (integer(y) && y % 2. == 0.) => Inf(1.),
+        Some(_) if integer(y) => Inf(-1.),
+        _ => Undefined,
+      },
+      (Finite(x), Finite(y)) => match x.powf(y) {
+        p if p.is_nan() => Undefined,
+        p => Finite(p),
+      },
+      (Finite(1.), Inf(_)) => exp_ln()?,
+      (Finite(x), Inf(s)) if x > 1. => Inf(1.).pow_sign(s),
+      (Finite(x), Inf(s)) if x >= 0. => Finite(0.).pow_sign(s),
+      (Finite(x), Inf(s)) if x > -1. && s > 0. => Finite(0.),
+      (Finite(_), Inf(_)) => Undefined,
+      (Inf(_), Finite(0.)) => exp_ln()?,
+      (Inf(_), Finite(y)) if y < 0. => Finite(0.),
+      (Inf(s), Finite(_)) if s > 0. => Inf(1.),
+      (Inf(_), Finite(y)) if integer(y) && y % 2. == 0. => Inf(1.),
+      (Inf(_), Finite(y)) if integer(y) => Inf(-1.),
+      (Inf(_), Finite(_)) => Undefined,
+      (Inf(s), Inf(t)) if s > 0. && t > 0. => Inf(1.),
+      (Inf(s), Inf(_)) if s > 0. => Finite(0.),
+      _ => Undefined,
+    })
+  }
+
+  fn function(&self, name: &str, sub: &Expr, args: &[(Expr, Ext)]) -> Result<Ext, Error> {
+    use self::Ext::*;
+    let values: Option<Vec<f64>> = args
+      .iter()
+      .map(|(_, ext)| match ext {
+        Finite(x) => Some(*x),
+        _ => None,
+      })
+      .collect();
+    Ok(match (values, args) {
+      (Some(values), [(u, _)]) if STEP_FUNCTIONS.contains(&name) => self.step(name, u, values[0]),
+      (Some(values), _) => match self.ctx.eval_func(name, &values) {
+        Ok(x) if x.is_finite() => Finite(x),
+        Ok(x) if x.is_infinite() => self.infinite(sub, 1.),
+        _ => Undefined,
+      },
+      (None, [(_, Inf(s))]) => at_infinity(name, *s).ok_or_else(|| no_limit(sub))?,
+      (None, _) => return Err(no_limit(sub)),
+    })
+  }
+
+  /// The limit from the leading term of the series of `expr`, in `1 / var` at infinity.
+  fn by_series(&self, expr: &Expr) -> Result<Ext, Error> {
+    let (lead, side) = if self.point.is_finite() {
+      (
+        expr.leading_term(self.var, self.point, &self.ctx)?,
+        self.dir,
+      )
+    } else {
+      let mut subs = Substitutions::new();
+      subs.insert(self.var.into(), self.dir / Expr::var(self.var));
+      let expr = subs.apply(expr);
+      (expr.leading_term(self.var, 0., &self.ctx)?, 1.)
+    };
+    Ok(match lead {
+      None => Ext::Finite(0.),
+      Some((k, _)) if k > 0 => Ext::Finite(0.),
+      Some((0, c)) => Ext::Finite(c),
+      Some((k, c)) => Ext::Inf(c.signum() * side.powi(k)),
+    })
+  }
+}
+
+/// `1 / e`, undoing a reciprocal and negating the exponent of a power or an exponential, so
+/// that rewriting `0 * inf` as a quotient doesn't make L'Hôpital's rule go in circles.
+fn recip(e: &Expr) -> Expr {
+  let n = e.len();
+  match e.0.last() {
+    Some(Token::Binary(op @ Operation::Div)) | Some(Token::Binary(op @ Operation::Pow)) => {
+      let split = e.start_pointers()[n - 2];
+      let (u, v) = (
+        Expr(e.0[..split].to_vec()),
+        Expr(e.0[split..n - 1].to_vec()),
+      );
+      match op {
+        Operation::Div if u.as_num() == Some(1.) => v,
+        Operation::Div => v / u,
+        _ => u.powf(-v),
+      }
+    }
+    Some(Token::Func(name, Some(1))) if name == "exp" => (-Expr(e.0[..n - 1].to_vec())).exp(),
+    _ => 1. / e.clone(),
+  }
+}
+
+/// The sign of an infinite limit.
+fn a_sign(a: Ext) -> f64 {
+  match a {
+    Ext::Inf(s) => s,
+    _ => 1.,
+  }
+}
+
+trait PowSign {
+  /// The limit of `c^u` for `u` going to infinity with sign `s`, given as the limit for
+  /// `s > 0`.
+  fn pow_sign(self, s: f64) -> Ext;
+}
+
+impl PowSign for Ext {
+  fn pow_sign(self, s: f64) -> Ext {
+    match (self, s > 0.) {
+      (ext, true) => ext,
+      (Ext::Inf(_), false) => Ext::Finite(0.),
+      (_, false) => Ext::Inf(1.),
+    }
+  }
+}
+
+impl Expr {
+  /// The limit of the expression as `var` goes to `point`, with the built-in constants and
+  /// functions.
+  ///
+  /// See [`limit_with_context`](#method.limit_with_context).
+  pub fn limit(&self, var: &str, point: f64, side: Side) -> Result<Limit, Error> {
+    self.limit_with_context(var, point, side, builtin())
+  }
+
+  /// The limit of the expression as `var` goes to `point`, which may be infinite, from `side`
+  /// for a finite point.
+  ///
+  /// The limit is found by direct substitution where the expression is finite, continuous and
+  /// defined on the side of the point, from the leading terms of fractions of polynomials at
+  /// infinity, and otherwise from the limits of the arguments of each operation and function.
+  /// The indeterminate forms `0 / 0` and `inf / inf` are resolved by L'Hôpital's rule,
+  /// `0 * inf` by rewriting it as a quotient, `0^0`, `1^inf` and `inf^0` as `exp(v * ln(u))`,
+  /// and the rest from series expansions. The signs of infinite limits are those of the
+  /// expression close to the point.
+  ///
+  /// Step functions like `floor` and `%` jump where their argument crosses an integer, and
+  /// take the value on the side the argument approaches it from.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NoLimit)` if an indeterminate form can't be resolved, and `Err` if a
+  /// variable other than `var` is missing from `ctx`.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Limit, Side};
+  /// let limit = |s: &str, point: f64, side: Side| {
+  ///   s.parse::<Expr>().unwrap().limit("x", point, side).unwrap().to_string()
+  /// };
+  /// assert_eq!(limit("sin(x) / x", 0., Side::Both), "1");
+  /// assert_eq!(limit("(2 * x^2 + 1) / (x^2 - x)", f64::INFINITY, Side::Both), "2");
+  /// assert_eq!(limit("1 / x", 0., Side::Right), "inf");
+  /// assert_eq!(limit("1 / x", 0., Side::Left), "-inf");
+  /// assert_eq!(limit("1 / x", 0., Side::Both), "undefined");
+  /// assert_eq!(limit("(1 + 1 / x)^x", f64::INFINITY, Side::Both), std::f64::consts::E.to_string());
+  /// ```
+  pub fn limit_with_context<C: ContextProvider>(
+    &self,
+    var: &str,
+    point: f64,
+    side: Side,
+    ctx: C,
+  ) -> Result<Limit, Error> {
+    let eval = |dir: f64| {
+      LimitEval {
+        var,
+        point,
+        dir,
+        ctx: &ctx,
+      }
+      .limit(self, 0)
+    };
+    let ext = match side {
+      _ if point.is_infinite() => eval(point.signum())?,
+      Side::Left => eval(-1.)?,
+      Side::Right => eval(1.)?,
+      Side::Both => match (eval(-1.)?, eval(1.)?) {
+        (Ext::Finite(x), Ext::Finite(y)) if (x - y).abs() <= 1e-9 * (1. + x.abs()) => {
+          Ext::Finite(y)
+        }
+        (Ext::Inf(s), Ext::Inf(t)) if s == t => Ext::Inf(s),
+        _ => Ext::Undefined,
+      },
+    };
+    Ok(match ext {
+      Ext::Finite(x) => Limit::Finite(Expr::num(snap(x))),
+      Ext::Inf(s) if s > 0. => Limit::Infinity,
+      Ext::Inf(_) => Limit::NegInfinity,
+      Ext::Undefined => Limit::Undefined,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f64::consts::{E, PI};
+
+  const INF: f64 = f64::INFINITY;
+
+  fn limit(expr: Expr, point: f64, side: Side) -> f64 {
+    expr.limit("x", point, side).unwrap().as_f64()
+  }
+
+  fn close(x: f64, y: f64) -> bool {
+    (x - y).abs() < 1e-9 * (1. + y.abs())
+  }
+
+  #[test]
+  fn substitution() {
+    assert_eq!(limit(expr!(x ^ 2 + 1), 2., Side::Both), 5.);
+    assert_eq!(limit(expr!(cos(x)), 0., Side::Left), 1.);
+    assert_eq!(
+      expr!(x).limit("x", 3., Side::Both),
+      Ok(Limit::Finite(expr!(3)))
+    );
+  }
+
+  #[test]
+  fn indeterminate_quotients() {
+    assert_eq!(limit(expr!(sin(x) / x), 0., Side::Both), 1.);
+    assert_eq!(limit(expr!((1 - cos(x)) / x ^ 2), 0., Side::Both), 0.5);
+    assert_eq!(limit(expr!((x ^ 2 - 1) / (x - 1)), 1., Side::Both), 2.);
+    assert_eq!(limit(expr!((exp(x) - 1) / x), 0., Side::Both), 1.);
+    assert_eq!(limit(expr!(ln(x) / x), INF, Side::Both), 0.);
+    assert_eq!(limit(expr!(exp(x) / x ^ 3), INF, Side::Both), INF);
+    assert!(close(
+      limit(expr!((x - sin(x)) / x ^ 3), 0., Side::Both),
+      1. / 6.
+    ));
+  }
+
+  #[test]
+  fn at_infinity() {
+    assert_eq!(
+      limit(expr!((3 * x ^ 2 + x) / (1 - x ^ 2)), INF, Side::Both),
+      -3.
+    );
+    assert_eq!(limit(expr!(x / (x ^ 2 + 1)), -INF, Side::Both), 0.);
+    assert_eq!(limit(expr!(x ^ 3 / (x + 1)), -INF, Side::Both), INF);
+    assert_eq!(limit(expr!(x ^ 3 - x ^ 5), INF, Side::Both), -INF);
+    assert_eq!(limit(expr!(exp(-x)), INF, Side::Both), 0.);
+    assert!(close(limit(expr!(atan(x)), -INF, Side::Both), -PI / 2.));
+    assert!(close(limit(expr!((1 + 1 / x) ^ x), INF, Side::Both), E));
+    assert!(close(
+      limit(expr!(sqrt(x ^ 2 + x) - x), INF, Side::Both),
+      0.5
+    ));
+    assert!(limit(expr!(sin(x)), INF, Side::Both).is_nan());
+  }
+
+  #[test]
+  fn one_sided() {
+    assert_eq!(limit(expr!(1 / x), 0., Side::Right), INF);
+    assert_eq!(limit(expr!(1 / x), 0., Side::Left), -INF);
+    assert!(limit(expr!(1 / x), 0., Side::Both).is_nan());
+    assert_eq!(limit(expr!(1 / x ^ 2), 0., Side::Both), INF);
+    assert_eq!(limit(expr!(ln(x)), 0., Side::Right), -INF);
+    assert!(limit(expr!(ln(x)), 0., Side::Left).is_nan());
+    assert_eq!(limit(expr!(floor(x)), 1., Side::Left), 0.);
+    assert_eq!(limit(expr!(floor(x)), 1., Side::Right), 1.);
+    assert_eq!(limit(expr!(abs(x) / x), 0., Side::Left), -1.);
+    assert_eq!(limit(expr!(exp(1 / x)), 0., Side::Left), 0.);
+    assert_eq!(limit(expr!(exp(1 / x)), 0., Side::Right), INF);
+    // outside the domain on one side
+    assert!(limit(expr!(sqrt(x)), 0., Side::Left).is_nan());
+    assert_eq!(limit(expr!(sqrt(x)), 0., Side::Right), 0.);
+    assert!(limit(expr!(x ^ 0.5 + 1), 0., Side::Left).is_nan());
+  }
+
+  #[test]
+  fn steps() {
+    // continuous away from the jumps
+    assert_eq!(limit(expr!(x % 2), 1., Side::Both), 1.);
+    assert_eq!(limit(expr!(fract(x)), 0.5, Side::Both), 0.5);
+    assert_eq!(limit(expr!(floor(x)), 0.5, Side::Both), 0.);
+    assert_eq!(limit(expr!(fract(x)), 0., Side::Both), 0.);
+    // and one-sided at them
+    assert_eq!(limit(expr!(fract(x)), 1., Side::Left), 1.);
+    assert_eq!(limit(expr!(fract(x)), 1., Side::Right), 0.);
+    assert_eq!(limit(expr!(fract(x)), -1., Side::Right), -1.);
+    assert!(limit(expr!(fract(x)), 1., Side::Both).is_nan());
+    assert_eq!(limit(expr!(x % 2), 2., Side::Left), 2.);
+    assert_eq!(limit(expr!(x % 2), 2., Side::Right), 0.);
+    assert_eq!(limit(expr!(ceil(x)), 1., Side::Left), 1.);
+    assert_eq!(limit(expr!(ceil(x)), 1., Side::Right), 2.);
+    assert_eq!(limit(expr!(round(x)), 0.5, Side::Left), 0.);
+    assert_eq!(limit(expr!(round(x)), 0.5, Side::Right), 1.);
+    assert_eq!(limit(expr!(trunc(x)), 0., Side::Both), 0.);
+    assert_eq!(limit(expr!(signum(x)), 0., Side::Left), -1.);
+    assert_eq!(limit(expr!(signum(x ^ 2)), 0., Side::Both), 1.);
+    assert_eq!(limit(expr!(floor(sin(x) / x)), 0., Side::Both), 0.);
+    assert_eq!(limit(expr!(floor(2)), 0., Side::Both), 2.);
+  }
+
+  #[test]
+  fn badly_scaled_fractions() {
+    assert_eq!(
+      limit("(x^2 - 1e10) / (x - 1e5)".parse().unwrap(), 1e5, Side::Both),
+      200000.
+    );
+  }
+
+  #[test]
+  fn other_forms() {
+    assert_eq!(limit(expr!(x * ln(x)), 0., Side::Right), 0.);
+    assert_eq!(limit(expr!(x ^ x), 0., Side::Right), 1.);
+    assert_eq!(limit(expr!(x * exp(-x)), INF, Side::Both), 0.);
+    assert!(close(limit(expr!(x ^ (1 / x)), INF, Side::Both), 1.));
+    assert_eq!(limit(expr!(1 / x - 1 / sin(x)), 0., Side::Both), 0.);
+  }
+
+  #[test]
+  fn other_variables() {
+    let ctx = (("a", 3.), builtin());
+    assert_eq!(
+      expr!(sin(a * x) / x)
+        .limit_with_context("x", 0., Side::Both, &ctx)
+        .unwrap()
+        .as_f64(),
+      3.
+    );
+    assert_eq!(
+      expr!(a * x).limit("x", 1., Side::Both),
+      Err(Error::UnknownVariable("a".into()))
+    );
+  }
+
+  #[test]
+  fn unresolved_form() {
+    assert_eq!(
+      expr!(max(x, 1 / x)).limit("x", 0., Side::Right),
+      Err(Error::NoLimit("max(x, 1 / x)".into()))
+    );
+  }
+}
@@ -0,0 +1,980 @@
+//! Minimisation of expressions by the Nelder-Mead method or quasi-Newton methods within box
+//! bounds, and nonlinear least squares over a dataset.
+use super::{builtin, ContextProvider, Expr};
+use eqtn::{gauss, Derivative};
+use std::collections::VecDeque;
+use std::f64;
+use std::fmt::{self, Display, Formatter};
+use Error;
+
+/// A method of minimising an expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MinimizeMethod {
+  /// The downhill simplex method of Nelder and Mead, which doesn't need derivatives.
+  NelderMead,
+  /// The quasi-Newton method of Broyden, Fletcher, Goldfarb and Shanno, updating an
+  /// approximation of the inverse Hessian.
+  Bfgs,
+  /// BFGS with limited memory, approximating the inverse Hessian from the last `memory`
+  /// steps.
+  Lbfgs { memory: usize },
+}
+
+/// The method, stopping criteria and bounds of [`Expr::minimize`].
+///
+/// [`Expr::minimize`]: struct.Expr.html#method.minimize
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinimizeOptions {
+  pub method: MinimizeMethod,
+  /// The quasi-Newton methods stop once every component of the gradient is at most
+  /// `tolerance * max(1, |f|)`, ignoring those pushing a variable out of its bounds, and the
+  /// Nelder-Mead method once the values at the vertices of the simplex differ by at most that
+  /// and its vertices by at most `sqrt(tolerance) * max(1, |x|)`.
+  pub tolerance: f64,
+  pub max_iterations: usize,
+  /// How the quasi-Newton methods find gradients.
+  pub derivative: Derivative,
+  /// The lower and upper bound of each variable, or none for an unconstrained minimum.
+  pub bounds: Vec<(f64, f64)>,
+}
+
+impl MinimizeOptions {
+  /// Options for `method`, with a tolerance of `1e-10`, at most 1000 iterations, symbolic
+  /// derivatives and no bounds.
+  pub fn new(method: MinimizeMethod) -> Self {
+    MinimizeOptions {
+      method,
+      tolerance: 1e-10,
+      max_iterations: 1000,
+      derivative: Derivative::default(),
+      bounds: vec![],
+    }
+  }
+
+  pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+    self.tolerance = tolerance;
+    self
+  }
+
+  pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+    self.max_iterations = max_iterations;
+    self
+  }
+
+  pub fn with_derivative(mut self, derivative: Derivative) -> Self {
+    self.derivative = derivative;
+    self
+  }
+
+  /// Restricts each variable to the interval `[lo, hi]` given in the same order, where either
+  /// may be infinite.
+  pub fn with_bounds(mut self, bounds: Vec<(f64, f64)>) -> Self {
+    self.bounds = bounds;
+    self
+  }
+}
+
+/// The result of a minimisation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimum {
+  /// The values of the variables at the minimum, in the order given.
+  pub values: Vec<f64>,
+  /// The value of the expression at the minimum, or the sum of the squared residuals.
+  pub value: f64,
+  /// Whether the tolerance was reached, rather than the maximum number of iterations or a
+  /// point where no step decreases the value.
+  pub converged: bool,
+  pub iterations: usize,
+  /// The number of times the expression or the residuals were evaluated, not counting their
+  /// derivatives.
+  pub evaluations: usize,
+}
+
+/// An error minimising an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MinimizeError {
+  /// There must be a start value and bounds for each variable, and a value in each row of the
+  /// data.
+  Dimensions { expected: usize, found: usize },
+  /// The lower bound of the variable with this index is above its upper bound, or isn't a
+  /// number.
+  Bounds(usize),
+  /// An error evaluating or differentiating the expression.
+  Eval(Error),
+}
+
+impl Display for MinimizeError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      MinimizeError::Dimensions { expected, found } => {
+        write!(f, "Expected {} values, got {}.", expected, found)
+      }
+      MinimizeError::Bounds(i) => {
+        write!(f, "The bounds of variable {} are not an interval.", i + 1)
+      }
+      MinimizeError::Eval(e) => e.fmt(f),
+    }
+  }
+}
+
+impl From<Error> for MinimizeError {
+  fn from(err: Error) -> MinimizeError {
+    MinimizeError::Eval(err)
+  }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+  a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn max_abs(a: &[f64]) -> f64 {
+  a.iter().fold(0., |max, x| x.abs().max(max))
+}
+
+/// `a + t * b`.
+fn axpy(a: &[f64], t: f64, b: &[f64]) -> Vec<f64> {
+  a.iter().zip(b).map(|(a, b)| a + t * b).collect()
+}
+
+/// The expression as a function of the variables, with its gradient.
+struct Objective<'a, C> {
+  expr: Expr,
+  vars: &'a [&'a str],
+  /// The symbolic gradient, unless it is found with dual numbers.
+  gradient: Option<Vec<Expr>>,
+  bounds: Vec<(f64, f64)>,
+  ctx: C,
+  evaluations: usize,
+}
+
+impl<'a, C: ContextProvider> Objective<'a, C> {
+  fn context(&self, x: &[f64]) -> Vec<(&'a str, f64)> {
+    self.vars.iter().cloned().zip(x.iter().cloned()).collect()
+  }
+
+  /// The value at `x`, where a value that isn't a number is taken as infinitely large.
+  fn value(&mut self, x: &[f64]) -> Result<f64, Error> {
+    self.evaluations += 1;
+    let f = self.expr.eval_with_context((&self.context(x), &self.ctx))?;
+    Ok(if f.is_nan() { f64::INFINITY } else { f })
+  }
+
+  fn gradient(&self, x: &[f64]) -> Result<Vec<f64>, Error> {
+    let vars = self.context(x);
+    match self.gradient {
+      Some(ref gradient) => gradient
+        .iter()
+        .map(|d| d.eval_with_context((&vars, &self.ctx)))
+        .collect(),
+      None => vars
+        .iter()
+        .map(|&(v, x)| Ok(self.expr.eval_dual(v, x, (&vars, &self.ctx))?.1))
+        .collect(),
+    }
+  }
+
+  fn project(&self, x: &mut [f64]) {
+    for (x, &(lo, hi)) in x.iter_mut().zip(&self.bounds) {
+      *x = x.max(lo).min(hi);
+    }
+  }
+
+  /// Whether the gradient pushes the variable with index `i` out of its bounds.
+  fn is_active(&self, x: &[f64], g: &[f64], i: usize) -> bool {
+    let (lo, hi) = self.bounds[i];
+    (x[i] <= lo && g[i] > 0.) || (x[i] >= hi && g[i] < 0.)
+  }
+
+  /// The gradient without the components pushing variables out of their bounds.
+  fn free_gradient(&self, x: &[f64], g: &[f64]) -> Vec<f64> {
+    (0..g.len())
+      .map(|i| if self.is_active(x, g, i) { 0. } else { g[i] })
+      .collect()
+  }
+
+  fn minimum(&self, x: Vec<f64>, value: f64, converged: bool, iterations: usize) -> Minimum {
+    Minimum {
+      values: x,
+      value,
+      converged,
+      iterations,
+      evaluations: self.evaluations,
+    }
+  }
+
+  /// The Nelder-Mead method with the usual coefficients, projecting points onto the bounds.
+  fn nelder_mead(&mut self, mut x: Vec<f64>, opts: &MinimizeOptions) -> Result<Minimum, Error> {
+    let n = x.len();
+    self.project(&mut x);
+    let f = self.value(&x)?;
+    let mut simplex = vec![(x.clone(), f)];
+    for i in 0..n {
+      let step = if x[i] != 0. { 0.05 * x[i] } else { 0.00025 };
+      let mut v = x.clone();
+      v[i] += step;
+      self.project(&mut v);
+      if v[i] == x[i] {
+        v[i] -= step;
+        self.project(&mut v);
+      }
+      let f = self.value(&v)?;
+      simplex.push((v, f));
+    }
+
+    let point = |c: &[f64], t: f64, v: &[f64]| axpy(c, t, &axpy(v, -1., c));
+    for iteration in 0..=opts.max_iterations {
+      simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+      let (best, f_best) = simplex[0].clone();
+      let f_spread = simplex[n].1 - f_best;
+      let x_spread = simplex
+        .iter()
+        .map(|(v, _)| max_abs(&axpy(v, -1., &best)))
+        .fold(0., f64::max);
+      let converged = f_spread <= opts.tolerance * f_best.abs().max(1.)
+        && x_spread <= opts.tolerance.sqrt() * max_abs(&best).max(1.);
+      if converged || iteration == opts.max_iterations {
+        return Ok(self.minimum(best, f_best, converged, iteration));
+      }
+
+      let mut c = vec![0.; n];
+      for (v, _) in &simplex[..n] {
+        c = axpy(&c, 1. / n as f64, v);
+      }
+      let (worst, f_worst) = simplex[n].clone();
+      let mut reflected = point(&c, -1., &worst);
+      self.project(&mut reflected);
+      let f_reflected = self.value(&reflected)?;
+      if f_reflected < f_best {
+        let mut expanded = point(&c, -2., &worst);
+        self.project(&mut expanded);
+        let f_expanded = self.value(&expanded)?;
+        simplex[n] = if f_expanded < f_reflected {
+          (expanded, f_expanded)
+        } else {
+          (reflected, f_reflected)
+        };
+      } else if f_reflected < simplex[n - 1].1 {
+        simplex[n] = (reflected, f_reflected);
+      } else {
+        let (outside, f_outer) = if f_reflected < f_worst {
+          (&reflected, f_reflected)
+        } else {
+          (&worst, f_worst)
+        };
+        let contracted = point(&c, 0.5, outside);
+        let f_contracted = self.value(&contracted)?;
+        if f_contracted < f_outer {
+          simplex[n] = (contracted, f_contracted);
+        } else {
+          for vertex in &mut simplex[1..] {
+            let v = point(&best, 0.5, &vertex.0);
+            let f = self.value(&v)?;
+            *vertex = (v, f);
+          }
+        }
+      }
+    }
+    unreachable!("the last iteration returns")
+  }
+
+  /// BFGS or L-BFGS with a backtracking line search along the projected path, keeping the
+  /// variables at a bound the gradient pushes against fixed.
+  fn quasi_newton(&mut self, mut x: Vec<f64>, opts: &MinimizeOptions) -> Result<Minimum, Error> {
+    let n = x.len();
+    self.project(&mut x);
+    let mut f = self.value(&x)?;
+    let mut g = self.gradient(&x)?;
+    let identity = |n: usize| -> Vec<Vec<f64>> {
+      (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1. } else { 0. }).collect())
+        .collect()
+    };
+    let mut h = identity(n);
+    let mut history: VecDeque<(Vec<f64>, Vec<f64>)> = VecDeque::new();
+
+    for iteration in 0..=opts.max_iterations {
+      let free = self.free_gradient(&x, &g);
+      let scale = f.abs().max(1.);
+      if max_abs(&free) <= opts.tolerance * scale || iteration == opts.max_iterations {
+        let converged = max_abs(&free) <= opts.tolerance * scale;
+        return Ok(self.minimum(x, f, converged, iteration));
+      }
+
+      let mut d: Vec<f64> = match opts.method {
+        MinimizeMethod::Lbfgs { .. } => two_loop(&history, &free),
+        _ => h.iter().map(|row| -dot(row, &free)).collect(),
+      };
+      for i in 0..n {
+        if free[i] == 0. {
+          d[i] = 0.;
+        }
+      }
+      let slope = dot(&d, &free);
+      if slope.is_nan() || slope >= 0. {
+        d = free.iter().map(|g| -g).collect();
+        h = identity(n);
+        history.clear();
+      }
+
+      let mut step = None;
+      let mut alpha = 1.;
+      for _ in 0..60 {
+        let mut next = axpy(&x, alpha, &d);
+        self.project(&mut next);
+        let f_next = self.value(&next)?;
+        if f_next <= f + 1e-4 * dot(&g, &axpy(&next, -1., &x)) && f_next < f {
+          step = Some((next, f_next));
+          break;
+        }
+        alpha /= 2.;
+      }
+      let (next, f_next) = match step {
+        Some(step) => step,
+        None => {
+          // no step decreases the value
+          let converged = max_abs(&free) <= opts.tolerance.sqrt() * scale;
+          return Ok(self.minimum(x, f, converged, iteration + 1));
+        }
+      };
+      let g_next = self.gradient(&next)?;
+      let s = axpy(&next, -1., &x);
+      let y = axpy(&g_next, -1., &g);
+      let sy = dot(&s, &y);
+      if sy > 0. {
+        match opts.method {
+          MinimizeMethod::Lbfgs { memory } => {
+            if history.len() >= memory.max(1) {
+              history.pop_front();
+            }
+            history.push_back((s, y));
+          }
+          _ => {
+            if iteration == 0 {
+              h = identity(n);
+              let gamma = sy / dot(&y, &y);
+              h.iter_mut().enumerate().for_each(|(i, row)| row[i] = gamma);
+            }
+            bfgs_update(&mut h, &s, &y, sy);
+          }
+        }
+      }
+      x = next;
+      f = f_next;
+      g = g_next;
+    }
+    unreachable!("the last iteration returns")
+  }
+}
+
+/// The BFGS update `H = (I - ρ s yᵀ) H (I - ρ y sᵀ) + ρ s sᵀ` of the inverse Hessian.
+fn bfgs_update(h: &mut [Vec<f64>], s: &[f64], y: &[f64], sy: f64) {
+  let n = s.len();
+  let rho = 1. / sy;
+  let hy: Vec<f64> = h.iter().map(|row| dot(row, y)).collect();
+  let yhy = dot(y, &hy);
+  for i in 0..n {
+    for j in 0..n {
+      h[i][j] += rho * ((1. + rho * yhy) * s[i] * s[j] - hy[i] * s[j] - s[i] * hy[j]);
+    }
+  }
+}
+
+/// The L-BFGS direction `-H g` by the two-loop recursion over the last steps and changes of
+/// the gradient.
+fn two_loop(history: &VecDeque<(Vec<f64>, Vec<f64>)>, g: &[f64]) -> Vec<f64> {
+  let mut q = g.to_vec();
+  let mut alphas = Vec::with_capacity(history.len());
+  for (s, y) in history.iter().rev() {
+    let rho = 1. / dot(y, s);
+    let alpha = rho * dot(s, &q);
+    q = axpy(&q, -alpha, y);
+    alphas.push((alpha, rho));
+  }
+  let gamma = history.back().map_or(1., |(s, y)| dot(s, y) / dot(y, y));
+  let mut r: Vec<f64> = q.iter().map(|q| gamma * q).collect();
+  for ((s, y), (alpha, rho)) in history.iter().zip(alphas.iter().rev()) {
+    let beta = rho * dot(y, &r);
+    r = axpy(&r, alpha - beta, s);
+  }
+  r.iter().map(|r| -r).collect()
+}
+
+impl Expr {
+  /// Finds a local minimum of the expression as a function of `vars` numerically, starting from
+  /// `start`, with default constants and functions.
+  ///
+  /// See [`minimize_with_context`](#method.minimize_with_context).
+  pub fn minimize(
+    &self,
+    vars: &[&str],
+    start: &[f64],
+    options: &MinimizeOptions,
+  ) -> Result<Minimum, MinimizeError> {
+    self.minimize_with_context(vars, start, builtin(), options)
+  }
+
+  /// Finds a local minimum of the expression as a function of `vars` numerically, starting from
+  /// `start`, with the other variables and the functions given by `ctx`.
+  ///
+  /// The Nelder-Mead method only evaluates the expression, while the quasi-Newton methods also
+  /// need its gradient, either differentiating it symbolically once or with dual numbers at
+  /// every step. Points where the expression isn't a number are treated as infinitely large.
+  /// With bounds, points are projected onto them, and the start needn't be within them.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(Dimensions)` unless there is a start value and bounds, if any, for each
+  /// variable, `Err(Bounds)` if the bounds aren't intervals, and `Err(Eval)` if the expression
+  /// can't be evaluated, or differentiated for the quasi-Newton methods.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, MinimizeMethod, MinimizeOptions};
+  /// let expr: Expr = "(1 - x)^2 + 100 * (y - x^2)^2".parse().unwrap();
+  /// let options = MinimizeOptions::new(MinimizeMethod::Bfgs);
+  /// let min = expr.minimize(&["x", "y"], &[-1.2, 1.], &options).unwrap();
+  /// assert!(min.converged);
+  /// assert!((min.values[0] - 1.).abs() < 1e-8 && (min.values[1] - 1.).abs() < 1e-8);
+  ///
+  /// let options = options.with_bounds(vec![(-2., 0.5), (-2., 2.)]);
+  /// let min = expr.minimize(&["x", "y"], &[-1.2, 1.], &options).unwrap();
+  /// assert_eq!(min.values[0], 0.5);
+  /// ```
+  pub fn minimize_with_context<C: ContextProvider>(
+    &self,
+    vars: &[&str],
+    start: &[f64],
+    ctx: C,
+    options: &MinimizeOptions,
+  ) -> Result<Minimum, MinimizeError> {
+    let n = vars.len();
+    if start.len() != n {
+      return Err(MinimizeError::Dimensions {
+        expected: n,
+        found: start.len(),
+      });
+    }
+    let bounds = if options.bounds.is_empty() {
+      vec![(f64::NEG_INFINITY, f64::INFINITY); n]
+    } else if options.bounds.len() != n {
+      return Err(MinimizeError::Dimensions {
+        expected: n,
+        found: options.bounds.len(),
+      });
+    } else {
+      options.bounds.clone()
+    };
+    if let Some(i) = bounds
+      .iter()
+      .position(|&(lo, hi)| lo.is_nan() || hi.is_nan() || lo > hi)
+    {
+      return Err(MinimizeError::Bounds(i));
+    }
+
+    let vars_ctx: Vec<_> = vars.iter().map(|&v| (v, 0.)).collect();
+    self.check_context((&vars_ctx, &ctx))?;
+    let expr = self.fold_constants(&ctx);
+    let gradient = match (options.method, options.derivative) {
+      (MinimizeMethod::NelderMead, _) | (_, Derivative::Dual) => None,
+      (_, Derivative::Symbolic) => Some(
+        vars
+          .iter()
+          .map(|v| Ok(expr.derivative_with_context(v, &ctx)?.fold_constants(&ctx)))
+          .collect::<Result<_, Error>>()?,
+      ),
+    };
+    let mut objective = Objective {
+      expr,
+      vars,
+      gradient,
+      bounds,
+      ctx,
+      evaluations: 0,
+    };
+    let res = match options.method {
+      MinimizeMethod::NelderMead => objective.nelder_mead(start.to_vec(), options),
+      _ => objective.quasi_newton(start.to_vec(), options),
+    };
+    Ok(res?)
+  }
+}
+
+/// A method of solving nonlinear least squares problems.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeastSquaresMethod {
+  /// Newton's method on the residuals linearised at each step, halving steps that don't
+  /// decrease the sum of squares.
+  GaussNewton,
+  /// Gauss-Newton steps damped towards gradient descent as far as needed to decrease the sum
+  /// of squares, which also works far from the minimum.
+  LevenbergMarquardt,
+}
+
+/// The method and stopping criteria of [`LeastSquares::solve`].
+///
+/// [`LeastSquares::solve`]: struct.LeastSquares.html#method.solve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeastSquaresOptions {
+  pub method: LeastSquaresMethod,
+  /// The iteration stops once every parameter changes by at most
+  /// `tolerance * max(1, |p|)`, the sum of squares decreases by at most `tolerance` times
+  /// itself, or the gradient vanishes.
+  pub tolerance: f64,
+  pub max_iterations: usize,
+  /// How the Jacobian is found.
+  pub derivative: Derivative,
+}
+
+impl LeastSquaresOptions {
+  /// Options for `method`, with a tolerance of `1e-10`, at most 100 iterations and symbolic
+  /// derivatives.
+  pub fn new(method: LeastSquaresMethod) -> Self {
+    LeastSquaresOptions {
+      method,
+      tolerance: 1e-10,
+      max_iterations: 100,
+      derivative: Derivative::default(),
+    }
+  }
+
+  pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+    self.tolerance = tolerance;
+    self
+  }
+
+  pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+    self.max_iterations = max_iterations;
+    self
+  }
+
+  pub fn with_derivative(mut self, derivative: Derivative) -> Self {
+    self.derivative = derivative;
+    self
+  }
+}
+
+/// Residual expressions over the rows of a dataset, whose squares summed over all rows are
+/// minimised by choosing parameters, e.g. `y - a * exp(b * x)` over columns `x` and `y`.
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::{Context, Expr, LeastSquares, LeastSquaresMethod, LeastSquaresOptions};
+/// let xs: Vec<f64> = (0..10).map(|i| i as f64 / 2.).collect();
+/// let ys = xs.iter().map(|x| 3. * (-0.7 * x).exp()).collect();
+/// let residual: Expr = "y - a * exp(b * x)".parse().unwrap();
+/// let problem = LeastSquares::new(vec![residual])
+///   .with_data("x", xs)
+///   .with_data("y", ys);
+///
+/// let options = LeastSquaresOptions::new(LeastSquaresMethod::LevenbergMarquardt);
+/// let fit = problem.solve(&["a", "b"], &[1., 0.], Context::new(), &options).unwrap();
+/// assert!(fit.converged && fit.value < 1e-20);
+/// assert!((fit.values[0] - 3.).abs() < 1e-10 && (fit.values[1] + 0.7).abs() < 1e-10);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeastSquares {
+  residuals: Vec<Expr>,
+  data: Vec<(String, Vec<f64>)>,
+}
+
+impl LeastSquares {
+  pub fn new(residuals: Vec<Expr>) -> Self {
+    LeastSquares {
+      residuals,
+      data: vec![],
+    }
+  }
+
+  /// Adds the values of the variable `name` in each row of the data. Without data, the
+  /// residuals are evaluated once.
+  pub fn with_data(mut self, name: &str, values: Vec<f64>) -> Self {
+    self.data.push((name.to_owned(), values));
+    self
+  }
+
+  /// Finds the `params` minimising the sum of the squared residuals numerically, starting
+  /// from `start`, with the other variables and the functions given by `ctx`.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(Dimensions)` unless there is a start value for each parameter and the
+  /// columns of the data have the same length, and `Err(Eval)` if the residuals can't be
+  /// evaluated or differentiated.
+  pub fn solve<C: ContextProvider>(
+    &self,
+    params: &[&str],
+    start: &[f64],
+    ctx: C,
+    options: &LeastSquaresOptions,
+  ) -> Result<Minimum, MinimizeError> {
+    let n = params.len();
+    if start.len() != n {
+      return Err(MinimizeError::Dimensions {
+        expected: n,
+        found: start.len(),
+      });
+    }
+    let rows = self.data.first().map_or(1, |(_, col)| col.len());
+    if let Some((_, col)) = self.data.iter().find(|(_, col)| col.len() != rows) {
+      return Err(MinimizeError::Dimensions {
+        expected: rows,
+        found: col.len(),
+      });
+    }
+
+    let vars = |p: &[f64], row: usize| -> Vec<(&str, f64)> {
+      let data = self
+        .data
+        .iter()
+        .map(|(name, col)| (name.as_str(), col[row]));
+      params
+        .iter()
+        .cloned()
+        .zip(p.iter().cloned())
+        .chain(data)
+        .collect()
+    };
+    let zeros = vec![0.; n];
+    let mut residuals = vec![];
+    for expr in &self.residuals {
+      if rows > 0 {
+        expr.check_context((&vars(&zeros, 0), &ctx))?;
+      }
+      residuals.push(expr.fold_constants(&ctx));
+    }
+    let jacobian = match options.derivative {
+      Derivative::Symbolic => Some(
+        residuals
+          .iter()
+          .map(|expr| {
+            params
+              .iter()
+              .map(|p| Ok(expr.derivative_with_context(p, &ctx)?.fold_constants(&ctx)))
+              .collect::<Result<Vec<_>, Error>>()
+          })
+          .collect::<Result<Vec<_>, Error>>()?,
+      ),
+      Derivative::Dual => None,
+    };
+
+    let mut evaluations = 0;
+    let mut eval = |p: &[f64]| -> Result<Vec<f64>, Error> {
+      evaluations += 1;
+      let mut r = Vec::with_capacity(rows * residuals.len());
+      for row in 0..rows {
+        let vars = vars(p, row);
+        for expr in &residuals {
+          r.push(expr.eval_with_context((&vars, &ctx))?);
+        }
+      }
+      Ok(r)
+    };
+    // the rows of the Jacobian, in the order of the residuals
+    let jacobian = |p: &[f64]| -> Result<Vec<Vec<f64>>, Error> {
+      let mut j = Vec::with_capacity(rows * residuals.len());
+      for row in 0..rows {
+        let vars = vars(p, row);
+        for (k, expr) in residuals.iter().enumerate() {
+          j.push(match jacobian {
+            Some(ref jacobian) => jacobian[k]
+              .iter()
+              .map(|d| d.eval_with_context((&vars, &ctx)))
+              .collect::<Result<_, _>>()?,
+            None => (0..n)
+              .map(|i| Ok(expr.eval_dual(params[i], p[i], (&vars, &ctx))?.1))
+              .collect::<Result<_, Error>>()?,
+          });
+        }
+      }
+      Ok(j)
+    };
+    let cost = |r: &[f64]| -> f64 {
+      let c = dot(r, r);
+      if c.is_nan() {
+        f64::INFINITY
+      } else {
+        c
+      }
+    };
+
+    let mut p = start.to_vec();
+    // the residuals at `p`, kept from the step that was accepted
+    let mut r = eval(&p)?;
+    let mut c = cost(&r);
+    let mut lambda = None;
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < options.max_iterations && !converged {
+      iterations += 1;
+      let j = jacobian(&p)?;
+      // the normal equations `JᵀJ δ = -Jᵀr`
+      let jtj: Vec<Vec<f64>> = (0..n)
+        .map(|a| {
+          (0..n)
+            .map(|b| j.iter().map(|row| row[a] * row[b]).sum())
+            .collect()
+        })
+        .collect();
+      let jtr: Vec<f64> = (0..n)
+        .map(|a| -j.iter().zip(&r).map(|(row, r)| row[a] * r).sum::<f64>())
+        .collect();
+      if c == 0. || max_abs(&jtr) <= options.tolerance * c.max(1.) {
+        converged = true;
+        break;
+      }
+
+      let next = match options.method {
+        LeastSquaresMethod::GaussNewton => {
+          let delta = match gauss(jtj, jtr.clone(), n) {
+            Ok(delta) => delta,
+            Err(_) => break,
+          };
+          let mut alpha = 1.;
+          let mut next = None;
+          for _ in 0..40 {
+            let q = axpy(&p, alpha, &delta);
+            let r_next = eval(&q)?;
+            let c_next = cost(&r_next);
+            if c_next < c {
+              next = Some((q, r_next, c_next));
+              break;
+            }
+            alpha /= 2.;
+          }
+          next
+        }
+        LeastSquaresMethod::LevenbergMarquardt => {
+          let diag: Vec<f64> = (0..n).map(|i| jtj[i][i].max(1e-12)).collect();
+          let mut l = lambda.unwrap_or_else(|| 1e-3 * max_abs(&diag));
+          let mut next = None;
+          while l < 1e16 {
+            let mut a = jtj.clone();
+            for i in 0..n {
+              a[i][i] += l * diag[i];
+            }
+            if let Ok(delta) = gauss(a, jtr.clone(), n) {
+              let q = axpy(&p, 1., &delta);
+              let r_next = eval(&q)?;
+              let c_next = cost(&r_next);
+              if c_next < c {
+                next = Some((q, r_next, c_next));
+                l /= 10.;
+                break;
+              }
+            }
+            l *= 10.;
+          }
+          lambda = Some(l);
+          next
+        }
+      };
+      let (q, r_next, c_next) = match next {
+        Some(next) => next,
+        None => {
+          // no step decreases the sum of squares
+          converged = max_abs(&jtr) <= options.tolerance.sqrt() * c.max(1.);
+          break;
+        }
+      };
+      let small_step = p
+        .iter()
+        .zip(&q)
+        .all(|(p, q)| (q - p).abs() <= options.tolerance * p.abs().max(1.));
+      converged = small_step || c - c_next <= options.tolerance * c;
+      p = q;
+      r = r_next;
+      c = c_next;
+    }
+    Ok(Minimum {
+      values: p,
+      value: c,
+      converged,
+      iterations,
+      evaluations,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const METHODS: [MinimizeMethod; 3] = [
+    MinimizeMethod::NelderMead,
+    MinimizeMethod::Bfgs,
+    MinimizeMethod::Lbfgs { memory: 5 },
+  ];
+
+  fn close(xs: &[f64], ys: &[f64], tolerance: f64) -> bool {
+    xs.iter().zip(ys).all(|(x, y)| (x - y).abs() <= tolerance)
+  }
+
+  #[test]
+  fn unconstrained() {
+    let quadratic = expr!((x - 1) ^ 2 + 2 * (y + 2) ^ 2 + x * y);
+    let rosenbrock = expr!((1 - x) ^ 2 + 100 * (y - x ^ 2) ^ 2);
+    for &method in &METHODS {
+      let options = MinimizeOptions::new(method);
+      let tolerance = if method == MinimizeMethod::NelderMead {
+        1e-4
+      } else {
+        1e-8
+      };
+
+      let min = quadratic
+        .minimize(&["x", "y"], &[0., 0.], &options)
+        .unwrap();
+      assert!(min.converged, "{:?}: {:?}", method, min);
+      assert!(
+        close(&min.values, &[16. / 7., -18. / 7.], tolerance),
+        "{:?}",
+        min
+      );
+
+      let min = rosenbrock
+        .minimize(&["x", "y"], &[-1.2, 1.], &options)
+        .unwrap();
+      assert!(min.converged, "{:?}: {:?}", method, min);
+      assert!(close(&min.values, &[1., 1.], tolerance), "{:?}", min);
+      assert!(min.value < 1e-8);
+    }
+
+    let options = MinimizeOptions::new(MinimizeMethod::Bfgs).with_derivative(Derivative::Dual);
+    let min = rosenbrock
+      .minimize(&["x", "y"], &[-1.2, 1.], &options)
+      .unwrap();
+    assert!(min.converged && close(&min.values, &[1., 1.], 1e-8));
+
+    let min = expr!(cosh(x - a))
+      .minimize_with_context(&["x"], &[0.], (("a", 2.), builtin()), &options)
+      .unwrap();
+    assert!(close(&min.values, &[2.], 1e-10) && min.value == 1.);
+  }
+
+  #[test]
+  fn bounds() {
+    let expr = expr!((x - 3) ^ 2 + (y + 1) ^ 2 + z ^ 2);
+    let bounds = vec![(0., 2.), (0., 5.), (f64::NEG_INFINITY, f64::INFINITY)];
+    for &method in &METHODS {
+      let options = MinimizeOptions::new(method).with_bounds(bounds.clone());
+      let min = expr
+        .minimize(&["x", "y", "z"], &[1., 10., 1.], &options)
+        .unwrap();
+      assert!(min.converged, "{:?}: {:?}", method, min);
+      assert!(close(&min.values, &[2., 0., 0.], 1e-4), "{:?}", min);
+      assert!(min.values[0] <= 2. && min.values[1] >= 0.);
+    }
+
+    // the logarithm is only defined for positive x
+    let options = MinimizeOptions::new(MinimizeMethod::NelderMead);
+    let min = expr!(x - ln(x)).minimize(&["x"], &[3.], &options).unwrap();
+    assert!(close(&min.values, &[1.], 1e-4));
+  }
+
+  #[test]
+  fn errors() {
+    let expr = expr!(x ^ 2 + y ^ 2);
+    let options = MinimizeOptions::new(MinimizeMethod::Bfgs);
+    assert_eq!(
+      expr.minimize(&["x", "y"], &[1.], &options),
+      Err(MinimizeError::Dimensions {
+        expected: 2,
+        found: 1
+      })
+    );
+    let bounded = options.clone().with_bounds(vec![(0., 1.), (1., 0.)]);
+    assert_eq!(
+      expr.minimize(&["x", "y"], &[1., 1.], &bounded),
+      Err(MinimizeError::Bounds(1))
+    );
+    assert_eq!(
+      expr.minimize(&["x"], &[1.], &options),
+      Err(MinimizeError::Eval(Error::UnknownVariable("y".into())))
+    );
+
+    let min = expr!(x).minimize(&["x"], &[0.], &options).unwrap();
+    assert!(!min.converged);
+    let options = options.with_max_iterations(3);
+    let min = expr!((1 - x) ^ 2 + 100 * (y - x ^ 2) ^ 2)
+      .minimize(&["x", "y"], &[-1.2, 1.], &options)
+      .unwrap();
+    assert!(!min.converged && min.iterations == 3);
+  }
+
+  #[test]
+  fn least_squares() {
+    let xs: Vec<f64> = (0..12).map(|i| i as f64 / 3.).collect();
+    let ys: Vec<f64> = xs.iter().map(|x| 2. * (-0.5 * x).exp() + 0.1).collect();
+    let problem = LeastSquares::new(vec![expr!(y - a * exp(b * x) - c)])
+      .with_data("x", xs.clone())
+      .with_data("y", ys.clone());
+    for &method in &[
+      LeastSquaresMethod::GaussNewton,
+      LeastSquaresMethod::LevenbergMarquardt,
+    ] {
+      for &derivative in &[Derivative::Symbolic, Derivative::Dual] {
+        let options = LeastSquaresOptions::new(method).with_derivative(derivative);
+        let fit = problem
+          .solve(&["a", "b", "c"], &[1., -1., 0.], builtin(), &options)
+          .unwrap();
+        assert!(fit.converged, "{:?}: {:?}", method, fit);
+        assert!(close(&fit.values, &[2., -0.5, 0.1], 1e-8), "{:?}", fit);
+      }
+    }
+
+    // Levenberg-Marquardt from further away
+    let options = LeastSquaresOptions::new(LeastSquaresMethod::LevenbergMarquardt);
+    let fit = problem
+      .solve(&["a", "b", "c"], &[8., -3., 2.], builtin(), &options)
+      .unwrap();
+    assert!(
+      fit.converged && close(&fit.values, &[2., -0.5, 0.1], 1e-8),
+      "{:?}",
+      fit
+    );
+
+    // a residual that doesn't vanish, with a line through three points
+    let fit = LeastSquares::new(vec![expr!(y - m * x - q)])
+      .with_data("x", vec![0., 1., 2.])
+      .with_data("y", vec![0., 2., 1.])
+      .solve(&["m", "q"], &[0., 0.], builtin(), &options)
+      .unwrap();
+    assert!(fit.converged && close(&fit.values, &[0.5, 0.5], 1e-8));
+    assert!((fit.value - 1.5).abs() < 1e-10);
+
+    // the residuals are evaluated once at the start and once per step tried: a line through
+    // two points takes one Gauss-Newton step
+    let options = LeastSquaresOptions::new(LeastSquaresMethod::GaussNewton);
+    let fit = LeastSquares::new(vec![expr!(y - m * x - q)])
+      .with_data("x", vec![0., 1.])
+      .with_data("y", vec![1., 3.])
+      .solve(&["m", "q"], &[0., 0.], builtin(), &options)
+      .unwrap();
+    assert!(
+      fit.converged && close(&fit.values, &[2., 1.], 1e-12),
+      "{:?}",
+      fit
+    );
+    assert_eq!(fit.evaluations, 2);
+
+    // several residuals without data
+    let fit = LeastSquares::new(vec![expr!(x ^ 2 + y ^ 2 - 4), expr!(x - y)])
+      .solve(&["x", "y"], &[1., 0.5], builtin(), &options)
+      .unwrap();
+    assert!(close(&fit.values, &[2f64.sqrt(), 2f64.sqrt()], 1e-8));
+
+    let err = LeastSquares::new(vec![expr!(y - a * x)])
+      .with_data("x", vec![1., 2.])
+      .with_data("y", vec![1.])
+      .solve(&["a"], &[1.], builtin(), &options);
+    assert_eq!(
+      err,
+      Err(MinimizeError::Dimensions {
+        expected: 2,
+        found: 1
+      })
+    );
+  }
+}
@@ -1,25 +1,56 @@
+use std::convert::TryFrom;
 use std::ops::Deref;
 use std::str::FromStr;
 
-use self::{extra_math::factorial, parser::tokenizer::Operation};
+use self::{extra_math::factorial, parser::tokenizer::Operation, quadrature::BindingCall};
 use crate::Evaluatable_Trait;
 use Error;
 
+#[cfg(feature = "serde")]
+pub use self::parser::de;
 pub use self::{
+  ac::Matching,
   context::{builtin, max_array, min_array, ArgGuard, Context, ContextProvider},
+  dag::Dag,
   errors::FuncEvalError,
+  func_info::{Arity, FuncInfo, VariadicDerivatives},
+  limit::{Limit, Side},
+  minimize::{
+    LeastSquares, LeastSquaresMethod, LeastSquaresOptions, MinimizeError, MinimizeMethod,
+    MinimizeOptions, Minimum,
+  },
   parser::{
     shunting_yard::{self, RPNError},
     tokenizer::{self, ParseError, Token},
   },
-  symbolic::SubstituteError,
+  pattern::{Comparison, Condition, Constraint, Guard, Pattern},
+  quadrature::{Quadrature, QuadratureOptions},
+  random::RandomContext,
+  scoped::ScopedContext,
+  series::Series,
+  symbolic::{SubstituteError, Substitutions},
 };
 
+pub(crate) use self::quadrature::PreparedExpr;
+
+mod ac;
 mod context;
+mod dag;
+mod derivative;
+mod display;
 mod errors;
 mod extra_math;
-mod operators;
+mod func_info;
+mod integral;
+mod limit;
+mod minimize;
+pub mod operators;
 mod parser;
+mod pattern;
+mod quadrature;
+mod random;
+mod scoped;
+mod series;
 mod symbolic;
 
 /// Representation of a parsed expression.
@@ -36,19 +67,44 @@ mod symbolic;
 /// assert_eq!(r, Some(4.));
 /// ```
 ///
+/// Expressions can be hashed and are totally ordered by comparing their tokens in RPN, with
+/// numbers compared as described for [`Token`](tokenizer/enum.Token.html), so they can be used
+/// as keys of maps and deduplicated.
+///
 /// [RPN]: https://en.wikipedia.org/wiki/Reverse_Polish_notation
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Expr(pub(crate) Vec<Token>);
 
 impl Expr {
   /// Evaluates the expression with variables given by the argument.
   pub fn eval_with_context<C: ContextProvider>(&self, ctx: C) -> Result<f64, Error> {
-    use self::Operation::*;
+    self.eval_with_calls(&self.binding_calls(), ctx)
+  }
+
+  /// Evaluates the expression, with its calls binding a variable from `binding_calls`.
+  pub(crate) fn eval_with_calls<C: ContextProvider>(
+    &self,
+    calls: &[BindingCall],
+    ctx: C,
+  ) -> Result<f64, Error> {
     use self::Token::*;
 
     let mut stack = Vec::with_capacity(16);
+    // calls of `integrate`, `sum_over` and `prod_over` binding a variable are evaluated as a
+    // whole
+    let mut calls = calls.iter().peekable();
+    let mut skip_to = 0;
 
-    for token in &self.0 {
+    for (i, token) in self.0.iter().enumerate() {
+      if i < skip_to {
+        continue;
+      }
+      while calls.next_if(|call| call.start < i).is_some() {}
+      if let Some(call) = calls.next_if(|call| call.start == i) {
+        stack.push(self.eval_binding(call, &ctx)?);
+        skip_to = call.end + 1;
+        continue;
+      }
       match *token {
         Var(ref n) => {
           if let Some(v) = ctx.get_var(n) {
@@ -61,42 +117,11 @@ impl Expr {
         Binary(op) => {
           let right = stack.pop().unwrap();
           let left = stack.pop().unwrap();
-          let r = match op {
-            Plus => left + right,
-            Minus => left - right,
-            Times => left * right,
-            Div => left / right,
-            Rem => left % right,
-            Pow => left.powf(right),
-            _ => {
-              return Err(Error::EvalError(format!(
-                "Unimplemented binary operation: {:?}",
-                op
-              )));
-            }
-          };
-          stack.push(r);
+          stack.push(Expr::binary(op, left, right)?);
         }
         Unary(op) => {
           let x = stack.pop().unwrap();
-          let r = match op {
-            Plus => x,
-            Minus => -x,
-            Fact => {
-              // Check to make sure x has no fractional component (can be converted to int without loss)
-              match factorial(x) {
-                Ok(res) => res,
-                Err(e) => return Err(Error::EvalError(String::from(e))),
-              }
-            }
-            _ => {
-              return Err(Error::EvalError(format!(
-                "Unimplemented unary operation: {:?}",
-                op
-              )));
-            }
-          };
-          stack.push(r);
+          stack.push(Expr::unary(op, x)?);
         }
         Func(ref n, Some(i)) => {
           if stack.len() < i {
@@ -129,23 +154,64 @@ impl Expr {
     Ok(r)
   }
 
+  /// Applies a binary operator.
+  #[inline]
+  pub(crate) fn binary(op: Operation, left: f64, right: f64) -> Result<f64, Error> {
+    use self::Operation::*;
+    match op {
+      Plus => Ok(left + right),
+      Minus => Ok(left - right),
+      Times => Ok(left * right),
+      Div => Ok(left / right),
+      Rem => Ok(left % right),
+      Pow => Ok(left.powf(right)),
+      _ => Err(Error::EvalError(format!(
+        "Unimplemented binary operation: {:?}",
+        op
+      ))),
+    }
+  }
+
+  /// Applies a unary operator.
+  #[inline]
+  pub(crate) fn unary(op: Operation, x: f64) -> Result<f64, Error> {
+    use self::Operation::*;
+    match op {
+      Plus => Ok(x),
+      Minus => Ok(-x),
+      // Check to make sure x has no fractional component (can be converted to int without loss)
+      Fact => factorial(x).map_err(|e| Error::EvalError(String::from(e))),
+      _ => Err(Error::EvalError(format!(
+        "Unimplemented unary operation: {:?}",
+        op
+      ))),
+    }
+  }
+
   /// Checks that the value of every variable in the expression is specified by
-  /// the context `ctx`.
+  /// the context `ctx`, and that every function is called with an accepted number
+  /// of arguments.
   ///
   /// # Failure
   ///
   /// Returns `Err` if a missing variable is detected.
   pub(crate) fn check_context<C: ContextProvider>(&self, ctx: C) -> Result<(), Error> {
-    for t in &self.0 {
+    let calls = self.binding_calls();
+    for (k, t) in self.0.iter().enumerate() {
       match *t {
         Token::Var(ref name) => {
-          if ctx.get_var(name).is_none() {
+          if ctx.get_var(name).is_none() && !self.is_bound(&calls, k) {
             return Err(Error::UnknownVariable(name.clone()));
           }
         }
+        Token::Func(..) if calls.iter().any(|call| call.end == k) => {}
         Token::Func(ref name, Some(i)) => {
-          let v = vec![0.; i];
-          if let Err(e) = ctx.eval_func(name, &v) {
+          // only call the function if the context can't tell us the arity
+          let res = match ctx.func_info(name) {
+            Some(info) => info.arity.check(i),
+            None => ctx.eval_func(name, &vec![0.; i]).map(|_| ()),
+          };
+          if let Err(e) = res {
             return Err(Error::Function(name.to_owned(), e));
           }
         }
@@ -182,6 +248,96 @@ impl From<&Expr> for Expr {
   }
 }
 
+impl From<f64> for Expr {
+  fn from(value: f64) -> Self {
+    Expr::num(value)
+  }
+}
+
+impl From<i64> for Expr {
+  fn from(value: i64) -> Self {
+    Expr::num(value as f64)
+  }
+}
+
+impl From<i32> for Expr {
+  fn from(value: i32) -> Self {
+    Expr::num(value as f64)
+  }
+}
+
+impl TryFrom<&str> for Expr {
+  type Error = Error;
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    value.parse()
+  }
+}
+
+impl TryFrom<String> for Expr {
+  type Error = Error;
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    value.parse()
+  }
+}
+
+/// Constructors and accessors for building expressions with the operator traits in
+/// [`operators`](operators/index.html).
+///
+/// # Example
+///
+/// ```rust
+/// use meval_symbolic::operators::{Powf, Sin};
+/// use meval_symbolic::Expr;
+///
+/// let (x, y) = (Expr::var("x"), Expr::var("y"));
+/// let expr = x.powf(2.0) + 3.0 * y.sin();
+/// assert_eq!(expr, "x^2 + 3 * sin(y)".parse().unwrap());
+/// ```
+impl Expr {
+  /// A number.
+  pub fn num(value: f64) -> Self {
+    Expr(vec![Token::Number(value)])
+  }
+
+  /// A variable.
+  pub fn var<S: Into<String>>(name: S) -> Self {
+    Expr(vec![Token::Var(name.into())])
+  }
+
+  /// A call of the function `name` with the given arguments.
+  pub fn call<S, I>(name: S, args: I) -> Self
+  where
+    S: Into<String>,
+    I: IntoIterator,
+    I::Item: Into<Expr>,
+  {
+    let mut tokens = vec![];
+    let mut n_args = 0;
+    for arg in args {
+      tokens.extend(arg.into().0);
+      n_args += 1;
+    }
+    tokens.push(Token::Func(name.into(), Some(n_args)));
+    Expr(tokens)
+  }
+
+  /// The value of the expression if it's a single number.
+  pub fn as_num(&self) -> Option<f64> {
+    match self.0[..] {
+      [Token::Number(x)] => Some(x),
+      _ => None,
+    }
+  }
+
+  /// The name of the variable if the expression is a single variable.
+  pub fn as_var(&self) -> Option<&str> {
+    match self.0[..] {
+      [Token::Var(ref name)] => Some(name),
+      _ => None,
+    }
+  }
+}
+
 /// Evaluates a string with the given context.
 ///
 /// No built-ins are defined in this case.
@@ -224,6 +380,16 @@ mod tests {
     assert_eq!(eval_str("max(1.)"), Ok(1.));
     assert_eq!(eval_str("max(1., 2., -1)"), Ok(2.));
     assert_eq!(eval_str("min(1., 2., -1)"), Ok(-1.));
+    assert_eq!(eval_str("sum(1, 2, 3)"), Ok(6.));
+    assert_eq!(eval_str("product(2, 3, 4)"), Ok(24.));
+    assert_eq!(eval_str("mean(1, 2, 3, 6)"), Ok(3.));
+    assert_eq!(eval_str("median(5, 1, 3)"), Ok(3.));
+    assert_eq!(eval_str("var(1, 3)"), Ok(1.));
+    assert_eq!(eval_str("stddev(2, 4, 4, 4, 5, 5, 7, 9)"), Ok(2.));
+    assert_eq!(eval_str("quantile(0.5, 1, 2, 3, 4)"), Ok(2.5));
+    assert_eq!(eval_str("norm(3, 4)"), Ok(5.));
+    assert_eq!(eval_str("softmax_lse(0)"), Ok(0.));
+    assert_eq!(eval_str("clamp(7, 0, 5)"), Ok(5.));
     assert_eq!(
       eval_str("sin(1.) + cos(2.)"),
       Ok((1f64).sin() + (2f64).cos())
@@ -328,10 +494,43 @@ mod tests {
       Err(Error::Function(_, FuncEvalError::NumberArgs(1))) => {}
       _ => panic!("bind did not error"),
     }
+    let expr = Expr::from_str("clamp(x, 2)").unwrap();
+    match expr.clone().bind("x") {
+      Err(Error::Function(_, FuncEvalError::NumberArgs(3))) => {}
+      _ => panic!("bind did not error"),
+    }
+    let expr = Expr::from_str("quantile(x)").unwrap();
+    match expr.clone().bind("x") {
+      Err(Error::Function(_, FuncEvalError::TooFewArguments)) => {}
+      _ => panic!("bind did not error"),
+    }
     let expr = Expr::from_str("hey(x,2)").unwrap();
     match expr.clone().bind("x") {
       Err(Error::Function(_, FuncEvalError::UnknownFunction)) => {}
       _ => panic!("bind did not error"),
     }
   }
+
+  #[test]
+  fn test_map_keys() {
+    use std::collections::{BTreeSet, HashMap};
+
+    let mut memo = HashMap::new();
+    memo.insert(Expr::from_str("x^2 + 1").unwrap(), 1);
+    memo.insert(Expr::from_str("x ^ 2 + (1)").unwrap(), 2);
+    memo.insert(Expr::num(f64::NAN), 3);
+    memo.insert(Expr::num(-f64::NAN), 4);
+    memo.insert(Expr::num(-0.), 5);
+    memo.insert(Expr::num(0.), 6);
+    assert_eq!(memo.len(), 3);
+    assert_eq!(memo[&Expr::num(f64::NAN)], 4);
+
+    let sorted: BTreeSet<_> = ["y", "x", "2", "x + 1", "x"]
+      .iter()
+      .map(|s| Expr::from_str(s).unwrap())
+      .collect();
+    assert_eq!(sorted.len(), 4);
+    let first = sorted.iter().next().unwrap();
+    assert_eq!(first, &Expr::from_str("2").unwrap());
+  }
 }
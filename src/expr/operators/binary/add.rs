@@ -14,3 +14,5 @@ impl<Rhs: Into<Expr>> Add<Rhs> for &Expr {
     self.clone().add(rhs)
   }
 }
+
+binary_op_f64!(Add, add);
@@ -14,3 +14,5 @@ impl<Rhs: Into<Expr>> Div<Rhs> for &Expr {
     self.clone().div(rhs)
   }
 }
+
+binary_op_f64!(Div, div);
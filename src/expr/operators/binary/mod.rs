@@ -11,6 +11,25 @@ macro_rules! binary_trait_ref {
   };
 }
 
+/// Implements a `std::ops` operator with an `f64` on the left hand side.
+macro_rules! binary_op_f64 {
+  ($trait_name:ident, $method_name:ident) => {
+    impl $trait_name<Expr> for f64 {
+      type Output = Expr;
+      fn $method_name(self, rhs: Expr) -> Self::Output {
+        Expr::from(self).$method_name(rhs)
+      }
+    }
+
+    impl $trait_name<&Expr> for f64 {
+      type Output = Expr;
+      fn $method_name(self, rhs: &Expr) -> Self::Output {
+        Expr::from(self).$method_name(rhs)
+      }
+    }
+  };
+}
+
 /// Private helper function to construct a new expression from the current
 /// expression, a reference to a right hand side and a operation to apply.
 fn binary_operator(mut lhs: Expr, rhs: impl Into<Expr>, tkn: Token) -> Expr {
@@ -14,3 +14,5 @@ impl<Rhs: Into<Expr>> Mul<Rhs> for &Expr {
     self.clone().mul(rhs)
   }
 }
+
+binary_op_f64!(Mul, mul);
@@ -14,3 +14,5 @@ impl<Rhs: Into<Expr>> Rem<Rhs> for &Expr {
     self.clone().rem(rhs)
   }
 }
+
+binary_op_f64!(Rem, rem);
@@ -14,3 +14,5 @@ impl<Rhs: Into<Expr>> Sub<Rhs> for &Expr {
     self.clone().sub(rhs)
   }
 }
+
+binary_op_f64!(Sub, sub);
@@ -1,10 +1,12 @@
+//! Traits for building expressions from other expressions, numbers and variables with the
+//! usual operators and the methods of `f64`, e.g. `x.powf(2.0) + 3.0 * y.sin()`.
 use super::{Expr, Operation, Token};
 
 pub mod binary;
 pub mod ternary;
 pub mod unary;
 
-use self::{binary::*, ternary::*, unary::*};
+pub use self::{binary::*, ternary::*, unary::*};
 
 pub trait ExprNum<Mid = Self, Rhs = Self, Output = Self>:
   Unary<Output> + Binary<Rhs, Output> + Ternary<Mid, Rhs, Output>
@@ -12,3 +14,40 @@ pub trait ExprNum<Mid = Self, Rhs = Self, Output = Self>:
 }
 
 impl ExprNum for Expr {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(s: &str) -> Expr {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn conversions() {
+    assert_eq!(Expr::from(2.5), parse("2.5"));
+    assert_eq!(Expr::from(3i64), parse("3"));
+    assert_eq!(Expr::var("x"), parse("x"));
+    assert_eq!(Expr::call("atan2", [1., 2.]), parse("atan2(1, 2)"));
+    assert_eq!(Expr::call("rand", Vec::<Expr>::new()), parse("rand()"));
+    assert_eq!(parse("2").as_num(), Some(2.));
+    assert_eq!(parse("x").as_var(), Some("x"));
+    assert_eq!(parse("x + 1").as_var(), None);
+  }
+
+  #[test]
+  fn builders_match_parser() {
+    let (x, y) = (&Expr::var("x"), &Expr::var("y"));
+
+    assert_eq!(x.powf(2.0) + 3.0 * y.sin(), parse("x^2 + 3 * sin(y)"));
+    assert_eq!(2.0 - x / 4, parse("2 - x / 4"));
+    assert_eq!(1.0 / (x % y), parse("1 / (x % y)"));
+    assert_eq!(-(x + 1.0), parse("-(x + 1)"));
+    assert_eq!(x.mul_add(y, 1.0), parse("mul_add(x, y, 1)"));
+    assert_eq!(
+      Expr::call("max", [x.clone(), y.exp(), 0.5.into()]).atan2(x),
+      parse("atan2(max(x, exp(y), 0.5), x)")
+    );
+    assert_eq!(x.hypot(y.ln()).sqrt(), parse("sqrt(hypot(x, ln(y)))"));
+  }
+}
@@ -12,12 +12,12 @@ use serde::Deserialize;
 /// #[macro_use]
 /// extern crate serde_derive;
 /// extern crate toml;
-/// extern crate meval;
-/// use meval::{Expr, Context};
+/// extern crate meval_symbolic;
+/// use meval_symbolic::{Expr, Context};
 ///
 /// #[derive(Deserialize)]
 /// struct Foo {
-///     #[serde(deserialize_with = "meval::de::as_f64")]
+///     #[serde(deserialize_with = "meval_symbolic::de::as_f64")]
 ///     x: f64,
 /// }
 ///
@@ -37,10 +37,13 @@ pub fn as_f64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Erro
     .map_err(D::Error::custom)
 }
 
+use shunting_yard::check_rpn;
 use std::fmt;
 use std::str::FromStr;
 use tokenizer::Token;
 
+/// Deserializes from a string in infix notation, a number or a list of tokens in RPN. Only
+/// the latter is accepted from formats that aren't human readable.
 impl<'de> Deserialize<'de> for Expr {
   fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
   where
@@ -82,9 +85,25 @@ impl<'de> Deserialize<'de> for Expr {
       {
         Ok(Expr(vec![Token::Number(v as f64)]))
       }
+
+      fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+      where
+        A: serde::de::SeqAccess<'de>,
+      {
+        let mut rpn = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(token) = seq.next_element()? {
+          rpn.push(token);
+        }
+        check_rpn(&rpn).map_err(serde::de::Error::custom)?;
+        Ok(Expr(rpn))
+      }
     }
 
-    deserializer.deserialize_any(ExprVisitor)
+    if deserializer.is_human_readable() {
+      deserializer.deserialize_any(ExprVisitor)
+    } else {
+      deserializer.deserialize_seq(ExprVisitor)
+    }
   }
 }
 
@@ -96,17 +115,29 @@ mod tests {
   use serde_test;
   #[test]
   fn test_deserialization() {
-    use serde_test::Token;
+    use serde_test::{Configure, Token};
     let expr = Expr::from_str("sin(x)").unwrap();
 
-    serde_test::assert_de_tokens(&expr, &[Token::Str("sin(x)")]);
-    serde_test::assert_de_tokens(&expr, &[Token::String("sin(x)")]);
+    serde_test::assert_de_tokens(&expr.clone().readable(), &[Token::Str("sin(x)")]);
+    serde_test::assert_de_tokens(&expr.clone().readable(), &[Token::String("sin(x)")]);
 
     let expr = Expr::from_str("5").unwrap();
 
-    serde_test::assert_de_tokens(&expr, &[Token::F64(5.)]);
-    serde_test::assert_de_tokens(&expr, &[Token::U8(5)]);
-    serde_test::assert_de_tokens(&expr, &[Token::I8(5)]);
+    serde_test::assert_de_tokens(&expr.clone().readable(), &[Token::F64(5.)]);
+    serde_test::assert_de_tokens(&expr.clone().readable(), &[Token::U8(5)]);
+    serde_test::assert_de_tokens(&expr.clone().readable(), &[Token::I8(5)]);
+  }
+
+  #[test]
+  fn test_rpn_deserialization() {
+    let expr: Expr =
+      serde_json::from_str(r#"[{"Var": "x"}, {"Number": 2.0}, {"Binary": "Pow"}]"#).unwrap();
+    assert_eq!(expr, Expr::from_str("x^2").unwrap());
+
+    let res = serde_json::from_str::<Expr>(r#"[{"Var": "x"}, {"Binary": "Pow"}]"#);
+    assert!(res.is_err());
+    let res = serde_json::from_str::<Expr>(r#"["LParen", {"Var": "x"}, "RParen"]"#);
+    assert!(res.is_err());
   }
 
   #[test]
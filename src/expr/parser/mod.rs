@@ -1,4 +1,7 @@
-use self::{shunting_yard::to_rpn, tokenizer::tokenize};
+use self::{
+  shunting_yard::{check_rpn, to_rpn, RPNError},
+  tokenizer::tokenize,
+};
 use super::{Error, Expr, Operation, Token};
 use std::str::FromStr;
 
@@ -16,7 +19,27 @@ impl FromStr for Expr {
   }
 }
 
-#[cfg(feature = "serde")]
-use super::Expr;
+impl Expr {
+  /// Constructs an expression from tokens in infix notation, as produced by the tokenizer.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err` if the tokens don't form a valid expression.
+  pub fn from_infix(tokens: Vec<Token>) -> Result<Self, RPNError> {
+    to_rpn(tokens).map(Expr)
+  }
+
+  /// Constructs an expression from tokens in RPN.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err` if the tokens don't form a valid expression.
+  pub fn from_rpn(tokens: Vec<Token>) -> Result<Self, RPNError> {
+    check_rpn(&tokens).map(|_| Expr(tokens))
+  }
+}
+
 #[cfg(feature = "serde")]
 pub mod de;
+#[cfg(feature = "serde")]
+mod ser;
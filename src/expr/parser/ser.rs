@@ -0,0 +1,72 @@
+//! Serialization of expressions.
+use super::Expr;
+use serde::{Serialize, Serializer};
+
+/// Serializes into infix notation for human readable formats, e.g. JSON or TOML, and into the
+/// list of tokens in RPN otherwise.
+impl Serialize for Expr {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+      serializer.collect_str(self)
+    } else {
+      self.0.serialize(serializer)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_test::{assert_ser_tokens, Configure, Token};
+  use std::str::FromStr;
+
+  #[test]
+  fn test_serialization() {
+    let expr = Expr::from_str("2 * sin(x)").unwrap();
+    assert_ser_tokens(&expr.clone().readable(), &[Token::Str("2 * sin(x)")]);
+    assert_ser_tokens(
+      &expr.compact(),
+      &[
+        Token::Seq { len: Some(4) },
+        Token::NewtypeVariant {
+          name: "Token",
+          variant: "Number",
+        },
+        Token::F64(2.),
+        Token::NewtypeVariant {
+          name: "Token",
+          variant: "Var",
+        },
+        Token::Str("x"),
+        Token::TupleVariant {
+          name: "Token",
+          variant: "Func",
+          len: 2,
+        },
+        Token::Str("sin"),
+        Token::Some,
+        Token::U64(1),
+        Token::TupleVariantEnd,
+        Token::NewtypeVariant {
+          name: "Token",
+          variant: "Binary",
+        },
+        Token::UnitVariant {
+          name: "Operation",
+          variant: "Times",
+        },
+        Token::SeqEnd,
+      ],
+    );
+  }
+
+  #[test]
+  fn test_json_round_trip() {
+    let exprs = ["x^2 - 1", "-(a + b) / c!", "max(1, y, rand())", "2.5"];
+    for s in &exprs {
+      let expr = Expr::from_str(s).unwrap();
+      let json = serde_json::to_string(&expr).unwrap();
+      assert_eq!(serde_json::from_str::<Expr>(&json).unwrap(), expr);
+    }
+  }
+}
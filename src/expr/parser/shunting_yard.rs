@@ -29,6 +29,8 @@ pub enum RPNError {
   NotEnoughOperands(usize),
   /// Too many operands reported.
   TooManyOperands,
+  /// A token that can't appear in an expression in RPN.
+  UnexpectedToken(usize),
 }
 
 impl Display for RPNError {
@@ -45,6 +47,7 @@ impl Display for RPNError {
       RPNError::TooManyOperands => {
         write!(f, "Too many operands left at the end of expression.")
       }
+      RPNError::UnexpectedToken(i) => write!(f, "Unexpected token {} in RPN.", i),
     }
   }
 }
@@ -116,7 +119,12 @@ pub fn to_rpn(input: Vec<Token>) -> Result<Vec<Token>, RPNError> {
             }
             Func(name, nargs) => {
               found = true;
-              output.push(Func(name, Some(nargs.unwrap_or(0) + 1)));
+              // `func()` is called without any arguments
+              let nargs = match input[index - 1] {
+                Func(..) => 0,
+                _ => nargs.unwrap_or(0) + 1,
+              };
+              output.push(Func(name, Some(nargs)));
               break;
             }
             _ => output.push(t),
@@ -157,27 +165,41 @@ pub fn to_rpn(input: Vec<Token>) -> Result<Vec<Token>, RPNError> {
     }
   }
 
-  // verify rpn
+  check_rpn(&output)?;
+
+  output.shrink_to_fit();
+  Ok(output)
+}
+
+/// Checks that a sequence of tokens is a well-formed expression in RPN.
+///
+/// # Failure
+///
+/// Returns `Err` if a token is not allowed in RPN or has the wrong number of operands.
+pub fn check_rpn(rpn: &[Token]) -> Result<(), RPNError> {
+  use super::Operation::*;
+  use super::Token::*;
+
   let mut n_operands = 0isize;
-  for (index, token) in output.iter().enumerate() {
+  for (index, token) in rpn.iter().enumerate() {
     match *token {
       Var(_) | Number(_) => n_operands += 1,
-      Unary(_) => (),
+      Unary(Plus) | Unary(Minus) | Unary(Fact) => (),
+      Binary(Fact) => return Err(RPNError::UnexpectedToken(index)),
       Binary(_) => n_operands -= 1,
       Func(_, Some(n_args)) => n_operands -= n_args as isize - 1,
-      _ => panic!("Nothing else should be here"),
+      _ => return Err(RPNError::UnexpectedToken(index)),
     }
     if n_operands <= 0 {
       return Err(RPNError::NotEnoughOperands(index));
     }
   }
 
-  if n_operands > 1 {
-    return Err(RPNError::TooManyOperands);
+  match n_operands {
+    0 => Err(RPNError::NotEnoughOperands(0)),
+    1 => Ok(()),
+    _ => Err(RPNError::TooManyOperands),
   }
-
-  output.shrink_to_fit();
-  Ok(output)
 }
 
 #[cfg(test)]
@@ -297,6 +319,20 @@ mod tests {
       ])
     );
 
+    assert_eq!(
+      to_rpn(vec![
+        Func("rand".into(), None),
+        RParen,
+        Binary(Times),
+        Number(2f64)
+      ]),
+      Ok(vec![
+        Func("rand".into(), Some(0)),
+        Number(2f64),
+        Binary(Times)
+      ])
+    );
+
     assert_eq!(
       to_rpn(vec![Binary(Plus)]),
       Err(RPNError::NotEnoughOperands(0))
@@ -325,4 +361,27 @@ mod tests {
       Err(RPNError::UnexpectedComma(2))
     );
   }
+
+  #[test]
+  fn test_check_rpn() {
+    assert_eq!(check_rpn(&[Number(1.), Unary(Fact)]), Ok(()));
+    assert_eq!(check_rpn(&[Func("rand".into(), Some(0))]), Ok(()));
+    assert_eq!(check_rpn(&[]), Err(RPNError::NotEnoughOperands(0)));
+    assert_eq!(
+      check_rpn(&[Number(1.), Unary(Times)]),
+      Err(RPNError::UnexpectedToken(1))
+    );
+    assert_eq!(
+      check_rpn(&[Number(1.), Number(2.), Binary(Fact)]),
+      Err(RPNError::UnexpectedToken(2))
+    );
+    assert_eq!(
+      check_rpn(&[Number(1.), Func("f".into(), None)]),
+      Err(RPNError::UnexpectedToken(1))
+    );
+    assert_eq!(
+      check_rpn(&[Number(1.), Number(2.), Func("f".into(), Some(3))]),
+      Err(RPNError::NotEnoughOperands(2))
+    );
+  }
 }
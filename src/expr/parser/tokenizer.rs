@@ -9,6 +9,9 @@ use crate::shunting_yard::to_rpn;
 
 use super::Expr;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use nom::{
   branch::alt,
   bytes::complete::{is_not, tag},
@@ -22,7 +25,9 @@ use nom::{
 };
 
 use std::{
+  cmp::Ordering,
   fmt::{self, Display, Formatter},
+  hash::{Hash, Hasher},
   num::NonZeroUsize,
   str::{from_utf8, FromStr},
 };
@@ -76,7 +81,8 @@ impl Display for ParseError {
 }
 
 /// Mathematical operations.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Operation {
   Plus,
   Minus,
@@ -88,7 +94,15 @@ pub enum Operation {
 }
 
 /// Expression tokens.
-#[derive(Debug, PartialEq, Clone)]
+///
+/// Tokens are totally ordered, first by variant and then by their contents, and can be hashed.
+/// To make this possible, numbers are compared after normalising them, such that:
+///
+/// - all NaNs are equal to each other, and greater than every other number;
+/// - `-0.0` is equal to `0.0`;
+/// - all other numbers are compared as usual.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Token {
   /// Binary operation.
   Binary(Operation),
@@ -110,6 +124,75 @@ pub enum Token {
   Func(String, Option<usize>),
 }
 
+impl Token {
+  /// The position of the variant in the declaration, used for ordering.
+  fn index(&self) -> u8 {
+    use self::Token::*;
+    match *self {
+      Binary(_) => 0,
+      Unary(_) => 1,
+      LParen => 2,
+      RParen => 3,
+      Comma => 4,
+      Number(_) => 5,
+      Var(_) => 6,
+      Func(..) => 7,
+    }
+  }
+}
+
+/// Normalises NaNs and zeros, see [`Token`](enum.Token.html).
+fn normalised(x: f64) -> f64 {
+  if x.is_nan() {
+    f64::NAN
+  } else if x == 0. {
+    0.
+  } else {
+    x
+  }
+}
+
+impl PartialEq for Token {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Token {}
+
+impl PartialOrd for Token {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Token {
+  fn cmp(&self, other: &Self) -> Ordering {
+    use self::Token::*;
+    match (self, other) {
+      (Binary(a), Binary(b)) | (Unary(a), Unary(b)) => a.cmp(b),
+      (Number(a), Number(b)) => normalised(*a).total_cmp(&normalised(*b)),
+      (Var(a), Var(b)) => a.cmp(b),
+      (Func(a, n), Func(b, m)) => (a, n).cmp(&(b, m)),
+      _ => self.index().cmp(&other.index()),
+    }
+  }
+}
+
+impl Hash for Token {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    use self::Token::*;
+    self.index().hash(state);
+    match self {
+      Binary(op) | Unary(op) => op.hash(state),
+      Number(x) => normalised(*x).to_bits().hash(state),
+      Var(name) => name.hash(state),
+      Func(name, n) => (name, n).hash(state),
+      LParen | RParen | Comma => (),
+    }
+  }
+}
+
 fn binop(input: &[u8]) -> IResult<&[u8], Token> {
   alt((
     value(Token::Binary(Operation::Plus), tag("+")),
@@ -185,6 +268,14 @@ fn lexpr(input: &[u8]) -> IResult<&[u8], Token> {
     multispace0,
   )(input)
 }
+/// Directly after `func(`, also accept `)` for functions without arguments.
+fn lexpr_no_args(input: &[u8]) -> IResult<&[u8], Token> {
+  delimited(
+    multispace0,
+    alt((number, func, var, negpos, lparen, rparen)),
+    multispace0,
+  )(input)
+}
 fn after_rexpr(input: &[u8]) -> IResult<&[u8], Token> {
   delimited(multispace0, alt((fact, binop, rparen)), multispace0)(input)
 }
@@ -229,6 +320,7 @@ pub(crate) fn tokens(mut input: &[u8]) -> IResult<&[u8], Vec<Token>> {
   while !input.is_empty() {
     let t;
     (input, t) = match (state, paren_stack.last()) {
+      (LExpr, _) if matches!(res.last(), Some(Func(..))) => lexpr_no_args(input),
       (LExpr, _) => lexpr(input),
       (AfterRExpr, None) => after_rexpr_no_paren(input),
       (AfterRExpr, Some(&SubExpr)) => after_rexpr(input),
@@ -244,6 +336,7 @@ pub(crate) fn tokens(mut input: &[u8]) -> IResult<&[u8], Vec<Token>> {
       }
       RParen => {
         paren_stack.pop().expect("The paren_stack is empty!");
+        state = AfterRExpr;
       }
       Var(_) | Number(_) => {
         state = AfterRExpr;
@@ -449,6 +542,16 @@ mod tests {
       ])
     );
 
+    assert_eq!(
+      tokenize("rand() + 1"),
+      Ok(vec![
+        Func("rand".into(), None),
+        RParen,
+        Binary(Plus),
+        Number(1f64)
+      ])
+    );
+
     assert_eq!(tokenize("!3"), Err(ParseError::UnexpectedToken(0)));
 
     assert_eq!(tokenize("()"), Err(ParseError::UnexpectedToken(1)));
@@ -459,5 +562,30 @@ mod tests {
     assert_eq!(tokenize("(((2)"), Err(ParseError::MissingRParen(2)));
     assert_eq!(tokenize("f(2,)"), Err(ParseError::UnexpectedToken(4)));
     assert_eq!(tokenize("f(,2)"), Err(ParseError::UnexpectedToken(2)));
+    assert_eq!(tokenize("f()2"), Err(ParseError::UnexpectedToken(3)));
+  }
+
+  #[test]
+  fn test_number_equality() {
+    use std::collections::hash_map::DefaultHasher;
+    let hash = |t: &Token| {
+      let mut h = DefaultHasher::new();
+      t.hash(&mut h);
+      h.finish()
+    };
+
+    let nan = Token::Number(f64::NAN);
+    let other_nan = Token::Number(-f64::NAN);
+    assert_eq!(nan, other_nan);
+    assert_eq!(hash(&nan), hash(&other_nan));
+    assert_eq!(Token::Number(-0.), Token::Number(0.));
+    assert_eq!(hash(&Token::Number(-0.)), hash(&Token::Number(0.)));
+    assert_ne!(Token::Number(1.), Token::Number(-1.));
+
+    assert!(Token::Number(f64::INFINITY) < nan);
+    assert!(Token::Number(-1.) < Token::Number(-0.5));
+    assert!(Token::Binary(Operation::Pow) < Token::Unary(Operation::Plus));
+    assert!(Token::Number(1e10) < Token::Var("a".into()));
+    assert!(Token::Func("f".into(), Some(1)) < Token::Func("f".into(), Some(2)));
   }
 }
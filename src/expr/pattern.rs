@@ -0,0 +1,352 @@
+//! Constraints on the variables of patterns and side conditions of matches.
+use super::symbolic::{SubstituteError, Substitutions};
+use super::{builtin, ContextProvider, Expr, Token};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+/// A constraint on the expressions a pattern variable can match.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Constraint {
+  /// An expression that evaluates to a number with the built-in constants and functions, e.g.
+  /// `2 * pi`, written `?c:const` in rules.
+  Const,
+  /// A single variable, written `?v:var` in rules.
+  Var,
+  /// An expression that doesn't contain the given variable, written `?e:free_of(x)` in rules.
+  ///
+  /// If the variable is itself bound by the match, the expression mustn't contain whatever it
+  /// was bound to.
+  FreeOf(String),
+}
+
+impl Display for Constraint {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Constraint::Const => write!(f, "const"),
+      Constraint::Var => write!(f, "var"),
+      Constraint::FreeOf(x) => write!(f, "free_of({})", x),
+    }
+  }
+}
+
+/// A comparison between two numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+}
+
+impl Comparison {
+  /// The comparisons and how they are written, with longer symbols first.
+  pub(crate) const SYMBOLS: [(&'static str, Comparison); 6] = [
+    ("!=", Comparison::Ne),
+    ("<=", Comparison::Le),
+    (">=", Comparison::Ge),
+    ("=", Comparison::Eq),
+    ("<", Comparison::Lt),
+    (">", Comparison::Gt),
+  ];
+
+  /// The first comparison symbol in `s`, with its position.
+  ///
+  /// A `!` right after an operand is its factorial, so `3!=6` is `3! = 6`, while `3 != 6`
+  /// compares `3` and `6`.
+  pub(crate) fn find(s: &str) -> Option<(usize, &'static str, Comparison)> {
+    s.char_indices().find_map(|(i, _)| {
+      let after_operand = s[..i]
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || "_.)!".contains(c));
+      Comparison::SYMBOLS
+        .iter()
+        .filter(|(symbol, _)| !(*symbol == "!=" && after_operand))
+        .find(|(symbol, _)| s[i..].starts_with(symbol))
+        .map(|&(symbol, op)| (i, symbol, op))
+    })
+  }
+
+  /// Compares two numbers.
+  pub fn holds(self, lhs: f64, rhs: f64) -> bool {
+    match self {
+      Comparison::Eq => lhs == rhs,
+      Comparison::Ne => lhs != rhs,
+      Comparison::Lt => lhs < rhs,
+      Comparison::Le => lhs <= rhs,
+      Comparison::Gt => lhs > rhs,
+      Comparison::Ge => lhs >= rhs,
+    }
+  }
+
+  /// The comparison with the sides swapped, which is also the one that holds after multiplying
+  /// both sides by a negative number, e.g. `>` for `<`.
+  pub fn reversed(self) -> Comparison {
+    match self {
+      Comparison::Lt => Comparison::Gt,
+      Comparison::Le => Comparison::Ge,
+      Comparison::Gt => Comparison::Lt,
+      Comparison::Ge => Comparison::Le,
+      cmp => cmp,
+    }
+  }
+}
+
+impl Display for Comparison {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let &(symbol, _) = Comparison::SYMBOLS
+      .iter()
+      .find(|(_, cmp)| cmp == self)
+      .unwrap();
+    f.write_str(symbol)
+  }
+}
+
+/// A side condition `lhs op rhs` on the expressions bound by a match, e.g. `x != 0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Condition {
+  pub lhs: Expr,
+  pub op: Comparison,
+  pub rhs: Expr,
+}
+
+impl Condition {
+  pub fn new(lhs: Expr, op: Comparison, rhs: Expr) -> Self {
+    Condition { lhs, op, rhs }
+  }
+
+  /// Whether the condition holds with the variables bound by `subs`.
+  ///
+  /// Both sides are evaluated with the constants and functions of `ctx`, so the condition only
+  /// holds if it can be decided numerically, e.g. `x != 0` doesn't hold if `x` is bound to `y`.
+  pub fn holds<C: ContextProvider>(&self, subs: &Substitutions, ctx: C) -> bool {
+    let lhs = subs.apply(&self.lhs).eval_with_context(&ctx);
+    let rhs = subs.apply(&self.rhs).eval_with_context(&ctx);
+    match (lhs, rhs) {
+      (Ok(lhs), Ok(rhs)) => self.op.holds(lhs, rhs),
+      _ => false,
+    }
+  }
+}
+
+impl Display for Condition {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
+  }
+}
+
+/// Constraints on the variables of a pattern and side conditions that a match has to satisfy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Guard {
+  pub constraints: BTreeMap<String, Constraint>,
+  pub conditions: Vec<Condition>,
+}
+
+impl Guard {
+  /// A guard without any constraints or conditions, accepting every match.
+  pub fn new() -> Self {
+    Guard::default()
+  }
+
+  /// Adds a constraint on the variable `var`.
+  pub fn constrain<S: Into<String>>(mut self, var: S, constraint: Constraint) -> Self {
+    self.constraints.insert(var.into(), constraint);
+    self
+  }
+
+  /// Adds a side condition.
+  pub fn when(mut self, condition: Condition) -> Self {
+    self.conditions.push(condition);
+    self
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.constraints.is_empty() && self.conditions.is_empty()
+  }
+
+  /// Checks that the variables bound by `subs` satisfy the constraints and conditions, with
+  /// the constants and functions of `ctx`.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(Unsatisfied)` with the first constraint or condition that doesn't hold.
+  pub fn check<C: ContextProvider>(
+    &self,
+    subs: &Substitutions,
+    ctx: C,
+  ) -> Result<(), SubstituteError> {
+    for (var, constraint) in &self.constraints {
+      let expr = match subs.get(var) {
+        Some(expr) => expr,
+        None => continue,
+      };
+      let ok = match constraint {
+        Constraint::Const => expr.eval_with_context(&ctx).is_ok(),
+        Constraint::Var => matches!(expr[..], [Token::Var(_)]),
+        Constraint::FreeOf(x) => match subs.get(x) {
+          Some(bound) => !expr.contains(bound),
+          None => !expr
+            .iter()
+            .any(|t| matches!(t, Token::Var(name) if name == x)),
+        },
+      };
+      if !ok {
+        return Err(SubstituteError::Unsatisfied(format!(
+          "?{}:{}",
+          var, constraint
+        )));
+      }
+    }
+    match self.conditions.iter().find(|cond| !cond.holds(subs, &ctx)) {
+      Some(cond) => Err(SubstituteError::Unsatisfied(cond.to_string())),
+      None => Ok(()),
+    }
+  }
+}
+
+/// A term to match, with the guard that the variables it binds have to satisfy, e.g. the left
+/// hand side of a rule with its `where` clause.
+///
+/// Both `&Expr` and `(&Expr, &Guard)` convert into a pattern, so either can be passed to
+/// [`Expr::extract`](struct.Expr.html#method.extract).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pattern<'a> {
+  pub term: &'a Expr,
+  pub guard: Option<&'a Guard>,
+}
+
+impl<'a> From<&'a Expr> for Pattern<'a> {
+  fn from(term: &'a Expr) -> Self {
+    Pattern { term, guard: None }
+  }
+}
+
+impl<'a> From<(&'a Expr, &'a Guard)> for Pattern<'a> {
+  fn from((term, guard): (&'a Expr, &'a Guard)) -> Self {
+    Pattern {
+      term,
+      guard: Some(guard),
+    }
+  }
+}
+
+/// Formats the constraints as `?c:const` and the conditions as `x != 0`, separated by commas,
+/// as written after `where` in rules.
+impl Display for Guard {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let constraints = self
+      .constraints
+      .iter()
+      .map(|(var, constraint)| format!("?{}:{}", var, constraint));
+    let conditions = self.conditions.iter().map(|cond| cond.to_string());
+    let parts: Vec<_> = constraints.chain(conditions).collect();
+    f.write_str(&parts.join(", "))
+  }
+}
+
+impl Expr {
+  /// Whether `sub` is a subexpression of self.
+  pub fn contains(&self, sub: &Expr) -> bool {
+    let ptrs = self.start_pointers();
+    (0..self.len()).any(|j| self.0[ptrs[j]..=j] == sub.0[..])
+  }
+
+  /// Like [`extract`](#method.extract), checking that the variables bound satisfy `guard`.
+  ///
+  /// The same as `self.extract((term, guard))`.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Constraint, Expr, Guard};
+  /// let guard = Guard::new().constrain("c", Constraint::Const);
+  /// let mtch: Expr = "c * x".parse().unwrap();
+  ///
+  /// let expr: Expr = "(2 * pi) * y".parse().unwrap();
+  /// assert!(expr.extract_where(&mtch, &guard).is_ok());
+  /// let expr: Expr = "z * y".parse().unwrap();
+  /// assert!(expr.extract_where(&mtch, &guard).is_err());
+  /// ```
+  pub fn extract_where(
+    &self,
+    term: &Expr,
+    guard: &Guard,
+  ) -> Result<Substitutions, SubstituteError> {
+    self.extract((term, guard))
+  }
+
+  /// Like [`extract_ac`](#method.extract_ac), keeping only the substitutions satisfying
+  /// `guard`.
+  pub fn extract_ac_where(&self, term: &Expr, guard: &Guard) -> Vec<Substitutions> {
+    let ctx = builtin();
+    let mut subs = self.extract_ac(term);
+    subs.retain(|subs| guard.check(subs, &ctx).is_ok());
+    subs
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn constraints() {
+    let guard = Guard::new()
+      .constrain("v", Constraint::Var)
+      .constrain("e", Constraint::FreeOf("v".into()));
+    let mtch = expr!(e * v);
+
+    assert!(expr!((y + 1) * x).extract_where(&mtch, &guard).is_ok());
+    assert_eq!(
+      expr!((x + 1) * x).extract_where(&mtch, &guard),
+      Err(SubstituteError::Unsatisfied("?e:free_of(v)".into()))
+    );
+    assert_eq!(
+      expr!(y * (x + 1)).extract_where(&mtch, &guard),
+      Err(SubstituteError::Unsatisfied("?v:var".into()))
+    );
+
+    let guard = Guard::new().constrain("e", Constraint::FreeOf("t".into()));
+    assert!(expr!(sin(x)).extract_where(&expr!(e), &guard).is_ok());
+    assert!(expr!(sin(t)).extract_where(&expr!(e), &guard).is_err());
+  }
+
+  #[test]
+  fn extract_checks_guard() {
+    let guard = Guard::new()
+      .constrain("c", Constraint::Const)
+      .when(Condition::new(expr!(c), Comparison::Gt, expr!(1)));
+    let mtch = expr!(c * x);
+    assert!(expr!((2 * pi) * y).extract((&mtch, &guard)).is_ok());
+    assert_eq!(
+      expr!(z * y).extract((&mtch, &guard)),
+      Err(SubstituteError::Unsatisfied("?c:const".into()))
+    );
+    assert_eq!(
+      expr!(0.5 * y).extract((&mtch, &guard)),
+      Err(SubstituteError::Unsatisfied("c > 1".into()))
+    );
+    // without a guard every match is accepted
+    assert!(expr!(z * y).extract(&mtch).is_ok());
+  }
+
+  #[test]
+  fn conditions() {
+    let guard = Guard::new().when(Condition::new(expr!(x), Comparison::Ne, expr!(0)));
+    let mtch = expr!(x / x);
+    assert!(expr!(2 / 2).extract_where(&mtch, &guard).is_ok());
+    assert_eq!(
+      expr!((1 - 1) / (1 - 1)).extract_where(&mtch, &guard),
+      Err(SubstituteError::Unsatisfied("x != 0".into()))
+    );
+    // conditions that can't be decided don't hold
+    assert!(expr!(y / y).extract_where(&mtch, &guard).is_err());
+    assert_eq!(guard.to_string(), "x != 0");
+
+    let guard = Guard::new().when(Condition::new(expr!(a), Comparison::Gt, expr!(b)));
+    let subs = expr!(3 + 2 + 1).extract_ac_where(&expr!(a + b), &guard);
+    assert_eq!(subs.len(), 2);
+  }
+}
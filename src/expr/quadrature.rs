@@ -0,0 +1,496 @@
+//! Adaptive numerical quadrature, and the functions `integrate`, `sum_over` and `prod_over`
+//! of an expression in a variable bound by the call, e.g. `sum_over(i^2, i, 1, n)`.
+use super::{ContextProvider, Expr, Token};
+use std::f64;
+use Error;
+
+/// The functions whose first argument is an expression in the variable given as the second
+/// argument.
+pub(crate) const BINDING_FUNCS: &[&str] = &["integrate", "sum_over", "prod_over"];
+
+/// The largest number of terms of `sum_over` and `prod_over`.
+const MAX_TERMS: f64 = 1e7;
+
+/// The nodes of the 15-point Kronrod rule on `[-1, 1]`, from the outermost to the centre. The
+/// odd ones are the nodes of the embedded 7-point Gauss rule.
+const KRONROD_NODES: [f64; 8] = [
+  0.991_455_371_120_812_6,
+  0.949_107_912_342_758_5,
+  0.864_864_423_359_769_1,
+  0.741_531_185_599_394_4,
+  0.586_087_235_467_691_1,
+  0.405_845_151_377_397_2,
+  0.207_784_955_007_898_5,
+  0.,
+];
+
+const KRONROD_WEIGHTS: [f64; 8] = [
+  0.022_935_322_010_529_22,
+  0.063_092_092_629_978_55,
+  0.104_790_010_322_250_2,
+  0.140_653_259_715_525_9,
+  0.169_004_726_639_267_9,
+  0.190_350_578_064_785_4,
+  0.204_432_940_075_298_9,
+  0.209_482_141_084_727_8,
+];
+
+const GAUSS_WEIGHTS: [f64; 4] = [
+  0.129_484_966_168_869_7,
+  0.279_705_391_489_276_7,
+  0.381_830_050_505_118_9,
+  0.417_959_183_673_469_4,
+];
+
+/// The stopping criteria of [`Expr::quadrature`].
+///
+/// [`Expr::quadrature`]: struct.Expr.html#method.quadrature
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuadratureOptions {
+  /// The integral is accepted once its estimated error is at most
+  /// `max(abs_tolerance, tolerance * |integral|)`.
+  pub tolerance: f64,
+  pub abs_tolerance: f64,
+  /// The largest number of subintervals the interval is split into.
+  pub max_intervals: usize,
+}
+
+impl QuadratureOptions {
+  /// Options with a relative tolerance of `1e-10`, an absolute tolerance of `1e-12` and at
+  /// most 500 subintervals.
+  pub fn new() -> Self {
+    QuadratureOptions {
+      tolerance: 1e-10,
+      abs_tolerance: 1e-12,
+      max_intervals: 500,
+    }
+  }
+
+  pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+    self.tolerance = tolerance;
+    self
+  }
+
+  pub fn with_abs_tolerance(mut self, abs_tolerance: f64) -> Self {
+    self.abs_tolerance = abs_tolerance;
+    self
+  }
+
+  pub fn with_max_intervals(mut self, max_intervals: usize) -> Self {
+    self.max_intervals = max_intervals;
+    self
+  }
+}
+
+impl Default for QuadratureOptions {
+  fn default() -> Self {
+    QuadratureOptions::new()
+  }
+}
+
+/// The result of numerical integration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quadrature {
+  pub value: f64,
+  /// An estimate of the absolute error of the value.
+  pub error: f64,
+  /// Whether the tolerance was reached, rather than the maximum number of subintervals.
+  pub converged: bool,
+  /// The number of times the integrand was evaluated.
+  pub evaluations: usize,
+}
+
+/// The integral over `[a, b]` by the 15-point Kronrod rule, and the difference to the 7-point
+/// Gauss rule as an estimate of its error.
+fn kronrod<F>(f: &mut F, a: f64, b: f64) -> Result<(f64, f64), Error>
+where
+  F: FnMut(f64) -> Result<f64, Error>,
+{
+  let (mid, half) = ((a + b) / 2., (b - a) / 2.);
+  let centre = f(mid)?;
+  let mut k = centre * KRONROD_WEIGHTS[7];
+  let mut g = centre * GAUSS_WEIGHTS[3];
+  for i in 0..7 {
+    let dx = half * KRONROD_NODES[i];
+    let sum = f(mid - dx)? + f(mid + dx)?;
+    k += KRONROD_WEIGHTS[i] * sum;
+    if i % 2 == 1 {
+      g += GAUSS_WEIGHTS[i / 2] * sum;
+    }
+  }
+  Ok((k * half, ((k - g) * half).abs()))
+}
+
+/// Integrates `f` over `[a, b]`, splitting the subinterval with the largest estimated error in
+/// two until the estimated error of the sum is small enough. Infinite bounds are mapped to
+/// finite ones by a change of variables.
+fn integrate<F>(mut f: F, a: f64, b: f64, options: &QuadratureOptions) -> Result<Quadrature, Error>
+where
+  F: FnMut(f64) -> Result<f64, Error>,
+{
+  if a > b {
+    let res = integrate(f, b, a, options)?;
+    return Ok(Quadrature {
+      value: -res.value,
+      ..res
+    });
+  }
+  if a == b {
+    return Ok(Quadrature {
+      value: 0.,
+      error: 0.,
+      converged: true,
+      evaluations: 0,
+    });
+  }
+
+  let mut evaluations = 0;
+  let mut g = |t: f64| -> Result<f64, Error> {
+    evaluations += 1;
+    match (a.is_finite(), b.is_finite()) {
+      (true, true) => f(t),
+      // x = a + t / (1 - t) for t in [0, 1)
+      (true, false) => f(a + t / (1. - t)).map(|y| y / ((1. - t) * (1. - t))),
+      // x = b - (1 - t) / t for t in (0, 1]
+      (false, true) => f(b - (1. - t) / t).map(|y| y / (t * t)),
+      // x = t / (1 - t^2) for t in (-1, 1)
+      (false, false) => {
+        let s = 1. - t * t;
+        f(t / s).map(|y| y * (1. + t * t) / (s * s))
+      }
+    }
+  };
+  let (lo, hi) = match (a.is_finite(), b.is_finite()) {
+    (true, true) => (a, b),
+    (true, false) => (0., 1.),
+    (false, true) => (0., 1.),
+    (false, false) => (-1., 1.),
+  };
+
+  // the subintervals, with their integrals and estimated errors
+  let (value, error) = kronrod(&mut g, lo, hi)?;
+  let mut intervals = vec![(lo, hi, value, error)];
+  let (mut value, mut error) = (value, error);
+  let done =
+    |value: f64, error: f64| error <= options.abs_tolerance.max(options.tolerance * value.abs());
+  while !done(value, error) && intervals.len() < options.max_intervals {
+    let worst = (0..intervals.len())
+      .max_by(|&i, &j| intervals[i].3.total_cmp(&intervals[j].3))
+      .unwrap_or(0);
+    let (a, b, ..) = intervals.swap_remove(worst);
+    let mid = (a + b) / 2.;
+    let (left, left_error) = kronrod(&mut g, a, mid)?;
+    let (right, right_error) = kronrod(&mut g, mid, b)?;
+    intervals.push((a, mid, left, left_error));
+    intervals.push((mid, b, right, right_error));
+    value = intervals.iter().map(|i| i.2).sum();
+    error = intervals.iter().map(|i| i.3).sum();
+  }
+  Ok(Quadrature {
+    value,
+    error,
+    converged: done(value, error),
+    evaluations,
+  })
+}
+
+/// A call `name(body, var, lo, hi)` of one of the [`BINDING_FUNCS`], by the positions of its
+/// tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BindingCall {
+  /// The first token of the body.
+  pub start: usize,
+  /// The bound variable, just after the body.
+  pub var: usize,
+  /// The first token of the upper bound.
+  pub hi: usize,
+  /// The token of the function.
+  pub end: usize,
+}
+
+/// Whether the token is a call of one of the [`BINDING_FUNCS`].
+pub(crate) fn is_binding(tkn: &Token) -> bool {
+  match tkn {
+    Token::Func(name, Some(4)) => BINDING_FUNCS.contains(&name.as_str()),
+    _ => false,
+  }
+}
+
+/// An expression with its calls binding a variable, found once to evaluate it many times, as
+/// the closures made by `bind` do.
+#[derive(Debug, Clone)]
+pub(crate) struct PreparedExpr {
+  expr: Expr,
+  calls: Vec<BindingCall>,
+}
+
+impl PreparedExpr {
+  pub(crate) fn new(expr: Expr) -> Self {
+    let calls = expr.binding_calls();
+    PreparedExpr { expr, calls }
+  }
+
+  pub(crate) fn eval_with_context<C: ContextProvider>(&self, ctx: C) -> Result<f64, Error> {
+    self.expr.eval_with_calls(&self.calls, ctx)
+  }
+}
+
+impl Expr {
+  /// The expression with its calls binding a variable, to evaluate it many times.
+  pub(crate) fn prepared(self) -> PreparedExpr {
+    PreparedExpr::new(self)
+  }
+
+  /// The calls binding a variable, ordered by their first token and outer calls first.
+  pub(crate) fn binding_calls(&self) -> Vec<BindingCall> {
+    if !self.iter().any(is_binding) {
+      return vec![];
+    }
+
+    let ptrs = self.start_pointers();
+    let mut calls = vec![];
+    for (end, tkn) in self.iter().enumerate() {
+      if !is_binding(tkn) {
+        continue;
+      }
+      let hi = ptrs[end - 1];
+      let lo = ptrs[hi - 1];
+      let var = lo - 1;
+      if let Token::Var(_) = &self[var] {
+        if ptrs[var] == var {
+          calls.push(BindingCall {
+            start: ptrs[var - 1],
+            var,
+            hi,
+            end,
+          });
+        }
+      }
+    }
+    calls.sort_by_key(|call| (call.start, !call.end));
+    calls
+  }
+
+  /// Whether the variable at `k` is bound by one of the `calls`, i.e. is the variable of a
+  /// call or occurs in its body.
+  pub(crate) fn is_bound(&self, calls: &[BindingCall], k: usize) -> bool {
+    calls
+      .iter()
+      .any(|call| call.start <= k && k <= call.var && self[call.var] == self[k])
+  }
+
+  /// Whether the expression has no variables besides those bound by calls, and only calls pure
+  /// functions.
+  pub(crate) fn is_closed<C: ContextProvider>(&self, ctx: C) -> bool {
+    let calls = self.binding_calls();
+    self.iter().enumerate().all(|(k, tkn)| match tkn {
+      Token::Var(_) => self.is_bound(&calls, k),
+      Token::Func(name, _) => calls.iter().any(|call| call.end == k) || ctx.is_pure(name),
+      _ => true,
+    })
+  }
+
+  /// Evaluates a call binding a variable. The context is taken as `&dyn ContextProvider`, so
+  /// that nested calls don't make for ever new context types.
+  pub(crate) fn eval_binding<C: ContextProvider>(
+    &self,
+    call: &BindingCall,
+    ctx: &C,
+  ) -> Result<f64, Error> {
+    let slice = |from: usize, to: usize| Expr(self[from..to].to_vec());
+    let (body, lo, hi) = (
+      slice(call.start, call.var),
+      slice(call.var + 1, call.hi),
+      slice(call.hi, call.end),
+    );
+    let (name, var) = match (&self[call.end], &self[call.var]) {
+      (Token::Func(name, _), Token::Var(var)) => (name.as_str(), var.as_str()),
+      _ => unreachable!("not a binding call"),
+    };
+    let ctx: &dyn ContextProvider = ctx;
+    let (lo, hi) = (lo.eval_with_context(ctx)?, hi.eval_with_context(ctx)?);
+    let body = PreparedExpr::new(body);
+    let f = |x: f64| body.eval_with_context(((var, x), ctx));
+
+    if name == "integrate" {
+      let res = integrate(f, lo, hi, &QuadratureOptions::new())?;
+      if !res.converged {
+        return Err(Error::EvalError(format!(
+          "integrate: no convergence for `{}`, with an estimated error of {}",
+          body.expr, res.error
+        )));
+      }
+      return Ok(res.value);
+    }
+
+    if !(lo.fract() == 0. && hi.fract() == 0.) {
+      return Err(Error::EvalError(format!(
+        "{}: the bounds {} and {} are not integers",
+        name, lo, hi
+      )));
+    }
+    let terms = (hi - lo + 1.).max(0.);
+    if terms > MAX_TERMS {
+      return Err(Error::EvalError(format!(
+        "{}: {} terms from {} to {}, more than {}",
+        name, terms, lo, hi, MAX_TERMS
+      )));
+    }
+    let mut res = if name == "sum_over" { 0. } else { 1. };
+    for k in 0..terms as u64 {
+      let y = f(lo + k as f64)?;
+      if name == "sum_over" {
+        res += y;
+      } else {
+        res *= y;
+      }
+    }
+    Ok(res)
+  }
+
+  /// Integrates the expression in `var` over `[a, b]` numerically, by adaptive Gauss-Kronrod
+  /// quadrature with 15 points per subinterval.
+  ///
+  /// Either bound may be infinite. The integral of `f` over `[b, a]` is minus that over
+  /// `[a, b]`. The same integral can be written in an expression as `integrate(f, x, a, b)`,
+  /// which fails unless the tolerance is reached.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err` if the expression can't be evaluated at a point of the interval.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Context, Expr, QuadratureOptions};
+  /// let expr: Expr = "exp(-x^2)".parse().unwrap();
+  /// let options = QuadratureOptions::new();
+  /// let res = expr.quadrature("x", f64::NEG_INFINITY, f64::INFINITY, Context::new(), &options);
+  /// let res = res.unwrap();
+  /// assert!(res.converged);
+  /// assert!((res.value - std::f64::consts::PI.sqrt()).abs() < 1e-10);
+  /// assert!(res.error < 1e-10);
+  /// ```
+  pub fn quadrature<C: ContextProvider>(
+    &self,
+    var: &str,
+    a: f64,
+    b: f64,
+    ctx: C,
+    options: &QuadratureOptions,
+  ) -> Result<Quadrature, Error> {
+    let f = |x: f64| self.eval_with_context(((var, x), &ctx));
+    integrate(f, a, b, options)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f64::consts::PI;
+  use {builtin, eval_str, Context};
+
+  fn close(x: f64, y: f64) -> bool {
+    (x - y).abs() < 1e-9 * (1. + y.abs())
+  }
+
+  #[test]
+  fn quadrature() {
+    let ctx = builtin();
+    let options = QuadratureOptions::new();
+    let integral = |expr: Expr, a: f64, b: f64| {
+      let res = expr.quadrature("x", a, b, &ctx, &options).unwrap();
+      assert!(res.converged, "{} on [{}, {}]: {:?}", expr, a, b, res);
+      assert!(res.error < 1e-9, "{:?}", res);
+      res.value
+    };
+    assert!(close(integral(expr!(x ^ 2), 0., 3.), 9.));
+    assert!(close(integral(expr!(sin(x)), 0., PI), 2.));
+    assert!(close(integral(expr!(sin(x)), PI, 0.), -2.));
+    assert!(close(integral(expr!(sqrt(x)), 0., 1.), 2. / 3.));
+    assert!(close(
+      integral(expr!(1 / (1 + x ^ 2)), 0., f64::INFINITY),
+      PI / 2.
+    ));
+    assert!(close(integral(expr!(exp(x)), f64::NEG_INFINITY, 0.), 1.));
+    assert!(close(
+      integral(expr!(exp(-x ^ 2)), f64::NEG_INFINITY, f64::INFINITY),
+      PI.sqrt()
+    ));
+    assert_eq!(integral(expr!(x), 2., 2.), 0.);
+
+    let res = expr!(1 / x)
+      .quadrature("x", 0., 1., &ctx, &options)
+      .unwrap();
+    assert!(!res.converged);
+    assert!(res.evaluations > 1000);
+  }
+
+  #[test]
+  fn in_expressions() {
+    assert!(close(eval_str("integrate(x^2, x, 0, 3)").unwrap(), 9.));
+    assert_eq!(eval_str("sum_over(i^2, i, 1, 10)").unwrap(), 385.);
+    assert_eq!(eval_str("prod_over(i, i, 1, 5)").unwrap(), 120.);
+    assert_eq!(eval_str("sum_over(i, i, 1, 0)").unwrap(), 0.);
+    assert_eq!(eval_str("prod_over(i, i, 3, 2)").unwrap(), 1.);
+    // nested, with the bounds depending on outer variables
+    assert_eq!(
+      eval_str("sum_over(sum_over(j, j, 1, i), i, 1, 4)").unwrap(),
+      20.
+    );
+    assert!(close(
+      eval_str("integrate(integrate(x * y, y, 0, x), x, 0, 1)").unwrap(),
+      1. / 8.
+    ));
+    assert!(close(
+      eval_str("2 * integrate(sqrt(1 - t^2), t, -1, 1)").unwrap(),
+      PI
+    ));
+    // the bound variable shadows one in the context
+    let expr = expr!(x + prod_over(x, x, 1, 3));
+    assert_eq!(expr.eval_with_context(("x", 10.)), Ok(16.));
+
+    assert!(eval_str("sum_over(i, i, 1, 2.5)").is_err());
+    // too many terms, also beyond the integers of an f64
+    assert!(eval_str("sum_over(i, i, 1, 10^8)").is_err());
+    assert!(eval_str("prod_over(1, i, 0, 10^300)").is_err());
+    assert!(eval_str("integrate(1 / x, x, 0, 1)").is_err());
+    assert_eq!(
+      eval_str("sum_over(i * k, i, 1, 3)"),
+      Err(Error::UnknownVariable("k".into()))
+    );
+  }
+
+  #[test]
+  fn closures_and_variadic_sum_over() {
+    let expr = expr!(sum_over(i ^ 2, i, 1, n));
+    let f = expr.bind("n").unwrap();
+    assert_eq!(f(3.), 14.);
+    assert_eq!(f(4.), 30.);
+    let g = expr!(integrate(t, t, 0, x)).bind("x").unwrap();
+    assert!(close(g(2.), 2.));
+
+    // `sum` is still the sum of its arguments
+    let mut ctx = Context::new();
+    ctx.var("b", 10.);
+    assert_eq!(expr!(sum(1, b, 2, 3)).eval_with_context(&ctx), Ok(16.));
+    assert_eq!(
+      eval_str("sum(1, b, 2, 3)"),
+      Err(Error::UnknownVariable("b".into()))
+    );
+    assert_eq!(expr!(sum_over(b, i, 1, 3)).eval_with_context(&ctx), Ok(30.));
+  }
+
+  #[test]
+  fn folding_constants() {
+    let ctx = builtin();
+    assert_eq!(
+      expr!(x + sum_over(i ^ 2, i, 1, 3)).fold_constants(&ctx),
+      expr!(x + 14)
+    );
+    assert_eq!(
+      expr!(sum_over(i * x, i, 1, 3)).fold_constants(&ctx),
+      expr!(sum_over(i * x, i, 1, 3))
+    );
+  }
+}
@@ -0,0 +1,266 @@
+//! Random number functions backed by a small, seedable pseudo-random number generator.
+use super::{Arity, ContextProvider, FuncEvalError, FuncInfo};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::f64::consts::PI;
+use std::hash::{BuildHasher, Hasher};
+
+/// Functions provided by a [`RandomContext`](struct.RandomContext.html), with the number of
+/// arguments they take and a description.
+const RANDOM_FUNCS: [(&str, Arity, &str); 9] = [
+  ("rand", Arity::exact(0), "Uniform in `[0, 1)`"),
+  ("randn", Arity::exact(0), "Standard normal"),
+  ("randint", Arity::exact(2), "Uniform integer in `[x1, x2]`"),
+  (
+    "choose",
+    Arity::at_least(1),
+    "One of the arguments, chosen uniformly",
+  ),
+  ("uniform", Arity::exact(2), "Uniform in `[x1, x2)`"),
+  (
+    "normal",
+    Arity::exact(2),
+    "Normal with mean `x1` and standard deviation `x2`",
+  ),
+  (
+    "lognormal",
+    Arity::exact(2),
+    "Exponential of `normal(x1, x2)`",
+  ),
+  ("exponential", Arity::exact(1), "Exponential with rate `x1`"),
+  (
+    "bernoulli",
+    Arity::exact(1),
+    "`1` with probability `x1`, `0` otherwise",
+  ),
+];
+
+/// A context providing random number functions.
+///
+/// The numbers are generated by the [xoshiro256**] generator, so results are reproducible for
+/// a fixed seed. As every call advances the generator, all of the functions are impure, see
+/// [`ContextProvider::is_pure`](trait.ContextProvider.html#method.is_pure).
+///
+/// The following functions are provided:
+///
+/// - `rand()`: uniform in `[0, 1)`
+/// - `randn()`: standard normal
+/// - `randint(a, b)`: uniform integer in `[a, b]`
+/// - `choose(x, ...)`: one of the arguments, chosen uniformly
+/// - `uniform(a, b)`: uniform in `[a, b)`
+/// - `normal(mu, sigma)`: normal with mean `mu` and standard deviation `sigma`
+/// - `lognormal(mu, sigma)`: exponential of `normal(mu, sigma)`
+/// - `exponential(lambda)`: exponential with rate `lambda`
+/// - `bernoulli(p)`: `1` with probability `p`, `0` otherwise
+///
+/// # Example
+///
+/// ```rust
+/// use meval_symbolic::{Context, Expr, RandomContext};
+///
+/// let expr: Expr = "mu + sigma * randn()".parse().unwrap();
+/// let mut vars = Context::new();
+/// vars.var("mu", 1.).var("sigma", 0.5);
+///
+/// let rng = RandomContext::seeded(42);
+/// let first = expr.eval_with_context((&rng, &vars)).unwrap();
+/// rng.reseed(42);
+/// assert_eq!(expr.eval_with_context((&rng, &vars)).unwrap(), first);
+/// ```
+///
+/// [xoshiro256**]: https://prng.di.unimi.it/
+#[derive(Debug, Clone)]
+pub struct RandomContext {
+  state: Cell<[u64; 4]>,
+  /// The second normal sample generated by the Box-Muller transform.
+  spare: Cell<Option<f64>>,
+}
+
+impl RandomContext {
+  /// Creates a context seeded from the randomness used by `std` for hashing.
+  pub fn new() -> Self {
+    Self::seeded(RandomState::new().build_hasher().finish())
+  }
+
+  /// Creates a context with the given seed.
+  pub fn seeded(seed: u64) -> Self {
+    let ctx = RandomContext {
+      state: Cell::new([0; 4]),
+      spare: Cell::new(None),
+    };
+    ctx.reseed(seed);
+    ctx
+  }
+
+  /// Resets the generator to the state given by `seed`.
+  pub fn reseed(&self, mut seed: u64) {
+    let mut state = [0; 4];
+    for s in state.iter_mut() {
+      *s = splitmix64(&mut seed);
+    }
+    self.state.set(state);
+    self.spare.set(None);
+  }
+
+  /// The next 64 random bits.
+  fn next_u64(&self) -> u64 {
+    let mut s = self.state.get();
+    let res = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+    let t = s[1] << 17;
+
+    s[2] ^= s[0];
+    s[3] ^= s[1];
+    s[1] ^= s[2];
+    s[0] ^= s[3];
+    s[2] ^= t;
+    s[3] = s[3].rotate_left(45);
+
+    self.state.set(s);
+    res
+  }
+
+  /// A uniform sample from `[0, 1)`.
+  pub fn rand(&self) -> f64 {
+    (self.next_u64() >> 11) as f64 * (1. / (1u64 << 53) as f64)
+  }
+
+  /// A sample from the standard normal distribution.
+  pub fn randn(&self) -> f64 {
+    if let Some(x) = self.spare.take() {
+      return x;
+    }
+    // 1 - rand() lies in (0, 1], so the logarithm is finite
+    let r = (-2. * (1. - self.rand()).ln()).sqrt();
+    let theta = 2. * PI * self.rand();
+    self.spare.set(Some(r * theta.sin()));
+    r * theta.cos()
+  }
+
+  /// A uniform integer from `[a, b]`, or `NaN` if there is no integer in the interval.
+  pub fn randint(&self, a: f64, b: f64) -> f64 {
+    let (a, b) = (a.ceil(), b.floor());
+    if a > b || !(b - a).is_finite() {
+      return f64::NAN;
+    }
+    (a + (self.rand() * (b - a + 1.)).floor()).min(b)
+  }
+
+  /// One of `xs`, chosen uniformly.
+  pub fn choose(&self, xs: &[f64]) -> f64 {
+    let i = (self.rand() * xs.len() as f64) as usize;
+    xs[i.min(xs.len() - 1)]
+  }
+}
+
+impl Default for RandomContext {
+  fn default() -> Self {
+    RandomContext::new()
+  }
+}
+
+/// Used to expand a single seed into the full generator state.
+fn splitmix64(x: &mut u64) -> u64 {
+  *x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+  let mut z = *x;
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+  z ^ (z >> 31)
+}
+
+fn n_args(args: &[f64], n: usize) -> Result<(), FuncEvalError> {
+  if args.len() == n {
+    Ok(())
+  } else {
+    Err(FuncEvalError::NumberArgs(n))
+  }
+}
+
+impl ContextProvider for RandomContext {
+  fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, FuncEvalError> {
+    match name {
+      "rand" => n_args(args, 0).map(|_| self.rand()),
+      "randn" => n_args(args, 0).map(|_| self.randn()),
+      "randint" => n_args(args, 2).map(|_| self.randint(args[0], args[1])),
+      "choose" if args.is_empty() => Err(FuncEvalError::TooFewArguments),
+      "choose" => Ok(self.choose(args)),
+      "uniform" => n_args(args, 2).map(|_| args[0] + (args[1] - args[0]) * self.rand()),
+      "normal" => n_args(args, 2).map(|_| args[0] + args[1] * self.randn()),
+      "lognormal" => n_args(args, 2).map(|_| (args[0] + args[1] * self.randn()).exp()),
+      "exponential" => n_args(args, 1).map(|_| -(1. - self.rand()).ln() / args[0]),
+      "bernoulli" => n_args(args, 1).map(|_| (self.rand() < args[0]) as u8 as f64),
+      _ => Err(FuncEvalError::UnknownFunction),
+    }
+  }
+  fn func_info(&self, name: &str) -> Option<FuncInfo> {
+    let &(_, arity, doc) = RANDOM_FUNCS.iter().find(|(n, ..)| *n == name)?;
+    Some(FuncInfo {
+      pure: false,
+      doc: Some(doc.into()),
+      ..FuncInfo::new(arity)
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{eval_str_with_context, Context};
+
+  #[test]
+  fn reproducible_for_seed() {
+    let a = RandomContext::seeded(7);
+    let b = RandomContext::seeded(7);
+    let xs: Vec<_> = (0..10).map(|_| a.next_u64()).collect();
+    let ys: Vec<_> = (0..10).map(|_| b.next_u64()).collect();
+    assert_eq!(xs, ys);
+
+    let c = RandomContext::seeded(8);
+    assert_ne!(xs[0], c.next_u64());
+
+    a.reseed(7);
+    assert_eq!(a.next_u64(), xs[0]);
+  }
+
+  #[test]
+  fn uniform_range() {
+    let rng = RandomContext::seeded(1);
+    for _ in 0..1000 {
+      let x = rng.rand();
+      assert!((0. ..1.).contains(&x));
+      let n = rng.randint(-2., 3.);
+      assert!(n.fract() == 0. && (-2. ..=3.).contains(&n));
+      let c = rng.choose(&[1., 5., 9.]);
+      assert!(c == 1. || c == 5. || c == 9.);
+    }
+    assert!(rng.randint(0.2, 0.8).is_nan());
+  }
+
+  #[test]
+  fn normal_moments() {
+    let rng = RandomContext::seeded(2);
+    let n = 20000;
+    let xs: Vec<_> = (0..n).map(|_| rng.randn()).collect();
+    let mean = xs.iter().sum::<f64>() / n as f64;
+    let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+    assert!(mean.abs() < 0.05);
+    assert!((var - 1.).abs() < 0.05);
+  }
+
+  #[test]
+  fn in_expressions() {
+    let rng = RandomContext::seeded(3);
+    let ctx = (&rng, Context::new());
+    let x = eval_str_with_context("2 * rand() + sum(1, 2)", &ctx).unwrap();
+    assert!((3. ..5.).contains(&x));
+    assert_eq!(eval_str_with_context("randint(4, 4)", &ctx), Ok(4.));
+    assert_eq!(
+      eval_str_with_context("rand(1)", &ctx),
+      Err(crate::Error::Function(
+        "rand".into(),
+        FuncEvalError::NumberArgs(0)
+      ))
+    );
+    assert!(!ctx.is_pure("randn"));
+    assert!(ctx.is_pure("sin"));
+  }
+}
@@ -0,0 +1,209 @@
+//! A context made of a stack of local frames on top of a base context.
+use super::{Context, ContextProvider, FuncEvalError, FuncInfo};
+
+/// A context with a stack of local frames on top of a base context, e.g. for the bound
+/// variables of loops and sums.
+///
+/// Names are resolved with the following shadowing rules:
+///
+/// - frames are searched from the innermost (last pushed) to the outermost, and then the base
+///   context, and the first one defining a name wins;
+/// - a function shadows all functions of the same name further out, even if it doesn't accept
+///   the given number of arguments, in which case its error is reported;
+/// - variables and functions live in separate namespaces, so a variable never shadows a
+///   function of the same name or vice versa.
+///
+/// # Example
+///
+/// ```rust
+/// use meval_symbolic::{Context, Expr, ScopedContext};
+///
+/// let expr: Expr = "a * i^2".parse().unwrap();
+/// let mut base = Context::new();
+/// base.var("a", 2.).var("i", -1.);
+/// let mut scope = ScopedContext::new(base);
+///
+/// scope.push();
+/// let mut total = 0.;
+/// for i in 1..=3 {
+///   scope.var("i", i as f64);
+///   total += expr.eval_with_context(&scope).unwrap();
+/// }
+/// scope.pop();
+///
+/// assert_eq!(total, 2. * (1. + 4. + 9.));
+/// assert_eq!(expr.eval_with_context(&scope), Ok(2.));
+/// ```
+#[derive(Clone)]
+pub struct ScopedContext<'a, C = Context<'a>> {
+  base: C,
+  frames: Vec<Context<'a>>,
+}
+
+impl<'a, C: ContextProvider> ScopedContext<'a, C> {
+  /// Creates a context without any local frames on top of `base`.
+  pub fn new(base: C) -> Self {
+    ScopedContext {
+      base,
+      frames: vec![],
+    }
+  }
+
+  /// Pushes a new, empty frame and returns it, e.g. to define local functions.
+  pub fn push(&mut self) -> &mut Context<'a> {
+    self.frames.push(Context::empty());
+    self.frames.last_mut().unwrap()
+  }
+
+  /// Removes the innermost frame, returning it if there was one.
+  pub fn pop(&mut self) -> Option<Context<'a>> {
+    self.frames.pop()
+  }
+
+  /// Runs `f` with a new frame pushed, removing the frame afterwards.
+  pub fn with_frame<R, F: FnOnce(&mut Self) -> R>(&mut self, f: F) -> R {
+    let depth = self.depth();
+    self.push();
+    let res = f(self);
+    self.frames.truncate(depth);
+    res
+  }
+
+  /// The number of local frames.
+  pub fn depth(&self) -> usize {
+    self.frames.len()
+  }
+
+  /// The innermost frame, pushing one if there are none.
+  pub fn top(&mut self) -> &mut Context<'a> {
+    if self.frames.is_empty() {
+      self.push();
+    }
+    self.frames.last_mut().unwrap()
+  }
+
+  /// Defines a variable in the innermost frame, shadowing any definitions further out.
+  ///
+  /// Pushes a frame if there are none, so the base context is never modified.
+  pub fn var<S: Into<String>>(&mut self, var: S, value: f64) -> &mut Self {
+    self.top().var(var, value);
+    self
+  }
+
+  /// The base context.
+  pub fn base(&self) -> &C {
+    &self.base
+  }
+
+  /// Removes all frames and returns the base context.
+  pub fn into_base(self) -> C {
+    self.base
+  }
+}
+
+impl<'a, C: ContextProvider> ContextProvider for ScopedContext<'a, C> {
+  fn get_var(&self, name: &str) -> Option<f64> {
+    self
+      .frames
+      .iter()
+      .rev()
+      .find_map(|frame| frame.get_var(name))
+      .or_else(|| self.base.get_var(name))
+  }
+  fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, FuncEvalError> {
+    match self
+      .frames
+      .iter()
+      .rev()
+      .find(|frame| frame.info(name).is_some())
+    {
+      Some(frame) => frame.eval_func(name, args),
+      None => self.base.eval_func(name, args),
+    }
+  }
+  fn func_info(&self, name: &str) -> Option<FuncInfo> {
+    match self.frames.iter().rev().find_map(|frame| frame.info(name)) {
+      Some(info) => Some(info.clone()),
+      None => self.base.func_info(name),
+    }
+  }
+  fn is_pure(&self, name: &str) -> bool {
+    match self.frames.iter().rev().find_map(|frame| frame.info(name)) {
+      Some(info) => info.pure,
+      None => self.base.is_pure(name),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{eval_str_with_context, Error};
+
+  #[test]
+  fn frames_shadow_variables() {
+    let mut base = Context::empty();
+    base.var("x", 1.).var("y", 2.);
+    let mut scope = ScopedContext::new(base);
+
+    scope.push().var("x", 10.);
+    scope.push().var("y", 20.);
+    assert_eq!(eval_str_with_context("x + y", &scope), Ok(30.));
+
+    scope.pop();
+    assert_eq!(eval_str_with_context("x + y", &scope), Ok(12.));
+    scope.pop();
+    assert_eq!(eval_str_with_context("x + y", &scope), Ok(3.));
+    assert!(scope.pop().is_none());
+  }
+
+  #[test]
+  fn var_never_modifies_base() {
+    let mut base = Context::empty();
+    base.var("x", 1.);
+    let mut scope = ScopedContext::new(base);
+    scope.var("x", 5.);
+    assert_eq!(scope.get_var("x"), Some(5.));
+    assert_eq!(scope.depth(), 1);
+    assert_eq!(scope.into_base().get_var("x"), Some(1.));
+  }
+
+  #[test]
+  fn with_frame_pops() {
+    let mut scope = ScopedContext::new(Context::new());
+    let sum: f64 = scope.with_frame(|scope| {
+      (1..=4)
+        .map(|i| {
+          scope.var("i", i as f64);
+          eval_str_with_context("i^2", &*scope).unwrap()
+        })
+        .sum()
+    });
+    assert_eq!(sum, 30.);
+    assert_eq!(scope.depth(), 0);
+    assert_eq!(scope.get_var("i"), None);
+  }
+
+  #[test]
+  fn first_function_wins() {
+    let mut scope = ScopedContext::new(Context::new());
+    scope.push().func("sin", |x| 2. * x);
+    assert_eq!(eval_str_with_context("sin(2)", &scope), Ok(4.));
+    assert_eq!(eval_str_with_context("cos(0)", &scope), Ok(1.));
+
+    // the shadowing function's arity error is kept
+    scope.push().func2("sin", |x, y| x * y);
+    assert_eq!(
+      eval_str_with_context("sin(2)", &scope),
+      Err(Error::Function("sin".into(), FuncEvalError::NumberArgs(2)))
+    );
+    assert_eq!(scope.func_info("sin").map(|info| info.arity.min), Some(2));
+  }
+
+  #[test]
+  fn variables_and_functions_are_separate() {
+    let mut scope = ScopedContext::new(Context::new());
+    scope.var("sin", 3.);
+    assert_eq!(eval_str_with_context("sin(0) + sin", &scope), Ok(3.));
+  }
+}
@@ -0,0 +1,652 @@
+//! Taylor and Laurent series expansions, computed with arithmetic on truncated series.
+use super::operators::Powf;
+use super::{builtin, ContextProvider, Expr, Operation, Token};
+use std::fmt::{self, Display, Formatter};
+use Error;
+
+/// How many times the working precision is raised to make up for the terms lost in divisions
+/// and cancellations, e.g. in `(sin(x) - x) / x^3`.
+const MAX_RETRIES: usize = 8;
+
+/// The largest power of a series, and the largest number of powers from its lowest one to its
+/// order, beyond which an expansion fails rather than allocate and multiply huge series, as
+/// for the degree of polynomials.
+const MAX_SPAN: i32 = 1000;
+
+/// The power `x` of a series, if it is an integer no larger than `MAX_SPAN` in size.
+fn to_power(x: f64) -> Option<i32> {
+  if x.fract() == 0. && x.abs() <= MAX_SPAN as f64 {
+    Some(x as i32)
+  } else {
+    None
+  }
+}
+
+/// A truncated Laurent series in `t`, `Σ coeffs[k] * t^(start + k)` up to `t^order`
+/// exclusive, beyond which the coefficients are unknown.
+#[derive(Debug, Clone)]
+struct PowerSeries {
+  start: i32,
+  coeffs: Vec<f64>,
+  order: i32,
+}
+
+impl PowerSeries {
+  fn new(start: i32, mut coeffs: Vec<f64>, order: i32) -> Self {
+    coeffs.resize((order - start).max(0) as usize, 0.);
+    PowerSeries {
+      start,
+      coeffs,
+      order,
+    }
+  }
+
+  fn constant(c: f64, order: i32) -> Self {
+    PowerSeries::new(0, vec![c], order)
+  }
+
+  /// The series with the powers from `start` up to `order` exclusive, for `k` in that range.
+  fn from_fn<F: FnMut(i32) -> f64>(start: i32, order: i32, f: F) -> Self {
+    PowerSeries::new(start, (start..order).map(f).collect(), order)
+  }
+
+  /// The coefficient of `t^k`, 0 for the powers below `start`.
+  fn coeff(&self, k: i32) -> f64 {
+    if k >= self.start && k < self.order {
+      self.coeffs[(k - self.start) as usize]
+    } else {
+      0.
+    }
+  }
+
+  /// The lowest power with a coefficient that isn't negligible next to the largest one, `None`
+  /// if the series is zero as far as it is known.
+  fn valuation(&self) -> Option<i32> {
+    let scale = self.coeffs.iter().fold(0., |max: f64, c| c.abs().max(max));
+    self
+      .coeffs
+      .iter()
+      .position(|c| c.abs() > 1e-13 * scale)
+      .map(|k| self.start + k as i32)
+  }
+
+  /// The lowest power that may have a non-zero coefficient.
+  fn low(&self) -> i32 {
+    self.valuation().unwrap_or(self.order)
+  }
+
+  /// Whether the series is a known constant.
+  fn is_constant(&self) -> bool {
+    self.order > 0
+      && match self.valuation() {
+        Some(v) => v == 0 && (1..self.order).all(|k| self.coeff(k) == 0.),
+        None => true,
+      }
+  }
+
+  fn truncated(&self, order: i32) -> Self {
+    PowerSeries::new(self.start, self.coeffs.clone(), order.min(self.order))
+  }
+
+  /// The sum, which fails if it spans more than `MAX_SPAN` powers.
+  fn add(&self, other: &Self) -> Option<Self> {
+    let (start, order) = (self.start.min(other.start), self.order.min(other.order));
+    if order.checked_sub(start)? > MAX_SPAN {
+      return None;
+    }
+    Some(PowerSeries::from_fn(start, order, |k| {
+      self.coeff(k) + other.coeff(k)
+    }))
+  }
+
+  fn scale(&self, c: f64) -> Self {
+    PowerSeries::new(
+      self.start,
+      self.coeffs.iter().map(|x| c * x).collect(),
+      self.order,
+    )
+  }
+
+  /// The product, which fails if its lowest power is larger than `MAX_SPAN` in size.
+  fn mul(&self, other: &Self) -> Option<Self> {
+    let (a, b) = (self.low(), other.low());
+    let start = a.checked_add(b)?;
+    if start.abs() > MAX_SPAN {
+      return None;
+    }
+    let order = self.order.checked_add(b)?.min(other.order.checked_add(a)?);
+    Some(PowerSeries::from_fn(start, order, |k| {
+      (a..=k - b)
+        .map(|i| self.coeff(i) * other.coeff(k - i))
+        .sum()
+    }))
+  }
+
+  /// `1 / self`, which fails if the series is zero as far as it is known.
+  fn recip(&self) -> Option<Self> {
+    let v = self.valuation()?;
+    let b0 = self.coeff(v);
+    let known = self.order - v;
+    let mut r: Vec<f64> = Vec::with_capacity(known.max(0) as usize);
+    for k in 0..known {
+      let sum: f64 = (1..=k)
+        .map(|j| self.coeff(v + j) * r[(k - j) as usize])
+        .sum();
+      r.push(if k == 0 { 1. / b0 } else { -sum / b0 });
+    }
+    Some(PowerSeries::new(-v, r, -v + known))
+  }
+
+  /// `self^p` for a number `p`, with the Miller recurrence for the powers of `1 + u`.
+  fn powf(&self, p: f64) -> Option<Self> {
+    let v = match self.valuation() {
+      Some(v) => v,
+      // a power of a series that is zero as far as it is known
+      None if p > 0. && self.order > 0 => {
+        let order = to_power((self.order as f64 * p).ceil())?;
+        return Some(PowerSeries::new(order, vec![], order));
+      }
+      None => return None,
+    };
+    let start = v as f64 * p;
+    let a0 = self.coeff(v);
+    let lead = a0.powf(p);
+    if start.fract() != 0. || !lead.is_finite() {
+      return None;
+    }
+    let start = to_power(start)?;
+    let h = |j: i32| self.coeff(v + j) / a0;
+    let known = self.order - v;
+    let mut g: Vec<f64> = Vec::with_capacity(known.max(0) as usize);
+    for k in 0..known {
+      let sum: f64 = (1..=k)
+        .map(|j| ((p + 1.) * j as f64 - k as f64) * h(j) * g[(k - j) as usize])
+        .sum();
+      g.push(if k == 0 { 1. } else { sum / k as f64 });
+    }
+    Some(PowerSeries::new(start, g, start.checked_add(known)?).scale(lead))
+  }
+
+  fn derivative(&self) -> Self {
+    PowerSeries::from_fn(self.start - 1, self.order - 1, |k| {
+      (k + 1) as f64 * self.coeff(k + 1)
+    })
+  }
+
+  /// The antiderivative with the constant term `c`, which fails if there are negative powers.
+  fn integral(&self, c: f64) -> Option<Self> {
+    if self.low() < 0 {
+      return None;
+    }
+    Some(PowerSeries::from_fn(0, self.order + 1, |k| {
+      if k == 0 {
+        c
+      } else {
+        self.coeff(k - 1) / k as f64
+      }
+    }))
+  }
+
+  fn exp(&self) -> Option<Self> {
+    if self.low() < 0 {
+      return None;
+    }
+    let mut g: Vec<f64> = Vec::with_capacity(self.order.max(0) as usize);
+    for k in 0..self.order {
+      let sum: f64 = (1..=k)
+        .map(|j| j as f64 * self.coeff(j) * g[(k - j) as usize])
+        .sum();
+      g.push(if k == 0 {
+        self.coeff(0).exp()
+      } else {
+        sum / k as f64
+      });
+    }
+    Some(PowerSeries::new(0, g, self.order))
+  }
+
+  fn ln(&self) -> Option<Self> {
+    match self.valuation() {
+      Some(0) if self.coeff(0) > 0. => self
+        .derivative()
+        .mul(&self.recip()?)?
+        .integral(self.coeff(0).ln()),
+      _ => None,
+    }
+  }
+}
+
+/// A series expansion truncated at some order, and the order of the remainder, e.g.
+/// `1 + x + x^2 / 2 + O(x^3)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+  /// The sum of the terms, in increasing powers of `var - around`, with negative powers for a
+  /// Laurent series.
+  pub expr: Expr,
+  /// The power `n` of the remainder `O((var - around)^n)`.
+  pub remainder_order: i32,
+  /// `var - around`.
+  base: Expr,
+}
+
+impl Display for Series {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let remainder = match self.remainder_order {
+      1 => self.base.clone(),
+      n => (&self.base).powf(n as f64),
+    };
+    if self.expr.as_num() == Some(0.) {
+      write!(f, "O({})", remainder)
+    } else {
+      write!(f, "{} + O({})", self.expr, remainder)
+    }
+  }
+}
+
+/// The term `c * base^k`, writing coefficients like `-1 / 6` as a division.
+fn term(c: f64, base: &Expr, k: i32) -> Expr {
+  let integer = |x: f64| (x.round() - x).abs() < 1e-9 * x.abs();
+  let signed = |e: Expr| if c < 0. { -e } else { e };
+  let power = match k.abs() {
+    0 => Expr::num(1.),
+    1 => base.clone(),
+    k => base.powf(k as f64),
+  };
+  let c = c.abs();
+  match c {
+    c if integer(c) && k == 0 => signed(Expr::num(c.round())),
+    c if integer(c) && c.round() == 1. && k > 0 => signed(power),
+    c if integer(c) && k > 0 => signed(Expr::num(c.round())) * power,
+    c if integer(c) => signed(Expr::num(c.round())) / power,
+    c if integer(1. / c) && k >= 0 => signed(power) / (1. / c).round(),
+    c if k == 0 => signed(Expr::num(c)),
+    c if k > 0 => signed(Expr::num(c)) * power,
+    c => signed(Expr::num(c)) / power,
+  }
+}
+
+/// The expansion of an expression in `var = around + t`.
+struct Expansion<'a, C: ContextProvider> {
+  var: &'a str,
+  around: f64,
+  ctx: C,
+  /// The working precision of the series of variables and constants.
+  order: i32,
+}
+
+impl<'a, C: ContextProvider> Expansion<'a, C> {
+  /// The series of `expr`, with the variables in `bound` standing for series.
+  fn series(&self, expr: &Expr, bound: &[(String, PowerSeries)]) -> Result<PowerSeries, Error> {
+    use self::Operation::*;
+    use self::Token::*;
+
+    let ptrs = expr.start_pointers();
+    let mut stack: Vec<PowerSeries> = vec![];
+    for (i, tkn) in expr.iter().enumerate() {
+      let args = stack.split_off(stack.len() - Expr::no_children(tkn));
+      let no_series = || Error::NoSeries(Expr(expr.0[ptrs[i]..=i].to_vec()).to_string());
+
+      let series = match (tkn, &args[..]) {
+        (Number(x), _) => PowerSeries::constant(*x, self.order),
+        (Var(name), _) => match bound.iter().find(|(v, _)| v == name) {
+          Some((_, s)) => s.clone(),
+          None if name == self.var => PowerSeries::new(0, vec![self.around, 1.], self.order),
+          None => match self.ctx.get_var(name) {
+            Some(x) => PowerSeries::constant(x, self.order),
+            None => return Err(Error::UnknownVariable(name.clone())),
+          },
+        },
+        _ if args.iter().all(PowerSeries::is_constant) => {
+          let mut tkns: Vec<_> = args.iter().map(|s| Number(s.coeff(0))).collect();
+          tkns.push(tkn.clone());
+          match Expr(tkns).eval_with_context(&self.ctx)? {
+            x if x.is_finite() => PowerSeries::constant(x, self.order),
+            _ => return Err(no_series()),
+          }
+        }
+        (Unary(Plus), [a]) => a.clone(),
+        (Unary(Minus), [a]) => a.scale(-1.),
+        (Binary(Plus), [a, b]) => a.add(b).ok_or_else(no_series)?,
+        (Binary(Minus), [a, b]) => a.add(&b.scale(-1.)).ok_or_else(no_series)?,
+        (Binary(Times), [a, b]) => a.mul(b).ok_or_else(no_series)?,
+        (Binary(Div), [a, b]) => b.recip().and_then(|r| a.mul(&r)).ok_or_else(no_series)?,
+        (Binary(Pow), [a, b]) if b.is_constant() => a.powf(b.coeff(0)).ok_or_else(no_series)?,
+        (Binary(Pow), [a, b]) => a
+          .ln()
+          .and_then(|ln| b.mul(&ln)?.exp())
+          .ok_or_else(no_series)?,
+        // also the square roots of Laurent series with an even lowest power
+        (Func(name, Some(1)), [a]) if name == "sqrt" => a.powf(0.5).ok_or_else(no_series)?,
+        (Func(name, Some(_)), _) => self.compose(name, &args).map_err(|e| match e {
+          Error::NoSeries(_) => no_series(),
+          e => e,
+        })?,
+        _ => return Err(no_series()),
+      };
+      stack.push(series);
+    }
+    Ok(stack.pop().expect("Stack is empty, this is impossible."))
+  }
+
+  /// The series of `expr`, raising the working precision until `done` holds for it, or up to
+  /// [`MAX_RETRIES`] times. The errors at a low precision, e.g. from the reciprocal of a series
+  /// that is zero as far as it is known, are only returned if the last attempt fails too.
+  fn series_until<F: Fn(&PowerSeries) -> bool>(
+    &mut self,
+    expr: &Expr,
+    done: F,
+  ) -> Result<PowerSeries, Error> {
+    let mut res = self.series(expr, &[]);
+    for _ in 0..MAX_RETRIES {
+      self.order += match res {
+        Ok(ref s) if done(s) => break,
+        Ok(ref s) => (self.order - s.order).max(0) + 2,
+        Err(Error::NoSeries(_)) => 2,
+        Err(e) => return Err(e),
+      };
+      res = self.series(expr, &[]);
+    }
+    res
+  }
+
+  /// The series of `name(args)`, as the antiderivative of the chain rule, with the series of
+  /// the partial derivatives from the [`FuncInfo`](struct.FuncInfo.html) of the function
+  /// computed to one term less.
+  fn compose(&self, name: &str, args: &[PowerSeries]) -> Result<PowerSeries, Error> {
+    let order = args.iter().map(|s| s.order).min().unwrap_or(self.order);
+    if args.iter().any(|s| s.low() < 0) {
+      return Err(Error::NoSeries(name.into()));
+    }
+    if order <= 0 {
+      return Ok(PowerSeries::new(0, vec![], order));
+    }
+    let values: Vec<f64> = args.iter().map(|s| s.coeff(0)).collect();
+    let value = self
+      .ctx
+      .eval_func(name, &values)
+      .map_err(|e| Error::Function(name.into(), e))?;
+    if !value.is_finite() {
+      return Err(Error::NoSeries(name.into()));
+    }
+    let info = self
+      .ctx
+      .func_info(name)
+      .filter(|info| info.derivatives.len() == args.len())
+      .ok_or_else(|| Error::NoDerivative(name.into()))?;
+
+    let bound: Vec<(String, PowerSeries)> = args
+      .iter()
+      .enumerate()
+      .map(|(i, s)| (format!("x{}", i + 1), s.truncated(order - 1)))
+      .collect();
+    let mut d = PowerSeries::new(0, vec![], order - 1);
+    for (s, partial) in args.iter().zip(&info.derivatives) {
+      if !s.is_constant() {
+        let term = self.series(partial, &bound)?.mul(&s.derivative());
+        d = term
+          .and_then(|term| d.add(&term))
+          .ok_or_else(|| Error::NoSeries(name.into()))?;
+      }
+    }
+    d.integral(value)
+      .ok_or_else(|| Error::NoSeries(name.into()))
+  }
+}
+
+impl Expr {
+  /// The Taylor or Laurent series of the expression in `var` around `around`, with the terms
+  /// up to `(var - around)^order`, using the built-in constants and functions.
+  ///
+  /// See [`series_with_context`](#method.series_with_context).
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Expr;
+  /// let expr: Expr = "exp(x)".parse().unwrap();
+  /// assert_eq!(expr.series("x", 0., 3).unwrap().to_string(), "1 + x + x^2 / 2 + x^3 / 6");
+  /// let expr: Expr = "sin(x) / x".parse().unwrap();
+  /// assert_eq!(expr.series("x", 0., 4).unwrap().to_string(), "1 - x^2 / 6 + x^4 / 120");
+  /// ```
+  pub fn series(&self, var: &str, around: f64, order: u32) -> Result<Expr, Error> {
+    let series = self.series_with_context(var, around, order, builtin())?;
+    if series.remainder_order <= order as i32 {
+      return Err(Error::NoSeries(self.to_string()));
+    }
+    Ok(series.expr)
+  }
+
+  /// The series of the expression with the order of its remainder, using the built-in
+  /// constants and functions.
+  ///
+  /// See [`series_with_context`](#method.series_with_context).
+  pub fn series_with_remainder(&self, var: &str, around: f64, order: u32) -> Result<Series, Error> {
+    self.series_with_context(var, around, order, builtin())
+  }
+
+  /// The Taylor or Laurent series of the expression in `var` around `around`, with the terms
+  /// up to `(var - around)^order` and the order of the remainder.
+  ///
+  /// The coefficients are numbers, so the other variables are taken from `ctx`. The series of
+  /// functions are computed from the derivatives in their [`FuncInfo`](struct.FuncInfo.html).
+  /// Removable singularities and poles, e.g. of `sin(x) / x` or `1 / sin(x)` at 0, are
+  /// handled by the series arithmetic, raising the working precision until the terms up to
+  /// `order` are known. The remainder order is lower than `order + 1` if they still aren't.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NoSeries)` with the subexpression without a series at the point, e.g.
+  /// `sqrt(x)` or `ln(x)` at 0, or with powers or a range of powers beyond 1000, e.g.
+  /// `x^-2000 + 1`, with the whole expression for an `order` beyond 1000, and
+  /// `Err(NoDerivative)` for a function without known derivatives.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Expr;
+  /// let expr: Expr = "1 / sin(x)".parse().unwrap();
+  /// let series = expr.series_with_remainder("x", 0., 2).unwrap();
+  /// assert_eq!(series.to_string(), "1 / x + x / 6 + O(x^3)");
+  /// assert_eq!(series.remainder_order, 3);
+  ///
+  /// let expr: Expr = "ln(x)".parse().unwrap();
+  /// let series = expr.series_with_remainder("x", 1., 2).unwrap();
+  /// assert_eq!(series.to_string(), "x - 1 - (x - 1)^2 / 2 + O((x - 1)^3)");
+  /// ```
+  pub fn series_with_context<C: ContextProvider>(
+    &self,
+    var: &str,
+    around: f64,
+    order: u32,
+    ctx: C,
+  ) -> Result<Series, Error> {
+    if order > MAX_SPAN as u32 {
+      return Err(Error::NoSeries(self.to_string()));
+    }
+    let wanted = order as i32 + 1;
+    let mut expansion = Expansion {
+      var,
+      around,
+      ctx,
+      order: wanted,
+    };
+    let series = expansion.series_until(self, |s| s.order >= wanted)?;
+
+    let base = if around == 0. {
+      Expr::var(var)
+    } else if around < 0. {
+      Expr::var(var) + -around
+    } else {
+      Expr::var(var) - around
+    };
+    let remainder_order = series.order.min(wanted);
+    let scale = series
+      .coeffs
+      .iter()
+      .fold(0., |max: f64, c| c.abs().max(max));
+    let mut expr: Option<Expr> = None;
+    for k in series.start..remainder_order {
+      let c = series.coeff(k);
+      if c.abs() <= 1e-13 * scale {
+        continue;
+      }
+      expr = Some(match expr {
+        None => term(c, &base, k),
+        Some(sum) if c < 0. => sum - term(-c, &base, k),
+        Some(sum) => sum + term(c, &base, k),
+      });
+    }
+    Ok(Series {
+      expr: expr.unwrap_or_else(|| Expr::num(0.)),
+      remainder_order,
+      base,
+    })
+  }
+
+  /// The lowest power of `var - around` in the series of the expression and its coefficient,
+  /// or `None` if the series is zero as far as it can be computed.
+  pub(crate) fn leading_term<C: ContextProvider>(
+    &self,
+    var: &str,
+    around: f64,
+    ctx: C,
+  ) -> Result<Option<(i32, f64)>, Error> {
+    let mut expansion = Expansion {
+      var,
+      around,
+      ctx,
+      order: 1,
+    };
+    let series = expansion.series_until(self, |s| s.valuation().is_some())?;
+    Ok(series.valuation().map(|v| (v, series.coeff(v))))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use Context;
+
+  fn series(expr: Expr, around: f64, order: u32) -> String {
+    expr.series("x", around, order).unwrap().to_string()
+  }
+
+  fn close(expr: &Expr, a: f64, b: f64) -> bool {
+    let value = expr.eval_with_context(("x", a)).unwrap();
+    (value - b).abs() < 1e-9 * (1. + b.abs())
+  }
+
+  #[test]
+  fn taylor_series() {
+    assert_eq!(
+      series(expr!(exp(x)), 0., 4),
+      "1 + x + x^2 / 2 + x^3 / 6 + x^4 / 24"
+    );
+    assert_eq!(series(expr!(sin(x)), 0., 5), "x - x^3 / 6 + x^5 / 120");
+    assert_eq!(series(expr!(cos(x)), 0., 4), "1 - x^2 / 2 + x^4 / 24");
+    assert_eq!(series(expr!(ln_1p(x)), 0., 3), "x - x^2 / 2 + x^3 / 3");
+    assert_eq!(series(expr!(1 / (1 - x)), 0., 3), "1 + x + x^2 + x^3");
+    assert_eq!(series(expr!(sqrt(1 + x)), 0., 2), "1 + x / 2 - x^2 / 8");
+    assert_eq!(series(expr!(tan(x)), 0., 3), "x + x^3 / 3");
+    assert_eq!(series(expr!(sqrt(x ^ 2 + x ^ 3)), 0., 2), "x + x^2 / 2");
+    assert_eq!(series(expr!(atan(x)), 0., 5), "x - x^3 / 3 + x^5 / 5");
+    assert_eq!(series(expr!(3 * x ^ 2 + 1), 0., 5), "1 + 3 * x^2");
+    assert_eq!(series(expr!(2 ^ x), 0., 1), "1 + 0.6931471805599453 * x");
+    assert_eq!(series(expr!(ln(2 + x)), 0., 0), "0.6931471805599453");
+  }
+
+  #[test]
+  fn around_other_points() {
+    assert_eq!(series(expr!(x ^ 2), 1., 3), "1 + 2 * (x - 1) + (x - 1)^2");
+    assert_eq!(series(expr!(1 / x), -1., 2), "-1 - (x + 1) - (x + 1)^2");
+    let s = expr!(sin(x)).series("x", 2., 6).unwrap();
+    assert!(close(&s, 2.1, 2.1f64.sin()));
+    let s = expr!(exp(sin(x)) * sqrt(x)).series("x", 1., 8).unwrap();
+    assert!(close(&s, 1.05, 1.05f64.sin().exp() * 1.05f64.sqrt()));
+  }
+
+  #[test]
+  fn singularities() {
+    assert_eq!(series(expr!(sin(x) / x), 0., 4), "1 - x^2 / 6 + x^4 / 120");
+    assert_eq!(
+      series(expr!((sin(x) - x) / x ^ 3), 0., 2),
+      "-1 / 6 + x^2 / 120"
+    );
+    assert_eq!(
+      series(expr!((1 - cos(x)) / x ^ 2), 0., 2),
+      "1 / 2 - x^2 / 24"
+    );
+    assert_eq!(
+      series(expr!(x / (exp(x) - 1)), 0., 2),
+      "1 - x / 2 + x^2 / 12"
+    );
+    assert_eq!(series(expr!((x ^ 2 - 1) / (x - 1)), 1., 2), "2 + (x - 1)");
+    assert_eq!(
+      series(expr!(exp(x) / x ^ 2), 0., 1),
+      "1 / x^2 + 1 / x + 1 / 2 + x / 6"
+    );
+    assert_eq!(series(expr!(x ^ x), 1., 2), "1 + (x - 1) + (x - 1)^2");
+  }
+
+  #[test]
+  fn remainder() {
+    let s = expr!(cos(x)).series_with_remainder("x", 0., 3).unwrap();
+    assert_eq!(s.remainder_order, 4);
+    assert_eq!(s.to_string(), "1 - x^2 / 2 + O(x^4)");
+    let s = expr!(x - x).series_with_remainder("x", 2., 1).unwrap();
+    assert_eq!(s.to_string(), "O((x - 2)^2)");
+  }
+
+  #[test]
+  fn variables_from_the_context() {
+    let mut ctx = Context::new();
+    ctx.var("a", 2.);
+    let s = expr!(exp(a * x))
+      .series_with_context("x", 0., 2, &ctx)
+      .unwrap();
+    assert_eq!(s.expr.to_string(), "1 + 2 * x + 2 * x^2");
+    assert_eq!(
+      expr!(exp(a * x)).series("x", 0., 2),
+      Err(Error::UnknownVariable("a".into()))
+    );
+  }
+
+  fn err(expr: Expr) -> Error {
+    expr.series("x", 0., 3).unwrap_err()
+  }
+
+  #[test]
+  fn no_series_at_the_point() {
+    assert_eq!(err(expr!(sqrt(x))), Error::NoSeries("sqrt(x)".into()));
+    assert_eq!(err(expr!(1 + ln(x))), Error::NoSeries("ln(x)".into()));
+    assert_eq!(err(expr!(exp(1 / x))), Error::NoSeries("exp(1 / x)".into()));
+    assert_eq!(err(expr!(x ^ 0.5)), Error::NoSeries("x^0.5".into()));
+    assert_eq!(err(expr!(max(x, 1))), Error::NoDerivative("max".into()));
+  }
+
+  #[test]
+  fn powers_beyond_the_cap() {
+    assert_eq!(
+      err("x^1e10".parse().unwrap()),
+      Error::NoSeries("x^10000000000".into())
+    );
+    assert_eq!(
+      err(expr!(x ^ 600 * x ^ 600)),
+      Error::NoSeries("x^600 * x^600".into())
+    );
+    // the sum would span all the powers in between
+    assert_eq!(
+      err("x^-2000000000 + 1".parse().unwrap()),
+      Error::NoSeries("x^(-2000000000)".into())
+    );
+    assert_eq!(
+      err(expr!(x ^ -999 + 1)),
+      Error::NoSeries("x^(-999) + 1".into())
+    );
+    assert_eq!(
+      expr!(x).series("x", 0., 5000),
+      Err(Error::NoSeries("x".into()))
+    );
+    assert_eq!(series(expr!(x ^ -990 + 1), 0., 0), "1 / x^990 + 1");
+  }
+}
@@ -3,7 +3,7 @@ use std::{
   ops::{Deref, DerefMut},
 };
 
-use super::{Expr, Token};
+use super::{builtin, quadrature::is_binding, ContextProvider, Expr, Matching, Pattern, Token};
 
 #[derive(Debug, PartialEq)]
 pub struct Substitutions(HashMap<String, Expr>);
@@ -12,6 +12,22 @@ impl Substitutions {
   pub fn new() -> Self {
     Self(HashMap::new())
   }
+
+  /// Replaces the variables in `expr` by the expressions bound to them.
+  pub fn apply(&self, expr: &Expr) -> Expr {
+    use self::Token::Var;
+
+    let try_sub = |tkn: &Token| {
+      if let Var(ident) = tkn {
+        if let Some(expr) = self.get(ident) {
+          return expr.0.clone().into_iter();
+        }
+      }
+      vec![(tkn.clone())].into_iter()
+    };
+
+    Expr(expr.iter().flat_map(try_sub).collect())
+  }
 }
 
 impl<const N: usize, S1: Into<String>, S2: Into<String>> From<[(S1, S2); N]> for Substitutions {
@@ -41,10 +57,12 @@ impl DerefMut for Substitutions {
 pub enum SubstituteError {
   Inconsistent(String, Expr, Expr),
   NotMatching,
+  /// A constraint or side condition of the match doesn't hold.
+  Unsatisfied(String),
 }
 
 impl Expr {
-  fn no_children(tkn: &Token) -> usize {
+  pub(crate) fn no_children(tkn: &Token) -> usize {
     use self::Token::*;
     match tkn {
       Binary(_) => 2,
@@ -109,6 +127,55 @@ impl Expr {
     vals.pop().unwrap()
   }
 
+  /// Evaluates every subexpression that doesn't depend on any variables, replacing it by
+  /// its value.
+  ///
+  /// Only pure functions (see [`ContextProvider::is_pure`]) are evaluated, and subexpressions
+  /// that fail to evaluate are kept as they are.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Context, Expr, RandomContext};
+  /// let expr: Expr = "x * (2 + 3) + sqrt(4) * rand()".parse().unwrap();
+  /// let ctx = (RandomContext::seeded(0), Context::new());
+  ///
+  /// assert_eq!(
+  ///   expr.fold_constants(ctx),
+  ///   "x * 5 + 2 * rand()".parse().unwrap()
+  /// );
+  /// ```
+  ///
+  /// [`ContextProvider::is_pure`]: trait.ContextProvider.html#method.is_pure
+  pub fn fold_constants<C: ContextProvider>(&self, ctx: C) -> Expr {
+    use self::Token::*;
+
+    let (tkns, _) = self.fold_expr(|vs: Vec<(Vec<Token>, bool)>, t| {
+      let mut is_const = vs.iter().all(|(_, c)| *c)
+        && match &t {
+          Var(_) => false,
+          Func(name, _) => ctx.is_pure(name),
+          _ => true,
+        };
+      // a call binding a variable is constant if its body depends on nothing else
+      let binding = is_binding(&t);
+      let mut tkns: Vec<_> = vs.into_iter().flat_map(|(tkns, _)| tkns).collect();
+      tkns.push(t);
+      if !is_const && binding {
+        is_const = Expr(tkns.clone()).is_closed(&ctx);
+      }
+
+      if is_const && tkns.len() > 1 {
+        if let Ok(x) = Expr(tkns.clone()).eval_with_context(&ctx) {
+          return (vec![Number(x)], true);
+        }
+      }
+      (tkns, is_const)
+    });
+
+    Expr(tkns)
+  }
+
   /// Matches a subexpression within self, returning any variables within
   /// `to_match` bound to the expression in the same place within self.
   ///
@@ -116,6 +183,8 @@ impl Expr {
   /// - `Ok(Substitutions)` if matching successful
   /// - `Err(NotMatching)` if the structure of `to_match` doesn't match self
   /// - `Err(Inconsistent)` if a variable is assigned different subexpressions
+  /// - `Err(Unsatisfied)` if `to_match` is given with a [`Guard`](struct.Guard.html), as
+  ///   `(&to_match, &guard)`, and the variables bound don't satisfy it
   ///
   /// # Examples
   ///
@@ -131,10 +200,14 @@ impl Expr {
   ///   ("c", "9"),
   /// ].into());
   /// ```
-  pub fn extract(&self, term: &Expr) -> Result<Substitutions, SubstituteError> {
+  pub fn extract<'a, P: Into<Pattern<'a>>>(
+    &self,
+    pattern: P,
+  ) -> Result<Substitutions, SubstituteError> {
     use self::SubstituteError::{Inconsistent, NotMatching};
     use self::Token::*;
 
+    let Pattern { term, guard } = pattern.into();
     let term_ptrs = term.start_pointers();
     let self_ptrs = self.start_pointers();
     let mut subs = Substitutions::new();
@@ -160,6 +233,9 @@ impl Expr {
       }
     }
 
+    if let Some(guard) = guard {
+      guard.check(&subs, builtin())?;
+    }
     Ok(subs)
   }
 
@@ -183,19 +259,41 @@ impl Expr {
   /// assert_eq!(subs, "(x+7)*(2*x) + (x+7)*9".parse().unwrap());
   /// ```
   pub fn replace(&self, term: &Expr, rplc: &Expr) -> Result<Expr, SubstituteError> {
-    use self::Token::Var;
+    self.replace_with(term, rplc, Matching::Exact)
+  }
 
-    let subs = self.extract(term)?;
-    let try_sub = |tkn: &Token| {
-      if let Var(ident) = tkn {
-        if let Some(expr) = subs.get(ident) {
-          return expr.0.clone().into_iter();
-        }
-      }
-      vec![(tkn.clone())].into_iter()
+  /// Like [`replace`](#method.replace), matching `term` as given by `matching`.
+  ///
+  /// With [`Matching::AC`](enum.Matching.html), the first of the substitutions found by
+  /// [`extract_ac`](#method.extract_ac) is used.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Matching};
+  /// let expr: Expr = "0 + 2 * x".parse().unwrap();
+  /// let mtch: Expr = "a * 2 + 0".parse().unwrap();
+  /// let rplc: Expr = "a".parse().unwrap();
+  ///
+  /// assert!(expr.replace(&mtch, &rplc).is_err());
+  /// assert_eq!(expr.replace_with(&mtch, &rplc, Matching::AC), Ok("x".parse().unwrap()));
+  /// ```
+  pub fn replace_with(
+    &self,
+    term: &Expr,
+    rplc: &Expr,
+    matching: Matching,
+  ) -> Result<Expr, SubstituteError> {
+    let subs = match matching {
+      Matching::Exact => self.extract(term)?,
+      Matching::AC => self
+        .extract_ac(term)
+        .into_iter()
+        .next()
+        .ok_or(SubstituteError::NotMatching)?,
     };
 
-    Ok(Expr(rplc.iter().flat_map(try_sub).collect()))
+    Ok(subs.apply(rplc))
   }
 
   /// Substitutes subexpressions matching term with the replacement term.
@@ -212,6 +310,16 @@ impl Expr {
   /// expression would change, requiring us to increment or decrement the
   /// pointers after this initial section.
   pub fn substitute(&self, term: &Expr, rplc: &Expr) -> Result<Expr, SubstituteError> {
+    self.substitute_with(term, rplc, Matching::Exact)
+  }
+
+  /// Like [`substitute`](#method.substitute), matching `term` as given by `matching`.
+  pub fn substitute_with(
+    &self,
+    term: &Expr,
+    rplc: &Expr,
+    matching: Matching,
+  ) -> Result<Expr, SubstituteError> {
     let self_ptrs = self.start_pointers();
     let mut res = self.clone();
     let mut to_sub = vec![false; res.len()];
@@ -225,7 +333,7 @@ impl Expr {
       let i = self_ptrs[j];
       let sub_expr = Expr(self.0[i..=j].iter().cloned().collect());
 
-      if let Ok(expr) = sub_expr.replace(term, rplc) {
+      if let Ok(expr) = sub_expr.replace_with(term, rplc, matching) {
         // replace end and don't recurse
         res.0.splice(i..=j, expr.0);
       } else {
@@ -244,6 +352,21 @@ impl Expr {
 mod test {
   use super::*;
 
+  #[test]
+  fn fold_constants_nested() {
+    let expr: Expr = "max(1, 2 * 3) + f(x, 2^2)".parse().unwrap();
+    assert_eq!(
+      expr.fold_constants(crate::Context::new()),
+      "6 + f(x, 4)".parse().unwrap()
+    );
+  }
+
+  #[test]
+  fn fold_constants_keeps_errors() {
+    let expr: Expr = "0.5! + g(1)".parse().unwrap();
+    assert_eq!(expr.fold_constants(crate::Context::new()), expr);
+  }
+
   #[test]
   fn extract_reflexive_ok() {
     let expr: Expr = "abs(+7 + 10 * -2)".parse().unwrap();
@@ -360,4 +483,38 @@ mod test {
     let rplc: Expr = "a".parse().unwrap();
     assert_eq!(expr.substitute(&mtch, &rplc), Ok("x / 7".parse().unwrap()))
   }
+
+  #[test]
+  fn macros_match_parsed() {
+    let expr = expr!(-b + sqrt(b ^ 2 - 4 * a * c));
+    assert_eq!(expr, "-b + sqrt(b^2 - 4*a*c)".parse().unwrap());
+    assert_eq!(
+      expr.extract(&expr!(-x + sqrt(y))),
+      Ok([("x", "b"), ("y", "b^2 - 4*a*c")].into())
+    );
+    assert_eq!(
+      expr!((x + 2) / (7 + y)).replace(&expr!(a / b), &expr!(a * b)),
+      Ok(expr!((x + 2) * (7 + y)))
+    );
+    assert_eq!(
+      expr!(max(1, 2 * 3) + f(x, 2 ^ 2)).fold_constants(crate::Context::new()),
+      expr!(6 + f(x, 4))
+    );
+  }
+
+  #[test]
+  fn substitute_ac() {
+    let expr = expr!(0 + sin(0 + x) * y);
+    let mtch = expr!(a + 0);
+    let rplc = expr!(a);
+    assert_eq!(expr.substitute(&mtch, &rplc), Ok(expr.clone()));
+    assert_eq!(
+      expr.substitute_with(&mtch, &rplc, Matching::AC),
+      Ok(expr!(sin(0 + x) * y))
+    );
+    assert_eq!(
+      expr!(sin(0 + x)).substitute_with(&mtch, &rplc, Matching::AC),
+      Ok(expr!(sin(x)))
+    );
+  }
 }
@@ -91,6 +91,18 @@
 //! assert_eq!(func(2.), 5.);
 //! ```
 //!
+//! Random numbers, e.g. for Monte Carlo simulations, are provided by a seedable
+//! [`RandomContext`][RandomContext]:
+//!
+//! ```rust
+//! use meval_symbolic::{Context, Expr, RandomContext};
+//!
+//! let expr: Expr = "1 + 0.5 * randn()".parse().unwrap();
+//! let ctx = (RandomContext::seeded(42), Context::new());
+//! let samples: Vec<_> = (0..100).map(|_| expr.eval_with_context(&ctx).unwrap()).collect();
+//! # assert_eq!(samples.len(), 100);
+//! ```
+//!
 //! # Supported expressions
 //!
 //! `meval` supports basic mathematical operations on floating point numbers:
@@ -117,6 +129,13 @@
 //! - other functions:
 //!
 //!     - `max(x, ...)`, `min(x, ...)`: maximum and minimumum of 1 or more numbers
+//!     - `sum(x, ...)`, `product(x, ...)`: sum and product of 1 or more numbers
+//!     - `mean(x, ...)`, `median(x, ...)`, `var(x, ...)`, `stddev(x, ...)`: statistics of 1 or
+//!       more numbers (`var` and `stddev` are the population variance and standard deviation)
+//!     - `quantile(p, x, ...)`: the `p`-th quantile of 1 or more numbers, `0 <= p <= 1`
+//!     - `norm(x, ...)`: Euclidean norm of 1 or more numbers
+//!     - `softmax_lse(x, ...)`: the log-sum-exp `ln(exp(x) + ...)` of 1 or more numbers
+//!     - `clamp(x, lo, hi)`: `x` restricted to the interval `[lo, hi]`
 //!
 //! - constants:
 //!
@@ -128,19 +147,24 @@
 //! [`Expr`][Expr] supports deserialization using the [serde] library to make flexible
 //! configuration easy to set up, if the feature `serde` is enabled (disable by default).
 //!
+//! Expressions, equations and rules are serialized as strings in human readable formats
+//! (expressions as a list of tokens otherwise), and the variables of a [`Context`][Context] as a
+//! map from names to values, so formula libraries and parameter sets can be stored in e.g. TOML
+//! or JSON files.
+//!
 #![cfg_attr(feature = "serde", doc = " ```rust")]
 #![cfg_attr(not(feature = "serde"), doc = " ```rust,ignore")]
 //! #[macro_use]
 //! extern crate serde_derive;
 //! extern crate toml;
-//! extern crate meval;
-//! use meval::{Expr, Context};
+//! extern crate meval_symbolic;
+//! use meval_symbolic::{Expr, Context};
 //!
 //! #[derive(Deserialize)]
 //! struct Ode {
-//!     #[serde(deserialize_with = "meval::de::as_f64")]
+//!     #[serde(deserialize_with = "meval_symbolic::de::as_f64")]
 //!     x0: f64,
-//!     #[serde(deserialize_with = "meval::de::as_f64")]
+//!     #[serde(deserialize_with = "meval_symbolic::de::as_f64")]
 //!     t0: f64,
 //!     f: Expr,
 //!     g: Expr,
@@ -179,6 +203,7 @@
 //! [Expr]: struct.Expr.html
 //! [Expr::bind]: struct.Expr.html#method.bind
 //! [Context]: struct.Context.html
+//! [RandomContext]: struct.RandomContext.html
 //! [serde]: https://crates.io/crates/serde
 //! [dyon]: https://crates.io/crates/dyon
 //! [gluon]: https://crates.io/crates/gluon
@@ -194,18 +219,33 @@ extern crate serde_derive;
 extern crate serde_json;
 #[cfg(test)]
 extern crate serde_test;
+#[cfg(all(test, feature = "serde"))]
+extern crate toml;
 
+#[macro_use]
+mod macros;
 mod eqtn;
 mod evaluatable;
 mod expr;
+mod ode;
+mod polynomial;
 mod solver;
 
 pub use eqtn::*;
 #[cfg(feature = "serde")]
 pub use expr::de;
 pub use expr::{
-  eval_str, eval_str_with_context, max_array, min_array, shunting_yard, tokenizer, ArgGuard,
-  Context, ContextProvider, Expr, FuncEvalError,
+  eval_str, eval_str_with_context, max_array, min_array, operators, shunting_yard, tokenizer,
+  ArgGuard, Arity, Comparison, Condition, Constraint, Context, ContextProvider, Dag, Expr,
+  FuncEvalError, FuncInfo, Guard, LeastSquares, LeastSquaresMethod, LeastSquaresOptions, Limit,
+  Matching, MinimizeError, MinimizeMethod, MinimizeOptions, Minimum, Pattern, Quadrature,
+  QuadratureOptions, RandomContext, ScopedContext, Series, Side, VariadicDerivatives,
+};
+pub use ode::{OdeError, OdeEvent, OdeMethod, OdeOptions, OdeSystem, Trajectory};
+pub use polynomial::{Factors, Polynomial, PolynomialError};
+pub use solver::{
+  Counterexample, Direction, EGraph, Heuristic, Id, Length, Limits, MaxNesting, MinNesting,
+  NoOccurences, OnVar, Rule, RuleError, Rules, Stop,
 };
 
 use expr::{builtin, ParseError, RPNError, SubstituteError, Token};
@@ -220,6 +260,16 @@ pub enum Error {
   ParseError(ParseError),
   /// The shunting-yard algorithm returned an error.
   RPNError(RPNError),
+  /// An error in the text of a rule.
+  RuleError(RuleError),
+  /// A function or operator without a known derivative.
+  NoDerivative(String),
+  /// A subexpression without a power series expansion around the point expanded at.
+  NoSeries(String),
+  /// An expression whose limit couldn't be determined.
+  NoLimit(String),
+  /// An expression without an antiderivative found by symbolic integration.
+  NoIntegral(String),
   // A catch all for all other errors during evaluation
   EvalError(String),
 }
@@ -241,6 +291,34 @@ impl Display for Error {
         write!(f, "RPN error: ")?;
         e.fmt(f)
       }
+      Error::RuleError(ref e) => {
+        write!(f, "Rule error: ")?;
+        e.fmt(f)
+      }
+      Error::NoDerivative(ref name) => {
+        write!(f, "Differentiation error: no derivative of `{}`.", name)
+      }
+      Error::NoSeries(ref expr) => {
+        write!(
+          f,
+          "Series error: no power series of `{}` at this point.",
+          expr
+        )
+      }
+      Error::NoLimit(ref expr) => {
+        write!(
+          f,
+          "Limit error: could not determine the limit of `{}`.",
+          expr
+        )
+      }
+      Error::NoIntegral(ref expr) => {
+        write!(
+          f,
+          "Integration error: no antiderivative of `{}` found.",
+          expr
+        )
+      }
       Error::EvalError(ref e) => {
         write!(f, "Eval error: ")?;
         e.fmt(f)
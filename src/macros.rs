@@ -0,0 +1,266 @@
+//! Macros for writing expressions, equations and rules as Rust tokens.
+
+/// Builds an [`Expr`](struct.Expr.html) from a formula written directly as Rust tokens, such
+/// that malformed formulas fail to compile rather than panic when parsed.
+///
+/// The formula uses the same syntax as the parser: numeric literals, variables, function calls
+/// (`f(x, y)`, `rand()`), parentheses, the binary operators `+ - * / % ^`, unary `+`/`-` and the
+/// factorial `!`. As a formula is read one token at a time, very long formulas might need a
+/// larger `#![recursion_limit]`.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate meval_symbolic;
+/// use meval_symbolic::Expr;
+///
+/// fn main() {
+///   let expr = expr!(x^2 + 2 * sin(y) - 3!);
+///   assert_eq!(expr, "x^2 + 2 * sin(y) - 3!".parse::<Expr>().unwrap());
+///   assert_eq!(expr!(max(1, -2, pi * 0)).eval(), Ok(1.));
+/// }
+/// ```
+///
+/// Mistakes are reported by the compiler:
+///
+/// ```rust,compile_fail
+/// #[macro_use]
+/// extern crate meval_symbolic;
+///
+/// fn main() {
+///   let expr = expr!(x^2 + * y);
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// #[macro_use]
+/// extern crate meval_symbolic;
+///
+/// fn main() {
+///   let expr = expr!((x, y));
+/// }
+/// ```
+#[macro_export]
+macro_rules! expr {
+  // Expecting an operand, with a stack of the open parentheses (`p`) and function calls (`f`)
+  // and the infix tokens so far.
+  (@l [$($s:ident)*] [$($out:tt)*] - $($rest:tt)*) => {
+    $crate::expr!(@l [$($s)*] [$($out)* $crate::tokenizer::Token::Unary(
+      $crate::tokenizer::Operation::Minus),] $($rest)*)
+  };
+  (@l [$($s:ident)*] [$($out:tt)*] + $($rest:tt)*) => {
+    $crate::expr!(@l [$($s)*] [$($out)* $crate::tokenizer::Token::Unary(
+      $crate::tokenizer::Operation::Plus),] $($rest)*)
+  };
+  (@l [$($s:ident)*] [$($out:tt)*] $f:ident () $($rest:tt)*) => {
+    $crate::expr!(@r [$($s)*] [$($out)*
+      $crate::tokenizer::Token::Func(stringify!($f).into(), None),
+      $crate::tokenizer::Token::RParen,] $($rest)*)
+  };
+  (@l [$($s:ident)*] [$($out:tt)*] $f:ident ($($args:tt)+) $($rest:tt)*) => {
+    $crate::expr!(@l [f $($s)*] [$($out)*
+      $crate::tokenizer::Token::Func(stringify!($f).into(), None),] $($args)+ @close $($rest)*)
+  };
+  (@l [$($s:ident)*] [$($out:tt)*] $v:ident $($rest:tt)*) => {
+    $crate::expr!(@r [$($s)*] [$($out)*
+      $crate::tokenizer::Token::Var(stringify!($v).into()),] $($rest)*)
+  };
+  (@l [$($s:ident)*] [$($out:tt)*] $n:literal $($rest:tt)*) => {
+    $crate::expr!(@r [$($s)*] [$($out)*
+      $crate::tokenizer::Token::Number($n as f64),] $($rest)*)
+  };
+  (@l [$($s:ident)*] [$($out:tt)*] ($($inner:tt)+) $($rest:tt)*) => {
+    $crate::expr!(@l [p $($s)*] [$($out)*
+      $crate::tokenizer::Token::LParen,] $($inner)+ @close $($rest)*)
+  };
+
+  // Expecting an operator or the end of the formula.
+  (@r [$($s:ident)*] [$($out:tt)*] ! $($rest:tt)*) => {
+    $crate::expr!(@r [$($s)*] [$($out)* $crate::tokenizer::Token::Unary(
+      $crate::tokenizer::Operation::Fact),] $($rest)*)
+  };
+  (@r [$($s:ident)*] [$($out:tt)*] + $($rest:tt)*) => {
+    $crate::expr!(@binary Plus [$($s)*] [$($out)*] $($rest)*)
+  };
+  (@r [$($s:ident)*] [$($out:tt)*] - $($rest:tt)*) => {
+    $crate::expr!(@binary Minus [$($s)*] [$($out)*] $($rest)*)
+  };
+  (@r [$($s:ident)*] [$($out:tt)*] * $($rest:tt)*) => {
+    $crate::expr!(@binary Times [$($s)*] [$($out)*] $($rest)*)
+  };
+  (@r [$($s:ident)*] [$($out:tt)*] / $($rest:tt)*) => {
+    $crate::expr!(@binary Div [$($s)*] [$($out)*] $($rest)*)
+  };
+  (@r [$($s:ident)*] [$($out:tt)*] % $($rest:tt)*) => {
+    $crate::expr!(@binary Rem [$($s)*] [$($out)*] $($rest)*)
+  };
+  (@r [$($s:ident)*] [$($out:tt)*] ^ $($rest:tt)*) => {
+    $crate::expr!(@binary Pow [$($s)*] [$($out)*] $($rest)*)
+  };
+  (@r [f $($s:ident)*] [$($out:tt)*] , $($rest:tt)*) => {
+    $crate::expr!(@l [f $($s)*] [$($out)* $crate::tokenizer::Token::Comma,] $($rest)*)
+  };
+  (@r [$top:ident $($s:ident)*] [$($out:tt)*] @close $($rest:tt)*) => {
+    $crate::expr!(@r [$($s)*] [$($out)* $crate::tokenizer::Token::RParen,] $($rest)*)
+  };
+  (@r [] [$($out:tt)*]) => {
+    $crate::Expr::from_infix(vec![$($out)*]).expect("formula checked by `expr!` did not parse")
+  };
+
+  (@binary $op:ident [$($s:ident)*] [$($out:tt)*] $($rest:tt)*) => {
+    $crate::expr!(@l [$($s)*] [$($out)* $crate::tokenizer::Token::Binary(
+      $crate::tokenizer::Operation::$op),] $($rest)*)
+  };
+
+  (@l $s:tt $out:tt @close $($rest:tt)*) => {
+    compile_error!("expected a number, variable, function call or parenthesis before `)`")
+  };
+  (@l $s:tt $out:tt $t:tt $($rest:tt)*) => {
+    compile_error!(concat!(
+      "expected a number, variable, function call or parenthesis, found `",
+      stringify!($t),
+      "`"
+    ))
+  };
+  (@l $s:tt $out:tt) => {
+    compile_error!("unexpected end of formula")
+  };
+  (@r $s:tt $out:tt $t:tt $($rest:tt)*) => {
+    compile_error!(concat!("expected an operator, found `", stringify!($t), "`"))
+  };
+
+  ($($t:tt)+) => {
+    $crate::expr!(@l [] [] $($t)+)
+  };
+}
+
+/// Builds an [`Eqtn`](struct.Eqtn.html) from an equation written as Rust tokens, with each side
+/// written as in [`expr!`](macro.expr.html).
+///
+/// # Example
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate meval_symbolic;
+/// use meval_symbolic::Eqtn;
+///
+/// fn main() {
+///   let eqtn = eqtn!(x^2 = 2 * (y + 1));
+///   assert_eq!(eqtn, "x^2 = 2 * (y + 1)".parse::<Eqtn>().unwrap());
+/// }
+/// ```
+#[macro_export]
+macro_rules! eqtn {
+  (@lhs [$($lhs:tt)*] = $($rhs:tt)+) => {
+    $crate::Eqtn::new($crate::expr!($($lhs)*), $crate::expr!($($rhs)+))
+  };
+  (@lhs [$($lhs:tt)*] $t:tt $($rest:tt)*) => {
+    $crate::eqtn!(@lhs [$($lhs)* $t] $($rest)*)
+  };
+  (@lhs $lhs:tt $($rest:tt)*) => {
+    compile_error!("expected an equation `lhs = rhs`")
+  };
+  ($($t:tt)+) => {
+    $crate::eqtn!(@lhs [] $($t)+)
+  };
+}
+
+/// Builds a [`Rule`](struct.Rule.html) relating two expressions or two equations, written as
+/// Rust tokens as in [`expr!`](macro.expr.html) and [`eqtn!`](macro.eqtn.html), with `<=>`
+/// between the sides, or `=>` or `<=` for one-way rules.
+///
+/// # Example
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate meval_symbolic;
+/// use meval_symbolic::Rule;
+///
+/// fn main() {
+///   assert_eq!(rule!(x + 0 <=> x), "x + 0 <=> x".parse::<Rule>().unwrap());
+///   assert_eq!(
+///     rule!(x + y = z <=> x = z - y),
+///     "x + y = z <=> x = z - y".parse::<Rule>().unwrap()
+///   );
+///   assert_eq!(rule!(1 / x <= x^(-1)), "x^(-1) => 1 / x".parse::<Rule>().unwrap());
+/// }
+/// ```
+#[macro_export]
+macro_rules! rule {
+  (@lhs [$($lhs:tt)*] <=> $($rhs:tt)+) => {
+    $crate::rule!(@kind Both [$($lhs)*] [$($rhs)+] $($lhs)*)
+  };
+  (@lhs [$($lhs:tt)*] => $($rhs:tt)+) => {
+    $crate::rule!(@kind Forward [$($lhs)*] [$($rhs)+] $($lhs)*)
+  };
+  (@lhs [$($rhs:tt)*] <= $($lhs:tt)+) => {
+    $crate::rule!(@kind Forward [$($lhs)+] [$($rhs)*] $($lhs)+)
+  };
+  (@lhs [$($lhs:tt)*] $t:tt $($rest:tt)*) => {
+    $crate::rule!(@lhs [$($lhs)* $t] $($rest)*)
+  };
+  (@lhs $lhs:tt $($rest:tt)*) => {
+    compile_error!("expected a rule `lhs <=> rhs`, `lhs => rhs` or `rhs <= lhs`")
+  };
+
+  // The rule relates equations if the left hand side has an `=`.
+  (@kind $dir:ident [$($lhs:tt)*] [$($rhs:tt)*] = $($rest:tt)*) => {
+    $crate::Rule::new($crate::eqtn!($($lhs)*), $crate::eqtn!($($rhs)*))
+      .with_direction($crate::Direction::$dir)
+  };
+  (@kind $dir:ident $lhs:tt $rhs:tt $t:tt $($rest:tt)*) => {
+    $crate::rule!(@kind $dir $lhs $rhs $($rest)*)
+  };
+  (@kind $dir:ident [$($lhs:tt)*] [$($rhs:tt)*]) => {
+    $crate::Rule::from_exprs($crate::expr!($($lhs)*), $crate::expr!($($rhs)*))
+      .with_direction($crate::Direction::$dir)
+  };
+
+  ($($t:tt)+) => {
+    $crate::rule!(@lhs [] $($t)+)
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use {Eqtn, Expr, Rule};
+
+  fn parse(s: &str) -> Expr {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn expr_matches_parser() {
+    assert_eq!(expr!(1 + 2 * 3), parse("1 + 2 * 3"));
+    assert_eq!(expr!(-2 ^ -x), parse("-2^-x"));
+    assert_eq!(expr!((a - b) % c / d), parse("(a - b) % c / d"));
+    assert_eq!(expr!(3! * (x + 1)!), parse("3! * (x + 1)!"));
+    assert_eq!(expr!(+x - -1.5e-3), parse("+x - -1.5e-3"));
+    assert_eq!(
+      expr!(max(1, sin(x), atan2(y, (z))) + rand()),
+      parse("max(1, sin(x), atan2(y, (z))) + rand()")
+    );
+    assert_eq!(expr!(f(g(h(1)))), parse("f(g(h(1)))"));
+  }
+
+  #[test]
+  fn eqtn_and_rule() {
+    assert_eq!(
+      eqtn!(sin(x) ^ 2 + cos(x) ^ 2 = 1),
+      "sin(x)^2 + cos(x)^2 = 1".parse::<Eqtn>().unwrap()
+    );
+    assert_eq!(
+      rule!(a * (b + c) <=> a * b + a * c),
+      "a * (b + c) <=> a * b + a * c".parse::<Rule>().unwrap()
+    );
+    assert_eq!(
+      rule!(x * y = z <=> x = z / y),
+      "x * y = z <=> x = z / y".parse::<Rule>().unwrap()
+    );
+    assert_eq!(
+      rule!(x * y = z => y = z / x),
+      "x * y = z => y = z / x".parse::<Rule>().unwrap()
+    );
+  }
+}
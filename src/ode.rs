@@ -0,0 +1,857 @@
+//! Systems of ordinary differential equations `dy/dt = f(t, y)` given by expressions, integrated
+//! with a fixed step by the classical Runge-Kutta method or adaptively by the Dormand-Prince
+//! method.
+use expr::Token;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+use std::slice;
+use std::str::FromStr;
+use {ContextProvider, Error, Expr};
+
+/// The derivatives of some state variables with respect to time, e.g.
+/// `dx/dt = v; dv/dt = -k * x`, and events at which an expression crosses zero.
+///
+/// Systems are parsed from equations `dy/dt = ...` separated by `;` or new lines, ignoring blank
+/// lines. Events aren't part of the text and are added by [`with_event`](#method.with_event).
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::{OdeMethod, OdeOptions, OdeSystem};
+/// let system: OdeSystem = "dy/dt = -k*y + sin(t)".parse().unwrap();
+/// let ctx = (("k", 2.), meval_symbolic::Context::new());
+/// let options = OdeOptions::new(OdeMethod::DormandPrince);
+/// let res = system.solve(0., &[1.], 3., ctx, &options).unwrap();
+///
+/// let exact = |t: f64| (2. * t.sin() - t.cos()) / 5. + 1.2 * (-2. * t).exp();
+/// assert!(res.completed);
+/// assert!((res.states.last().unwrap()[0] - exact(3.)).abs() < 1e-7);
+/// assert!((res.at(1.5).unwrap()[0] - exact(1.5)).abs() < 1e-7);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OdeSystem {
+  time: String,
+  states: Vec<String>,
+  rhs: Vec<Expr>,
+  /// The event expressions, and whether they stop the integration.
+  events: Vec<(Expr, bool)>,
+}
+
+/// Why a system of differential equations couldn't be integrated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OdeError {
+  /// The equation with this index isn't of the form `dy/dt = ...`, or has a different time
+  /// variable than the first.
+  NotDerivative(usize),
+  /// There must be an initial value for each state variable.
+  InitialValues { expected: usize, found: usize },
+  /// The step became too small at this time, e.g. at a singularity, or a fixed step isn't
+  /// positive.
+  StepSize(f64),
+  /// An error evaluating the derivatives or an event.
+  Eval(Error),
+}
+
+impl Display for OdeError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      OdeError::NotDerivative(i) => {
+        write!(f, "Equation {} is not of the form `dy/dt = ...`.", i + 1)
+      }
+      OdeError::InitialValues { expected, found } => write!(
+        f,
+        "Expected {} initial values, one for each state variable, got {}.",
+        expected, found
+      ),
+      OdeError::StepSize(t) => write!(f, "The step size became too small at time {}.", t),
+      OdeError::Eval(e) => e.fmt(f),
+    }
+  }
+}
+
+impl From<Error> for OdeError {
+  fn from(err: Error) -> OdeError {
+    OdeError::Eval(err)
+  }
+}
+
+/// A method of integrating a system of differential equations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OdeMethod {
+  /// The classical Runge-Kutta method of order 4 with a fixed step, shortened to end at the
+  /// final time.
+  Rk4 { step: f64 },
+  /// The Dormand-Prince method of order 5, adapting the step by comparing with an embedded
+  /// method of order 4.
+  DormandPrince,
+}
+
+/// The method and tolerances of [`OdeSystem::solve`].
+///
+/// [`OdeSystem::solve`]: struct.OdeSystem.html#method.solve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OdeOptions {
+  pub method: OdeMethod,
+  /// An adaptive step is accepted once the estimated error of every state variable `y` is at
+  /// most `abs_tolerance + tolerance * |y|`, in the root mean square.
+  pub tolerance: f64,
+  pub abs_tolerance: f64,
+  /// The largest number of steps, including rejected ones.
+  pub max_steps: usize,
+}
+
+impl OdeOptions {
+  /// Options for `method`, with a relative tolerance of `1e-8`, an absolute tolerance of
+  /// `1e-10` and at most 100000 steps.
+  pub fn new(method: OdeMethod) -> Self {
+    OdeOptions {
+      method,
+      tolerance: 1e-8,
+      abs_tolerance: 1e-10,
+      max_steps: 100_000,
+    }
+  }
+
+  pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+    self.tolerance = tolerance;
+    self
+  }
+
+  pub fn with_abs_tolerance(mut self, abs_tolerance: f64) -> Self {
+    self.abs_tolerance = abs_tolerance;
+    self
+  }
+
+  pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+    self.max_steps = max_steps;
+    self
+  }
+}
+
+/// A time at which an event expression crossed zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OdeEvent {
+  /// The index of the event, in the order they were added.
+  pub index: usize,
+  pub time: f64,
+  pub state: Vec<f64>,
+}
+
+/// The solution of a system of differential equations, as the states at the end of each step
+/// and interpolated in between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+  /// The names of the state variables, in the order of the values of each state.
+  pub names: Vec<String>,
+  /// The initial time, and the time at the end of each step.
+  pub times: Vec<f64>,
+  pub states: Vec<Vec<f64>>,
+  /// The events, in the order they happened.
+  pub events: Vec<OdeEvent>,
+  /// Whether the final time was reached, rather than a terminal event or the maximum number of
+  /// steps.
+  pub completed: bool,
+  /// The number of times the derivatives were evaluated.
+  pub evaluations: usize,
+  segments: Vec<Segment>,
+}
+
+impl Trajectory {
+  /// The values of the state variable `name` at each of the [`times`](#structfield.times).
+  pub fn series(&self, name: &str) -> Option<Vec<f64>> {
+    let i = self.names.iter().position(|n| n == name)?;
+    Some(self.states.iter().map(|state| state[i]).collect())
+  }
+
+  /// The state at time `t`, interpolated to the order of the method, or `None` if `t` is
+  /// outside of the times integrated over.
+  pub fn at(&self, t: f64) -> Option<Vec<f64>> {
+    let (first, last) = (self.times[0], *self.times.last().unwrap());
+    if !(first.min(last)..=first.max(last)).contains(&t) {
+      return None;
+    }
+    if t == first {
+      return Some(self.states[0].clone());
+    }
+    let dir = (last - first).signum();
+    let i = self
+      .segments
+      .partition_point(|s| (s.t + s.h - t) * dir < 0.);
+    let segment = self.segments.get(i.min(self.segments.len() - 1))?;
+    Some(segment.eval((t - segment.t) / segment.h))
+  }
+}
+
+/// A step from `t` to `t + h`, with the coefficients of its dense output in the form of
+/// Hairer's DOPRI5.
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+  t: f64,
+  h: f64,
+  coeffs: [Vec<f64>; 5],
+}
+
+impl Segment {
+  /// The state at `t + theta * h`.
+  fn eval(&self, theta: f64) -> Vec<f64> {
+    let [r1, r2, r3, r4, r5] = &self.coeffs;
+    let eta = 1. - theta;
+    (0..r1.len())
+      .map(|i| r1[i] + theta * (r2[i] + eta * (r3[i] + theta * (r4[i] + eta * r5[i]))))
+      .collect()
+  }
+}
+
+/// A step of one of the methods, with the derivatives at its end.
+struct Step {
+  y: Vec<f64>,
+  k: Vec<f64>,
+  coeffs: [Vec<f64>; 5],
+  /// The root mean square of the estimated errors relative to the tolerances.
+  error: f64,
+}
+
+/// The coefficients of the Dormand-Prince method.
+mod dopri {
+  pub const C: [f64; 5] = [1. / 5., 3. / 10., 4. / 5., 8. / 9., 1.];
+  pub const A: [&[f64]; 6] = [
+    &[1. / 5.],
+    &[3. / 40., 9. / 40.],
+    &[44. / 45., -56. / 15., 32. / 9.],
+    &[
+      19372. / 6561.,
+      -25360. / 2187.,
+      64448. / 6561.,
+      -212. / 729.,
+    ],
+    &[
+      9017. / 3168.,
+      -355. / 33.,
+      46732. / 5247.,
+      49. / 176.,
+      -5103. / 18656.,
+    ],
+    &[
+      35. / 384.,
+      0.,
+      500. / 1113.,
+      125. / 192.,
+      -2187. / 6784.,
+      11. / 84.,
+    ],
+  ];
+  /// The differences to the embedded method of order 4.
+  pub const E: [f64; 7] = [
+    71. / 57600.,
+    0.,
+    -71. / 16695.,
+    71. / 1920.,
+    -17253. / 339200.,
+    22. / 525.,
+    -1. / 40.,
+  ];
+  pub const D: [f64; 7] = [
+    -12715105075. / 11282082432.,
+    0.,
+    87487479700. / 32700410799.,
+    -10690763975. / 1880347072.,
+    701980252875. / 199316789632.,
+    -1453857185. / 822651844.,
+    69997945. / 29380423.,
+  ];
+}
+
+/// A context with the values of the time and the state variables.
+struct Values<'a> {
+  names: &'a [String],
+  values: &'a [f64],
+}
+
+impl<'a> ContextProvider for Values<'a> {
+  fn get_var(&self, name: &str) -> Option<f64> {
+    let i = self.names.iter().position(|n| n == name)?;
+    Some(self.values[i])
+  }
+}
+
+/// The derivatives and events with the constants folded and the variables checked, evaluated
+/// at a time and state.
+struct Compiled<'a, C> {
+  /// The time, then the state variables.
+  names: Vec<String>,
+  rhs: Vec<Expr>,
+  events: Vec<Expr>,
+  ctx: C,
+  options: &'a OdeOptions,
+  evaluations: usize,
+}
+
+impl<'a, C: ContextProvider> Compiled<'a, C> {
+  fn new(system: &OdeSystem, ctx: C, options: &'a OdeOptions) -> Result<Self, Error> {
+    let mut names = vec![system.time.clone()];
+    names.extend(system.states.iter().cloned());
+    let zeros = vec![0.; names.len()];
+    let check = Values {
+      names: &names,
+      values: &zeros,
+    };
+    let compile = |expr: &Expr| {
+      expr.check_context((&check, &ctx))?;
+      Ok(expr.fold_constants(&ctx))
+    };
+    let rhs = system
+      .rhs
+      .iter()
+      .map(compile)
+      .collect::<Result<_, Error>>()?;
+    let events = system
+      .events
+      .iter()
+      .map(|(expr, _)| compile(expr))
+      .collect::<Result<_, Error>>()?;
+    Ok(Compiled {
+      names,
+      rhs,
+      events,
+      ctx,
+      options,
+      evaluations: 0,
+    })
+  }
+
+  fn values(&self, t: f64, y: &[f64]) -> Vec<f64> {
+    let mut values = Vec::with_capacity(y.len() + 1);
+    values.push(t);
+    values.extend_from_slice(y);
+    values
+  }
+
+  /// The derivatives at time `t` and state `y`.
+  fn derivatives(&mut self, t: f64, y: &[f64]) -> Result<Vec<f64>, Error> {
+    self.evaluations += 1;
+    let values = self.values(t, y);
+    let vars = Values {
+      names: &self.names,
+      values: &values,
+    };
+    self
+      .rhs
+      .iter()
+      .map(|expr| expr.eval_with_context((&vars, &self.ctx)))
+      .collect()
+  }
+
+  fn event(&self, i: usize, t: f64, y: &[f64]) -> Result<f64, Error> {
+    let values = self.values(t, y);
+    let vars = Values {
+      names: &self.names,
+      values: &values,
+    };
+    self.events[i].eval_with_context((&vars, &self.ctx))
+  }
+
+  /// The root mean square of `err` relative to the tolerances for states `y` and `z`.
+  fn error_norm(&self, err: &[f64], y: &[f64], z: &[f64]) -> f64 {
+    if err.is_empty() {
+      return 0.;
+    }
+    let sum: f64 = (0..err.len())
+      .map(|i| {
+        let scale =
+          self.options.abs_tolerance + self.options.tolerance * y[i].abs().max(z[i].abs());
+        (err[i] / scale).powi(2)
+      })
+      .sum();
+    (sum / err.len() as f64).sqrt()
+  }
+
+  /// The state `y + h * sum_j a_j k_j`.
+  fn advance(y: &[f64], h: f64, a: &[f64], ks: &[Vec<f64>]) -> Vec<f64> {
+    (0..y.len())
+      .map(|i| y[i] + h * a.iter().zip(ks).map(|(a, k)| a * k[i]).sum::<f64>())
+      .collect()
+  }
+
+  /// A step of the classical Runge-Kutta method, with cubic Hermite interpolation.
+  fn rk4(&mut self, t: f64, y: &[f64], k1: &[f64], h: f64) -> Result<Step, Error> {
+    let k1 = k1.to_vec();
+    let k2 = self.derivatives(
+      t + h / 2.,
+      &Self::advance(y, h / 2., &[1.], slice::from_ref(&k1)),
+    )?;
+    let k3 = self.derivatives(
+      t + h / 2.,
+      &Self::advance(y, h / 2., &[1.], slice::from_ref(&k2)),
+    )?;
+    let k4 = self.derivatives(t + h, &Self::advance(y, h, &[1.], slice::from_ref(&k3)))?;
+    let z = Self::advance(
+      y,
+      h,
+      &[1. / 6., 1. / 3., 1. / 3., 1. / 6.],
+      &[k1.clone(), k2, k3, k4],
+    );
+    let k = self.derivatives(t + h, &z)?;
+    let diff: Vec<_> = (0..y.len()).map(|i| z[i] - y[i]).collect();
+    let r3: Vec<_> = (0..y.len()).map(|i| h * k1[i] - diff[i]).collect();
+    let r4 = (0..y.len()).map(|i| diff[i] - h * k[i] - r3[i]).collect();
+    Ok(Step {
+      coeffs: [y.to_vec(), diff, r3, r4, vec![0.; y.len()]],
+      y: z,
+      k,
+      error: 0.,
+    })
+  }
+
+  /// A step of the Dormand-Prince method, with its error estimate and dense output.
+  fn dormand_prince(&mut self, t: f64, y: &[f64], k1: &[f64], h: f64) -> Result<Step, Error> {
+    let mut ks = vec![k1.to_vec()];
+    for (c, a) in dopri::C.iter().zip(&dopri::A) {
+      let k = self.derivatives(t + c * h, &Self::advance(y, h, a, &ks))?;
+      ks.push(k);
+    }
+    let z = Self::advance(y, h, dopri::A[5], &ks);
+    let k = self.derivatives(t + h, &z)?;
+    ks.push(k.clone());
+
+    let err: Vec<_> = (0..y.len())
+      .map(|i| h * dopri::E.iter().zip(&ks).map(|(e, k)| e * k[i]).sum::<f64>())
+      .collect();
+    let diff: Vec<_> = (0..y.len()).map(|i| z[i] - y[i]).collect();
+    let r3: Vec<_> = (0..y.len()).map(|i| h * k1[i] - diff[i]).collect();
+    let r4 = (0..y.len()).map(|i| diff[i] - h * k[i] - r3[i]).collect();
+    let r5 = (0..y.len())
+      .map(|i| h * dopri::D.iter().zip(&ks).map(|(d, k)| d * k[i]).sum::<f64>())
+      .collect();
+    Ok(Step {
+      error: self.error_norm(&err, y, &z),
+      coeffs: [y.to_vec(), diff, r3, r4, r5],
+      y: z,
+      k,
+    })
+  }
+
+  /// A first step for the adaptive method, from the sizes of the state and its derivatives.
+  fn initial_step(&self, y: &[f64], k: &[f64], span: f64) -> f64 {
+    let (d0, d1) = (self.error_norm(y, y, y), self.error_norm(k, y, y));
+    let h = if d0 < 1e-5 || d1 < 1e-5 {
+      1e-6
+    } else {
+      0.01 * d0 / d1
+    };
+    h.min(span)
+  }
+
+  /// The fraction of `segment` at which event `i` crosses zero, by bisection.
+  fn locate(&self, i: usize, segment: &Segment, g0: f64) -> Result<f64, Error> {
+    let (mut a, mut b) = (0., 1.);
+    for _ in 0..60 {
+      let m = (a + b) / 2.;
+      let g = self.event(i, segment.t + m * segment.h, &segment.eval(m))?;
+      if g == 0. {
+        return Ok(m);
+      } else if g.signum() == g0.signum() {
+        a = m;
+      } else {
+        b = m;
+      }
+    }
+    Ok(b)
+  }
+}
+
+impl OdeSystem {
+  /// The system of the derivatives `dy/dt = rhs` of each state variable `y` with respect to
+  /// `time`.
+  pub fn new(time: &str, equations: Vec<(&str, Expr)>) -> Self {
+    let (states, rhs) = equations
+      .into_iter()
+      .map(|(state, rhs)| (state.to_owned(), rhs))
+      .unzip();
+    OdeSystem {
+      time: time.to_owned(),
+      states,
+      rhs,
+      events: vec![],
+    }
+  }
+
+  /// Adds an event at which `expr` crosses zero, which stops the integration if `terminal`.
+  pub fn with_event(mut self, expr: Expr, terminal: bool) -> Self {
+    self.events.push((expr, terminal));
+    self
+  }
+
+  pub fn time(&self) -> &str {
+    &self.time
+  }
+
+  /// The names of the state variables.
+  pub fn states(&self) -> &[String] {
+    &self.states
+  }
+
+  /// The derivatives of the state variables.
+  pub fn rhs(&self) -> &[Expr] {
+    &self.rhs
+  }
+
+  /// Integrates the system from the state `y0` at time `t0` to time `t1`, with the other
+  /// variables and the functions given by `ctx`. `t1` may be before `t0`.
+  ///
+  /// The derivatives and events have their constants folded and their variables checked once
+  /// before the integration. Whenever an event changes sign during a step, the time at which
+  /// it crosses zero is found on the interpolated solution, and a terminal event ends the
+  /// trajectory there.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(InitialValues)` unless there is an initial value for each state variable,
+  /// `Err(StepSize)` if the adaptive step becomes too small, and `Err(Eval)` if the
+  /// derivatives or events can't be evaluated.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Context, OdeMethod, OdeOptions, OdeSystem};
+  /// let system: OdeSystem = "dh/dt = v; dv/dt = -g".parse().unwrap();
+  /// let system = system.with_event("h".parse().unwrap(), true);
+  /// let options = OdeOptions::new(OdeMethod::Rk4 { step: 0.1 });
+  /// let res = system.solve(0., &[20., 0.], 10., ("g", 10.), &options).unwrap();
+  ///
+  /// assert!(!res.completed);
+  /// assert!((res.events[0].time - 2.).abs() < 1e-10);
+  /// assert!((res.series("h").unwrap()[10] - 15.).abs() < 1e-12);
+  /// ```
+  pub fn solve<C: ContextProvider>(
+    &self,
+    t0: f64,
+    y0: &[f64],
+    t1: f64,
+    ctx: C,
+    options: &OdeOptions,
+  ) -> Result<Trajectory, OdeError> {
+    if y0.len() != self.states.len() {
+      return Err(OdeError::InitialValues {
+        expected: self.states.len(),
+        found: y0.len(),
+      });
+    }
+    let mut compiled = Compiled::new(self, ctx, options)?;
+    let dir = if t1 >= t0 { 1. } else { -1. };
+    let (mut t, mut y) = (t0, y0.to_vec());
+    let mut k = compiled.derivatives(t, &y)?;
+    let mut g = (0..self.events.len())
+      .map(|i| compiled.event(i, t, &y))
+      .collect::<Result<Vec<_>, _>>()?;
+    let mut h = match options.method {
+      OdeMethod::Rk4 { step } if step > 0. && step.is_finite() => step * dir,
+      OdeMethod::Rk4 { .. } => return Err(OdeError::StepSize(t0)),
+      OdeMethod::DormandPrince => compiled.initial_step(&y, &k, (t1 - t0).abs()) * dir,
+    };
+
+    let mut res = Trajectory {
+      names: self.states.clone(),
+      times: vec![t0],
+      states: vec![y.clone()],
+      events: vec![],
+      completed: false,
+      evaluations: 0,
+      segments: vec![],
+    };
+    let mut steps = 0;
+    let mut stopped = false;
+    while (t1 - t) * dir > 0. && steps < options.max_steps && !stopped {
+      steps += 1;
+      let last = (t + h - t1) * dir >= 0.;
+      let step_h = if last { t1 - t } else { h };
+      let step = match options.method {
+        OdeMethod::Rk4 { .. } => compiled.rk4(t, &y, &k, step_h)?,
+        OdeMethod::DormandPrince => {
+          let step = compiled.dormand_prince(t, &y, &k, step_h)?;
+          // the usual step size control, with a safety factor and bounded changes
+          let factor = if step.error.is_finite() {
+            0.9 * step.error.powf(-0.2)
+          } else {
+            0.2
+          };
+          if step.error.is_nan() || step.error > 1. {
+            h = step_h * factor.clamp(0.2, 1.);
+            if h.abs() <= 1e-14 * t.abs().max(1.) {
+              return Err(OdeError::StepSize(t));
+            }
+            continue;
+          }
+          if !last {
+            h = step_h * factor.clamp(0.2, 5.);
+          }
+          step
+        }
+      };
+
+      let segment = Segment {
+        t,
+        h: step_h,
+        coeffs: step.coeffs,
+      };
+      let (mut t_next, mut y_next) = (if last { t1 } else { t + step_h }, step.y);
+      let mut g_next = Vec::with_capacity(g.len());
+      let mut crossings = vec![];
+      for (i, &g0) in g.iter().enumerate() {
+        let g1 = compiled.event(i, t_next, &y_next)?;
+        if g0 != 0. && (g1 == 0. || g1.signum() != g0.signum()) {
+          let theta = if g1 == 0. {
+            1.
+          } else {
+            compiled.locate(i, &segment, g0)?
+          };
+          crossings.push((theta, i));
+        }
+        g_next.push(g1);
+      }
+      crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+      for (theta, i) in crossings {
+        let state = segment.eval(theta);
+        let time = if theta == 1. {
+          t_next
+        } else {
+          t + theta * step_h
+        };
+        res.events.push(OdeEvent {
+          index: i,
+          time,
+          state: state.clone(),
+        });
+        if self.events[i].1 {
+          t_next = time;
+          y_next = state;
+          stopped = true;
+          break;
+        }
+      }
+
+      res.times.push(t_next);
+      res.states.push(y_next.clone());
+      res.segments.push(segment);
+      if !stopped {
+        k = step.k;
+      }
+      t = t_next;
+      y = y_next;
+      g = g_next;
+    }
+    res.completed = !stopped && (t1 - t) * dir <= 0.;
+    res.evaluations = compiled.evaluations;
+    Ok(res)
+  }
+}
+
+impl FromStr for OdeSystem {
+  type Err = OdeError;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut time: Option<String> = None;
+    let mut equations = vec![];
+    let eqtns = s.split(['\n', ';']).filter(|eqtn| !eqtn.trim().is_empty());
+    for (i, eqtn) in eqtns.enumerate() {
+      let (lhs, rhs) = eqtn.split_once('=').ok_or(OdeError::NotDerivative(i))?;
+      let (lhs, rhs): (Expr, Expr) = (lhs.parse()?, rhs.parse()?);
+      let (state, t) = match &lhs[..] {
+        [Token::Var(dy), Token::Var(dt), Token::Binary(op)]
+          if *op == ::expr::tokenizer::Operation::Div
+            && dy.len() > 1
+            && dt.len() > 1
+            && dy.starts_with('d')
+            && dt.starts_with('d') =>
+        {
+          (dy[1..].to_owned(), &dt[1..])
+        }
+        _ => return Err(OdeError::NotDerivative(i)),
+      };
+      if time.get_or_insert_with(|| t.to_owned()) != t {
+        return Err(OdeError::NotDerivative(i));
+      }
+      equations.push((state, rhs));
+    }
+    let (states, rhs) = equations.into_iter().unzip();
+    Ok(OdeSystem {
+      time: time.unwrap_or_else(|| "t".to_owned()),
+      states,
+      rhs,
+      events: vec![],
+    })
+  }
+}
+
+impl Display for OdeSystem {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let eqtns: Vec<_> = self
+      .states
+      .iter()
+      .zip(&self.rhs)
+      .map(|(y, rhs)| format!("d{}/d{} = {}", y, self.time, rhs))
+      .collect();
+    write!(f, "{}", eqtns.join("; "))
+  }
+}
+
+/// Serializes as a string, e.g. `"dx/dt = v; dv/dt = -x"`, without the events.
+#[cfg(feature = "serde")]
+impl Serialize for OdeSystem {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for OdeSystem {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use expr::builtin;
+  use std::f64::consts::PI;
+
+  fn system(s: &str) -> OdeSystem {
+    s.parse().unwrap()
+  }
+
+  fn rk4(step: f64) -> OdeOptions {
+    OdeOptions::new(OdeMethod::Rk4 { step })
+  }
+
+  fn dopri() -> OdeOptions {
+    OdeOptions::new(OdeMethod::DormandPrince)
+  }
+
+  #[test]
+  fn parse() {
+    let sys = system("dx/dt = v;\n\n dv/dt = -k * x ;");
+    assert_eq!(sys.time(), "t");
+    assert_eq!(sys.states(), ["x", "v"]);
+    assert_eq!(sys.rhs()[1], expr!(-k * x));
+    assert_eq!(system(&sys.to_string()), sys);
+    assert_eq!(
+      sys,
+      OdeSystem::new("t", vec![("x", expr!(v)), ("v", expr!(-k * x))])
+    );
+    assert_eq!(system("dy/ds = y").time(), "s");
+
+    let err = |s: &str| s.parse::<OdeSystem>().unwrap_err();
+    assert_eq!(err("dx/dt = v; v = 1"), OdeError::NotDerivative(1));
+    assert_eq!(err("dx/dt = v; dv/ds = 1"), OdeError::NotDerivative(1));
+    assert_eq!(err("dx * dt = v"), OdeError::NotDerivative(0));
+    assert_eq!(err("dx/dt"), OdeError::NotDerivative(0));
+    assert!(matches!(err("dx/dt = v +"), OdeError::Eval(_)));
+  }
+
+  #[test]
+  fn decay() {
+    let sys = system("dy/dt = -y");
+    let exact = (-2f64).exp();
+    let res = sys.solve(0., &[1.], 2., builtin(), &rk4(0.01)).unwrap();
+    assert!(res.completed);
+    assert_eq!(res.times.len(), 201);
+    assert_eq!(*res.times.last().unwrap(), 2.);
+    assert!((res.states[200][0] - exact).abs() < 1e-9);
+    assert_eq!(res.evaluations, 1 + 4 * 200);
+
+    let res = sys.solve(0., &[1.], 2., builtin(), &dopri()).unwrap();
+    assert!(res.completed);
+    assert!((res.states.last().unwrap()[0] - exact).abs() < 1e-8);
+    assert!(res.times.len() < 60);
+
+    // backwards in time
+    let res = sys.solve(2., &[exact], 0., builtin(), &dopri()).unwrap();
+    assert!(res.completed);
+    assert!((res.states.last().unwrap()[0] - 1.).abs() < 1e-8);
+    assert!((res.at(1.).unwrap()[0] - (-1f64).exp()).abs() < 1e-8);
+  }
+
+  #[test]
+  fn dense_output() {
+    let sys = system("dx/dt = v; dv/dt = -x");
+    for options in [dopri(), rk4(0.05)] {
+      let res = sys
+        .solve(0., &[1., 0.], 2. * PI, builtin(), &options)
+        .unwrap();
+      let last = res.states.last().unwrap();
+      assert!((last[0] - 1.).abs() < 1e-6 && last[1].abs() < 1e-6);
+      for &t in &[0., 0.3, PI / 2., 4., 2. * PI] {
+        let state = res.at(t).unwrap();
+        assert!((state[0] - t.cos()).abs() < 1e-6, "{:?} at {}", options, t);
+        assert!((state[1] + t.sin()).abs() < 1e-6);
+      }
+      assert_eq!(res.at(-0.1), None);
+      assert_eq!(res.at(7.), None);
+      let xs = res.series("x").unwrap();
+      assert_eq!(xs.len(), res.times.len());
+      assert_eq!(xs[0], 1.);
+      assert_eq!(res.series("y"), None);
+    }
+  }
+
+  #[test]
+  fn events() {
+    let sys = system("dx/dt = v; dv/dt = -x").with_event(expr!(x), false);
+    let res = sys.solve(0., &[1., 0.], 5., builtin(), &dopri()).unwrap();
+    assert!(res.completed);
+    let times: Vec<_> = res.events.iter().map(|e| e.time).collect();
+    assert_eq!(times.len(), 2);
+    assert!((times[0] - PI / 2.).abs() < 1e-8);
+    assert!((times[1] - 3. * PI / 2.).abs() < 1e-8);
+    assert!((res.events[0].state[1] + 1.).abs() < 1e-8);
+
+    let ctx = (("g", 9.81), builtin());
+    let sys = system("dh/dt = v; dv/dt = -g")
+      .with_event(expr!(v + 5), false)
+      .with_event(expr!(h), true);
+    let res = sys.solve(0., &[10., 0.], 100., ctx, &dopri()).unwrap();
+    assert!(!res.completed);
+    assert_eq!(res.events.len(), 2);
+    assert_eq!(res.events[0].index, 0);
+    assert!((res.events[0].time - 5. / 9.81).abs() < 1e-10);
+    let fall = (20f64 / 9.81).sqrt();
+    assert_eq!(res.events[1].index, 1);
+    assert!((res.events[1].time - fall).abs() < 1e-10);
+    assert_eq!(*res.times.last().unwrap(), res.events[1].time);
+    assert!(res.states.last().unwrap()[0].abs() < 1e-10);
+    assert_eq!(res.at(fall + 0.1), None);
+  }
+
+  #[test]
+  fn errors() {
+    let sys = system("dy/dt = -k * y");
+    assert_eq!(
+      sys.solve(0., &[1., 2.], 1., builtin(), &dopri()),
+      Err(OdeError::InitialValues {
+        expected: 1,
+        found: 2
+      })
+    );
+    assert_eq!(
+      sys.solve(0., &[1.], 1., builtin(), &dopri()),
+      Err(OdeError::Eval(Error::UnknownVariable("k".into())))
+    );
+    assert_eq!(
+      sys.solve(0., &[1.], 1., ("k", 1.), &rk4(0.)),
+      Err(OdeError::StepSize(0.))
+    );
+
+    // the solution 1 / (1 - t) blows up at 1
+    match system("dy/dt = y^2").solve(0., &[1.], 2., builtin(), &dopri()) {
+      Err(OdeError::StepSize(t)) => assert!((t - 1.).abs() < 1e-3, "{}", t),
+      res => panic!("expected a step size error, got {:?}", res),
+    }
+    let res = system("dy/dt = y")
+      .solve(0., &[1.], 100., builtin(), &dopri().with_max_steps(10))
+      .unwrap();
+    assert!(!res.completed);
+    assert!(res.times.len() <= 11);
+  }
+}
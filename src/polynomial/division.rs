@@ -0,0 +1,190 @@
+//! Division with remainder and greatest common divisors of polynomials.
+use super::{Monomial, Polynomial, PolynomialError};
+use std::collections::BTreeMap;
+
+/// The relative size below which a coefficient computed from larger terms is taken as zero.
+const ROUNDING: f64 = 1e-12;
+
+impl Polynomial {
+  /// Divides by `divisor` as polynomials in `var`, returning the quotient and the remainder,
+  /// whose degree in `var` is less than that of the divisor.
+  ///
+  /// The other variables are treated as part of the coefficients, so the leading coefficient of
+  /// the divisor in `var` has to be a number.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(DivisionByZero)` if the divisor is zero, and
+  /// `Err(NonConstantLeadingCoefficient)` if its leading coefficient isn't a number.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Polynomial};
+  /// let poly = |s: &str| Polynomial::from_expr(&s.parse().unwrap()).unwrap();
+  /// let (q, r) = poly("x^2 - y^2 + 1").div_rem(&poly("x - y"), "x").unwrap();
+  /// assert_eq!((q.to_string(), r.to_string()), ("x + y".into(), "1".into()));
+  /// ```
+  pub fn div_rem(
+    &self,
+    divisor: &Polynomial,
+    var: &str,
+  ) -> Result<(Polynomial, Polynomial), PolynomialError> {
+    if divisor.is_zero() {
+      return Err(PolynomialError::DivisionByZero);
+    }
+    let d = divisor.degree_in(var);
+    let lead = divisor
+      .leading_coefficient(var)
+      .as_constant()
+      .ok_or(PolynomialError::NonConstantLeadingCoefficient)?;
+
+    // the size of the terms each coefficient of the remainder was computed from, so that
+    // coefficients that cancel up to rounding can be told from small ones
+    let mut sizes: BTreeMap<Monomial, f64> = self
+      .terms
+      .iter()
+      .map(|(mono, c)| (mono.clone(), c.abs()))
+      .collect();
+    let mut quotient = Polynomial::zero();
+    let mut rem = self.clone();
+    while !rem.is_zero() && rem.degree_in(var) >= d {
+      let k = rem.degree_in(var);
+      let leading: Vec<_> = rem
+        .terms
+        .iter()
+        .filter(|(mono, _)| mono.get(var).cloned().unwrap_or(0) == k)
+        .map(|(mono, &c)| (mono.clone(), c))
+        .collect();
+      for (mono, c) in leading {
+        let size = sizes[&mono] / lead.abs();
+        // the term of the quotient, c / lead * var^(k - d)
+        let mut a = mono;
+        if k > d {
+          a.insert(var.into(), k - d);
+        } else {
+          a.remove(var);
+        }
+        let c = c / lead;
+        quotient.add_term(c, a.clone());
+        for (b, &e) in &divisor.terms {
+          let mono = Polynomial::mono_product(&a, b);
+          *sizes.entry(mono.clone()).or_insert(0.) += size * e.abs();
+          rem.add_term(-c * e, mono);
+        }
+      }
+      // the terms of degree k cancel by the choice of the term, and the others if they are
+      // negligible next to the terms they were computed from
+      rem.terms.retain(|mono, c| {
+        mono.get(var).cloned().unwrap_or(0) < k && c.abs() > ROUNDING * sizes[mono]
+      });
+    }
+    Ok((quotient, rem))
+  }
+
+  /// The greatest common divisor of two polynomials in the same variable, scaled to have
+  /// leading coefficient 1, or zero if both are zero.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotUnivariate)` if the polynomials have more than one variable between them.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Polynomial};
+  /// let poly = |s: &str| Polynomial::from_expr(&s.parse().unwrap()).unwrap();
+  /// let gcd = poly("2 * x^2 - 2").gcd(&poly("x^2 + 2 * x + 1")).unwrap();
+  /// assert_eq!(gcd.to_string(), "x + 1");
+  /// ```
+  pub fn gcd(&self, other: &Polynomial) -> Result<Polynomial, PolynomialError> {
+    let var = match (self.univariate()?, other.univariate()?) {
+      (Some(a), Some(b)) if a != b => return Err(PolynomialError::NotUnivariate),
+      (a, b) => a.or(b).unwrap_or_default(),
+    };
+
+    let (mut a, mut b) = (self.clone(), other.clone());
+    while !b.is_zero() {
+      let (_, rem) = a.div_rem(&b, &var)?;
+      a = b;
+      b = rem;
+    }
+    Ok(a.monic(&var).snapped())
+  }
+
+  /// The polynomial divided by its leading coefficient in `var`, if it is a non-zero number.
+  pub(super) fn monic(&self, var: &str) -> Polynomial {
+    match self.leading_coefficient(var).as_constant() {
+      Some(c) if c != 0. => self * &Polynomial::constant(1. / c),
+      _ => self.clone(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use Expr;
+
+  fn poly(expr: Expr) -> Polynomial {
+    Polynomial::from_expr(&expr).unwrap()
+  }
+
+  #[test]
+  fn division() {
+    let (q, r) = poly(expr!(x ^ 3 - 2 * x ^ 2 - 4))
+      .div_rem(&poly(expr!(x - 3)), "x")
+      .unwrap();
+    assert_eq!((q, r), (poly(expr!(x ^ 2 + x + 3)), poly(expr!(5))));
+
+    let (q, r) = poly(expr!(x ^ 2 * y + x + 1))
+      .div_rem(&poly(expr!(2 * x)), "x")
+      .unwrap();
+    assert_eq!((q, r), (poly(expr!(0.5 * x * y + 0.5)), poly(expr!(1))));
+
+    // dividing by a smaller power leaves everything in the remainder
+    let (q, r) = poly(expr!(x + y))
+      .div_rem(&poly(expr!(x ^ 2)), "x")
+      .unwrap();
+    assert!(q.is_zero());
+    assert_eq!(r, poly(expr!(x + y)));
+
+    let (q, r) = poly(expr!(x ^ 2 + 1e10 * x - 1e-12))
+      .div_rem(&poly(expr!(x)), "x")
+      .unwrap();
+    assert_eq!((q, r), (poly(expr!(x + 1e10)), poly(expr!(-1e-12))));
+
+    assert_eq!(
+      poly(expr!(x)).div_rem(&Polynomial::zero(), "x"),
+      Err(PolynomialError::DivisionByZero)
+    );
+    assert_eq!(
+      poly(expr!(x ^ 2)).div_rem(&poly(expr!(x * y)), "x"),
+      Err(PolynomialError::NonConstantLeadingCoefficient)
+    );
+  }
+
+  #[test]
+  fn gcd() {
+    let gcd = |a: Expr, b: Expr| poly(a).gcd(&poly(b));
+    assert_eq!(
+      gcd(expr!((x - 1) ^ 2 * (x + 2)), expr!((x - 1) * (x + 3))),
+      Ok(poly(expr!(x - 1)))
+    );
+    assert_eq!(gcd(expr!(x ^ 2 + 1), expr!(x - 1)), Ok(poly(expr!(1))));
+    assert_eq!(gcd(expr!(4 * x + 2), expr!(0)), Ok(poly(expr!(x + 0.5))));
+    assert_eq!(gcd(expr!(6), expr!(4)), Ok(poly(expr!(1))));
+    assert_eq!(gcd(expr!(x), expr!(y)), Err(PolynomialError::NotUnivariate));
+
+    // small coefficients are kept, and large ones cancel
+    assert_eq!(gcd(expr!(x ^ 2 - 1e10), expr!(2 * x)), Ok(poly(expr!(1))));
+    assert_eq!(gcd(expr!(x ^ 2 - 1e-12), expr!(x)), Ok(poly(expr!(1))));
+    assert_eq!(
+      gcd(expr!((x - 1e5) * (x + 1)), expr!((x - 1e5) * (x - 2))),
+      Ok(poly(expr!(x - 1e5)))
+    );
+    let g = gcd(expr!((x - 1e-6) * (x - 2e-6)), expr!((x - 1e-6) * (x + 1))).unwrap();
+    assert_eq!(g.degree(), 1);
+    assert!((g.coefficient("x", 0).as_constant().unwrap() + 1e-6).abs() < 1e-15);
+  }
+}
@@ -0,0 +1,343 @@
+//! Square-free and rational root factorisation of polynomials in one variable.
+use super::{Polynomial, PolynomialError};
+use expr::tokenizer::Operation;
+use expr::Token;
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+use Expr;
+
+/// The largest coefficient whose divisors are tried as rational roots.
+const MAX_ROOT_SEARCH: f64 = 1e6;
+
+/// A polynomial as a number times powers of factors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Factors {
+  pub constant: f64,
+  /// The factors and their powers, with no factor repeated.
+  pub factors: Vec<(Polynomial, u32)>,
+}
+
+impl Factors {
+  /// Multiplies the factors out.
+  pub fn product(&self) -> Polynomial {
+    self
+      .factors
+      .iter()
+      .fold(Polynomial::constant(self.constant), |acc, (f, k)| {
+        &acc * &f.pow(*k)
+      })
+  }
+
+  /// The product as an expression, e.g. `2 * (x - 1)^2 * (x + 3)`.
+  pub fn to_expr(&self) -> Expr {
+    use self::Operation::*;
+    use self::Token::*;
+
+    let mut tkns = vec![];
+    let mut factors = 0;
+    if self.constant.abs() != 1. || self.factors.is_empty() {
+      tkns.push(Number(self.constant));
+      factors += 1;
+    }
+    for (f, k) in &self.factors {
+      tkns.extend(f.to_expr().0);
+      if *k > 1 {
+        tkns.extend(vec![Number(*k as f64), Binary(Pow)]);
+      }
+      // a constant of -1 negates the first factor
+      if factors == 0 && self.constant == -1. {
+        tkns.push(Unary(Minus));
+      }
+      factors += 1;
+      if factors > 1 {
+        tkns.push(Binary(Times));
+      }
+    }
+    Expr(tkns)
+  }
+
+  /// Adds a factor, merging it with an equal factor.
+  fn push(&mut self, f: Polynomial, k: u32) {
+    match self.factors.iter_mut().find(|(g, _)| *g == f) {
+      Some((_, j)) => *j += k,
+      None => self.factors.push((f, k)),
+    }
+  }
+}
+
+impl Display for Factors {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    self.to_expr().fmt(f)
+  }
+}
+
+/// The positive divisors of `n`, which has to be a positive integer.
+fn divisors(n: f64) -> Vec<f64> {
+  let n = n as u64;
+  let mut res = vec![];
+  let mut i = 1;
+  while i * i <= n {
+    if n.is_multiple_of(i) {
+      res.push(i as f64);
+      if i * i != n {
+        res.push((n / i) as f64);
+      }
+    }
+    i += 1;
+  }
+  res
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+  if b == 0 {
+    a
+  } else {
+    gcd(b, a % b)
+  }
+}
+
+impl Polynomial {
+  /// The value at `x` of a polynomial in `var` only.
+  pub(crate) fn eval_at(&self, var: &str, x: f64) -> f64 {
+    self
+      .coefficients(var)
+      .iter()
+      .rev()
+      .fold(0., |acc, c| acc * x + c.as_constant().unwrap_or(0.))
+  }
+
+  /// Splits a polynomial in one variable into a number times powers of square-free factors,
+  /// i.e. factors without repeated roots, each with leading coefficient 1 (Yun's algorithm).
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotUnivariate)` if the polynomial has more than one variable.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Polynomial};
+  /// let poly = Polynomial::from_expr(&"2 * x^3 - 6 * x + 4".parse().unwrap()).unwrap();
+  /// let factors = poly.square_free().unwrap();
+  /// assert_eq!(factors.to_string(), "2 * (x + 2) * (x - 1)^2");
+  /// ```
+  pub fn square_free(&self) -> Result<Factors, PolynomialError> {
+    let var = match self.univariate()? {
+      Some(var) => var,
+      None => {
+        return Ok(Factors {
+          constant: self.as_constant().unwrap_or(0.),
+          factors: vec![],
+        })
+      }
+    };
+    let constant = self.leading_coefficient(&var).as_constant().unwrap_or(1.);
+    let f = self.monic(&var);
+    let mut res = Factors {
+      constant,
+      factors: vec![],
+    };
+
+    let df = f.derivative(&var);
+    let a = f.gcd(&df)?;
+    let mut b = f.div_rem(&a, &var)?.0;
+    let c = df.div_rem(&a, &var)?.0;
+    let mut d = &c - &b.derivative(&var);
+    let mut k = 1;
+    while b.degree_in(&var) > 0 {
+      let a = b.gcd(&d)?;
+      let c = d.div_rem(&a, &var)?.0;
+      b = b.div_rem(&a, &var)?.0;
+      d = &c - &b.derivative(&var);
+      if a.degree_in(&var) > 0 {
+        res.push(a.snapped(), k);
+      }
+      k += 1;
+    }
+    Ok(res)
+  }
+
+  /// Factorises a polynomial in one variable into square-free factors, splitting off a linear
+  /// factor `q * x - p` for every rational root `p / q`.
+  ///
+  /// Rational roots are only looked for in factors with integer coefficients, up to a scale.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotUnivariate)` if the polynomial has more than one variable.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Expr;
+  /// let expr: Expr = "2 * x^4 + x^3 - 2 * x - 1".parse().unwrap();
+  /// assert_eq!(expr.factor().unwrap().to_string(), "(2 * x + 1) * (x - 1) * (x^2 + x + 1)");
+  /// ```
+  pub fn factor(&self) -> Result<Factors, PolynomialError> {
+    let square_free = self.square_free()?;
+    let mut res = Factors {
+      constant: square_free.constant,
+      factors: vec![],
+    };
+    for (f, k) in square_free.factors {
+      let var = f.univariate()?.unwrap_or_default();
+      let (roots, rest) = f.rational_roots(&var);
+      for (p, q) in roots {
+        let linear =
+          &(&Polynomial::var(var.clone()) * &Polynomial::constant(q)) - &Polynomial::constant(p);
+        res.constant /= q.powi(k as i32);
+        res.push(linear, k);
+      }
+      if rest.degree_in(&var) > 0 {
+        res.push(rest, k);
+      }
+    }
+    // linear factors first, by their roots
+    res.factors.sort_by(|(f, _), (g, _)| {
+      let key = |f: &Polynomial| {
+        let root = match f.coefficients(&f.vars().into_iter().next().unwrap_or_default())[..] {
+          [ref c0, ref c1] => -c0.as_constant().unwrap_or(0.) / c1.as_constant().unwrap_or(1.),
+          _ => 0.,
+        };
+        (f.degree(), root)
+      };
+      key(f).partial_cmp(&key(g)).unwrap_or(Ordering::Equal)
+    });
+    Ok(res)
+  }
+
+  /// The rational roots `p / q` of a square-free polynomial with leading coefficient 1, as
+  /// pairs `(p, q)` in lowest terms, and what is left after dividing out the roots.
+  fn rational_roots(&self, var: &str) -> (Vec<(f64, f64)>, Polynomial) {
+    let mut roots = vec![];
+    let mut rest = self.clone();
+
+    // the coefficients scaled to integers, if they are rationals with small denominators
+    let scale = (1..=MAX_DENOMINATOR).map(|s| s as f64).find(|s| {
+      rest
+        .terms
+        .values()
+        .all(|c| (c * s).round() != 0. && ((c * s).round() - c * s).abs() < 1e-9 * (c * s).abs())
+    });
+    let scale = match scale {
+      Some(scale) => scale,
+      None => return (roots, rest),
+    };
+
+    if rest.coefficient(var, 0).is_zero() {
+      roots.push((0., 1.));
+      rest = rest.div_rem(&Polynomial::var(var), var).unwrap().0;
+    }
+    let first = (rest.coefficient(var, 0).as_constant().unwrap_or(0.) * scale)
+      .round()
+      .abs();
+    let last = (rest.leading_coefficient(var).as_constant().unwrap_or(1.) * scale)
+      .round()
+      .abs();
+    if first == 0. || first > MAX_ROOT_SEARCH || last > MAX_ROOT_SEARCH {
+      return (roots, rest);
+    }
+
+    for p in divisors(first) {
+      for q in divisors(last) {
+        if gcd(p as u64, q as u64) != 1 {
+          continue;
+        }
+        for &p in &[p, -p] {
+          let x = p / q;
+          // small next to the largest term at x
+          let size = rest.coefficients(var).iter().rev().fold(0., |acc, c| {
+            acc * x.abs() + c.as_constant().unwrap_or(0.).abs()
+          });
+          if rest.degree_in(var) > 0 && rest.eval_at(var, x).abs() <= 1e-9 * size {
+            let linear = &Polynomial::var(var) - &Polynomial::constant(x);
+            rest = rest.div_rem(&linear, var).unwrap().0;
+            roots.push((p, q));
+          }
+        }
+      }
+    }
+    (roots, rest)
+  }
+}
+
+/// The largest denominator of the coefficients of polynomials whose rational roots are found.
+const MAX_DENOMINATOR: u32 = 1000;
+
+impl Expr {
+  /// Factorises the expression as a polynomial in one variable, see
+  /// [`Polynomial::factor`](struct.Polynomial.html#method.factor).
+  pub fn factor(&self) -> Result<Expr, PolynomialError> {
+    Ok(Polynomial::from_expr(self)?.factor()?.to_expr())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn factor(expr: Expr) -> String {
+    let poly = Polynomial::from_expr(&expr).unwrap();
+    let factors = poly.factor().unwrap();
+    // the factors multiply back to the polynomial, up to rounding
+    let diff = &factors.product() - &poly;
+    let norm = |p: &Polynomial| p.terms.values().fold(0., |max: f64, c| c.abs().max(max));
+    assert!(norm(&diff) < 1e-9 * norm(&poly), "{} != {}", factors, poly);
+    factors.to_string()
+  }
+
+  #[test]
+  fn square_free() {
+    let poly = Polynomial::from_expr(&expr!((x - 1) ^ 3 * (x + 2) ^ 2 * (x ^ 2 + 1))).unwrap();
+    let factors = poly.square_free().unwrap();
+    assert_eq!(factors.factors.len(), 3);
+    assert_eq!(factors.to_string(), "(x^2 + 1) * (x + 2)^2 * (x - 1)^3");
+
+    let poly = Polynomial::from_expr(&expr!(x * y)).unwrap();
+    assert_eq!(poly.square_free(), Err(PolynomialError::NotUnivariate));
+  }
+
+  #[test]
+  fn rational_roots() {
+    assert_eq!(factor(expr!(x ^ 2 - 5 * x + 6)), "(x - 2) * (x - 3)");
+    assert_eq!(
+      factor(expr!(6 * x ^ 2 + x - 1)),
+      "(2 * x + 1) * (3 * x - 1)"
+    );
+    assert_eq!(factor(expr!(x ^ 3 - x)), "(x + 1) * x * (x - 1)");
+    assert_eq!(
+      factor(expr!(-4 * (x - 2) ^ 2 * (x ^ 2 - 2))),
+      "-4 * (x - 2)^2 * (x^2 - 2)"
+    );
+    assert_eq!(
+      factor(expr!(x ^ 2 / 2 - 1 / 8)),
+      "0.125 * (2 * x + 1) * (2 * x - 1)"
+    );
+    assert_eq!(factor(expr!(7)), "7");
+  }
+
+  #[test]
+  fn badly_scaled() {
+    assert_eq!(factor(expr!(x ^ 2 - 1e10)), "x^2 - 10000000000");
+    assert_eq!(
+      factor(expr!(x ^ 2 - 1e30)),
+      "x^2 - 1000000000000000000000000000000"
+    );
+    assert_eq!(factor(expr!(x ^ 2 - 1e-12)), "x^2 - 0.000000000001");
+    assert_eq!(
+      factor(expr!((x - 1e-6) * (x - 2e-6))),
+      "x^2 - 0.000003 * x + 0.000000000002"
+    );
+    assert_eq!(
+      factor(expr!((x - 1e5) ^ 2 * (x + 3))),
+      "(x + 3) * (x - 100000)^2"
+    );
+
+    let poly = Polynomial::from_expr(&expr!((x - 1e-6) ^ 2 * (x - 1))).unwrap();
+    let factors = poly.square_free().unwrap().factors;
+    assert_eq!(factors.len(), 2);
+    assert_eq!(factors[1].1, 2);
+    let root = -factors[1].0.coefficient("x", 0).as_constant().unwrap();
+    assert!((root - 1e-6).abs() < 1e-15);
+  }
+}
@@ -0,0 +1,783 @@
+//! Multivariate polynomials with `f64` coefficients, and conversions to and from expressions.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Mul, Neg, Sub};
+
+use expr::tokenizer::Operation;
+use expr::{builtin, Token};
+use std::convert::TryFrom;
+use Expr;
+
+mod division;
+mod factor;
+mod solve;
+
+pub use self::factor::Factors;
+
+/// The powers of the variables of a term, without the variables with power 0.
+type Monomial = BTreeMap<String, u32>;
+
+/// The largest degree of the polynomials converted from expressions.
+const MAX_DEGREE: u32 = 1000;
+
+/// An error converting an expression to a polynomial, or in an operation on polynomials.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolynomialError {
+  /// A subexpression that isn't a polynomial, e.g. `sin(x)`, `x^y` or `1 / x`.
+  NotPolynomial(Expr),
+  /// A subexpression whose degree is above 1000, e.g. `x^1e9`.
+  DegreeTooLarge(Expr),
+  DivisionByZero,
+  /// The leading coefficient of the divisor in the variable divided by isn't a number.
+  NonConstantLeadingCoefficient,
+  /// An operation only defined for polynomials in at most one variable.
+  NotUnivariate,
+  /// The equation holds for every value of the variable solved for.
+  Identity,
+  /// A polynomial of this degree that can't be solved in closed form.
+  NoClosedForm(u32),
+}
+
+impl Display for PolynomialError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      PolynomialError::NotPolynomial(expr) => write!(f, "`{}` is not a polynomial.", expr),
+      PolynomialError::DegreeTooLarge(expr) => {
+        write!(f, "The degree of `{}` is above {}.", expr, MAX_DEGREE)
+      }
+      PolynomialError::DivisionByZero => write!(f, "Division by the zero polynomial."),
+      PolynomialError::NonConstantLeadingCoefficient => {
+        write!(f, "The leading coefficient of the divisor is not a number.")
+      }
+      PolynomialError::NotUnivariate => write!(f, "The polynomial has more than one variable."),
+      PolynomialError::Identity => write!(f, "The equation holds for every value."),
+      PolynomialError::NoClosedForm(d) => {
+        write!(
+          f,
+          "No closed form solution of a polynomial of degree {}.",
+          d
+        )
+      }
+    }
+  }
+}
+
+/// A polynomial in any number of variables, with `f64` coefficients.
+///
+/// Polynomials are converted from expressions made of numbers, variables, `+`, `-`, `*`, and
+/// `^` with constant non-negative integer exponents. Subexpressions without variables, e.g.
+/// `sqrt(2)` or `1 / 3`, are evaluated to coefficients.
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::{Expr, Polynomial};
+/// let expr: Expr = "(x + y)^2 - 2 * x * y".parse().unwrap();
+/// let poly = Polynomial::from_expr(&expr).unwrap();
+/// assert_eq!(poly.degree(), 2);
+/// assert_eq!(poly.to_string(), "x^2 + y^2");
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polynomial {
+  /// The non-zero coefficient of each monomial.
+  terms: BTreeMap<Monomial, f64>,
+}
+
+impl Polynomial {
+  /// The zero polynomial.
+  pub fn zero() -> Self {
+    Polynomial::default()
+  }
+
+  pub fn constant(c: f64) -> Self {
+    Polynomial::term(c, Monomial::new())
+  }
+
+  /// The polynomial `var`.
+  pub fn var<S: Into<String>>(var: S) -> Self {
+    let mut mono = Monomial::new();
+    mono.insert(var.into(), 1);
+    Polynomial::term(1., mono)
+  }
+
+  fn term(c: f64, mono: Monomial) -> Self {
+    let mut poly = Polynomial::zero();
+    poly.add_term(c, mono);
+    poly
+  }
+
+  fn add_term(&mut self, c: f64, mono: Monomial) {
+    let sum = self.terms.get(&mono).map_or(c, |prev| prev + c);
+    if sum == 0. {
+      self.terms.remove(&mono);
+    } else {
+      self.terms.insert(mono, sum);
+    }
+  }
+
+  /// Rounds the coefficients that are non-zero integers up to rounding errors.
+  fn snapped(mut self) -> Self {
+    for c in self.terms.values_mut() {
+      if c.round() != 0. && (c.round() - *c).abs() < 1e-9 * c.abs() {
+        *c = c.round();
+      }
+    }
+    self
+  }
+
+  /// The monomial of the product of the terms with monomials `a` and `b`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a power doesn't fit in a `u32`.
+  fn mono_product(a: &Monomial, b: &Monomial) -> Monomial {
+    let mut mono = a.clone();
+    for (var, &k) in b {
+      let power = mono.entry(var.clone()).or_insert(0);
+      *power = power
+        .checked_add(k)
+        .expect("power of a polynomial overflowed");
+    }
+    mono
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.terms.is_empty()
+  }
+
+  /// The value of the polynomial if it has no variables.
+  pub fn as_constant(&self) -> Option<f64> {
+    match self.terms.len() {
+      0 => Some(0.),
+      1 => self.terms.get(&Monomial::new()).cloned(),
+      _ => None,
+    }
+  }
+
+  /// The variables of the polynomial, in alphabetical order.
+  pub fn vars(&self) -> BTreeSet<String> {
+    self
+      .terms
+      .keys()
+      .flat_map(|mono| mono.keys().cloned())
+      .collect()
+  }
+
+  /// The single variable of the polynomial, `None` for a constant polynomial.
+  fn univariate(&self) -> Result<Option<String>, PolynomialError> {
+    let mut vars = self.vars().into_iter();
+    match (vars.next(), vars.next()) {
+      (var, None) => Ok(var),
+      _ => Err(PolynomialError::NotUnivariate),
+    }
+  }
+
+  /// The total degree, i.e. the largest sum of the powers in a term, which is 0 for the zero
+  /// polynomial.
+  pub fn degree(&self) -> u32 {
+    self
+      .terms
+      .keys()
+      .map(|mono| mono.values().sum())
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// The largest power of `var`.
+  pub fn degree_in(&self, var: &str) -> u32 {
+    self
+      .terms
+      .keys()
+      .map(|mono| mono.get(var).cloned().unwrap_or(0))
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// The coefficient of `var^k`, as a polynomial in the other variables.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Polynomial};
+  /// let poly = Polynomial::from_expr(&"3 * x^2 * y + x^2 - x".parse().unwrap()).unwrap();
+  /// assert_eq!(poly.coefficient("x", 2).to_string(), "3 * y + 1");
+  /// assert_eq!(poly.coefficient("x", 1).as_constant(), Some(-1.));
+  /// ```
+  pub fn coefficient(&self, var: &str, k: u32) -> Polynomial {
+    let mut res = Polynomial::zero();
+    for (mono, &c) in &self.terms {
+      if mono.get(var).cloned().unwrap_or(0) == k {
+        let mut mono = mono.clone();
+        mono.remove(var);
+        res.add_term(c, mono);
+      }
+    }
+    res
+  }
+
+  /// The coefficients of the powers of `var`, from `var^0` up to the degree in `var`.
+  pub fn coefficients(&self, var: &str) -> Vec<Polynomial> {
+    (0..=self.degree_in(var))
+      .map(|k| self.coefficient(var, k))
+      .collect()
+  }
+
+  /// The coefficient of the largest power of `var`.
+  pub fn leading_coefficient(&self, var: &str) -> Polynomial {
+    self.coefficient(var, self.degree_in(var))
+  }
+
+  /// The `n`-th power, by repeated squaring.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a power of a variable in the result doesn't fit in a `u32`.
+  pub fn pow(&self, n: u32) -> Polynomial {
+    let mut res = Polynomial::constant(1.);
+    let mut square = self.clone();
+    let mut n = n;
+    while n > 0 {
+      if n & 1 == 1 {
+        res = &res * &square;
+      }
+      n >>= 1;
+      if n > 0 {
+        square = &square * &square;
+      }
+    }
+    res
+  }
+
+  /// The derivative with respect to `var`.
+  pub fn derivative(&self, var: &str) -> Polynomial {
+    let mut res = Polynomial::zero();
+    for (mono, &c) in &self.terms {
+      if let Some(&k) = mono.get(var) {
+        let mut mono = mono.clone();
+        if k == 1 {
+          mono.remove(var);
+        } else {
+          mono.insert(var.into(), k - 1);
+        }
+        res.add_term(c * k as f64, mono);
+      }
+    }
+    res
+  }
+
+  /// Converts an expression to a polynomial.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotPolynomial)` with the first subexpression that isn't a polynomial, and
+  /// `Err(DegreeTooLarge)` with the first whose degree is above 1000.
+  pub fn from_expr(expr: &Expr) -> Result<Polynomial, PolynomialError> {
+    use self::Operation::*;
+    use self::Token::*;
+
+    let ptrs = expr.start_pointers();
+    let mut stack: Vec<Polynomial> = vec![];
+    for (i, tkn) in expr.iter().enumerate() {
+      let args = stack.split_off(stack.len() - Expr::no_children(tkn));
+      let not_polynomial = || PolynomialError::NotPolynomial(Expr(expr.0[ptrs[i]..=i].to_vec()));
+      let too_large = || PolynomialError::DegreeTooLarge(Expr(expr.0[ptrs[i]..=i].to_vec()));
+
+      let consts: Option<Vec<f64>> = args.iter().map(Polynomial::as_constant).collect();
+      let poly = match (tkn, &args[..]) {
+        (Number(x), _) => Polynomial::constant(*x),
+        (Var(name), _) => Polynomial::var(name.clone()),
+        (Binary(Plus), [a, b]) => a + b,
+        (Binary(Minus), [a, b]) => a - b,
+        (Binary(Times), [a, b]) => a * b,
+        (Binary(Div), [a, b]) if b.as_constant().is_some() => match b.as_constant() {
+          Some(c) if c != 0. => a * &Polynomial::constant(1. / c),
+          _ => return Err(PolynomialError::DivisionByZero),
+        },
+        (Binary(Pow), [a, b]) if consts.is_none() => match b.as_constant() {
+          Some(n) if n >= 0. && n.fract() == 0. => {
+            if a.degree() as f64 * n > MAX_DEGREE as f64 {
+              return Err(too_large());
+            }
+            a.pow(n as u32)
+          }
+          _ => return Err(not_polynomial()),
+        },
+        (Unary(Plus), [a]) => a.clone(),
+        (Unary(Minus), [a]) => -a,
+        // anything else has to be a constant, evaluated with the built-in functions
+        _ => {
+          let consts = consts.ok_or_else(not_polynomial)?;
+          let mut tkns: Vec<_> = consts.into_iter().map(Number).collect();
+          tkns.push(tkn.clone());
+          let value = Expr(tkns)
+            .eval_with_context(builtin())
+            .map_err(|_| not_polynomial())?;
+          Polynomial::constant(value)
+        }
+      };
+      if poly.degree() > MAX_DEGREE {
+        return Err(too_large());
+      }
+      stack.push(poly);
+    }
+    stack
+      .pop()
+      .ok_or(PolynomialError::NotPolynomial(expr.clone()))
+  }
+
+  /// Converts an expression to a fraction of polynomials, as a numerator and a denominator.
+  ///
+  /// As for [`from_expr`](#method.from_expr), but divisions by polynomials and negative integer
+  /// exponents are allowed too. Common factors aren't cancelled, so that the roots of the
+  /// denominator are still those where the expression is undefined, and the denominator is 1 if
+  /// the expression is a polynomial.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotPolynomial)` with the first subexpression that isn't a fraction of
+  /// polynomials, `Err(DegreeTooLarge)` with the first whose numerator or denominator has a
+  /// degree above 1000, and `Err(DivisionByZero)` for a division by the zero polynomial.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Polynomial};
+  /// let expr: Expr = "1 / x - 1 / (x + 1)".parse().unwrap();
+  /// let (num, den) = Polynomial::fraction_from_expr(&expr).unwrap();
+  /// assert_eq!(num.to_string(), "1");
+  /// assert_eq!(den.to_string(), "x^2 + x");
+  /// ```
+  pub fn fraction_from_expr(expr: &Expr) -> Result<(Polynomial, Polynomial), PolynomialError> {
+    use self::Operation::*;
+    use self::Token::*;
+
+    let one = Polynomial::constant(1.);
+    let ptrs = expr.start_pointers();
+    let mut stack: Vec<(Polynomial, Polynomial)> = vec![];
+    for (i, tkn) in expr.iter().enumerate() {
+      let args = stack.split_off(stack.len() - Expr::no_children(tkn));
+      let not_polynomial = || PolynomialError::NotPolynomial(Expr(expr.0[ptrs[i]..=i].to_vec()));
+      let too_large = || PolynomialError::DegreeTooLarge(Expr(expr.0[ptrs[i]..=i].to_vec()));
+
+      let consts: Option<Vec<f64>> = args
+        .iter()
+        .map(|(n, d)| Some(n.as_constant()? / d.as_constant()?))
+        .collect();
+      let (num, den) = match (tkn, &args[..]) {
+        (Number(x), _) => (Polynomial::constant(*x), one.clone()),
+        (Var(name), _) => (Polynomial::var(name.clone()), one.clone()),
+        (Binary(op @ Plus), [(an, ad), (bn, bd)]) | (Binary(op @ Minus), [(an, ad), (bn, bd)]) => {
+          let (an, bn, den) = if ad == bd {
+            (an.clone(), bn.clone(), ad.clone())
+          } else {
+            (an * bd, bn * ad, ad * bd)
+          };
+          match op {
+            Plus => (&an + &bn, den),
+            _ => (&an - &bn, den),
+          }
+        }
+        (Binary(Times), [(an, ad), (bn, bd)]) => (an * bn, ad * bd),
+        (Binary(Div), [(an, ad), (bn, bd)]) => {
+          if bn.is_zero() {
+            return Err(PolynomialError::DivisionByZero);
+          }
+          (an * bd, ad * bn)
+        }
+        (Binary(Pow), [(n, d), (bn, bd)]) if consts.is_none() => {
+          let k = match (bn.as_constant(), bd.as_constant()) {
+            (Some(k), Some(_)) if k.fract() == 0. => k,
+            _ => return Err(not_polynomial()),
+          };
+          if n.degree().max(d.degree()) as f64 * k.abs() > MAX_DEGREE as f64 {
+            return Err(too_large());
+          }
+          if k >= 0. {
+            (n.pow(k as u32), d.pow(k as u32))
+          } else if n.is_zero() {
+            return Err(PolynomialError::DivisionByZero);
+          } else {
+            (d.pow(-k as u32), n.pow(-k as u32))
+          }
+        }
+        (Unary(Plus), [a]) => a.clone(),
+        (Unary(Minus), [(n, d)]) => (-n, d.clone()),
+        // anything else has to be a constant, evaluated with the built-in functions
+        _ => {
+          let consts = consts.ok_or_else(not_polynomial)?;
+          let mut tkns: Vec<_> = consts.into_iter().map(Number).collect();
+          tkns.push(tkn.clone());
+          let value = Expr(tkns)
+            .eval_with_context(builtin())
+            .map_err(|_| not_polynomial())?;
+          (Polynomial::constant(value), one.clone())
+        }
+      };
+      if num.degree().max(den.degree()) > MAX_DEGREE {
+        return Err(too_large());
+      }
+      // keep constant denominators in the numerator
+      let frac = match den.as_constant() {
+        Some(c) if c != 1. => (&num * &Polynomial::constant(1. / c), one.clone()),
+        _ => (num, den),
+      };
+      stack.push(frac);
+    }
+    stack
+      .pop()
+      .ok_or(PolynomialError::NotPolynomial(expr.clone()))
+  }
+
+  /// The terms with their monomials, in graded lexicographic order, i.e. by total degree and
+  /// then by the powers of the variables in alphabetical order, highest first.
+  pub(crate) fn sorted_terms(&self) -> Vec<(&Monomial, f64)> {
+    let vars = self.vars();
+    let key = |mono: &Monomial| {
+      let powers: Vec<u32> = vars
+        .iter()
+        .map(|var| mono.get(var).cloned().unwrap_or(0))
+        .collect();
+      (mono.values().sum::<u32>(), powers)
+    };
+    let mut terms: Vec<_> = self.terms.iter().map(|(mono, &c)| (mono, c)).collect();
+    terms.sort_by_key(|(mono, _)| std::cmp::Reverse(key(mono)));
+    terms
+  }
+
+  /// Converts the polynomial to an expanded expression, with the terms in graded lexicographic
+  /// order, e.g. `x^2 + 2 * x * y - 3`.
+  pub fn to_expr(&self) -> Expr {
+    let mut tkns = vec![];
+    for (mono, c) in self.sorted_terms() {
+      push_term(&mut tkns, term_rpn(c.abs(), mono), c < 0.);
+    }
+    if tkns.is_empty() {
+      tkns.push(Token::Number(0.));
+    }
+    Expr(tkns)
+  }
+
+  /// Converts the polynomial to an expression collecting the powers of `var`, highest first,
+  /// with the coefficients expanded in the other variables.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Polynomial};
+  /// let poly = Polynomial::from_expr(&"(x + y + 1)^2".parse().unwrap()).unwrap();
+  /// assert_eq!(poly.collect("x").to_string(), "x^2 + (2 * y + 2) * x + y^2 + 2 * y + 1");
+  /// ```
+  pub fn collect(&self, var: &str) -> Expr {
+    use self::Operation::*;
+    use self::Token::*;
+
+    let mut tkns = vec![];
+    for (k, coef) in self.coefficients(var).into_iter().enumerate().rev() {
+      if k == 0 {
+        for (mono, c) in coef.sorted_terms() {
+          push_term(&mut tkns, term_rpn(c.abs(), mono), c < 0.);
+        }
+        continue;
+      }
+
+      match coef.sorted_terms()[..] {
+        [] => (),
+        // a single term is merged with the power of `var`
+        [(mono, c)] => {
+          let mut mono = mono.clone();
+          mono.insert(var.into(), k as u32);
+          push_term(&mut tkns, term_rpn(c.abs(), &mono), c < 0.);
+        }
+        // a sum is added as a whole, rather than term by term
+        _ => {
+          let mut term = coef.to_expr().0;
+          term.push(Var(var.into()));
+          if k > 1 {
+            term.extend(vec![Number(k as f64), Binary(Pow)]);
+          }
+          term.push(Binary(Times));
+          push_term(&mut tkns, term, false);
+        }
+      }
+    }
+    if tkns.is_empty() {
+      tkns.push(Number(0.));
+    }
+    Expr(tkns)
+  }
+}
+
+/// The term `c * x^i * y^j * ...` in RPN, leaving out a coefficient of 1.
+fn term_rpn(c: f64, mono: &Monomial) -> Vec<Token> {
+  use self::Operation::*;
+  use self::Token::*;
+
+  let mut tkns = vec![];
+  let mut factors = 0;
+  if c != 1. || mono.is_empty() {
+    tkns.push(Number(c));
+    factors += 1;
+  }
+  for (var, &k) in mono {
+    tkns.push(Var(var.clone()));
+    if k > 1 {
+      tkns.extend(vec![Number(k as f64), Binary(Pow)]);
+    }
+    factors += 1;
+    if factors > 1 {
+      tkns.push(Binary(Times));
+    }
+  }
+  tkns
+}
+
+/// Adds or subtracts a term to the sum in `tkns`, or starts the sum if it is empty.
+fn push_term(tkns: &mut Vec<Token>, term: Vec<Token>, negative: bool) {
+  use self::Operation::*;
+  use self::Token::*;
+
+  let first = tkns.is_empty();
+  tkns.extend(term);
+  match (first, negative) {
+    (true, true) => tkns.push(Unary(Minus)),
+    (true, false) => (),
+    (false, true) => tkns.push(Binary(Minus)),
+    (false, false) => tkns.push(Binary(Plus)),
+  }
+}
+
+impl TryFrom<&Expr> for Polynomial {
+  type Error = PolynomialError;
+
+  fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+    Polynomial::from_expr(expr)
+  }
+}
+
+impl From<Polynomial> for Expr {
+  fn from(poly: Polynomial) -> Expr {
+    poly.to_expr()
+  }
+}
+
+/// Formats the polynomial as its expanded expression.
+impl Display for Polynomial {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    self.to_expr().fmt(f)
+  }
+}
+
+impl Add for &Polynomial {
+  type Output = Polynomial;
+
+  fn add(self, other: &Polynomial) -> Polynomial {
+    let mut res = self.clone();
+    for (mono, &c) in &other.terms {
+      res.add_term(c, mono.clone());
+    }
+    res
+  }
+}
+
+impl Sub for &Polynomial {
+  type Output = Polynomial;
+
+  fn sub(self, other: &Polynomial) -> Polynomial {
+    self + &-other
+  }
+}
+
+impl Mul for &Polynomial {
+  type Output = Polynomial;
+
+  fn mul(self, other: &Polynomial) -> Polynomial {
+    let mut res = Polynomial::zero();
+    for (a, &c) in &self.terms {
+      for (b, &d) in &other.terms {
+        res.add_term(c * d, Polynomial::mono_product(a, b));
+      }
+    }
+    res
+  }
+}
+
+impl Neg for &Polynomial {
+  type Output = Polynomial;
+
+  fn neg(self) -> Polynomial {
+    Polynomial {
+      terms: self
+        .terms
+        .iter()
+        .map(|(mono, &c)| (mono.clone(), -c))
+        .collect(),
+    }
+  }
+}
+
+macro_rules! owned_ops {
+  ($($trait:ident $method:ident),*) => {$(
+    impl $trait for Polynomial {
+      type Output = Polynomial;
+
+      fn $method(self, other: Polynomial) -> Polynomial {
+        (&self).$method(&other)
+      }
+    }
+  )*};
+}
+
+owned_ops!(Add add, Sub sub, Mul mul);
+
+impl Neg for Polynomial {
+  type Output = Polynomial;
+
+  fn neg(self) -> Polynomial {
+    -&self
+  }
+}
+
+impl Expr {
+  /// Expands the expression as a polynomial, see [`Polynomial::to_expr`].
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Expr;
+  /// let expr: Expr = "(x - 1) * (x + 1) * 2".parse().unwrap();
+  /// assert_eq!(expr.expand().unwrap().to_string(), "2 * x^2 - 2");
+  /// ```
+  ///
+  /// [`Polynomial::to_expr`]: struct.Polynomial.html#method.to_expr
+  pub fn expand(&self) -> Result<Expr, PolynomialError> {
+    Ok(Polynomial::from_expr(self)?.to_expr())
+  }
+
+  /// Collects the powers of `var` in the expression as a polynomial, see
+  /// [`Polynomial::collect`](struct.Polynomial.html#method.collect).
+  pub fn collect(&self, var: &str) -> Result<Expr, PolynomialError> {
+    Ok(Polynomial::from_expr(self)?.collect(var))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn poly(expr: Expr) -> Polynomial {
+    Polynomial::from_expr(&expr).unwrap()
+  }
+
+  #[test]
+  fn from_expr() {
+    assert_eq!(
+      poly(expr!((x + 1) ^ 2)),
+      &(&Polynomial::var("x") * &Polynomial::var("x"))
+        + &(&Polynomial::var("x") * &Polynomial::constant(2.))
+        + Polynomial::constant(1.)
+    );
+    assert!(poly(expr!(x - x)).is_zero());
+    assert_eq!(
+      poly(expr!(x / 2 + sqrt(4) * 3!)).to_string(),
+      "0.5 * x + 12"
+    );
+    assert_eq!(poly(expr!(x ^ (1 + 1) / 1)).degree(), 2);
+
+    let err = |expr: Expr| Polynomial::from_expr(&expr).unwrap_err();
+    assert_eq!(
+      err(expr!(2 * sin(x) + 1)),
+      PolynomialError::NotPolynomial(expr!(sin(x)))
+    );
+    assert_eq!(
+      err(expr!(1 / x)),
+      PolynomialError::NotPolynomial(expr!(1 / x))
+    );
+    assert_eq!(
+      err(expr!(x ^ 0.5)),
+      PolynomialError::NotPolynomial(expr!(x ^ 0.5))
+    );
+    assert_eq!(
+      err(expr!(x ^ y)),
+      PolynomialError::NotPolynomial(expr!(x ^ y))
+    );
+    assert_eq!(err(expr!(x / (y - y))), PolynomialError::DivisionByZero);
+    assert_eq!(
+      err(expr!(x ^ 1e9)),
+      PolynomialError::DegreeTooLarge(expr!(x ^ 1e9))
+    );
+    assert_eq!(
+      err(expr!((x ^ 600) * (x ^ 600 + y))),
+      PolynomialError::DegreeTooLarge(expr!((x ^ 600) * (x ^ 600 + y)))
+    );
+  }
+
+  #[test]
+  fn powers() {
+    let p = poly(expr!(x + 1));
+    assert_eq!(p.pow(0), Polynomial::constant(1.));
+    assert_eq!(
+      p.pow(5),
+      poly(expr!((x + 1) * (x + 1) * (x + 1) * (x + 1) * (x + 1)))
+    );
+    assert_eq!(poly(expr!(x ^ 1000)).degree(), 1000);
+    assert_eq!(poly(expr!((x * y) ^ 500)).degree(), 1000);
+  }
+
+  #[test]
+  fn fraction_from_expr() {
+    let frac = |expr: Expr| {
+      let (num, den) = Polynomial::fraction_from_expr(&expr).unwrap();
+      (num.to_string(), den.to_string())
+    };
+    assert_eq!(frac(expr!(x / 2 + 1)), ("0.5 * x + 1".into(), "1".into()));
+    assert_eq!(frac(expr!(x / (x * y))), ("x".into(), "x * y".into()));
+    assert_eq!(frac(expr!(2 * x ^ -2)), ("2".into(), "x^2".into()));
+    assert_eq!(frac(expr!((1 / x) ^ -1)), ("x".into(), "1".into()));
+
+    let err = |expr: Expr| Polynomial::fraction_from_expr(&expr).unwrap_err();
+    assert_eq!(err(expr!(1 / (x - x))), PolynomialError::DivisionByZero);
+    assert_eq!(
+      err(expr!(1 / x ^ 1e9)),
+      PolynomialError::DegreeTooLarge(expr!(x ^ 1e9))
+    );
+    assert_eq!(
+      err(expr!(x ^ (1 / x))),
+      PolynomialError::NotPolynomial(expr!(x ^ (1 / x)))
+    );
+  }
+
+  #[test]
+  fn standard_form() {
+    let expand = |expr: Expr| expr.expand().unwrap().to_string();
+    assert_eq!(expand(expr!((x - y) ^ 2)), "x^2 - 2 * x * y + y^2");
+    assert_eq!(
+      expand(expr!(-(x + 1) * (x ^ 2 - 3))),
+      "-x^3 - x^2 + 3 * x + 3"
+    );
+    assert_eq!(expand(expr!(y * x * z + 1 - z ^ 3)), "x * y * z - z^3 + 1");
+    assert_eq!(expand(expr!(x - x)), "0");
+    assert_eq!(expand(expr!(-2)), "-2");
+
+    let collect = |expr: Expr, var| expr.collect(var).unwrap().to_string();
+    assert_eq!(
+      collect(expr!(a * x ^ 2 - b * x + x ^ 2 + c), "x"),
+      "(a + 1) * x^2 - b * x + c"
+    );
+    assert_eq!(collect(expr!(-x ^ 2 + x), "x"), "-x^2 + x");
+    assert_eq!(collect(expr!(y), "x"), "y");
+  }
+
+  #[test]
+  fn coefficients() {
+    let p = poly(expr!(3 * x ^ 2 * y - x + 7 * y ^ 3));
+    assert_eq!((p.degree(), p.degree_in("x"), p.degree_in("z")), (3, 2, 0));
+    assert_eq!(
+      p.coefficients("x"),
+      vec![poly(expr!(7 * y ^ 3)), poly(expr!(-1)), poly(expr!(3 * y))]
+    );
+    assert_eq!(p.leading_coefficient("y"), poly(expr!(7)));
+    assert_eq!(p.derivative("x"), poly(expr!(6 * x * y - 1)));
+    assert_eq!(p.vars().len(), 2);
+    assert_eq!(poly(expr!(2 ^ 3)).as_constant(), Some(8.));
+  }
+}
@@ -0,0 +1,579 @@
+//! Solutions of polynomial equations in one variable, in closed form up to degree 4.
+use super::{Polynomial, PolynomialError};
+use expr::operators::{ACos, Cbrt, Cos, Sqrt};
+use std::cmp::Ordering;
+use std::iter;
+use {Eqtn, Expr};
+
+/// Rounds `x` if it is a non-zero integer up to rounding errors.
+fn snap(x: f64) -> f64 {
+  if x.round() != 0. && (x.round() - x).abs() < 1e-9 * x.abs() {
+    x.round()
+  } else {
+    x
+  }
+}
+
+/// The root `-c0 / c1` of `c1 * x + c0`, as a fraction if the coefficients are integers.
+fn linear(c: &[Polynomial]) -> Expr {
+  match (c[0].as_constant(), c[1].as_constant()) {
+    (Some(c0), Some(c1)) if c0.fract() == 0. && c1.fract() == 0. && (c0 / c1).fract() != 0. => {
+      Expr::num(-c0 * c1.signum()) / c1.abs()
+    }
+    (_, Some(c1)) => (&c[0] * &Polynomial::constant(-1. / c1)).to_expr(),
+    _ => (-&c[0]).to_expr() / c[1].to_expr(),
+  }
+}
+
+/// The roots of `c2 * x^2 + c1 * x + c0`, the smaller first if the coefficients are numbers.
+///
+/// There are no roots if the coefficients are numbers and the discriminant is negative.
+fn quadratic(c: &[Polynomial]) -> Vec<Expr> {
+  let (c0, c1, c2) = (&c[0], &c[1], &c[2]);
+  let a = match c2.as_constant() {
+    Some(a) => a,
+    None => {
+      // (-c1 ± sqrt(c1^2 - 4 * c2 * c0)) / (2 * c2)
+      let disc = &(c1 * c1) - &(&Polynomial::constant(4.) * &(c2 * c0));
+      let sqrt = disc.to_expr().sqrt();
+      let den = (&Polynomial::constant(2.) * c2).to_expr();
+      let (minus, plus) = if c1.is_zero() {
+        (-&sqrt, sqrt)
+      } else {
+        let b = (-c1).to_expr();
+        (&b - sqrt.clone(), b + sqrt)
+      };
+      return vec![minus / den.clone(), plus / den];
+    }
+  };
+
+  // x^2 + 2 * p * x + c0 / a = 0, so x = -p ± sqrt(p^2 - c0 / a)
+  let p = c1 * &Polynomial::constant(0.5 / a);
+  let q = &(&p * &p) - &(c0 * &Polynomial::constant(1. / a));
+  match q.as_constant() {
+    Some(q) if q < 0. => vec![],
+    Some(q) if snap(q.sqrt()).fract() == 0. => {
+      let sqrt = Polynomial::constant(snap(q.sqrt()));
+      vec![(&-&p - &sqrt).to_expr(), (&-&p + &sqrt).to_expr()]
+    }
+    _ => {
+      let sqrt = q.to_expr().sqrt();
+      if let (Some(p), Some(q), Some(c0)) = (p.as_constant(), q.as_constant(), c0.as_constant()) {
+        // the root closer to zero cancels in -p ± sqrt, so it's the product of the roots
+        // divided by the other one
+        let big = -p - p.signum() * q.sqrt();
+        let small = c0 / a / big;
+        if small.abs() < 1e-3 * big.abs() {
+          let big = Expr::num(-p) - p.signum() * sqrt;
+          let small = Expr::num(small);
+          return if p > 0. {
+            vec![big, small]
+          } else {
+            vec![small, big]
+          };
+        }
+      }
+      if p.is_zero() {
+        vec![-&sqrt, sqrt]
+      } else {
+        let p = (-&p).to_expr();
+        vec![&p - sqrt.clone(), p + sqrt]
+      }
+    }
+  }
+}
+
+/// The roots of `c4 * x^4 + c2 * x^2 + c0`, as square roots of the roots of the quadratic in
+/// `x^2`.
+fn biquadratic(c: &[Polynomial]) -> Vec<Expr> {
+  let mut roots = vec![];
+  for z in quadratic(&[c[0].clone(), c[2].clone(), c[4].clone()]) {
+    match z.eval().ok() {
+      Some(z) if z < 0. => {}
+      Some(z) if snap(z.sqrt()).fract() == 0. => {
+        let sqrt = snap(z.sqrt());
+        roots.extend(vec![Expr::num(-sqrt), Expr::num(sqrt)]);
+      }
+      _ => {
+        let sqrt = z.sqrt();
+        roots.extend(vec![-&sqrt, sqrt]);
+      }
+    }
+  }
+  roots
+}
+
+/// The expression of `p`, a single number if it's a constant.
+fn expr(p: &Polynomial) -> Expr {
+  p.as_constant().map_or_else(|| p.to_expr(), Expr::num)
+}
+
+/// `a + b`, folding numbers and skipping zeros.
+fn add(a: Expr, b: Expr) -> Expr {
+  match (a.as_num(), b.as_num()) {
+    (Some(a), Some(b)) => Expr::num(a + b),
+    (Some(0.), _) => b,
+    (_, Some(0.)) => a,
+    (_, Some(b)) if b < 0. => a - (-b),
+    _ => a + b,
+  }
+}
+
+/// `a - b`, folding numbers and skipping zeros.
+fn sub(a: Expr, b: Expr) -> Expr {
+  match (a.as_num(), b.as_num()) {
+    (Some(a), Some(b)) => Expr::num(a - b),
+    (Some(0.), _) => -b,
+    (_, Some(0.)) => a,
+    (_, Some(b)) if b < 0. => a + (-b),
+    _ => a - b,
+  }
+}
+
+/// `c * x`, folding numbers.
+fn times(c: f64, x: Expr) -> Expr {
+  match x.as_num() {
+    Some(x) => Expr::num(c * x),
+    None => c * x,
+  }
+}
+
+/// `sqrt(x)`, as a number if `x` is the square of an integer.
+fn sqrt(x: Expr) -> Expr {
+  match x.eval() {
+    Ok(x) if x >= 0. && snap(x.sqrt()).fract() == 0. => Expr::num(snap(x.sqrt())),
+    _ => x.sqrt(),
+  }
+}
+
+/// `cbrt(x)`, as a number if `x` is the cube of an integer.
+fn cbrt(x: Expr) -> Expr {
+  match x.eval() {
+    Ok(x) if snap(x.cbrt()).fract() == 0. => Expr::num(snap(x.cbrt())),
+    _ => x.cbrt(),
+  }
+}
+
+/// The coefficients of the monic polynomial in `u = lead * x`, with the same degree as the one
+/// with coefficients `c`, and the leading coefficient `lead` dividing its roots, if it isn't a
+/// number.
+fn monic(c: &[Polynomial]) -> (Vec<Polynomial>, Option<Expr>) {
+  let d = c.len() - 1;
+  match c[d].as_constant() {
+    Some(lead) => {
+      let scale = Polynomial::constant(1. / lead);
+      (c.iter().map(|c| c * &scale).collect(), None)
+    }
+    None => {
+      let monic = c
+        .iter()
+        .enumerate()
+        .map(|(k, ck)| match d - k {
+          0 => Polynomial::constant(1.),
+          n => ck * &c[d].pow(n as u32 - 1),
+        })
+        .collect();
+      (monic, Some(c[d].to_expr()))
+    }
+  }
+}
+
+/// The roots of the polynomial with coefficients `c` from those of the monic polynomial in
+/// `lead * x`.
+fn scaled_roots(c: &[Polynomial], roots: fn(&[Polynomial]) -> Vec<Expr>) -> Vec<Expr> {
+  let (c, lead) = monic(c);
+  let roots = roots(&c);
+  match lead {
+    Some(lead) => roots.into_iter().map(|u| u / lead.clone()).collect(),
+    None => roots,
+  }
+}
+
+/// The roots of `x^3 + c2 * x^2 + c1 * x + c0` by Cardano's formula. If the coefficients are
+/// numbers, these are the real roots, in trigonometric form where there are three. Otherwise
+/// it's the root given by real cube roots, which is the only real one where the discriminant
+/// under the square root is positive.
+fn monic_cubic(c: &[Polynomial]) -> Vec<Expr> {
+  let constant = Polynomial::constant;
+  // t^3 + p * t + q with x = t - a / 3
+  let a = &c[2];
+  let shift = a * &constant(1. / 3.);
+  let p = &c[1] - &(a * &shift);
+  let q = &(&(&a.pow(3) * &constant(2. / 27.)) - &(&shift * &c[1])) + &c[0];
+  let ts = match (p.as_constant(), q.as_constant()) {
+    (Some(0.), _) => vec![cbrt((-&q).to_expr())],
+    (Some(p), Some(q)) => {
+      let disc = (q / 2.).powi(2) + (p / 3.).powi(3);
+      if disc > 0. {
+        // the cube root whose terms don't cancel, and the other one from their product -p / 3
+        let sqrt = sqrt(Expr::num(disc));
+        let u = cbrt(if q > 0. {
+          sub(Expr::num(-q / 2.), sqrt)
+        } else {
+          add(Expr::num(-q / 2.), sqrt)
+        });
+        vec![match u.as_num() {
+          Some(u) => Expr::num(u - p / 3. / u),
+          None => sub(u.clone(), Expr::num(p / 3.) / u),
+        }]
+      } else if disc == 0. {
+        let (single, double) = (3. * q / p, -3. * q / (2. * p));
+        vec![Expr::num(single), Expr::num(double), Expr::num(double)]
+      } else {
+        // 2 * sqrt(-p / 3) * cos(acos(z) / 3 - 2 * pi * k / 3) for k = 0, 1, 2
+        let m = times(2., sqrt(Expr::num(-p / 3.)));
+        let z = (3. * q / (2. * p) * (-3. / p).sqrt()).clamp(-1., 1.);
+        let angle = Expr::num(z).acos() / 3.;
+        (0..3)
+          .map(|k| {
+            let angle = match k {
+              0 => angle.clone(),
+              k => angle.clone() - Expr::num(2. * k as f64) * Expr::var("pi") / 3.,
+            };
+            m.clone() * angle.cos()
+          })
+          .collect()
+      }
+    }
+    _ if q.is_zero() => {
+      // t * (t^2 + p)
+      let sqrt = sqrt((-&p).to_expr());
+      vec![-&sqrt, Expr::num(0.), sqrt]
+    }
+    _ => {
+      // cbrt(-q / 2 + sqrt(disc)) + cbrt(-q / 2 - sqrt(disc))
+      let disc = &(&q.pow(2) * &constant(0.25)) + &(&p.pow(3) * &constant(1. / 27.));
+      let half = (&q * &constant(-0.5)).to_expr();
+      let sqrt = sqrt(disc.to_expr());
+      vec![cbrt(half.clone() + sqrt.clone()) + cbrt(half - sqrt)]
+    }
+  };
+  ts.into_iter().map(|t| sub(t, expr(&shift))).collect()
+}
+
+/// The roots of `x^4 + c3 * x^3 + c2 * x^2 + c1 * x + c0` by Ferrari's method. If the
+/// coefficients are numbers, these are the real roots.
+fn monic_quartic(c: &[Polynomial]) -> Vec<Expr> {
+  let constant = Polynomial::constant;
+  // y^4 + p * y^2 + q * y + r with x = y - a / 4
+  let a = &c[3];
+  let shift = a * &constant(0.25);
+  let p = &c[2] - &(&a.pow(2) * &constant(3. / 8.));
+  let q = &(&(&a.pow(3) * &constant(1. / 8.)) - &(&(a * &c[2]) * &constant(0.5))) + &c[1];
+  let r = &(&(&(&a.pow(4) * &constant(-3. / 256.))
+    + &(&(&a.pow(2) * &c[2]) * &constant(1. / 16.)))
+    - &(&shift * &c[1]))
+    + &c[0];
+  let ys = if q.is_zero() {
+    let zero = Polynomial::zero();
+    biquadratic(&[r, zero.clone(), p, zero, constant(1.)])
+  } else {
+    // (y^2 + p / 2 + m)^2 = 2 * m * (y - q / (4 * m))^2 for a positive root m of the resolvent
+    // cubic m^3 + p * m^2 + (p^2 / 4 - r) * m - q^2 / 8
+    let resolvent = [
+      &q.pow(2) * &constant(-1. / 8.),
+      &(&p.pow(2) * &constant(0.25)) - &r,
+      p.clone(),
+      constant(1.),
+    ];
+    let value = |x: &Expr| x.eval().unwrap_or(0.);
+    let m = monic_cubic(&resolvent)
+      .into_iter()
+      .max_by(|x, y| value(x).partial_cmp(&value(y)).unwrap_or(Ordering::Equal))
+      .expect("a cubic has a root");
+    let m = match m.eval() {
+      Ok(x) if snap(x).fract() == 0. => Expr::num(snap(x)),
+      _ => m,
+    };
+    let s = sqrt(times(2., m.clone()));
+    // y = ±s / 2 ± sqrt(-m / 2 - p / 2 ∓ q / (2 * s)), with the outer signs the same
+    let base = sub(expr(&(&p * &constant(-0.5))), times(0.5, m));
+    let tilt = match (q.as_constant(), s.as_num()) {
+      (Some(q), Some(s)) => Expr::num(q / (2. * s)),
+      _ => (&q * &constant(0.5)).to_expr() / s.clone(),
+    };
+    let mut ys = vec![];
+    let sides = [
+      (times(0.5, s.clone()), sub(base.clone(), tilt.clone())),
+      (times(-0.5, s), add(base, tilt)),
+    ];
+    for (centre, disc) in sides {
+      let sqrt = match disc.eval() {
+        Ok(disc) if disc < 0. => continue,
+        _ => sqrt(disc),
+      };
+      ys.push(sub(centre.clone(), sqrt.clone()));
+      ys.push(add(centre, sqrt));
+    }
+    ys
+  };
+  ys.into_iter().map(|y| sub(y, expr(&shift))).collect()
+}
+
+impl Polynomial {
+  /// The solutions of `self = 0` for `var`, each repeated as often as its multiplicity.
+  ///
+  /// If the other variables of the polynomial are only `var`, the polynomial is factorised
+  /// first and the real roots of the factors are found, in increasing order. They are given
+  /// by the quadratic formula for factors of degree 2 and 4 in `var^2`, by Cardano's formula
+  /// for factors of degree 3, in trigonometric form where there are three real roots, and by
+  /// Ferrari's method for other factors of degree 4. Otherwise the coefficients are treated as
+  /// symbols, which are assumed not to vanish, and the roots are given by the same formulas
+  /// after dividing out a power of `var`, with the one root of a cubic that Cardano's formula
+  /// gives with real cube roots.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(Identity)` for the zero polynomial, and `Err(NoClosedForm)` if there is a
+  /// factor of degree higher than 4.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Expr, Polynomial};
+  /// let poly = Polynomial::from_expr(&"x^3 - 2 * x".parse().unwrap()).unwrap();
+  /// let roots: Vec<_> = poly.roots("x").unwrap().iter().map(|x| x.to_string()).collect();
+  /// assert_eq!(roots, ["-sqrt(2)", "0", "sqrt(2)"]);
+  /// ```
+  pub fn roots(&self, var: &str) -> Result<Vec<Expr>, PolynomialError> {
+    if self.is_zero() {
+      return Err(PolynomialError::Identity);
+    }
+    if self.vars().iter().all(|v| v == var) {
+      self.numeric_roots(var)
+    } else {
+      self.symbolic_roots(var)
+    }
+  }
+
+  fn numeric_roots(&self, var: &str) -> Result<Vec<Expr>, PolynomialError> {
+    let mut roots = vec![];
+    for (f, k) in self.factor()?.factors {
+      let c = f.coefficients(var);
+      let found = match f.degree_in(var) {
+        1 => vec![linear(&c)],
+        2 => quadratic(&c),
+        4 if c[1].is_zero() && c[3].is_zero() => biquadratic(&c),
+        3 => scaled_roots(&c, monic_cubic),
+        4 => scaled_roots(&c, monic_quartic),
+        d => return Err(PolynomialError::NoClosedForm(d)),
+      };
+      for root in found {
+        roots.extend(iter::repeat_n(root, k as usize));
+      }
+    }
+    let value = |x: &Expr| x.eval().unwrap_or(0.);
+    roots.sort_by(|x, y| value(x).partial_cmp(&value(y)).unwrap_or(Ordering::Equal));
+    Ok(roots)
+  }
+
+  fn symbolic_roots(&self, var: &str) -> Result<Vec<Expr>, PolynomialError> {
+    let c = self.coefficients(var);
+    // var^low divides the polynomial
+    let low = c.iter().position(|c| !c.is_zero()).unwrap_or(0);
+    let c = &c[low..];
+    let mut roots = vec![Expr::num(0.); low];
+    roots.extend(match c.len() - 1 {
+      0 => vec![],
+      1 => vec![linear(c)],
+      2 => quadratic(c),
+      3 => scaled_roots(c, monic_cubic),
+      4 if c[1].is_zero() && c[3].is_zero() => biquadratic(c),
+      4 => scaled_roots(c, monic_quartic),
+      d => return Err(PolynomialError::NoClosedForm(d as u32)),
+    });
+    Ok(roots)
+  }
+}
+
+impl Eqtn {
+  /// Solves a polynomial equation for `var`, see
+  /// [`Polynomial::roots`](struct.Polynomial.html#method.roots).
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(NotPolynomial)` if a side isn't a polynomial, so that other ways of solving
+  /// the equation can be tried, and the errors of `Polynomial::roots` otherwise.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::{Eqtn, Expr};
+  /// let eqtn: Eqtn = "x^2 + 3 * x = 4".parse().unwrap();
+  /// let roots: Vec<Expr> = vec!["-4".parse().unwrap(), "1".parse().unwrap()];
+  /// assert_eq!(eqtn.solve_polynomial("x").unwrap(), roots);
+  /// ```
+  pub fn solve_polynomial(&self, var: &str) -> Result<Vec<Expr>, PolynomialError> {
+    Polynomial::from_expr(&(&self.0 - self.1.clone()))?.roots(var)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use expr::builtin;
+  use std::f64::consts::PI;
+
+  fn solve(eqtn: Eqtn) -> Vec<String> {
+    let roots = eqtn.solve_polynomial("x").unwrap();
+    roots.iter().map(|x| x.to_string()).collect()
+  }
+
+  /// Checks that the roots are numerically close to `expected`.
+  fn approx(eqtn: Eqtn, expected: &[f64]) {
+    let roots: Vec<f64> = eqtn
+      .solve_polynomial("x")
+      .unwrap()
+      .iter()
+      .map(|x| x.eval().unwrap())
+      .collect();
+    assert_eq!(roots.len(), expected.len(), "{:?} != {:?}", roots, expected);
+    for (x, y) in roots.iter().zip(expected) {
+      assert!((x - y).abs() < 1e-10, "{:?} != {:?}", roots, expected);
+    }
+  }
+
+  #[test]
+  fn exact_roots() {
+    assert_eq!(solve(eqtn!(2 * x + 1 = 0)), ["-1 / 2"]);
+    assert_eq!(solve(eqtn!(3 * x = 6)), ["2"]);
+    assert_eq!(solve(eqtn!(x ^ 2 + 3 * x - 4 = 0)), ["-4", "1"]);
+    assert_eq!(solve(eqtn!(x ^ 2 = 2)), ["-sqrt(2)", "sqrt(2)"]);
+    assert_eq!(
+      solve(eqtn!(x ^ 2 - 2 * x - 1 = 0)),
+      ["1 - sqrt(2)", "1 + sqrt(2)"]
+    );
+    assert_eq!(solve(eqtn!(x ^ 2 + 1 = 0)), Vec::<String>::new());
+    assert_eq!(solve(eqtn!(x ^ 3 = 2)), ["cbrt(2)"]);
+    assert_eq!(solve(eqtn!(x ^ 3 + 3 * x ^ 2 + 3 * x = 1)), ["cbrt(2) - 1"]);
+    assert_eq!(
+      solve(eqtn!(x ^ 3 - 3 * x + 1 = 0)),
+      [
+        "2 * cos(acos(-0.5) / 3 - 4 * pi / 3)",
+        "2 * cos(acos(-0.5) / 3 - 2 * pi / 3)",
+        "2 * cos(acos(-0.5) / 3)"
+      ]
+    );
+    assert_eq!(solve(eqtn!(x ^ 4 = 10 * x ^ 2 - 9)), ["-3", "-1", "1", "3"]);
+    assert_eq!(
+      solve(eqtn!(x ^ 4 - 4 * x ^ 2 + 1 = 0)),
+      [
+        "-sqrt(2 + sqrt(3))",
+        "-sqrt(2 - sqrt(3))",
+        "sqrt(2 - sqrt(3))",
+        "sqrt(2 + sqrt(3))"
+      ]
+    );
+  }
+
+  #[test]
+  fn badly_scaled() {
+    approx(eqtn!(x ^ 2 = 0.0000000001 ^ 2), &[-1e-10, 1e-10]);
+    let roots: Vec<f64> = eqtn!((x - 0.00000000001) * (x - 3) = 0)
+      .solve_polynomial("x")
+      .unwrap()
+      .iter()
+      .map(|x| x.eval().unwrap())
+      .collect();
+    assert!((roots[0] - 1e-11).abs() < 1e-20, "{:?}", roots);
+    assert!((roots[1] - 3.).abs() < 1e-10, "{:?}", roots);
+  }
+
+  #[test]
+  fn multiplicities() {
+    assert_eq!(solve(eqtn!((x - 1) ^ 2 * (x + 2) = 0)), ["-2", "1", "1"]);
+    assert_eq!(solve(eqtn!(x ^ 3 = 0)), ["0", "0", "0"]);
+    assert_eq!(
+      solve(eqtn!((x ^ 2 - 3) ^ 2 = 0)),
+      ["-sqrt(3)", "-sqrt(3)", "sqrt(3)", "sqrt(3)"]
+    );
+  }
+
+  #[test]
+  fn cubics_and_quartics() {
+    // three real roots 2 * cos(2 * pi * k / 9) for k = 1, 2, 4
+    let k = |k: f64| 2. * (2. * PI * k / 9.).cos();
+    approx(eqtn!(x ^ 3 - 3 * x + 1 = 0), &[k(4.), k(2.), k(1.)]);
+    approx(eqtn!(x ^ 3 = 2), &[2f64.cbrt()]);
+    approx(eqtn!((x ^ 3 - 2) * (x - 1) = 0), &[1., 2f64.cbrt()]);
+    // (x^2 - 2 * x - 1) * (x^2 + x - 1), with roots 1 ± sqrt(2) and (-1 ± sqrt(5)) / 2
+    approx(
+      eqtn!(x ^ 4 - x ^ 3 - 4 * x ^ 2 + x + 1 = 0),
+      &[
+        (-1. - 5f64.sqrt()) / 2.,
+        1. - 2f64.sqrt(),
+        (-1. + 5f64.sqrt()) / 2.,
+        1. + 2f64.sqrt(),
+      ],
+    );
+    approx(eqtn!(x ^ 4 + x + 1 = 0), &[]);
+    approx(eqtn!(x ^ 3 + x + 1 = 0), &[-0.6823278038280193]);
+    approx(
+      eqtn!(x ^ 4 + x = 1),
+      &[-1.2207440846057596, 0.7244919590005157],
+    );
+    // four real roots, from the trigonometric form of the resolvent cubic
+    let roots = eqtn!(x ^ 4 - 10 * x ^ 2 + x + 1 = 0)
+      .solve_polynomial("x")
+      .unwrap();
+    assert_eq!(roots.len(), 4);
+    for x in roots {
+      let x = x.eval().unwrap();
+      assert!((x.powi(4) - 10. * x * x + x + 1.).abs() < 1e-10, "{}", x);
+    }
+  }
+
+  #[test]
+  fn symbolic_coefficients() {
+    assert_eq!(solve(eqtn!(a * x + b = 0)), ["-b / a"]);
+    assert_eq!(solve(eqtn!(2 * x = y)), ["0.5 * y"]);
+    assert_eq!(solve(eqtn!(x ^ 2 = y)), ["-sqrt(y)", "sqrt(y)"]);
+    assert_eq!(solve(eqtn!((x - y) ^ 2 = 1)), ["y - 1", "y + 1"]);
+    assert_eq!(
+      solve(eqtn!(a * x ^ 2 + b * x + c = 0)),
+      [
+        "(-b - sqrt(-(4 * a * c) + b^2)) / (2 * a)",
+        "(-b + sqrt(-(4 * a * c) + b^2)) / (2 * a)"
+      ]
+    );
+    assert_eq!(solve(eqtn!(y * x ^ 3 = x ^ 2)), ["0", "0", "1 / y"]);
+    assert_eq!(solve(eqtn!(x ^ 3 + y = 0)), ["cbrt(-y)"]);
+    assert_eq!(solve(eqtn!(a * x ^ 3 + b = 0)), ["cbrt(-(a^2 * b)) / a"]);
+
+    // Cardano's formula, and Ferrari's method with it, checked where the roots are real
+    let roots = eqtn!(x ^ 3 + y * x + 1 = 0).solve_polynomial("x").unwrap();
+    assert_eq!(roots.len(), 1);
+    let x = roots[0].eval_with_context((("y", 1.), builtin())).unwrap();
+    assert!((x - -0.6823278038280193).abs() < 1e-12, "{}", x);
+    let roots = eqtn!(x ^ 4 + x + y = 0).solve_polynomial("x").unwrap();
+    assert_eq!(roots.len(), 4);
+    let real: Vec<f64> = roots
+      .iter()
+      .map(|x| x.eval_with_context((("y", -1.), builtin())).unwrap())
+      .filter(|x| x.is_finite())
+      .collect();
+    assert_eq!(real.len(), 2);
+    for x in real {
+      assert!((x.powi(4) + x - 1.).abs() < 1e-12, "{}", x);
+    }
+  }
+
+  #[test]
+  fn failures() {
+    let solve = |eqtn: Eqtn| eqtn.solve_polynomial("x");
+    assert_eq!(
+      solve(eqtn!(sin(x) = 0)),
+      Err(PolynomialError::NotPolynomial(expr!(sin(x))))
+    );
+    assert_eq!(solve(eqtn!(x + 1 = x + 1)), Err(PolynomialError::Identity));
+    assert_eq!(solve(eqtn!(x = x + 1)), Ok(vec![]));
+    assert_eq!(
+      solve(eqtn!(x ^ 5 - x - 1 = 0)),
+      Err(PolynomialError::NoClosedForm(5))
+    );
+    assert_eq!(
+      solve(eqtn!(x ^ 5 + y = 0)),
+      Err(PolynomialError::NoClosedForm(5))
+    );
+  }
+}
@@ -0,0 +1,518 @@
+//! An e-graph, holding many equivalent expressions at once, and equality saturation over it.
+//!
+//! Rather than rewriting an expression one rule at a time, every rule is applied everywhere it
+//! matches and the results are recorded as equivalent to the terms they were rewritten from.
+//! Rules such as commutativity and associativity then only add a few nodes each, instead of
+//! blowing up the search, and the best equivalent term is extracted at the end.
+use super::heuristics::{Heuristic, Length};
+use super::{Expr, Rules, Token};
+use crate::expr::{builtin, Substitutions};
+use crate::{Context, ContextProvider, Guard};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// The id of an e-class, i.e. a set of equivalent terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(usize);
+
+/// An operator or leaf token, with the e-classes of its operands.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct ENode(Token, Vec<Id>);
+
+/// A left or right hand side of a rule, with the pattern variables to bind.
+///
+/// Variables that are built-in constants, e.g. `e` in `e^x <=> exp(x)`, only match themselves.
+#[derive(Debug)]
+enum Pattern {
+  Var(String),
+  Node(Token, Vec<Pattern>),
+}
+
+impl Pattern {
+  fn new(expr: &Expr, consts: &Context) -> Pattern {
+    expr.fold_expr(|children, tkn| match tkn {
+      Token::Var(name) if consts.get_var(&name).is_none() => Pattern::Var(name),
+      tkn => Pattern::Node(tkn, children),
+    })
+  }
+
+  fn vars<'a>(&'a self, vars: &mut Vec<&'a str>) {
+    match self {
+      Pattern::Var(name) => vars.push(name),
+      Pattern::Node(_, children) => children.iter().for_each(|child| child.vars(vars)),
+    }
+  }
+}
+
+type Bindings = HashMap<String, Id>;
+
+/// When to stop applying rules to an e-graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+  /// The maximum number of e-nodes.
+  pub nodes: usize,
+  /// The maximum number of times every rule is applied everywhere.
+  pub iterations: usize,
+  /// The maximum time spent applying rules.
+  pub time: Duration,
+}
+
+impl Default for Limits {
+  fn default() -> Self {
+    Limits {
+      nodes: 10_000,
+      iterations: 30,
+      time: Duration::from_secs(5),
+    }
+  }
+}
+
+/// Why applying rules to an e-graph stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+  /// No rule adds anything new, so every term equivalent under the rules is represented.
+  Saturated,
+  NodeLimit,
+  IterationLimit,
+  TimeLimit,
+}
+
+/// An e-graph of expressions, grouped into e-classes of equivalent terms.
+///
+/// E-nodes are hash-consed, so every operator applied to the same e-classes is only stored
+/// once, and merging two e-classes merges the e-classes of the terms built from them.
+///
+/// # Examples
+///
+/// ```rust
+/// use meval_symbolic::{EGraph, Expr, Length, Limits, Rules};
+/// let rules: Rules = "x + y <=> y + x\nx * 1 => x\nx * (y + z) <=> x * y + x * z"
+///   .parse()
+///   .unwrap();
+/// let mut egraph = EGraph::new();
+/// let id = egraph.add_expr(&"(1 + a) * 1 + 2 * (a + 1)".parse().unwrap());
+/// egraph.run(&rules, &Limits::default());
+///
+/// assert!(egraph.equiv(&"a + 1 + 2 * (1 + a)".parse().unwrap(), id));
+/// assert_eq!(egraph.extract(id, &Length).to_string(), "1 + a + 2 * (1 + a)");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EGraph {
+  /// The union-find over e-class ids.
+  parents: Vec<Id>,
+  /// The e-nodes of each canonical e-class.
+  classes: BTreeMap<Id, Vec<ENode>>,
+  /// The e-class of every canonical e-node.
+  memo: HashMap<ENode, Id>,
+}
+
+impl EGraph {
+  pub fn new() -> Self {
+    EGraph::default()
+  }
+
+  /// The canonical id of the e-class `id` belongs to.
+  pub fn find(&self, mut id: Id) -> Id {
+    while self.parents[id.0] != id {
+      id = self.parents[id.0];
+    }
+    id
+  }
+
+  /// The number of e-nodes.
+  pub fn len(&self) -> usize {
+    self.memo.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.memo.is_empty()
+  }
+
+  /// The number of e-classes.
+  pub fn classes(&self) -> usize {
+    self.classes.len()
+  }
+
+  fn canonical(&self, ENode(tkn, children): &ENode) -> ENode {
+    ENode(
+      tkn.clone(),
+      children.iter().map(|&id| self.find(id)).collect(),
+    )
+  }
+
+  fn add(&mut self, node: ENode) -> Id {
+    let node = self.canonical(&node);
+    if let Some(&id) = self.memo.get(&node) {
+      return self.find(id);
+    }
+    let id = Id(self.parents.len());
+    self.parents.push(id);
+    self.classes.insert(id, vec![node.clone()]);
+    self.memo.insert(node, id);
+    id
+  }
+
+  /// Adds the expression and all its subexpressions, returning the e-class of the expression.
+  pub fn add_expr(&mut self, expr: &Expr) -> Id {
+    let mut ids: Vec<Id> = vec![];
+    for tkn in expr.iter() {
+      let children = ids.split_off(ids.len() - Expr::no_children(tkn));
+      ids.push(self.add(ENode(tkn.clone(), children)));
+    }
+    ids.pop().expect("expression was empty")
+  }
+
+  /// The e-class of the expression, if it is represented in the e-graph.
+  pub fn lookup(&self, expr: &Expr) -> Option<Id> {
+    let mut ids: Vec<Id> = vec![];
+    for tkn in expr.iter() {
+      let children = ids.split_off(ids.len() - Expr::no_children(tkn));
+      let node = self.canonical(&ENode(tkn.clone(), children));
+      ids.push(self.find(*self.memo.get(&node)?));
+    }
+    ids.pop()
+  }
+
+  /// Whether the expression is in the e-class `id`.
+  pub fn equiv(&self, expr: &Expr, id: Id) -> bool {
+    self.lookup(expr) == Some(self.find(id))
+  }
+
+  /// Merges two e-classes, returning whether they were different.
+  ///
+  /// Call [`rebuild`](#method.rebuild) before using the e-graph again, to merge the e-classes
+  /// of terms built from the merged e-classes.
+  pub fn union(&mut self, a: Id, b: Id) -> bool {
+    let (a, b) = (self.find(a), self.find(b));
+    if a == b {
+      return false;
+    }
+    self.parents[b.0] = a;
+    let nodes = self.classes.remove(&b).unwrap_or_default();
+    self.classes.entry(a).or_default().extend(nodes);
+    true
+  }
+
+  /// Restores the invariants after unions, merging e-classes with equal e-nodes until there
+  /// are none left.
+  pub fn rebuild(&mut self) {
+    loop {
+      self.memo.clear();
+      let mut unions = vec![];
+      let ids: Vec<Id> = self.classes.keys().cloned().collect();
+      for id in ids {
+        let mut nodes: Vec<ENode> = self.classes[&id]
+          .iter()
+          .map(|node| self.canonical(node))
+          .collect();
+        nodes.sort();
+        nodes.dedup();
+        for node in &nodes {
+          match self.memo.get(node) {
+            Some(&other) if other != id => unions.push((other, id)),
+            Some(_) => (),
+            None => {
+              self.memo.insert(node.clone(), id);
+            }
+          }
+        }
+        self.classes.insert(id, nodes);
+      }
+      if unions.is_empty() {
+        break;
+      }
+      for (a, b) in unions {
+        self.union(a, b);
+      }
+    }
+
+    // compress the paths of the union-find
+    for i in 0..self.parents.len() {
+      self.parents[i] = self.find(Id(i));
+    }
+  }
+
+  /// All extensions of `bindings` with which `pat` matches a term in the e-class `id`.
+  fn ematch(&self, pat: &Pattern, id: Id, bindings: Bindings) -> Vec<Bindings> {
+    let id = self.find(id);
+    match pat {
+      Pattern::Var(name) => match bindings.get(name) {
+        Some(&bound) if self.find(bound) != id => vec![],
+        Some(_) => vec![bindings],
+        None => {
+          let mut bindings = bindings;
+          bindings.insert(name.clone(), id);
+          vec![bindings]
+        }
+      },
+      Pattern::Node(tkn, pats) => self.classes[&id]
+        .iter()
+        .filter(|ENode(node, children)| node == tkn && children.len() == pats.len())
+        .flat_map(|ENode(_, children)| {
+          pats
+            .iter()
+            .zip(children)
+            .fold(vec![bindings.clone()], |all, (pat, &child)| {
+              all
+                .into_iter()
+                .flat_map(|bindings| self.ematch(pat, child, bindings))
+                .collect()
+            })
+        })
+        .collect(),
+    }
+  }
+
+  /// Adds the term `pat` with the variables bound to e-classes.
+  fn instantiate(&mut self, pat: &Pattern, bindings: &Bindings) -> Id {
+    match pat {
+      Pattern::Var(name) => bindings[name],
+      Pattern::Node(tkn, pats) => {
+        let children = pats
+          .iter()
+          .map(|pat| self.instantiate(pat, bindings))
+          .collect();
+        self.add(ENode(tkn.clone(), children))
+      }
+    }
+  }
+
+  /// Whether the terms bound satisfy the guard, checked on the shortest term of each e-class.
+  fn check_guard(
+    &self,
+    guard: &Guard,
+    bindings: &Bindings,
+    terms: &HashMap<Id, Expr>,
+    consts: &Context,
+  ) -> bool {
+    let mut subs = Substitutions::new();
+    for (name, id) in bindings {
+      match terms.get(&self.find(*id)) {
+        Some(term) => subs.insert(name.clone(), term.clone()),
+        None => return false,
+      };
+    }
+    guard.check(&subs, consts).is_ok()
+  }
+
+  /// Applies the expression rules everywhere they match until the e-graph is saturated or a
+  /// limit is reached (equality saturation).
+  ///
+  /// Rules relating equations, rules whose left hand side is a lone variable, matching every
+  /// e-class, and rules with variables on the right that aren't bound on the left are skipped.
+  pub fn run(&mut self, rules: &Rules, limits: &Limits) -> Stop {
+    let start = Instant::now();
+    let consts = builtin();
+    let rules: Vec<_> = rules
+      .iter()
+      .filter_map(|rule| {
+        let (lhs, rhs) = rule.exprs()?;
+        let (lhs, rhs) = (Pattern::new(lhs, &consts), Pattern::new(rhs, &consts));
+        let (mut bound, mut used) = (vec![], vec![]);
+        lhs.vars(&mut bound);
+        rhs.vars(&mut used);
+        match lhs {
+          Pattern::Node(..) if used.iter().all(|var| bound.contains(var)) => {
+            Some((lhs, rhs, rule.guard()))
+          }
+          _ => None,
+        }
+      })
+      .collect();
+    let guarded = rules.iter().any(|(_, _, guard)| !guard.is_empty());
+
+    for _ in 0..limits.iterations {
+      let terms = if guarded {
+        self.best_terms(&Length)
+      } else {
+        HashMap::new()
+      };
+
+      let mut matches = vec![];
+      for (lhs, rhs, guard) in &rules {
+        for &id in self.classes.keys() {
+          for bindings in self.ematch(lhs, id, Bindings::new()) {
+            if guard.is_empty() || self.check_guard(guard, &bindings, &terms, &consts) {
+              matches.push((id, rhs, bindings));
+            }
+          }
+        }
+        if start.elapsed() > limits.time {
+          return Stop::TimeLimit;
+        }
+      }
+
+      let mut changed = false;
+      for (id, rhs, bindings) in matches {
+        let new = self.instantiate(rhs, &bindings);
+        changed |= self.union(id, new);
+        if self.len() > limits.nodes {
+          self.rebuild();
+          return Stop::NodeLimit;
+        }
+      }
+      self.rebuild();
+      if !changed {
+        return Stop::Saturated;
+      }
+    }
+    Stop::IterationLimit
+  }
+
+  /// The best term of every e-class under the heuristic, where a lower value is better.
+  ///
+  /// The terms are built bottom up, keeping the best term for each e-class built from the best
+  /// terms of the operands, which finds the best terms for heuristics such as `Length` that only
+  /// grow with the operands.
+  fn best_terms<H: Heuristic<Expr>>(&self, heur: &H) -> HashMap<Id, Expr> {
+    let mut best: HashMap<Id, (H::Order, Expr)> = HashMap::new();
+    // every pass settles at least one more e-class
+    for _ in 0..=self.classes.len() {
+      let mut changed = false;
+      for (&id, nodes) in &self.classes {
+        for ENode(tkn, children) in nodes {
+          let terms: Option<Vec<_>> = children
+            .iter()
+            .map(|child| best.get(&self.find(*child)).map(|(_, term)| term))
+            .collect();
+          let mut tkns: Vec<_> = match terms {
+            Some(terms) => terms.iter().flat_map(|term| term.iter().cloned()).collect(),
+            None => continue,
+          };
+          tkns.push(tkn.clone());
+          let term = Expr(tkns);
+          let value = heur.value(&term);
+          if best.get(&id).is_none_or(|(prev, _)| value < *prev) {
+            best.insert(id, (value, term));
+            changed = true;
+          }
+        }
+      }
+      if !changed {
+        break;
+      }
+    }
+    best.into_iter().map(|(id, (_, term))| (id, term)).collect()
+  }
+
+  /// The best term in the e-class `id` under the heuristic, where a lower value is better.
+  pub fn extract<H: Heuristic<Expr>>(&self, id: Id, heur: &H) -> Expr {
+    self
+      .best_terms(heur)
+      .remove(&self.find(id))
+      .expect("e-class has no finite term")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use solver::heuristics::{MaxNesting, OnVar};
+
+  fn rules(s: &str) -> Rules {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn hash_consing() {
+    let mut egraph = EGraph::new();
+    let a = egraph.add_expr(&expr!((x + 1) * (x + 1)));
+    assert_eq!(egraph.len(), 4);
+    assert_eq!(
+      egraph.add_expr(&expr!(x + 1)),
+      egraph.lookup(&expr!(x + 1)).unwrap()
+    );
+    assert!(egraph.equiv(&expr!((x + 1) * (x + 1)), a));
+    assert_eq!(egraph.lookup(&expr!(x + 2)), None);
+  }
+
+  #[test]
+  fn congruence() {
+    let mut egraph = EGraph::new();
+    let a = egraph.add_expr(&expr!(sin(x) + 1));
+    let b = egraph.add_expr(&expr!(sin(y) + 1));
+    let (x, y) = (
+      egraph.lookup(&expr!(x)).unwrap(),
+      egraph.lookup(&expr!(y)).unwrap(),
+    );
+    assert!(egraph.union(x, y));
+    egraph.rebuild();
+    assert_eq!(egraph.find(a), egraph.find(b));
+    assert_eq!(egraph.classes(), 4);
+  }
+
+  #[test]
+  fn commutativity_and_associativity() {
+    let rules = rules("x + y <=> y + x\n(x + y) + z <=> x + (y + z)");
+    let mut egraph = EGraph::new();
+    let id = egraph.add_expr(&expr!(a + b + c + d));
+    let stop = egraph.run(&rules, &Limits::default());
+    assert_eq!(stop, Stop::Saturated);
+    assert!(egraph.equiv(&expr!(d + (c + (b + a))), id));
+    assert!(egraph.equiv(&expr!((c + a) + (d + b)), id));
+  }
+
+  #[test]
+  fn extract_best() {
+    let rules = rules("x * 1 => x\nx + 0 => x\nx * (y + z) <=> x * y + x * z\nx * y <=> y * x");
+    let mut egraph = EGraph::new();
+    let id = egraph.add_expr(&expr!(y * x + (y * 2 + 0) * 1));
+    egraph.run(&rules, &Limits::default());
+    assert_eq!(egraph.extract(id, &Length), expr!(y * (x + 2)));
+
+    let nesting = MaxNesting::on("x".into());
+    let id = egraph.add_expr(&expr!(sin(x * 1 + 0)));
+    egraph.run(&rules, &Limits::default());
+    assert_eq!(egraph.extract(id, &nesting), expr!(sin(x)));
+  }
+
+  #[test]
+  fn guards_and_constants() {
+    let rules = rules("x / x => 1 where x != 0\ne^x <=> exp(x)");
+    let mut egraph = EGraph::new();
+    let a = egraph.add_expr(&expr!(2 / 2));
+    let b = egraph.add_expr(&expr!(y / y));
+    let c = egraph.add_expr(&expr!(e ^ 3));
+    let d = egraph.add_expr(&expr!(2 ^ 3));
+    egraph.run(&rules, &Limits::default());
+    assert!(egraph.equiv(&expr!(1), a));
+    assert!(!egraph.equiv(&expr!(1), b));
+    assert!(egraph.equiv(&expr!(exp(3)), c));
+    assert!(!egraph.equiv(&expr!(exp(3)), d));
+  }
+
+  #[test]
+  fn default_rules() {
+    let mut egraph = EGraph::new();
+    let id = egraph.add_expr(&expr!(sin(x) ^ 2 + cos(x) ^ 2 + y * 1 - y));
+    let limits = Limits {
+      nodes: 2_000,
+      ..Limits::default()
+    };
+    egraph.run(&Rules::default(), &limits);
+    assert!(egraph.equiv(&expr!(1 + y - y), id));
+  }
+
+  #[test]
+  fn limits() {
+    let rules = rules("x * y <=> y * x\n(x * y) * z <=> x * (y * z)");
+    let mut egraph = EGraph::new();
+    egraph.add_expr(&expr!(a * b * c * d * e1 * f * g));
+
+    let limits = Limits {
+      nodes: 50,
+      ..Limits::default()
+    };
+    assert_eq!(egraph.clone().run(&rules, &limits), Stop::NodeLimit);
+    let limits = Limits {
+      iterations: 1,
+      ..Limits::default()
+    };
+    assert_eq!(egraph.clone().run(&rules, &limits), Stop::IterationLimit);
+    let limits = Limits {
+      time: Duration::from_secs(0),
+      ..Limits::default()
+    };
+    assert_eq!(egraph.run(&rules, &limits), Stop::TimeLimit);
+  }
+}
@@ -1,12 +1,11 @@
 use std::collections::HashSet;
 
-pub use self::heuristics::{Heuristic, OnVar};
-use self::{
-  heuristics::{Length, MaxNesting, NoOccurences},
-  rules::Rules,
-};
+pub use self::egraph::{EGraph, Id, Limits, Stop};
+pub use self::heuristics::{Heuristic, Length, MaxNesting, MinNesting, NoOccurences, OnVar};
+pub use self::rules::{Counterexample, Direction, Rule, RuleError, Rules};
 use super::{Eqtn, Error, Expr, Token};
 
+mod egraph;
 mod heuristics;
 mod rules;
 
@@ -0,0 +1,88 @@
+use super::Rules;
+
+const RULESTR: &str = "
+median(x) => x
+norm(x) => abs(x)
+norm(x, y) => hypot(x, y)
+";
+
+/// The largest number of arguments of the aggregates the rules expand.
+const MAX_ARITY: usize = 16;
+
+/// `x1, x2, ..., xn`.
+fn args(n: usize) -> String {
+  let args: Vec<_> = (1..=n).map(|i| format!("x{}", i)).collect();
+  args.join(", ")
+}
+
+/// The expansions of the variadic aggregates with `n` arguments, each in terms of the
+/// aggregate of the first `n - 1`, so that calls of any arity up to `MAX_ARITY` unfold into
+/// binary operations. They only go one way, as read backwards they would wrap every expression
+/// in aggregates of itself.
+fn expansions(n: usize) -> String {
+  let (all, init, last) = (args(n), args(n - 1), format!("x{}", n));
+  let mut rules = vec![];
+  if n == 1 {
+    rules.push("mean(x1) => x1".into());
+    rules.push("sum(x1) => x1".into());
+    rules.push("product(x1) => x1".into());
+    rules.push("softmax_lse(x1) => x1".into());
+  } else {
+    rules.push(format!("mean({}) => sum({}) / {}", all, all, n));
+    rules.push(format!("sum({}) => sum({}) + {}", all, init, last));
+    rules.push(format!("product({}) => product({}) * {}", all, init, last));
+    rules.push(format!(
+      "softmax_lse({}) => ln(exp(softmax_lse({})) + exp({}))",
+      all, init, last
+    ));
+  }
+  if n > 2 {
+    rules.push(format!("norm({}) => hypot(norm({}), {})", all, init, last));
+  }
+  rules.join("\n")
+}
+
+pub fn aggregate_rules() -> Rules {
+  let mut rules = RULESTR.to_string();
+  for n in 1..=MAX_ARITY {
+    rules.push_str(&expansions(n));
+    rules.push('\n');
+  }
+  rules.parse().expect("aggregate rules did not parse")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use solver::{EGraph, Limits};
+
+  #[test]
+  fn any_arity() {
+    let rules = aggregate_rules();
+    let mut egraph = EGraph::new();
+    let sum = egraph.add_expr(&expr!(sum(a, b, c, d, e1, f, g)));
+    let product = egraph.add_expr(&expr!(product(a, b, c, d, e1)));
+    let mean = egraph.add_expr(&expr!(mean(a, b, c, d)));
+    egraph.run(&rules, &Limits::default());
+    assert!(egraph.equiv(&expr!(a + b + c + d + e1 + f + g), sum));
+    assert!(egraph.equiv(&expr!(a * b * c * d * e1), product));
+    assert!(egraph.equiv(&expr!((a + b + c + d) / 4), mean));
+  }
+
+  #[test]
+  fn one_way() {
+    let rules = aggregate_rules();
+    let mut egraph = EGraph::new();
+    egraph.add_expr(&expr!(a + b * c));
+    egraph.add_expr(&expr!(abs(d)));
+    egraph.run(&rules, &Limits::default());
+    for aggregate in &[
+      expr!(median(a)),
+      expr!(sum(a, b * c)),
+      expr!(product(b, c)),
+      expr!(norm(d)),
+    ] {
+      assert_eq!(egraph.lookup(aggregate), None, "{}", aggregate);
+    }
+  }
+}
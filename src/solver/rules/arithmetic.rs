@@ -25,16 +25,16 @@ x * (y + z) <=> x * y + x * z
 (x + y) / z <=> x / z + y / z
 
 // given by distributivity and commutativity
-x + x <-> 2 * x
-
-// units
-x + 0 <=> x
-x - 0 <=> x
-x - x <=> 0
-x * 1 <=> x
-x / 1 <=> x
-x / x <=> 1
--0 <=> 0
+x + x <=> 2 * x
+
+// units, which only ever simplify
+[simplify]: x + 0 => x
+[simplify]: x - 0 => x
+[simplify]: x - x => 0
+[simplify]: x * 1 => x
+[simplify]: x / 1 => x
+[simplify]: x / x => 1 where x != 0
+[simplify]: -0 => 0
 ";
 
 pub fn arithmetic_rules() -> Rules {
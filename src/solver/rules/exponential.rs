@@ -11,7 +11,7 @@ ln(x + 1) <=> ln_1p(x)
 x^(y * z) <=> (x^y)^z
 x^(y + z) <=> x^y * x^z
 
-x^(-1) <=> 1/x
+neg_pow [simplify]: x^(-1) => 1/x
 
 // inverses
 e^x = y <=> x = ln(y)
@@ -1,29 +1,174 @@
 use self::{
-  arithmetic::arithmetic_rules, exponential::exponential_rules, trigonometry::trigonometry_rules,
+  aggregate::aggregate_rules, arithmetic::arithmetic_rules, exponential::exponential_rules,
+  trigonometry::trigonometry_rules,
 };
-use super::{Eqtn, Error};
+use super::{Eqtn, Error, Expr, Token};
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use Guard;
 
+mod aggregate;
 mod arithmetic;
 mod exponential;
 mod parser;
 mod trigonometry;
+mod validate;
+
+pub use self::parser::RuleError;
+pub use self::validate::Counterexample;
+
+/// Which way a rule may be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Direction {
+  /// Both sides are equivalent, written `<=>`, so the rule may be applied either way.
+  #[default]
+  Both,
+  /// The rule may only rewrite the left hand side into the right hand side, written `=>`.
+  ///
+  /// Rules written `rhs <= lhs` are stored as `lhs => rhs`.
+  Forward,
+}
 
 /// Represents an axiom of the system.
 ///
-/// We're only going to be using iff. rules here
-/// as they comprise the majority of transformations on equations
-#[derive(Clone, PartialEq)]
-pub struct Rule(Eqtn, Eqtn);
+/// Most rules are iff. rules, as they comprise the majority of transformations on equations,
+/// but rules that are only sound one way, or that should only ever simplify, can be restricted
+/// to [`Direction::Forward`](enum.Direction.html). The constraints and side conditions in the
+/// guard apply to the variables of both sides.
+///
+/// Rules can be given a name and tags, e.g. `neg_pow [simplify]: x^(-1) => 1/x`, to select
+/// subsets of [`Rules`](struct.Rules.html).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rule {
+  lhs: Eqtn,
+  rhs: Eqtn,
+  guard: Guard,
+  direction: Direction,
+  name: Option<String>,
+  tags: BTreeSet<String>,
+}
 
 impl Rule {
-  /// As we're using iff. rules, it's safe to flip the implication
+  /// A rule relating two equations.
+  pub fn new(lhs: Eqtn, rhs: Eqtn) -> Self {
+    Rule {
+      lhs,
+      rhs,
+      guard: Guard::new(),
+      direction: Direction::Both,
+      name: None,
+      tags: BTreeSet::new(),
+    }
+  }
+
+  /// A rule relating two expressions.
+  pub fn from_exprs(lhs: Expr, rhs: Expr) -> Self {
+    Rule::new(parser::lhs_eqtn(lhs), parser::lhs_eqtn(rhs))
+  }
+
+  /// The rule with the given constraints and side conditions.
+  pub fn with_guard(mut self, guard: Guard) -> Self {
+    self.guard = guard;
+    self
+  }
+
+  /// The rule restricted to the given direction.
+  pub fn with_direction(mut self, direction: Direction) -> Self {
+    self.direction = direction;
+    self
+  }
+
+  /// The rule with the given name.
+  pub fn named<S: Into<String>>(mut self, name: S) -> Self {
+    self.name = Some(name.into());
+    self
+  }
+
+  /// The rule with the given tag added.
+  pub fn tagged<S: Into<String>>(mut self, tag: S) -> Self {
+    self.tags.insert(tag.into());
+    self
+  }
+
+  /// The constraints and side conditions of the rule.
+  pub fn guard(&self) -> &Guard {
+    &self.guard
+  }
+
+  pub fn direction(&self) -> Direction {
+    self.direction
+  }
+
+  pub fn name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+
+  pub fn tags(&self) -> &BTreeSet<String> {
+    &self.tags
+  }
+
+  pub fn has_tag(&self, tag: &str) -> bool {
+    self.tags.contains(tag)
+  }
+
+  /// The sides of the rule, each either an equation or an expression.
+  pub(crate) fn sides(&self) -> (&Eqtn, &Eqtn) {
+    (&self.lhs, &self.rhs)
+  }
+
+  /// The sides of an expression rule, or `None` for a rule relating equations.
+  pub(crate) fn exprs(&self) -> Option<(&Expr, &Expr)> {
+    match &self.lhs.1[..] {
+      [Token::Var(name)] if name.is_empty() => Some((&self.lhs.0, &self.rhs.0)),
+      _ => None,
+    }
+  }
+
+  /// As iff. rules are equivalences, it's safe to flip the implication
   pub(crate) fn flipped(self) -> Self {
-    Self(self.1, self.0)
+    debug_assert_eq!(self.direction, Direction::Both);
+    Rule {
+      lhs: self.rhs,
+      rhs: self.lhs,
+      ..self
+    }
   }
 
   // pub fn apply(&self, eqtn: Eqtn) -> Eqtn {}
 }
 
+impl Display for Rule {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    if let Some(name) = &self.name {
+      write!(f, "{}", name)?;
+    }
+    if !self.tags.is_empty() {
+      let tags: Vec<_> = self.tags.iter().map(String::as_str).collect();
+      let space = if self.name.is_some() { " " } else { "" };
+      write!(f, "{}[{}]", space, tags.join(", "))?;
+    }
+    if self.name.is_some() || !self.tags.is_empty() {
+      write!(f, ": ")?;
+    }
+
+    // expression rules are stored as equations with a placeholder right hand side
+    let side = |eqtn: &Eqtn| match &eqtn.1[..] {
+      [Token::Var(name)] if name.is_empty() => eqtn.0.to_string(),
+      _ => eqtn.to_string(),
+    };
+    let arrow = match self.direction {
+      Direction::Both => "<=>",
+      Direction::Forward => "=>",
+    };
+    write!(f, "{} {} {}", side(&self.lhs), arrow, side(&self.rhs))?;
+    if !self.guard.is_empty() {
+      write!(f, " where {}", self.guard)?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone)]
 pub struct Rules(Vec<Rule>);
 
 impl Default for Rules {
@@ -32,6 +177,7 @@ impl Default for Rules {
     res.extend(arithmetic_rules().0);
     res.extend(trigonometry_rules().0);
     res.extend(exponential_rules().0);
+    res.extend(aggregate_rules().0);
     Self(res)
   }
 }
@@ -41,10 +187,50 @@ impl Rules {
     Self(vec![])
   }
 
+  pub fn iter(&self) -> impl Iterator<Item = &Rule> {
+    self.0.iter()
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  /// The rules with the given name, i.e. the rule and its flipped copy for `<=>` rules.
+  pub fn named(&self, name: &str) -> Rules {
+    self.filter(|rule| rule.name() == Some(name))
+  }
+
+  /// The rules with the given tag.
+  pub fn with_tag(&self, tag: &str) -> Rules {
+    self.filter(|rule| rule.has_tag(tag))
+  }
+
+  /// The rules that may be applied in the given direction, e.g. the one-way simplifications
+  /// for `Direction::Forward`.
+  pub fn in_direction(&self, direction: Direction) -> Rules {
+    self.filter(|rule| rule.direction() == direction)
+  }
+
+  /// The rules satisfying `pred`.
+  pub fn filter<P: FnMut(&Rule) -> bool>(&self, mut pred: P) -> Rules {
+    Rules(self.0.iter().filter(|rule| pred(rule)).cloned().collect())
+  }
+
   /// Computes the symmetric closure of rules
-  /// i.e. if `e1 <-> e2` exists, include `e2 <-> e1`
+  /// i.e. if `e1 <=> e2` exists, include `e2 <=> e1`. One-way rules are kept as they are.
   pub(crate) fn symmetric_close(mut self) -> Self {
-    self.0.extend(self.0.clone().into_iter().map(Rule::flipped));
+    let flipped: Vec<_> = self
+      .0
+      .iter()
+      .filter(|rule| rule.direction() == Direction::Both)
+      .cloned()
+      .map(Rule::flipped)
+      .collect();
+    self.0.extend(flipped);
     self
   }
 }
@@ -1,49 +1,776 @@
-use super::{Error, Rule, Rules};
-use crate::{expr::Token, Eqtn, Expr, ParseError};
+use super::{Direction, Error, Rule, Rules};
+use crate::{
+  builtin, expr::Token, Comparison, Condition, Constraint, ContextProvider, Eqtn, Expr, Guard,
+};
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-fn lhs_eqtn(lhs: Expr) -> Eqtn {
+pub(super) fn lhs_eqtn(lhs: Expr) -> Eqtn {
   use self::Token::Var;
   // rhs is a variable that cannot be represented as an expression
   // this ensures that there can be no collisions in variable identifiers
   Eqtn(lhs, Expr(vec![Var("".into())]))
 }
 
+/// An error in the text of a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleError {
+  /// The rule has no `<=>`, `=>` or `<=` between its sides.
+  MissingArrow,
+  /// The name or tags before the `:` of a rule aren't identifiers.
+  InvalidHeader(String),
+  /// A `?` that isn't followed by the name of a variable (contains the byte offset of the `?`).
+  InvalidMetavariable(usize),
+  /// A constraint other than `const`, `var` or `free_of(x)` on the given variable.
+  UnknownConstraint(String, String),
+  /// A variable given two different constraints.
+  ConflictingConstraints(String),
+  /// A condition in a `where` clause that isn't of the form `lhs op rhs`.
+  InvalidCondition(String),
+  /// A variable in a `where` clause that doesn't appear in the rule.
+  UnboundVariable(String),
+  /// Two rules of a rule set with the same name.
+  DuplicateName(String),
+  /// A line starting with `#` other than `#include "path"`.
+  InvalidDirective(String),
+  /// An `#include` in rules that weren't loaded with
+  /// [`Rules::from_file`](struct.Rules.html#method.from_file), which has no file to include
+  /// others relative to.
+  IncludeOutsideFile(String),
+  /// A rule file that couldn't be read, with the reason.
+  Io(PathBuf, String),
+  /// A rule file that includes itself, directly or through other files.
+  IncludeCycle(PathBuf),
+  /// An error on the given line (counting from 1) of a rule set.
+  Line(usize, Box<Error>),
+  /// An error in the given rule file.
+  File(PathBuf, Box<Error>),
+}
+
+/// Formats the error without repeating the `Rule error:` prefix of nested rule errors.
+fn fmt_nested(err: &Error, f: &mut Formatter) -> fmt::Result {
+  match err {
+    Error::RuleError(err) => err.fmt(f),
+    err => err.fmt(f),
+  }
+}
+
+impl Display for RuleError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      RuleError::MissingArrow => write!(
+        f,
+        "Missing `<=>`, `=>` or `<=` between the sides of the rule."
+      ),
+      RuleError::InvalidHeader(header) => write!(
+        f,
+        "Invalid rule header `{}`, expected `name [tag, ...]:`.",
+        header
+      ),
+      RuleError::InvalidMetavariable(i) => {
+        write!(f, "Expected a variable name after `?` at byte {}.", i)
+      }
+      RuleError::UnknownConstraint(var, kind) => write!(
+        f,
+        "Unknown constraint `{}` on `{}`, expected `const`, `var` or `free_of(x)`.",
+        kind, var
+      ),
+      RuleError::ConflictingConstraints(var) => {
+        write!(f, "Conflicting constraints on `{}`.", var)
+      }
+      RuleError::InvalidCondition(cond) => write!(
+        f,
+        "Invalid condition `{}`, expected a comparison such as `x != 0`.",
+        cond
+      ),
+      RuleError::UnboundVariable(var) => {
+        write!(
+          f,
+          "Variable `{}` in `where` clause doesn't appear in the rule.",
+          var
+        )
+      }
+      RuleError::DuplicateName(name) => write!(f, "More than one rule named `{}`.", name),
+      RuleError::InvalidDirective(line) => write!(
+        f,
+        "Invalid directive `{}`, expected `#include \"path\"`.",
+        line
+      ),
+      RuleError::IncludeOutsideFile(line) => write!(
+        f,
+        "`{}` is only allowed in rule files loaded with `Rules::from_file`.",
+        line
+      ),
+      RuleError::Io(path, err) => write!(f, "Couldn't read `{}`: {}", path.display(), err),
+      RuleError::IncludeCycle(path) => {
+        write!(f, "`{}` includes itself.", path.display())
+      }
+      RuleError::Line(line, err) => {
+        write!(f, "line {}: ", line)?;
+        fmt_nested(err, f)
+      }
+      RuleError::File(path, err) => {
+        write!(f, "in `{}`: ", path.display())?;
+        fmt_nested(err, f)
+      }
+    }
+  }
+}
+
+impl From<RuleError> for Error {
+  fn from(err: RuleError) -> Error {
+    Error::RuleError(err)
+  }
+}
+
+fn is_ident_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Splits `s` at the first occurrence of the word `keyword`.
+fn split_keyword<'a>(s: &'a str, keyword: &str) -> Option<(&'a str, &'a str)> {
+  s.match_indices(keyword).find_map(|(i, _)| {
+    let (before, after) = (&s[..i], &s[i + keyword.len()..]);
+    let boundary = |c: Option<char>| c.is_none_or(|c| !is_ident_char(c));
+    if boundary(before.chars().next_back()) && boundary(after.chars().next()) {
+      Some((before, after))
+    } else {
+      None
+    }
+  })
+}
+
+/// Replaces the typed metavariables `?name` and `?name:constraint` in `s` by `name`, adding the
+/// constraints to `guard`.
+fn strip_metavariables(s: &str, guard: &mut Guard) -> Result<String, RuleError> {
+  let mut res = String::with_capacity(s.len());
+  let mut rest = s;
+
+  while let Some(i) = rest.find('?') {
+    res.push_str(&rest[..i]);
+    let offset = s.len() - rest.len() + i;
+    rest = &rest[i + 1..];
+
+    let len = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+    let name = &rest[..len];
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+      return Err(RuleError::InvalidMetavariable(offset));
+    }
+    res.push_str(name);
+    rest = &rest[len..];
+
+    let spec = match rest.strip_prefix(':') {
+      Some(spec) => spec,
+      None => continue,
+    };
+    let len = spec.find(|c| !is_ident_char(c)).unwrap_or(spec.len());
+    let (kind, after) = spec.split_at(len);
+    let unknown = || RuleError::UnknownConstraint(name.into(), kind.into());
+    let (constraint, after) = match kind {
+      "const" => (Constraint::Const, after),
+      "var" => (Constraint::Var, after),
+      "free_of" => {
+        let (arg, after) = after
+          .strip_prefix('(')
+          .and_then(|after| after.split_once(')'))
+          .ok_or_else(unknown)?;
+        let arg = arg.trim();
+        let arg = arg.strip_prefix('?').unwrap_or(arg);
+        if arg.is_empty() || !arg.chars().all(is_ident_char) {
+          return Err(unknown());
+        }
+        (Constraint::FreeOf(arg.into()), after)
+      }
+      _ => return Err(unknown()),
+    };
+    match guard.constraints.insert(name.into(), constraint.clone()) {
+      Some(prev) if prev != constraint => {
+        return Err(RuleError::ConflictingConstraints(name.into()))
+      }
+      _ => (),
+    }
+    rest = after;
+  }
+
+  res.push_str(rest);
+  Ok(res)
+}
+
+/// Splits `s` at the commas outside of parentheses.
+fn split_top_level(s: &str) -> Vec<&str> {
+  let (mut parts, mut depth, mut start) = (vec![], 0, 0);
+  for (i, c) in s.char_indices() {
+    match c {
+      '(' => depth += 1,
+      ')' => depth -= 1,
+      ',' if depth == 0 => {
+        parts.push(&s[start..i]);
+        start = i + 1;
+      }
+      _ => (),
+    }
+  }
+  parts.push(&s[start..]);
+  parts
+}
+
+/// Parses the constraints and conditions after `where`, separated by commas.
+fn parse_where(clause: &str, guard: &mut Guard) -> Result<(), RuleError> {
+  for item in split_top_level(clause) {
+    let item = item.trim();
+    let invalid = || RuleError::InvalidCondition(item.into());
+    let stripped = strip_metavariables(item, guard)?;
+
+    let found = Comparison::find(&stripped)
+      .map(|(i, symbol, op)| (&stripped[..i], op, &stripped[i + symbol.len()..]));
+    match found {
+      Some((lhs, op, rhs)) => {
+        let lhs = lhs.parse().map_err(|_| invalid())?;
+        let rhs = rhs.parse().map_err(|_| invalid())?;
+        guard.conditions.push(Condition::new(lhs, op, rhs));
+      }
+      // a typed metavariable on its own only adds a constraint
+      None if item.starts_with('?') && stripped.chars().all(is_ident_char) => (),
+      None => return Err(invalid()),
+    }
+  }
+  Ok(())
+}
+
+fn is_ident(s: &str) -> bool {
+  s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && s.chars().all(is_ident_char)
+}
+
+/// Splits the optional header `name [tag, ...]:` off a rule, returning the name, the tags and
+/// the rest of the rule.
+fn split_header(s: &str) -> Result<(Option<&str>, Vec<&str>, &str), RuleError> {
+  let trimmed = s.trim_start();
+  // a header is a name, a list of tags in brackets, or both, followed by a colon
+  let len = trimmed.find(|c| !is_ident_char(c)).unwrap_or(trimmed.len());
+  let (name, rest) = trimmed.split_at(len);
+  let rest = rest.trim_start();
+  let (tags, rest) = match rest.strip_prefix('[') {
+    Some(tags) => match tags.split_once(']') {
+      Some((tags, rest)) => (Some(tags), rest.trim_start()),
+      None => return Err(RuleError::InvalidHeader(trimmed.into())),
+    },
+    None => (None, rest),
+  };
+  let rest = match rest.strip_prefix(':') {
+    Some(rest) if !name.is_empty() || tags.is_some() => rest,
+    _ => return Ok((None, vec![], s)),
+  };
+
+  let header = &trimmed[..trimmed.len() - rest.len()];
+  let invalid = || RuleError::InvalidHeader(header.trim_end_matches(':').trim().into());
+  if !name.is_empty() && !is_ident(name) {
+    return Err(invalid());
+  }
+  let tags: Vec<_> = match tags {
+    Some(tags) => tags.split(',').map(str::trim).collect(),
+    None => vec![],
+  };
+  if !tags.iter().all(|tag| is_ident(tag)) {
+    return Err(invalid());
+  }
+  let name = if name.is_empty() { None } else { Some(name) };
+  Ok((name, tags, rest))
+}
+
+/// Splits a rule at its arrow, swapping the sides of rules written `rhs <= lhs`.
+fn split_arrow(s: &str) -> Result<(&str, &str, Direction), RuleError> {
+  if let Some((lhs, rhs)) = s.split_once("<=>") {
+    Ok((lhs, rhs, Direction::Both))
+  } else if let Some((lhs, rhs)) = s.split_once("=>") {
+    Ok((lhs, rhs, Direction::Forward))
+  } else if let Some((rhs, lhs)) = s.split_once("<=") {
+    Ok((lhs, rhs, Direction::Forward))
+  } else {
+    Err(RuleError::MissingArrow)
+  }
+}
+
+/// Checks that the variables of `guard` appear in `rule`, or are built-in constants.
+fn check_bound(rule: &Rule, guard: &Guard) -> Result<(), RuleError> {
+  let (lhs, rhs) = rule.sides();
+  let sides = [&lhs.0, &lhs.1, &rhs.0, &rhs.1];
+  let bound = |var: &str| {
+    sides.iter().any(|side| {
+      side
+        .iter()
+        .any(|t| matches!(t, Token::Var(name) if name == var))
+    })
+  };
+  let constants = builtin();
+
+  for var in guard.constraints.keys() {
+    if !bound(var) {
+      return Err(RuleError::UnboundVariable(var.clone()));
+    }
+  }
+  for cond in &guard.conditions {
+    for t in cond.lhs.iter().chain(cond.rhs.iter()) {
+      if let Token::Var(var) = t {
+        if !bound(var) && constants.get_var(var).is_none() {
+          return Err(RuleError::UnboundVariable(var.clone()));
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
 impl FromStr for Rule {
   type Err = Error;
 
   /// A parse from a string to a rule
   ///
   /// This supports two forms of parsing:
-  /// - equation -> equation, i.e. `x + y = z -> x = z - y`
-  /// - expression -> expression, i.e. `(x + y) + z -> x + (y + z)`
+  /// - equation <=> equation, i.e. `x + y = z <=> x = z - y`
+  /// - expression <=> expression, i.e. `(x + y) + z <=> x + (y + z)`
+  ///
+  /// Rules that may only be applied one way are written with `=>`, or with `<=` from right to
+  /// left, and rules can be named and tagged by starting them with `name [tag, ...]:`, where
+  /// either the name or the tags may be left out.
+  ///
+  /// Variables can be given constraints by writing them as `?c:const`, `?v:var` or
+  /// `?e:free_of(x)`, and side conditions can be added after `where`, separated by commas,
+  /// e.g. `x / x <=> 1 where x != 0`.
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    use self::{Error::*, ParseError::*};
-    let rule = s.split_once("<=>").ok_or(ParseError(MissingArgument))?;
+    let mut guard = Guard::new();
+    let (name, tags, s) = split_header(s)?;
+    let (rule, clause) = split_keyword(s, "where").unwrap_or((s, ""));
+    let rule = strip_metavariables(rule, &mut guard)?;
+    if !clause.trim().is_empty() {
+      parse_where(clause, &mut guard)?;
+    }
+    let (lhs, rhs, direction) = split_arrow(&rule)?;
+    let rule = (lhs, rhs);
 
-    ({
-      let lhs = rule.0.parse::<Eqtn>()?;
-      let rhs = rule.1.parse::<Eqtn>()?;
-      Ok::<Rule, Self::Err>(Rule(lhs, rhs))
-    })
-    .or({
-      let lhs = rule.0.parse::<Expr>()?;
-      let rhs = rule.1.parse::<Expr>()?;
-      Ok(Rule(lhs_eqtn(lhs), lhs_eqtn(rhs)))
-    })
+    let eqtns = || Ok::<_, Self::Err>(Rule::new(rule.0.parse()?, rule.1.parse()?));
+    let exprs = || Ok::<_, Self::Err>(Rule::from_exprs(rule.0.parse()?, rule.1.parse()?));
+    let rule = eqtns().or_else(|_| exprs())?;
+    check_bound(&rule, &guard)?;
+
+    let mut rule = rule.with_guard(guard).with_direction(direction);
+    if let Some(name) = name {
+      rule = rule.named(name);
+    }
+    Ok(tags.into_iter().fold(rule, Rule::tagged))
+  }
+}
+
+/// Parses the lines of a rule set, resolving `#include "path"` relative to `dir`, or
+/// rejecting it without a directory.
+///
+/// `stack` holds the files being parsed, to detect cycles, and `seen` the files already
+/// included, which are only included once.
+fn parse_lines(
+  s: &str,
+  dir: Option<&Path>,
+  stack: &mut Vec<PathBuf>,
+  seen: &mut HashSet<PathBuf>,
+) -> Result<Vec<Rule>, Error> {
+  let mut res = vec![];
+  for (i, line) in s.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with("//") {
+      continue;
+    }
+    let at_line = |err: Error| Error::from(RuleError::Line(i + 1, Box::new(err)));
+
+    match line.strip_prefix('#') {
+      Some(directive) => {
+        let path = directive
+          .strip_prefix("include")
+          .map(|path| path.trim().trim_matches('"'))
+          .filter(|path| !path.is_empty())
+          .ok_or_else(|| at_line(RuleError::InvalidDirective(line.into()).into()))?;
+        let dir = dir.ok_or_else(|| at_line(RuleError::IncludeOutsideFile(line.into()).into()))?;
+        res.extend(include(&dir.join(path), stack, seen).map_err(at_line)?);
+      }
+      None => res.push(line.parse().map_err(at_line)?),
+    }
+  }
+  Ok(res)
+}
+
+/// Parses the rule file at `path`, unless it was already included.
+fn include(
+  path: &Path,
+  stack: &mut Vec<PathBuf>,
+  seen: &mut HashSet<PathBuf>,
+) -> Result<Vec<Rule>, Error> {
+  let io_error = |err: io::Error| RuleError::Io(path.into(), err.to_string());
+  let canonical = path.canonicalize().map_err(io_error)?;
+  if stack.contains(&canonical) {
+    return Err(RuleError::IncludeCycle(path.into()).into());
+  }
+  if !seen.insert(canonical.clone()) {
+    return Ok(vec![]);
+  }
+
+  let s = fs::read_to_string(path).map_err(io_error)?;
+  let dir = path.parent().unwrap_or_else(|| Path::new(""));
+  stack.push(canonical);
+  let res = parse_lines(&s, Some(dir), stack, seen);
+  stack.pop();
+  res.map_err(|err| RuleError::File(path.into(), Box::new(err)).into())
+}
+
+/// Checks that no two rules have the same name, and adds the flipped `<=>` rules.
+fn close(rules: Vec<Rule>) -> Result<Rules, Error> {
+  let mut names = HashSet::new();
+  for name in rules.iter().filter_map(Rule::name) {
+    if !names.insert(name) {
+      return Err(RuleError::DuplicateName(name.into()).into());
+    }
+  }
+  Ok(Rules(rules).symmetric_close())
+}
+
+impl Rules {
+  /// Loads the rules from a file, one per line, as in [`from_str`](#method.from_str).
+  ///
+  /// Rule files can include other rule files with `#include "path"`, relative to the including
+  /// file, and each file is only included once.
+  ///
+  /// # Failure
+  ///
+  /// Returns `Err(RuleError::File)` with the line of the first rule that doesn't parse, or
+  /// `Err(RuleError::Io)` if a file can't be read.
+  pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Rules, Error> {
+    close(include(path.as_ref(), &mut vec![], &mut HashSet::new())?)
   }
 }
 
 impl FromStr for Rules {
   type Err = Error;
+
+  /// Parses rules, one per line, skipping empty lines and `//` comments.
+  ///
+  /// The flipped copies of the `<=>` rules are added. Errors are reported with the line they
+  /// are on, counting from 1.
+  ///
+  /// Rule files can only be included from other rule files, so `#include` is rejected with
+  /// `RuleError::IncludeOutsideFile`, as it is when deserializing rules.
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let mut res = vec![];
-    for line in s
-      .split('\n')
-      .filter(|line| (line != &"") & (!line.starts_with("//")))
-    {
-      res.push(line.parse()?)
-    }
-    Ok(Rules(res).symmetric_close())
+    close(parse_lines(s, None, &mut vec![], &mut HashSet::new())?)
+  }
+}
+
+/// Serializes as a string, e.g. `"x + 0 <=> x"`.
+#[cfg(feature = "serde")]
+impl Serialize for Rule {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Rule {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(de::Error::custom)
+  }
+}
+
+/// Serializes as a list of rules, including the flipped rules.
+#[cfg(feature = "serde")]
+impl Serialize for Rules {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.0.serialize(serializer)
+  }
+}
+
+/// Deserializes from a list of rules, used as is, or from a string of rules, one per line, in
+/// which case the flipped rules are added as when parsing.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Rules {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct RulesVisitor;
+
+    impl<'de> de::Visitor<'de> for RulesVisitor {
+      type Value = Rules;
+
+      fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of rules or a string of rules, one per line")
+      }
+
+      fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+      }
+
+      fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut rules = vec![];
+        while let Some(rule) = seq.next_element()? {
+          rules.push(rule);
+        }
+        Ok(Rules(rules))
+      }
+    }
+
+    deserializer.deserialize_any(RulesVisitor)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_expression_rule() {
+    let rule: Rule = "x + 0 <=> x".parse().unwrap();
+    let expected = Rule::from_exprs("x + 0".parse().unwrap(), "x".parse().unwrap());
+    assert_eq!(rule, expected);
+  }
+
+  #[test]
+  fn parse_equation_rule() {
+    let rule: Rule = "x + y = z <=> x = z - y".parse().unwrap();
+    let expected = Rule::new("x + y = z".parse().unwrap(), "x = z - y".parse().unwrap());
+    assert_eq!(rule, expected);
+  }
+
+  #[test]
+  fn parse_directions() {
+    let rule: Rule = "x^(-1) => 1/x".parse().unwrap();
+    assert_eq!(rule.direction(), Direction::Forward);
+    assert_eq!(rule, "1/x <= x^(-1)".parse().unwrap());
+    assert_eq!(rule.to_string(), "x^(-1) => 1 / x");
+
+    let rules: Rules = "x + 0 => x\nx * y <=> y * x".parse().unwrap();
+    // only the `<=>` rule is flipped
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules.in_direction(Direction::Forward).len(), 1);
+  }
+
+  #[test]
+  fn parse_names_and_tags() {
+    let rule: Rule = "neg_pow [simplify, exp]: x^(-1) => 1/x".parse().unwrap();
+    assert_eq!(rule.name(), Some("neg_pow"));
+    assert!(rule.has_tag("simplify") && rule.has_tag("exp"));
+    assert_eq!(rule.to_string(), "neg_pow [exp, simplify]: x^(-1) => 1 / x");
+    assert_eq!(rule.to_string().parse::<Rule>().unwrap(), rule);
+
+    let rule: Rule = "comm: x + y <=> y + x".parse().unwrap();
+    assert_eq!((rule.name(), rule.tags().len()), (Some("comm"), 0));
+    let rule: Rule = "[simplify]: x * 1 => x".parse().unwrap();
+    assert_eq!(rule.to_string(), "[simplify]: x * 1 => x");
+
+    let rules: Rules = "comm: x + y <=> y + x\n[simplify]: x * 1 => x"
+      .parse()
+      .unwrap();
+    assert_eq!(rules.named("comm").len(), 2);
+    assert_eq!(rules.with_tag("simplify").len(), 1);
+  }
+
+  #[test]
+  fn parse_errors_have_lines() {
+    let err = "x + 0 => x\n\n// comment\nx + <=> x".parse::<Rules>();
+    let expected = "x + ".parse::<Expr>().unwrap_err();
+    assert_eq!(
+      err.unwrap_err(),
+      RuleError::Line(4, Box::new(expected)).into()
+    );
+
+    let err = "a: x + 0 => x\na: x * 1 => x".parse::<Rules>();
+    assert_eq!(
+      err.unwrap_err(),
+      RuleError::DuplicateName("a".into()).into()
+    );
+    let err = "#define x".parse::<Rules>();
+    assert_eq!(
+      err.unwrap_err(),
+      RuleError::Line(
+        1,
+        Box::new(RuleError::InvalidDirective("#define x".into()).into())
+      )
+      .into()
+    );
+  }
+
+  #[test]
+  fn load_rule_files() {
+    let dir = std::env::temp_dir().join(format!("meval-rules-{}", std::process::id()));
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(
+      dir.join("main.rules"),
+      "#include \"sub/units.rules\"\nx * y <=> y * x\n",
+    )
+    .unwrap();
+    fs::write(
+      dir.join("sub/units.rules"),
+      "// units\n#include \"zero.rules\"\n#include \"zero.rules\"\nunit: x * 1 => x\n",
+    )
+    .unwrap();
+    fs::write(dir.join("sub/zero.rules"), "zero: x + 0 => x\n").unwrap();
+
+    let rules = Rules::from_file(dir.join("main.rules")).unwrap();
+    // included files are only included once
+    assert_eq!(rules.len(), 4);
+    assert_eq!(rules.named("zero").len(), 1);
+
+    fs::write(dir.join("sub/zero.rules"), "zero: x + 0 => x\nx + => x\n").unwrap();
+    let err = Rules::from_file(dir.join("main.rules")).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      format!(
+        "Rule error: in `{}`: line 1: in `{}`: line 2: in `{}`: line 2: {}",
+        dir.join("main.rules").display(),
+        dir.join("sub/units.rules").display(),
+        dir.join("sub/zero.rules").display(),
+        "x + ".parse::<Expr>().unwrap_err()
+      )
+    );
+
+    fs::write(dir.join("sub/zero.rules"), "#include \"../main.rules\"\n").unwrap();
+    let err = Rules::from_file(dir.join("main.rules")).unwrap_err();
+    assert!(err.to_string().contains("includes itself"), "{}", err);
+
+    let err = Rules::from_file(dir.join("missing.rules")).unwrap_err();
+    assert!(matches!(err, Error::RuleError(RuleError::Io(..))));
+
+    // only rule files may include others
+    let include = format!("#include \"{}\"", dir.join("main.rules").display());
+    assert_eq!(
+      include.parse::<Rules>().unwrap_err(),
+      RuleError::Line(1, Box::new(RuleError::IncludeOutsideFile(include).into())).into()
+    );
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn parse_default_rules() {
+    let rules = Rules::default();
+    assert!(!rules.with_tag("simplify").is_empty());
+    assert!(rules
+      .with_tag("simplify")
+      .iter()
+      .all(|rule| rule.direction() == Direction::Forward));
+  }
+
+  #[test]
+  fn parse_guarded_rule() {
+    let rule: Rule = "?c:const * (?a + ?b) <=> c * a + c * b".parse().unwrap();
+    assert_eq!(
+      rule.guard(),
+      &Guard::new().constrain("c", Constraint::Const)
+    );
+    assert_eq!(
+      rule,
+      rule!(c * (a + b) <=> c * a + c * b).with_guard(rule.guard().clone())
+    );
+
+    let rule: Rule = "?e:free_of(?v) * ?v:var <=> v * e where e != 0, v > -1"
+      .parse()
+      .unwrap();
+    let guard = Guard::new()
+      .constrain("e", Constraint::FreeOf("v".into()))
+      .constrain("v", Constraint::Var)
+      .when(Condition::new(expr!(e), Comparison::Ne, expr!(0)))
+      .when(Condition::new(expr!(v), Comparison::Gt, expr!(-1)));
+    assert_eq!(rule.guard(), &guard);
+
+    // the guard is kept when formatting
+    assert_eq!(
+      rule.to_string(),
+      "e * v <=> v * e where ?e:free_of(v), ?v:var, e != 0, v > -1"
+    );
+    assert_eq!(rule.to_string().parse::<Rule>().unwrap(), rule);
+
+    // variables in the where clause can be the built-in constants
+    let rule: Rule = "sin(x) <=> 0 where x = pi".parse().unwrap();
+    assert_eq!(rule.guard().conditions.len(), 1);
+    // `!` right after an operand is a factorial
+    let rule: Rule = "x <=> x where x!=2".parse().unwrap();
+    assert_eq!(
+      rule.guard().conditions,
+      [Condition::new(expr!(x!), Comparison::Eq, expr!(2))]
+    );
+    // `where` inside a variable name isn't the keyword
+    assert!("nowhere <=> x".parse::<Rule>().is_ok());
+  }
+
+  #[test]
+  fn parse_rule_errors() {
+    let err = |s: &str| match s.parse::<Rule>() {
+      Err(Error::RuleError(err)) => err,
+      res => panic!("expected a rule error for {:?}, got {:?}", s, res),
+    };
+    assert_eq!(err("x + 0 -> x"), RuleError::MissingArrow);
+    assert_eq!(
+      err("comm [a, b-c]: x + y <=> y + x"),
+      RuleError::InvalidHeader("comm [a, b-c]".into())
+    );
+    assert_eq!(err("x + ? <=> x"), RuleError::InvalidMetavariable(4));
+    assert_eq!(
+      err("?x:int <=> x"),
+      RuleError::UnknownConstraint("x".into(), "int".into())
+    );
+    assert_eq!(
+      err("?x:const + ?x:var <=> x"),
+      RuleError::ConflictingConstraints("x".into())
+    );
+    assert_eq!(
+      err("x / x <=> 1 where x"),
+      RuleError::InvalidCondition("x".into())
+    );
+    assert_eq!(
+      err("x / x <=> 1 where y != 0"),
+      RuleError::UnboundVariable("y".into())
+    );
+  }
+
+  #[test]
+  fn parse_aggregate_rules() {
+    super::super::aggregate::aggregate_rules();
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn rules_json_round_trip() {
+    use serde_json;
+
+    let rules: Rules = "x + 0 <=> x\nx + y = z <=> x = z - y".parse().unwrap();
+    let json = serde_json::to_string(&rules).unwrap();
+    assert_eq!(serde_json::from_str::<Rules>(&json).unwrap().0, rules.0);
+
+    let from_str: Rules = serde_json::from_str(r#""x + 0 <=> x\n// comment""#).unwrap();
+    assert_eq!(from_str.0.len(), 2);
+    assert_eq!(from_str.0[1].to_string(), "x <=> x + 0");
+
+    let err = serde_json::from_str::<Rules>(r##""#include \"/etc/passwd\"""##).unwrap_err();
+    assert!(err.to_string().contains("Rules::from_file"), "{}", err);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn rules_toml_round_trip() {
+    use toml;
+
+    #[derive(Serialize, Deserialize)]
+    struct Library {
+      rules: Rules,
+    }
+
+    let config = r#"rules = ["sin(x)^2 + cos(x)^2 <=> 1", "2 * x = y <=> x = y / 2"]"#;
+    let library: Library = toml::from_str(config).unwrap();
+    assert_eq!(library.rules.0.len(), 2);
+    assert_eq!(
+      library.rules.0[1],
+      "2 * x = y <=> x = y / 2".parse().unwrap()
+    );
+
+    let printed = toml::to_string(&library).unwrap();
+    let reparsed: Library = toml::from_str(&printed).unwrap();
+    assert_eq!(reparsed.rules.0, library.rules.0);
   }
 }
@@ -10,21 +10,51 @@ sin(-x) <=> -sin(x)
 cos(-x) <=> cos(x)
 
 // addition formula
-sin(x + y) <=> sin(x)cos(y) + sin(y)cos(x)
-cos(x + y) <=> cos(x)cos(y) - sin(x)sin(y)
-tan(x + y) <=> (tan(x) + tan(y)) / (1 + tan(x)tan(y))
+sin(x + y) <=> sin(x) * cos(y) + sin(y) * cos(x)
+cos(x + y) <=> cos(x) * cos(y) - sin(x) * sin(y)
+tan(x + y) <=> (tan(x) + tan(y)) / (1 - tan(x) * tan(y))
 
 // double formula
 sin(2 * x) <=> 2*sin(x)*cos(x)
 cos(2 * x) <=> cos(x)^2 - sin(x)^2
-tan(2 * x) <=> (2*tan(x)) / (1 + tan(x)^2)
+tan(2 * x) <=> (2*tan(x)) / (1 - tan(x)^2)
 
-// inverses
-sin(x) = y <=> x = asin(y)
-cos(x) = y <=> x = acos(y)
-tan(x) = y <=> x = atan(y)
+// inverses, which only give the principal solution
+sin(x) = y => x = asin(y)
+cos(x) = y => x = acos(y)
+tan(x) = y => x = atan(y)
 ";
 
 pub fn trigonometry_rules() -> Rules {
   RULESTR.parse().expect("trigonometry rules did not parse")
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use builtin;
+  use Expr;
+
+  #[test]
+  fn identities_hold_numerically() {
+    let placeholder = Expr::var("");
+    for &(x, y) in &[(0.3, 0.5), (-1.2, 0.7), (2.1, -0.4)] {
+      let ctx = (("x", x), (("y", y), builtin()));
+      for rule in trigonometry_rules().iter() {
+        let (lhs, rhs) = rule.sides();
+        if lhs.1 != placeholder {
+          continue;
+        }
+        let l = lhs.0.eval_with_context(&ctx).unwrap();
+        let r = rhs.0.eval_with_context(&ctx).unwrap();
+        assert!(
+          (l - r).abs() < 1e-9 * l.abs().max(1.),
+          "{} at x = {}, y = {}",
+          rule,
+          x,
+          y
+        );
+      }
+    }
+  }
+}
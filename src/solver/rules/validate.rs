@@ -0,0 +1,270 @@
+//! Numerical checks that the two sides of rules agree.
+use super::{Direction, Rule, Rules};
+use crate::expr::{builtin, Substitutions, Token};
+use crate::{Context, ContextProvider, Eqtn, Expr};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+/// The number of variable assignments tried for each rule by [`Rules::validate`].
+///
+/// [`Rules::validate`]: struct.Rules.html#method.validate
+const SAMPLES: usize = 64;
+
+/// A variable assignment on which the two sides of a rule disagree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample {
+  pub rule: Rule,
+  pub values: BTreeMap<String, f64>,
+  /// The values of the sides of the expression rule, or of the equation that should hold.
+  pub lhs: f64,
+  pub rhs: f64,
+}
+
+impl Display for Counterexample {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let values: Vec<_> = self
+      .values
+      .iter()
+      .map(|(var, value)| format!("{} = {}", var, value))
+      .collect();
+    write!(
+      f,
+      "`{}` doesn't hold for {}: {} != {}",
+      self.rule,
+      values.join(", "),
+      self.lhs,
+      self.rhs
+    )
+  }
+}
+
+/// A xorshift generator, which is plenty for picking sample points.
+struct Sampler(u64);
+
+impl Sampler {
+  fn new(seed: u64) -> Self {
+    // the state must never be zero
+    Sampler(seed ^ 0x9e37_79b9_7f4a_7c15 | 1)
+  }
+
+  /// A number in `[-4, 4)`.
+  fn next(&mut self) -> f64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    (self.0 >> 11) as f64 / (1u64 << 53) as f64 * 8. - 4.
+  }
+}
+
+fn approx_eq(a: f64, b: f64) -> bool {
+  (a - b).abs() <= 1e-8 * (1. + a.abs().max(b.abs()))
+}
+
+/// Whether the side is an expression, stored with a placeholder right hand side.
+fn is_expr(eqtn: &Eqtn) -> bool {
+  matches!(&eqtn.1[..], [Token::Var(name)] if name.is_empty())
+}
+
+/// The variable and its value if the equation is of the form `x = expr` or `expr = x`, with `x`
+/// not in `expr`.
+fn solved_for(eqtn: &Eqtn) -> Option<(&str, &Expr)> {
+  let free = |var: &str, expr: &Expr| !expr.iter().any(|t| matches!(t, Token::Var(v) if v == var));
+  match (&eqtn.0[..], &eqtn.1[..]) {
+    ([Token::Var(var)], _) if free(var, &eqtn.1) => Some((var, &eqtn.1)),
+    (_, [Token::Var(var)]) if free(var, &eqtn.0) => Some((var, &eqtn.0)),
+    _ => None,
+  }
+}
+
+impl Rule {
+  /// The variables of the rule, without the built-in constants.
+  fn variables(&self, consts: &Context) -> Vec<String> {
+    let (lhs, rhs) = self.sides();
+    let mut vars: Vec<String> = [&lhs.0, &lhs.1, &rhs.0, &rhs.1]
+      .iter()
+      .flat_map(|side| side.iter())
+      .filter_map(|t| match t {
+        Token::Var(var) if !var.is_empty() && consts.get_var(var).is_none() => Some(var.clone()),
+        _ => None,
+      })
+      .collect();
+    vars.sort();
+    vars.dedup();
+    vars
+  }
+
+  /// Looks for a counterexample to the rule among `samples` random variable assignments.
+  ///
+  /// Assignments for which the side conditions of the rule don't hold, or a side can't be
+  /// evaluated to a finite number, are skipped. Equation rules are checked by solving one side
+  /// for a variable, where it is of the form `x = expr`, and checking that the other side holds.
+  /// One-way equation rules only have to keep to solutions, e.g. `sin(x) = y => x = asin(y)`
+  /// loses solutions but doesn't add any, so only the right hand side is solved.
+  fn counterexample(&self, samples: usize, sampler: &mut Sampler) -> Option<Counterexample> {
+    let consts = builtin();
+    let vars = self.variables(&consts);
+    let (lhs, rhs) = self.sides();
+
+    // the equation sides that can be solved, with the sides that should then hold
+    let pairs: &[_] = match self.direction() {
+      Direction::Both => &[(lhs, rhs), (rhs, lhs)],
+      Direction::Forward => &[(rhs, lhs)],
+    };
+    let checks: Vec<_> = if is_expr(lhs) {
+      vec![]
+    } else {
+      pairs
+        .iter()
+        .filter_map(|&(solved, other)| solved_for(solved).map(|(var, expr)| (var, expr, other)))
+        .collect()
+    };
+    if !is_expr(lhs) && checks.is_empty() {
+      return None;
+    }
+
+    for _ in 0..samples {
+      let mut values: BTreeMap<String, f64> = vars
+        .iter()
+        .map(|var| (var.clone(), sampler.next()))
+        .collect();
+      let mut subs = Substitutions::new();
+      for (var, value) in &values {
+        subs.insert(var.clone(), Expr::num(*value));
+      }
+      if !self
+        .guard()
+        .conditions
+        .iter()
+        .all(|cond| cond.holds(&subs, &consts))
+      {
+        continue;
+      }
+
+      let eval = |expr: &Expr, values: &BTreeMap<String, f64>| {
+        expr
+          .eval_with_context((values, &consts))
+          .ok()
+          .filter(|x| x.is_finite())
+      };
+      if is_expr(lhs) {
+        if let (Some(a), Some(b)) = (eval(&lhs.0, &values), eval(&rhs.0, &values)) {
+          if !approx_eq(a, b) {
+            return Some(self.counterexample_at(values, a, b));
+          }
+        }
+        continue;
+      }
+      for &(var, expr, other) in &checks {
+        let value = match eval(expr, &values) {
+          Some(value) => value,
+          None => continue,
+        };
+        values.insert(var.into(), value);
+        if let (Some(a), Some(b)) = (eval(&other.0, &values), eval(&other.1, &values)) {
+          if !approx_eq(a, b) {
+            return Some(self.counterexample_at(values, a, b));
+          }
+        }
+      }
+    }
+    None
+  }
+
+  fn counterexample_at(&self, values: BTreeMap<String, f64>, lhs: f64, rhs: f64) -> Counterexample {
+    Counterexample {
+      rule: self.clone(),
+      values,
+      lhs,
+      rhs,
+    }
+  }
+}
+
+impl Rules {
+  /// Checks the rules numerically, by evaluating both sides at random variable assignments
+  /// with the built-in constants and functions.
+  ///
+  /// This catches unsound rules, such as a mistyped identity, before they are used. Rules
+  /// relating equations are only checked if a side is of the form `x = expr`, and one-way
+  /// rules relating equations only have to keep to the solutions of the left hand side. The
+  /// flipped copies of `<=>` rules aren't checked twice.
+  ///
+  /// # Failure
+  ///
+  /// Returns a counterexample for every rule found to be unsound.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use meval_symbolic::Rules;
+  /// let rules: Rules = "cos(2 * x) <=> cos(x)^2 + sin(x)^2".parse().unwrap();
+  /// let errors = rules.validate().unwrap_err();
+  /// assert_eq!(errors.len(), 1);
+  /// ```
+  pub fn validate(&self) -> Result<(), Vec<Counterexample>> {
+    self.validate_with(SAMPLES, 0)
+  }
+
+  /// Like [`validate`](#method.validate), trying `samples` assignments per rule, picked
+  /// pseudo-randomly from `seed`.
+  pub fn validate_with(&self, samples: usize, seed: u64) -> Result<(), Vec<Counterexample>> {
+    let mut sampler = Sampler::new(seed);
+    let mut checked: Vec<Rule> = vec![];
+    let mut errors = vec![];
+    for rule in self.iter() {
+      if rule.direction() == Direction::Both && checked.contains(&rule.clone().flipped()) {
+        continue;
+      }
+      checked.push(rule.clone());
+      errors.extend(rule.counterexample(samples, &mut sampler));
+    }
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(errors)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn default_rules_are_sound() {
+    if let Err(errors) = Rules::default().validate() {
+      let errors: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+      panic!("unsound rules:\n{}", errors.join("\n"));
+    }
+  }
+
+  #[test]
+  fn unsound_rules() {
+    let rules: Rules = "
+      cos(x + y) <=> cos(x) * cos(y) + sin(x) * sin(y)
+      x + y = z <=> x = z + y
+      x * y <=> y * x
+      x / x => 1 where x != 0
+      sqrt(x^2) => x"
+      .parse()
+      .unwrap();
+    let errors = rules.validate().unwrap_err();
+    let names: Vec<_> = errors.iter().map(|err| err.rule.to_string()).collect();
+    assert_eq!(
+      names,
+      [
+        "cos(x + y) <=> cos(x) * cos(y) + sin(x) * sin(y)",
+        "x + y = z <=> x = z + y",
+        "sqrt(x^2) => x",
+      ]
+    );
+    assert!(errors[0].values.contains_key("x") && errors[0].values.contains_key("y"));
+    assert!(!approx_eq(errors[0].lhs, errors[0].rhs));
+  }
+
+  #[test]
+  fn guarded_samples_are_skipped() {
+    let rules: Rules = "sqrt(x^2) => x where x >= 0".parse().unwrap();
+    assert_eq!(rules.validate(), Ok(()));
+  }
+}